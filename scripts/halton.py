@@ -0,0 +1,25 @@
+# Adds dots from the Halton sequence in bases 2 and 3, which covers the
+# window more evenly than random dots do. Reads the dots already there, so
+# running it again continues the sequence instead of repeating it.
+# interactive-voronoi --script scripts/halton.py, then press Shift+K
+import json
+import os
+import sys
+
+width = float(os.environ.get("VORONOI_WIDTH", 1280))
+height = float(os.environ.get("VORONOI_HEIGHT", 720))
+count = 200
+
+
+def halton(index, base):
+    result, fraction = 0.0, 1.0
+    while index > 0:
+        fraction /= base
+        result += fraction * (index % base)
+        index //= base
+    return result
+
+
+start = len(json.load(sys.stdin)) + 1
+for i in range(start, start + count):
+    print(f"add {halton(i, 2) * width:.3f} {halton(i, 3) * height:.3f}")
@@ -0,0 +1,19 @@
+# Dots on a sunflower spiral, each one turned the golden angle from the one
+# before, shaded from the middle out.
+# interactive-voronoi --script scripts/spiral.py, then press Shift+K
+import math
+import os
+
+width = float(os.environ.get("VORONOI_WIDTH", 1280))
+height = float(os.environ.get("VORONOI_HEIGHT", 720))
+count = 600
+golden_angle = math.pi * (3 - math.sqrt(5))
+radius = min(width, height) / 2 - 10
+
+print("clear")
+for i in range(count):
+    r = radius * math.sqrt((i + 0.5) / count)
+    x = width / 2 + r * math.cos(i * golden_angle)
+    y = height / 2 + r * math.sin(i * golden_angle)
+    shade = int(255 * (1 - 0.7 * i / count))
+    print(f"add {x:.3f} {y:.3f} #ff{shade:02x}40")
@@ -0,0 +1,42 @@
+//! Which cells border on which: the dual of the diagram.
+
+use std::collections::HashMap;
+
+use crate::diagram::Point;
+
+/// An edge between two cells. The endpoints are the cells' own corners, so
+/// for cells at the border of the window they may lie outside it.
+pub struct SharedEdge {
+    /// The lower index first.
+    pub cells: (usize, usize),
+    pub ends: (Point, Point),
+}
+
+/// Exact coordinates, for matching corners.
+pub fn point_key(p: &Point) -> (u64, u64) {
+    (p.0.to_bits(), p.1.to_bits())
+}
+
+/// The edges of a polygon, from each corner to the next, and from the last back to the first.
+pub fn polygon_edges(poly: &[Point]) -> impl Iterator<Item = (&Point, &Point)> {
+    poly.iter().zip(poly.iter().cycle().skip(1))
+}
+
+/// Every edge that two of the cells share. Neighboring cells get their
+/// shared corners from the same triangle, so their edges match exactly.
+pub fn shared_edges(polygons: &[Vec<Point>]) -> Vec<SharedEdge> {
+    let mut first_cell_on = HashMap::new();
+    let mut shared = Vec::new();
+    for (cell, poly) in polygons.iter().enumerate() {
+        for (a, b) in polygon_edges(poly) {
+            let (ka, kb) = (point_key(a), point_key(b));
+            let unordered = if ka < kb { (ka, kb) } else { (kb, ka) };
+            match first_cell_on.get(&unordered) {
+                Some(&other) if other != cell => shared.push(SharedEdge { cells: (other, cell), ends: (*a, *b) }),
+                Some(_) => (),
+                None => { first_cell_on.insert(unordered, cell); }
+            }
+        }
+    }
+    shared
+}
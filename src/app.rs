@@ -0,0 +1,2672 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use graphics::{Context, Graphics};
+use piston_window::*;
+
+use crate::bounds::{convex_layers, enclosing_circle, oriented_box};
+use crate::centers::{self, circumcenter, incenter};
+use crate::brush::{Spray, Tool};
+use crate::clipboard;
+use crate::cluster::{dbscan, KMeans};
+use crate::compare::{self, Comparison, Split};
+use crate::command_palette::{self, Action, CommandPalette, Run};
+use crate::control::{command_channel, Command};
+use crate::density::{gaussian_clusters, inset, Density};
+use crate::diagram::{random, Diagram, Point};
+use crate::color::{group_marker_color, hsv, team_color, Palette};
+use crate::diagram::{Duplicates, Relaxation};
+use crate::document::{Document, PRESETS};
+use crate::geometry::{area, centroid, perimeter};
+use crate::draw::{draw_cells, draw_circle, draw_ellipse, draw_histogram, draw_marker, draw_outline, draw_panel, draw_styled_dots, draw_wireframe, histogram_size, panel_size, DotStyle, DOT_RESOLUTION};
+use crate::export::{self, Bump, ExportOptions};
+use crate::field::Field;
+use crate::fit::Coords;
+use crate::game::Game;
+use crate::gamepad::{self, Gamepad, BUTTON_B, LEFT_SHOULDER, RIGHT_SHOULDER};
+use crate::history::History;
+use crate::hyperbolic::{self, in_disk};
+use crate::journal::{self, Journal};
+use crate::autosave::{self, Autosave};
+use crate::keyboard::{self, KeyboardCursor};
+use crate::model::{Layer, LOD_SPACING};
+use crate::spatial::{Grid, CELL_SIZE};
+use crate::stamp::Stamp;
+use crate::script::{self, Script};
+use crate::stream::Stream;
+use crate::sweep::Sweep;
+use crate::terrain::{columns, elevation_color, Terrain, SEA_LEVEL};
+use crate::theme::Theme;
+use crate::tour::Tour;
+use crate::stats::{aspect_ratio, cell_areas, edge_lengths, min_angle, short, triangle_corners, Quality, Stats};
+use crate::symmetry::Symmetry;
+use crate::morph::{Morph, SCRUB_STEP};
+use crate::pathfinding::{find_path, Graph, Method};
+use crate::plugin::{ByField, Registry};
+use crate::points::{fit_points, load_dots, load_points, parse_points, read_points, save_current_dots};
+use crate::proximity::{ProximityGraph, GABRIEL_BETA, RELATIVE_NEIGHBORHOOD_BETA};
+use crate::power::{area_error, MAX_ERROR};
+use crate::refine::{inside, refine};
+use crate::regions::merge_regions;
+use crate::route::{self, Route};
+use crate::trail::Trail;
+use crate::treemap::{read_sites, Treemap};
+use crate::globe::{read_lat_lon, Globe};
+use crate::growth::Growth;
+use crate::noise::NoiseColors;
+use crate::insertion::Insertion;
+use crate::lowpoly::{LowPoly, Photo};
+use crate::watch::FileWatcher;
+use crate::websocket::state_message;
+use crate::http::Documents;
+use crate::{Settings, DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// Relaxation steps per second while `F` has relaxation running.
+const CONTINUOUS_RELAX_RATE: f64 = 20.0;
+/// How close to a dot a press has to be to pick it up.
+const GRAB_RADIUS: f64 = 6.0;
+/// Pixels a scroll step grows or shrinks the circle of a dot's weight by in
+/// power mode.
+const WEIGHT_STEP: f64 = 2.0;
+/// Rotated copies for `K` when `--kaleidoscope` doesn't say.
+const DEFAULT_KALEIDOSCOPE: usize = 6;
+/// `Shift+R` stops adding points at this many dots, in case the minimum
+/// angle can't be reached.
+const MAX_REFINED_DOTS: usize = 20000;
+/// How much `Up` and `Down` change the beta of the `Shift+S` beta-skeleton.
+const BETA_STEP: f64 = 0.05;
+/// Where it stops growing, by when hardly any edges are left.
+const MAX_BETA: f64 = 10.0;
+const SKELETON_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.9];
+/// The smallest circle and box around the dots, see `Shift+C` and `Shift+X`.
+const ENCLOSING_CIRCLE_COLOR: [f32; 4] = [0.8, 0.0, 0.5, 1.0];
+const ORIENTED_BOX_COLOR: [f32; 4] = [0.0, 0.45, 0.8, 1.0];
+/// How much darker the outlines of the convex layers are than their cells.
+const ONION_SHADE: f32 = 0.7;
+/// Pixels across the marks of the triangle centers.
+const CENTER_GLYPH_SIZE: f64 = 6.0;
+const CIRCUMCENTER_COLOR: [f32; 4] = [0.85, 0.0, 0.1, 1.0];
+const CENTROID_COLOR: [f32; 4] = [0.0, 0.35, 0.85, 1.0];
+const INCENTER_COLOR: [f32; 4] = [0.0, 0.55, 0.1, 1.0];
+const TOUR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+/// Marks the action `Enter` runs in the command palette.
+const PALETTE_PICK_COLOR: [f32; 4] = [0.2, 0.4, 0.9, 1.0];
+const GRID_COLOR: [f32; 4] = [0.0, 0.5, 0.3, 0.6];
+const GRID_SEARCH_COLOR: [f32; 4] = [1.0, 0.45, 0.0, 1.0];
+/// Shades the window around the document, see `Ctrl+D`.
+const LETTERBOX_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+/// How many keys a page of the `F1` help lists, as many as fit the window.
+const HELP_KEYS_PER_PAGE: usize = 30;
+
+/// Everything the user can change interactively. Input events go in, frames
+/// come out; the app doesn't know whether it's drawn to a window or not.
+pub struct App {
+    /// Drawn bottom to top, at least one.
+    layers: Vec<Layer>,
+    /// The layer that edits go to.
+    active: usize,
+    pub lines_only: bool,
+    /// The looks `Shift+A` cycles through, see `--theme`.
+    themes: Vec<Theme>,
+    /// The one in use.
+    theme: usize,
+    /// Notice when the themes loaded from files change, by index.
+    theme_watchers: Vec<(usize, FileWatcher)>,
+    /// Neighboring cells of similar colors are drawn and exported as one region.
+    merged: bool,
+    /// How far apart, per color channel, the colors of merged cells may be.
+    merge_tolerance: f32,
+    /// Whether the histograms of edge lengths and cell areas are showing.
+    stats: bool,
+    /// Whether the smallest circle around the active layer's dots is showing.
+    enclosing_circle: bool,
+    /// Whether the smallest box around them at any angle is showing.
+    oriented_box: bool,
+    /// Whether the convex layers of the active layer's dots are showing,
+    /// see `Shift+O`.
+    onion: bool,
+    /// Whether its cells are colored by the convex layer of their dot too.
+    onion_cells: bool,
+    /// Whether the circumcenters, centroids and incenters of the active
+    /// layer's Delaunay triangles are marked, see `Shift+V`.
+    triangle_centers: bool,
+    /// Whether the grid that finds the active layer's dots is drawn, with
+    /// the part of it an eraser-sized search at the cursor looks through.
+    grid_overlay: bool,
+    /// The proximity graph of the active layer's dots drawn over the cells,
+    /// see `Shift+S`.
+    proximity: Option<ProximityGraph>,
+    /// The beta of the beta-skeleton, see `--beta`.
+    beta: f64,
+    /// Corners closer than this merge into one dot when collapsing with `W`.
+    collapse_merge: f64,
+    /// Whether collapsing keeps the corners outside the window.
+    collapse_keep_outside: bool,
+    /// Which dots count as one, in every layer.
+    duplicates: Duplicates,
+    /// How often the corners of the cells are cut off before drawing and
+    /// exporting them, see `Diagram::smoothed`.
+    smoothing: usize,
+    /// Layers with more dots than this are drawn thinned, 0 never.
+    lod_threshold: usize,
+    /// Logical pixels per diagram unit, so thinning can leave out what
+    /// the window is too small to show.
+    scale: f64,
+    history: History,
+    /// Presentation mode hides everything that isn't part of the diagram itself.
+    /// Anything drawn on top of the diagram must check this flag.
+    pub presentation: bool,
+    random_count: usize,
+    /// Pixels random dots keep away from the window's border, see `--margin`.
+    margin: f64,
+    /// What `R` and `C` step through.
+    registry: Registry,
+    /// The generator and colorer the next `R` and `C` use.
+    generator: usize,
+    colorer: usize,
+    /// The `--field` the cells can be painted by.
+    field: Option<Rc<Field>>,
+    /// Clusters, dots per cluster and their standard deviation for `U`.
+    clusters: (usize, usize, f64),
+    /// Neighborhood radius and the dots it takes for a core dot, for `D`.
+    dbscan: (f64, usize),
+    /// Runs k-means on the active layer's dots while `J` has it on.
+    kmeans: Option<KMeans>,
+    kmeans_k: usize,
+    /// k-means steps per second.
+    kmeans_rate: f64,
+    /// k-means steps owed since the last frame, including fractions.
+    kmeans_due: f64,
+    export_path: String,
+    timelapse_path: String,
+    relief: f64,
+    export_scale: f64,
+    cursor: [f64; 2],
+    /// What the mouse button does.
+    tool: Tool,
+    spray: Spray,
+    stamp: Stamp,
+    /// Dragging with Shift held removes the dots within this distance of the cursor.
+    eraser_radius: f64,
+    /// The weight of a dot put down with full pressure is the square of this.
+    pen_radius: f64,
+    /// Where the eraser was last, so fast drags don't skip any dots.
+    erased_at: Option<[f64; 2]>,
+    mouse_held: bool,
+    /// Dots added by hand get mirror images and rotated copies.
+    symmetry: Symmetry,
+    /// Rotated copies when `K` turns the kaleidoscope on.
+    kaleidoscope: usize,
+    /// The dots being dragged, each with the position of its transformation
+    /// in `Symmetry::images`.
+    dragged: Option<Vec<(usize, usize)>>,
+    /// The dot each finger on the screen put down and drags, by touch
+    /// device and id, as its layer and index.
+    touches: HashMap<(i64, i64), (usize, usize)>,
+    ctrl_held: bool,
+    shift_held: bool,
+    /// Clicking with Alt held inspects a cell instead of adding a dot.
+    alt_held: bool,
+    trail_mode: bool,
+    trail: Trail,
+    /// Clicks add dots owned by this team, see the number keys.
+    active_team: Option<usize>,
+    /// In game mode the players take turns, and the team is always the current player.
+    game: Option<Game>,
+    watcher: Option<FileWatcher>,
+    /// What loaded and dropped dots are in, see `--coords`.
+    coords: Coords,
+    /// Whether exports write the coordinates dots were loaded in, see
+    /// `--export_coords`.
+    data_coords: bool,
+    /// Run with `Shift+K`, see `--script`.
+    script: Option<Script>,
+    /// A big points file on its way into a layer.
+    stream: Option<Stream>,
+    /// Moves the dots of the bottom layer from one point set to another.
+    morph: Option<Morph>,
+    /// Fits the cells of the bottom layer to weights, see `--treemap`.
+    treemap: Option<Treemap>,
+    /// The cells of the active layer growing from their dots, see `Shift+G`.
+    growth: Option<Growth>,
+    /// Average pixels per second the cells grow.
+    growth_speed: f64,
+    /// The page picture exports are cut to, shown in the window with the
+    /// rest shaded, see `Ctrl+D`.
+    document: Option<Document>,
+    /// What `Ctrl+D` steps through after no document: the `--document`
+    /// and the presets.
+    documents: Vec<Document>,
+    /// The colors of a layer drifting with noise, see `Ctrl+N`.
+    noise: Option<NoiseColors>,
+    /// Noise cycles per second.
+    noise_speed: f64,
+    /// The dot whose weight scrolling changed last, and the revision of the
+    /// layer after, so scrolling on doesn't remember every step for undo.
+    weighted: Option<(usize, u64)>,
+    /// Fortune's algorithm at work on the active layer, see `Shift+F`.
+    sweep: Option<Sweep>,
+    /// Colors the Delaunay triangles of the active layer by how well shaped
+    /// they are, see `Shift+I`.
+    quality: Option<Quality>,
+    /// Shows the cells of the active layer as terrain, see `Shift+H`.
+    terrain: Option<Terrain>,
+    /// Picks the terrain's noise, new every time it's turned on.
+    terrain_seed: u64,
+    /// Where `Shift+M` exports the terrain's heights, see `--heightmap`.
+    heightmap_path: String,
+    /// Where `Shift+J` exports the centroids of the cells, see `--centroids`.
+    centroids_path: String,
+    /// Where `Shift+Y` exports the edges of the cells, see `--edges`.
+    edges_path: String,
+    /// The round trip through the active layer's dots, while it's showing.
+    tour: Option<Tour>,
+    /// Where `Shift+U` exports it, see `--tour`.
+    tour_path: String,
+    /// The polygon `Shift+R` meshes, see `--boundary`. `None` for the window.
+    boundary: Option<Vec<[f64; 2]>>,
+    /// Degrees no triangle's angles go below after `Shift+R`.
+    min_angle: f64,
+    /// Where `Shift+E` exports the cells for cutting, see `--cut`.
+    cut_path: String,
+    /// Width the cuts burn away, see `--kerf`.
+    kerf: f64,
+    /// Where `Shift+T` exports a seamless tile, see `--tile`.
+    tile_path: String,
+    /// Its width in pixels.
+    tile_size: u32,
+    /// Where `Shift+N` exports a normal or height map, see `--bump`.
+    bump_path: String,
+    /// What it shows and how.
+    bump: Bump,
+    /// Clicks pick the ends of a shortest path instead of adding dots, see
+    /// `Shift+P`.
+    pathfinding: Option<(Graph, Method)>,
+    /// The dots the path goes from and to, once picked.
+    path_start: Option<usize>,
+    path_goal: Option<usize>,
+    /// The search for it being played back.
+    route: Option<Route>,
+    /// Clicks add dots step by step like Bowyer–Watson, see `Shift+B`.
+    bowyer_watson: bool,
+    /// The dot being added that way.
+    insertion: Option<Insertion>,
+    /// Seconds between its steps.
+    insertion_delay: f64,
+    /// Low-poly art of a photo on the bottom layer, see `--lowpoly`.
+    lowpoly: Option<LowPoly>,
+    /// Takes the place of the layers with sites on a sphere, see `--globe`.
+    globe: Option<Globe>,
+    /// Treemap steps per second.
+    treemap_rate: f64,
+    /// Treemap steps owed since the last frame, including fractions.
+    treemap_due: f64,
+    journal: Option<Journal>,
+    autosave: Option<Autosave>,
+    commands: Receiver<Command>,
+    /// Handed out to the listeners that send commands, see `command_sender`.
+    command_sender: Sender<Command>,
+    listening: bool,
+    gamepad: Gamepad,
+    /// Moves the cursor with the arrow keys while turned on with `Z`.
+    keyboard_cursor: Option<KeyboardCursor>,
+    /// Open with `Ctrl+P`, takes the keyboard while it is.
+    command_palette: Option<CommandPalette>,
+    /// The page of the keys `F1` shows, if any.
+    help: Option<usize>,
+    /// Shows the active layer side by side under two settings, see `Shift+W`.
+    split: Option<Split>,
+    /// Get the scene state whenever the diagram changes, see `state_updates`.
+    observers: Vec<Sender<String>>,
+    /// Get the diagram in every format served, see `document_updates`.
+    document_observers: Vec<Sender<Documents>>,
+    published_revision: u64,
+    relaxation: Relaxation,
+    /// How close dots have to be to push each other away in repulsion relaxation.
+    repulsion_range: f64,
+    /// Whether repulsion relaxation also pulls the dots towards the cursor.
+    attract: bool,
+    /// Relaxation steps per second, zero for none.
+    relax_rate: f64,
+    /// Relaxation steps owed since the last frame, including fractions.
+    relax_due: f64,
+    last_event: Option<Instant>,
+}
+
+impl App {
+    pub fn new(settings: &Settings) -> App {
+        let mut base = Layer::new(0, settings.duplicates);
+        base.diagram.hyperbolic = settings.hyperbolic;
+        base.diagram.palette = theme_palette(&settings.themes[settings.theme], 0);
+        let mut stream = None;
+        if let Some(jsf) = settings.json_path.as_ref().filter(|path| *path != "-" && Stream::wanted(Path::new(path))) {
+            stream = Some(Stream::start(PathBuf::from(jsf), 0, true, settings.coords));
+        } else if let Some(jsf) = settings.json_path.as_ref() {
+            let mut points = load_points(jsf);
+            let coords = fit_points(&mut points, settings.coords);
+            base.diagram.set_sites(points.dots, points.metadata);
+            base.diagram.coords = coords;
+        }
+
+        let watcher = match settings.json_path.as_deref() {
+            Some("-") if settings.watch => { println!("Can't watch standard input, --watch ignored"); None },
+            Some(path) if settings.watch => Some(FileWatcher::new(PathBuf::from(path))),
+            _ => None
+        };
+
+        let morph = settings.morph_path.as_ref().map(|path| {
+            let target = load_dots(path);
+            Morph::new(&base.diagram.dots, &target, settings.morph_matching, settings.morph_duration, &base.diagram.palette)
+        });
+
+        let treemap = settings.treemap_path.as_ref().map(|path| {
+            let sites = read_sites(Path::new(path)).unwrap_or_else(|err| panic!("Can't load treemap from {}: {}", path, err));
+            Treemap::new(&sites, &base.diagram.palette)
+        });
+
+        let lowpoly = settings.image_path.as_ref().filter(|_| settings.lowpoly).map(|path| {
+            let photo = Photo::open(Path::new(path)).unwrap_or_else(|err| panic!("{}", err));
+            LowPoly::new(photo, settings.lowpoly_points)
+        });
+
+        let field = settings.field_path.as_ref().map(|path| {
+            Rc::new(Field::open(Path::new(path)).unwrap_or_else(|err| panic!("Bad --field: {}", err)))
+        });
+        let mut registry = Registry::built_in(Density::parse(&settings.density).unwrap_or_else(|err| panic!("Bad --density: {}", err)));
+        if let Some(field) = &field {
+            registry.add_colorer(ByField(field.clone()));
+        }
+        // The first C paints by the field the user asked for
+        let colorer = if field.is_some() { registry.colorers.len() - 1 } else { 0 };
+
+        let globe = settings.globe.then(|| {
+            let sites = settings.globe_path.as_ref().map_or_else(Vec::new, |path| {
+                read_lat_lon(Path::new(path)).unwrap_or_else(|err| panic!("Can't load globe sites from {}: {}", path, err))
+            });
+            Globe::new(&sites)
+        });
+
+        let (command_sender, commands) = command_channel();
+
+        App {
+            layers: vec![base],
+            active: 0,
+            lines_only: settings.lines_only,
+            themes: settings.themes.clone(),
+            theme: settings.theme,
+            theme_watchers: settings.themes.iter().enumerate()
+                .filter_map(|(i, theme)| Some((i, FileWatcher::new(theme.path.clone()?))))
+                .collect(),
+            merged: false,
+            merge_tolerance: settings.merge_tolerance,
+            stats: false,
+            enclosing_circle: false,
+            oriented_box: false,
+            onion: false,
+            onion_cells: false,
+            grid_overlay: false,
+            triangle_centers: false,
+            proximity: None,
+            beta: settings.beta.max(GABRIEL_BETA),
+            collapse_merge: settings.collapse_merge,
+            collapse_keep_outside: settings.collapse_keep_outside,
+            duplicates: settings.duplicates,
+            smoothing: settings.smoothing,
+            lod_threshold: settings.lod_threshold,
+            scale: 1.0,
+            history: History::default(),
+            presentation: settings.presentation,
+            random_count: settings.random_count,
+            margin: settings.margin,
+            clusters: (settings.clusters, settings.cluster_size, settings.cluster_spread),
+            dbscan: (settings.dbscan_eps, settings.dbscan_min_points),
+            kmeans: None,
+            kmeans_k: settings.kmeans_k,
+            kmeans_rate: settings.kmeans_rate,
+            kmeans_due: 0.0,
+            registry,
+            generator: 0,
+            colorer,
+            field,
+            export_path: settings.export_path.clone(),
+            timelapse_path: settings.timelapse_path.clone(),
+            relief: settings.relief,
+            export_scale: settings.export_scale,
+            cursor: [0.0, 0.0],
+            tool: Tool::Point,
+            spray: Spray::new(settings.brush_radius, settings.brush_density, settings.brush_jitter),
+            stamp: Stamp::new(settings.stamp_size, settings.stamp_path.as_deref().map(load_dots).unwrap_or_default()),
+            eraser_radius: settings.eraser_radius,
+            pen_radius: settings.pen_radius,
+            erased_at: None,
+            mouse_held: false,
+            symmetry: Symmetry { rotations: settings.kaleidoscope.unwrap_or(1), ..Symmetry::default() },
+            kaleidoscope: settings.kaleidoscope.unwrap_or(DEFAULT_KALEIDOSCOPE),
+            dragged: None,
+            touches: HashMap::new(),
+            ctrl_held: false,
+            shift_held: false,
+            alt_held: false,
+            trail_mode: settings.trail,
+            trail: Trail::new(settings.trail_spacing, Duration::from_secs_f64(settings.trail_lifetime)),
+            active_team: settings.game.then_some(0),
+            game: settings.game.then(|| Game::new(2, settings.game_moves)),
+            watcher,
+            coords: settings.coords,
+            data_coords: settings.data_coords,
+            script: settings.script_path.as_ref().map(|path| Script::new(PathBuf::from(path))),
+            stream,
+            morph,
+            treemap,
+            growth: None,
+            sweep: None,
+            growth_speed: settings.growth_speed,
+            noise: None,
+            noise_speed: settings.noise_speed,
+            document: settings.document,
+            documents: settings.document.filter(|d| !PRESETS.iter().any(|(_, p)| p == d)).into_iter()
+                .chain(PRESETS.iter().map(|&(_, d)| d))
+                .collect(),
+            weighted: None,
+            quality: None,
+            terrain: None,
+            terrain_seed: 0,
+            heightmap_path: settings.heightmap_path.clone(),
+            centroids_path: settings.centroids_path.clone(),
+            edges_path: settings.edges_path.clone(),
+            tour: None,
+            tour_path: settings.tour_path.clone(),
+            boundary: settings.boundary_path.as_deref().map(load_dots),
+            min_angle: settings.min_angle,
+            cut_path: settings.cut_path.clone(),
+            kerf: settings.kerf,
+            tile_path: settings.tile_path.clone(),
+            tile_size: settings.tile_size,
+            bump_path: settings.bump_path.clone(),
+            bump: Bump { field: settings.bump_field, normals: !settings.bump_height, bevel: settings.bevel },
+            pathfinding: None,
+            path_start: None,
+            path_goal: None,
+            route: None,
+            bowyer_watson: false,
+            insertion: None,
+            insertion_delay: settings.insertion_delay,
+            lowpoly,
+            globe,
+            treemap_rate: settings.treemap_rate,
+            treemap_due: 0.0,
+            journal: None,
+            autosave: None,
+            commands,
+            command_sender,
+            listening: false,
+            gamepad: Gamepad::default(),
+            keyboard_cursor: settings.keyboard_cursor.then(KeyboardCursor::default),
+            command_palette: None,
+            help: None,
+            split: None,
+            observers: Vec::new(),
+            document_observers: Vec::new(),
+            published_revision: 0,
+            relaxation: settings.relaxation,
+            repulsion_range: settings.repulsion_range,
+            attract: settings.attract,
+            relax_rate: 0.0,
+            relax_due: 0.0,
+            last_event: None,
+        }
+    }
+
+    /// The diagram of the active layer.
+    pub fn diagram(&self) -> &Diagram {
+        &self.layers[self.active].diagram
+    }
+
+    fn diagram_mut(&mut self) -> &mut Diagram {
+        &mut self.layers[self.active].diagram
+    }
+
+    fn theme(&self) -> &Theme {
+        &self.themes[self.theme]
+    }
+
+    /// How the dots look, `None` while they're hidden.
+    fn dot_style(&self) -> Option<DotStyle> {
+        self.theme().dots
+    }
+
+    /// Switches to the next theme and paints the cells of every layer anew
+    /// from its palette.
+    fn next_theme(&mut self) {
+        self.theme = (self.theme + 1) % self.themes.len();
+        self.repaint();
+        println!("Theme {}", self.theme().name);
+    }
+
+    /// Paints the cells of every layer anew from the theme's palette.
+    fn repaint(&mut self) {
+        for index in 0..self.layers.len() {
+            let palette = theme_palette(&self.themes[self.theme], index);
+            let diagram = &mut self.layers[index].diagram;
+            diagram.palette = palette;
+            diagram.recolor();
+        }
+    }
+
+    /// Reads the theme files that changed on disk again. Changes to the
+    /// theme in use show right away, and cells are only painted anew when
+    /// its palette changed.
+    fn reload_themes(&mut self, now: Instant) {
+        let mut repaint = false;
+        for (index, watcher) in self.theme_watchers.iter_mut() {
+            if !watcher.poll(now) {
+                continue;
+            }
+            match Theme::load(&watcher.path) {
+                Ok(theme) => {
+                    repaint |= *index == self.theme && theme.palette != self.themes[*index].palette;
+                    self.themes[*index] = theme;
+                    println!("Reloaded theme {} from {}", self.themes[*index].name, watcher.path.display());
+                }
+                // Most likely caught the editor halfway, the next save will fix it
+                Err(err) => println!("Could not reload theme: {}", err)
+            }
+        }
+        if repaint {
+            self.repaint();
+        }
+    }
+
+    /// Logs every change to the dots to the journal at `path` from now on.
+    /// With `recover`, the scene is first restored from what the journal
+    /// already holds.
+    pub fn start_journal(&mut self, path: &str, recover: bool) -> io::Result<()> {
+        if recover {
+            self.layers = journal::recover(path, self.duplicates)?;
+            self.active = 0;
+            let count = self.layers.iter().map(|l| l.diagram.dots.len()).sum::<usize>();
+            println!("Recovered {} dots in {} layers from {}", count, self.layers.len(), path);
+            self.journal = Some(Journal::resume(path, &self.layers)?);
+        } else {
+            self.journal = Some(Journal::create(path, &self.layers)?);
+        }
+        Ok(())
+    }
+
+    /// A sender for a listener that drives the app from outside. While any
+    /// listener is attached, the app keeps waking up to check for commands.
+    pub fn command_sender(&mut self) -> Sender<Command> {
+        self.listening = true;
+        self.command_sender.clone()
+    }
+
+    /// A receiver of the state message (see `websocket::state_message`),
+    /// sent whenever the diagram changes.
+    pub fn state_updates(&mut self) -> Receiver<String> {
+        let (sender, receiver) = channel();
+        sender.send(state_message(self.diagram())).expect("Receiver is alive");
+        self.observers.push(sender);
+        receiver
+    }
+
+    /// A receiver of the diagram as `http` serves it, sent whenever the
+    /// diagram changes.
+    pub fn document_updates(&mut self) -> Receiver<Documents> {
+        let (sender, receiver) = channel();
+        sender.send(Documents::new(self.diagram(), &self.export_options())).expect("Receiver is alive");
+        self.document_observers.push(sender);
+        receiver
+    }
+
+    /// Whether the picture can change on its own, so the app needs to be
+    /// woken up even without input.
+    pub fn wants_updates(&self) -> bool {
+        self.trail_mode || !self.trail.is_empty() || self.watcher.is_some() || !self.theme_watchers.is_empty() || self.listening || self.relax_rate > 0.0
+            || self.morph.as_ref().is_some_and(|m| m.playing) || self.brushing() || self.kmeans.is_some()
+            || self.treemap.as_ref().is_some_and(|t| !t.done) || self.gamepad.active()
+            || self.growth.as_ref().is_some_and(|g| !g.done()) || self.sweep.as_ref().is_some_and(|s| s.playing)
+            || self.insertion.is_some() || self.route.as_ref().is_some_and(|r| !r.done())
+            || self.keyboard_cursor.as_ref().is_some_and(KeyboardCursor::moving)
+            || self.script.as_ref().is_some_and(Script::running) || self.noise.is_some() || self.stream.is_some()
+    }
+
+    pub fn event(&mut self, e: &Event, now: Instant) {
+        let split_mapped;
+        let e = if self.split.is_some() { split_mapped = compare::map_event(e); &split_mapped } else { e };
+        let clicked;
+        let pressed_enter = (self.keyboard_cursor.is_some() && self.command_palette.is_none()).then(|| keyboard::as_mouse(e)).flatten();
+        let e = match gamepad::as_mouse(e).or(pressed_enter) {
+            Some(click) => { clicked = click; &clicked },
+            None => e
+        };
+        e.mouse_cursor(|p|{ self.cursor = p });
+        if let Some(args) = e.controller_axis_args() {
+            self.gamepad.axis(&args);
+        }
+        if self.trail_mode && e.mouse_cursor_args().is_some() {
+            self.trail.follow(self.cursor);
+        }
+        self.trail.expire(now);
+        self.reload_if_changed(now);
+        self.reload_themes(now);
+        self.finish_script();
+        self.stream_points(false);
+        while let Ok(command) = self.commands.try_recv() {
+            self.apply(command);
+        }
+        let elapsed = self.last_event.map_or(0.0, |last| now.duration_since(last).as_secs_f64());
+        self.last_event = Some(now);
+        self.relax_continuously(elapsed);
+        if let Some(morph) = self.morph.as_mut() {
+            morph.advance(elapsed);
+        }
+        self.kmeans_continuously(elapsed);
+        self.step_treemap(elapsed);
+        if let Some(growth) = self.growth.as_mut() {
+            growth.advance(elapsed);
+        }
+        if let Some(noise) = self.noise.as_mut() {
+            if let Some(layer) = self.layers.get_mut(noise.layer).filter(|_| noise.advance(elapsed)) {
+                let colors = noise.colors(&layer.diagram.dots);
+                layer.diagram.set_colors(colors);
+            }
+        }
+        if let Some(sweep) = self.sweep.as_mut() {
+            sweep.advance(elapsed);
+        }
+        if let Some(route) = self.route.as_mut() {
+            route.advance(elapsed);
+        }
+        if self.insertion.as_mut().is_some_and(|i| i.advance(elapsed, self.insertion_delay)) {
+            self.finish_insertion();
+        }
+        self.steer(elapsed);
+
+        if let Event::Input(Input::FileDrag(FileDrag::Drop(path)), _) = e {
+            self.drop_file(path);
+        }
+        if let Some(args) = e.touch_args() {
+            self.touch(&args);
+        }
+        if let Some([_, steps]) = e.mouse_scroll_args() {
+            if self.stamping() {
+                self.stamp.scroll(steps);
+            } else {
+                self.scroll_weight(steps);
+            }
+        }
+
+        let typed = self.type_in_palette(e);
+        match e.press_args().filter(|_| !typed) {
+            Some(Button::Keyboard(Key::LCtrl | Key::RCtrl)) => { self.ctrl_held = true; },
+            Some(Button::Keyboard(Key::LShift | Key::RShift)) => { self.shift_held = true; },
+            Some(Button::Keyboard(Key::LAlt | Key::RAlt)) => { self.alt_held = true; },
+            Some(Button::Controller(button)) => { self.gamepad_button(button.button); },
+            Some(Button::Keyboard(key)) if self.keyboard_cursor.is_some() => { self.arrow(key, true); },
+            Some(Button::Mouse(_)) if self.globe.is_some() => {
+                self.mouse_held = true;
+                self.globe.as_mut().unwrap().press(self.cursor);
+            },
+            Some(Button::Mouse(_)) if self.alt_held => { self.mouse_held = true; },
+            Some(Button::Mouse(_)) => {
+                self.mouse_held = true;
+                if self.brushing() {
+                    self.spray.start();
+                } else if self.tool == Tool::Point && !self.erasing() && self.game.is_none() {
+                    self.grab();
+                }
+            },
+            _ => ()
+        }
+        if let Some(button) = e.release_args().filter(|_| !typed) {
+            match button {
+                Button::Keyboard(Key::LCtrl | Key::RCtrl) => { self.ctrl_held = false; },
+                Button::Keyboard(Key::LShift | Key::RShift) => { self.shift_held = false; },
+                Button::Keyboard(Key::LAlt | Key::RAlt) => { self.alt_held = false; },
+                Button::Keyboard(key) if self.arrow(key, false) => {},
+                Button::Keyboard(key) => { self.key(key); },
+                Button::Mouse(_) if self.globe.is_some() => {
+                    self.mouse_held = false;
+                    self.globe.as_mut().unwrap().release(self.cursor);
+                },
+                Button::Mouse(_) if self.alt_held => {
+                    self.mouse_held = false;
+                    self.inspect();
+                },
+                Button::Mouse(_) => {
+                    self.mouse_held = false;
+                    if self.dragged.take().is_none() && !self.brushing() && !self.erasing() {
+                        self.click();
+                    }
+                },
+                _ => ()
+            }
+        };
+
+        if let Some(globe) = self.globe.as_mut() {
+            if self.mouse_held && e.mouse_cursor_args().is_some() {
+                globe.drag(self.cursor);
+            }
+        } else if self.mouse_held && self.erasing() {
+            self.erase();
+            // Erasing shifts the indices of the dragged dots
+            self.dragged = None;
+            self.touches.clear();
+        } else {
+            self.erased_at = None;
+            if self.mouse_held && self.brushing() {
+                // Time spent before the button went down doesn't count
+                let seconds = if e.press_args().is_some() { 0.0 } else { elapsed };
+                self.brush(seconds);
+            }
+            if self.mouse_held && self.dragged.is_some() {
+                self.drag();
+            }
+        }
+
+        self.show_morph();
+        self.show_treemap();
+        self.show_lowpoly();
+        if let Some(split) = self.split.as_mut() {
+            split.update(&self.layers[self.active].diagram);
+        }
+        self.follow_dots();
+        self.publish();
+        self.write_journal();
+        self.autosave(now);
+        self.thin_layers();
+    }
+
+    /// Sets how many logical pixels a diagram unit takes up in the window.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale;
+    }
+
+    /// Gets the layers ready to draw: thinned if they have too many dots,
+    /// and their cells triangulated if they changed.
+    fn thin_layers(&mut self) {
+        let spacing = LOD_SPACING / self.scale;
+        for layer in &mut self.layers {
+            layer.thin(self.lod_threshold, spacing);
+            if !self.lines_only {
+                layer.triangulate(self.smoothing);
+            }
+        }
+    }
+
+    /// Moves the cursor with the gamepad's stick or the arrow keys, and
+    /// resizes the brush with the gamepad's triggers.
+    fn steer(&mut self, elapsed: f64) {
+        let mut cursor = self.gamepad.move_cursor(self.cursor, elapsed);
+        if let Some(keyboard) = self.keyboard_cursor.as_mut() {
+            cursor = keyboard.move_cursor(cursor, elapsed);
+        }
+        self.move_cursor(cursor);
+        self.spray.radius = self.gamepad.resize_brush(self.spray.radius, elapsed);
+    }
+
+    /// Moves the cursor to `cursor` for devices the system cursor doesn't follow.
+    fn move_cursor(&mut self, cursor: [f64; 2]) {
+        if cursor != self.cursor {
+            self.cursor = cursor;
+            if self.trail_mode {
+                self.trail.follow(cursor);
+            }
+        }
+    }
+
+    /// Steers the keyboard cursor with an arrow key going down or up.
+    /// Returns whether it did, which it doesn't for other keys or when the
+    /// keyboard cursor is off.
+    fn arrow(&mut self, key: Key, down: bool) -> bool {
+        let cursor = self.cursor;
+        let Some(moved) = self.keyboard_cursor.as_mut().and_then(|k| k.arrow(key, down, cursor)) else { return false };
+        self.move_cursor(moved);
+        true
+    }
+
+    /// What the gamepad's buttons do, except A, which is the mouse button.
+    fn gamepad_button(&mut self, button: u8) {
+        match button {
+            BUTTON_B => self.remove_nearest(),
+            RIGHT_SHOULDER => self.key(Key::B),
+            LEFT_SHOULDER => self.key(Key::M),
+            _ => ()
+        }
+    }
+
+    /// Removes the dot whose cell the cursor is in.
+    fn remove_nearest(&mut self) {
+        let cursor = self.cursor;
+        if let Some(index) = self.diagram().nearest_dot(cursor) {
+            self.diagram_mut().remove_dot(index);
+            // Indices after it shift down
+            self.dragged = None;
+            self.touches.clear();
+        }
+    }
+
+    /// Saves the session every `seconds` from now on, see `autosave`.
+    pub fn start_autosave(&mut self, seconds: f64) {
+        self.autosave = Some(Autosave::new(seconds));
+    }
+
+    /// Replaces the layers with those of the newest autosave.
+    pub fn restore_last(&mut self) -> io::Result<()> {
+        let (layers, path) = autosave::restore_last(self.duplicates)?;
+        self.layers = layers;
+        self.active = 0;
+        let count = self.layers.iter().map(|l| l.diagram.dots.len()).sum::<usize>();
+        println!("Restored {} dots in {} layers from {}", count, self.layers.len(), path.display());
+        Ok(())
+    }
+
+    /// Saves the session one last time, if it changed, as the window closes.
+    pub fn close(&mut self) {
+        if let Some(Err(err)) = self.autosave.as_mut().map(|a| a.save(&self.layers)) {
+            println!("Could not autosave: {}", err);
+        }
+    }
+
+    fn autosave(&mut self, now: Instant) {
+        let Some(autosave) = self.autosave.as_mut() else { return };
+        if let Err(err) = autosave.tick(&self.layers, now) {
+            println!("Could not autosave, no longer saving: {}", err);
+            self.autosave = None;
+        }
+    }
+
+    fn write_journal(&mut self) {
+        let Some(journal) = self.journal.as_mut() else { return };
+        if let Err(err) = journal.record(&self.layers) {
+            println!("Could not write journal, no longer recording: {}", err);
+            self.journal = None;
+        }
+    }
+
+    /// Whether the mouse is used by a brush, rather than clicking dots.
+    fn brushing(&self) -> bool {
+        self.tool == Tool::Spray && self.game.is_none() && !self.alt_held
+    }
+
+    /// Whether a click puts down the stamp's shape instead of a single dot.
+    fn stamping(&self) -> bool {
+        self.tool == Tool::Stamp && self.game.is_none()
+    }
+
+    /// In power mode, grows or shrinks the weight of the dot whose cell is
+    /// under the cursor, `WEIGHT_STEP` pixels of the radius of its circle
+    /// for each scroll step.
+    fn scroll_weight(&mut self, steps: f64) {
+        let diagram = self.diagram();
+        if !diagram.power || diagram.hyperbolic || !self.layers[self.active].visible {
+            return;
+        }
+        let Some(index) = diagram.nearest_dot(self.cursor) else { return };
+        let radius = (diagram.weights[index].sqrt() + steps * WEIGHT_STEP).max(0.0);
+        if self.weighted != Some((index, diagram.revision())) {
+            self.history.remember(&self.layers, self.active);
+        }
+        self.diagram_mut().set_weight(index, radius * radius);
+        self.weighted = Some((index, self.diagram().revision()));
+    }
+
+    /// Whether the mouse erases dots, which it does while Shift is held.
+    fn erasing(&self) -> bool {
+        self.shift_held && self.game.is_none()
+    }
+
+    /// Erases along the way from where the eraser was last to the cursor.
+    fn erase(&mut self) {
+        let (to, radius) = (self.cursor, self.eraser_radius);
+        let from = self.erased_at.unwrap_or(to);
+        let length = ((to[0] - from[0]).powi(2) + (to[1] - from[1]).powi(2)).sqrt();
+        let steps = (length / (radius / 2.0).max(1.0)).ceil().max(1.0) as usize;
+        for step in 1..=steps {
+            let t = step as f64 / steps as f64;
+            let at = [from[0] + (to[0] - from[0]) * t, from[1] + (to[1] - from[1]) * t];
+            self.diagram_mut().remove_within(at, radius);
+        }
+        self.erased_at = Some(to);
+    }
+
+    fn brush(&mut self, seconds: f64) {
+        let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        let dots = self.spray.emit(self.cursor, seconds).into_iter()
+            .filter(|d| (0.0..=width).contains(&d[0]) && (0.0..=height).contains(&d[1]))
+            .collect::<Vec<_>>();
+        for dot in dots {
+            self.insert(dot);
+        }
+    }
+
+    /// Adds a dot by hand, along with its mirror images, all in one color.
+    fn insert(&mut self, dot: [f64; 2]) {
+        let images = self.symmetry.images(dot);
+        let (color, team) = match self.active_team {
+            Some(team) => (team_color(team), Some(team)),
+            None => (self.diagram().palette.color(), None)
+        };
+        self.diagram_mut().add_group(&images, color, team);
+    }
+
+    /// Picks up the dot under the cursor along with its images, if there is one.
+    fn grab(&mut self) {
+        let cursor = self.cursor;
+        let diagram = self.diagram();
+        let distance = |i: usize| (diagram.dots[i][0] - cursor[0]).powi(2) + (diagram.dots[i][1] - cursor[1]).powi(2);
+        let Some(grabbed) = diagram.dots_within(cursor, GRAB_RADIUS).into_iter()
+            .min_by(|&a, &b| distance(a).total_cmp(&distance(b))) else { return };
+        let mut group: Vec<(usize, usize)> = Vec::new();
+        for (k, image) in self.symmetry.images(diagram.dots[grabbed]).into_iter().enumerate() {
+            // Images on an axis coincide, and the dot only follows the first
+            let found = if k == 0 { Some(grabbed) } else { diagram.dots_within(image, 1.0).first().copied() };
+            if let Some(index) = found.filter(|i| group.iter().all(|(j, _)| j != i)) {
+                group.push((index, k));
+            }
+        }
+        self.dragged = Some(group);
+    }
+
+    /// Moves the dragged dots to the cursor and its images.
+    fn drag(&mut self) {
+        let Some(group) = self.dragged.as_ref() else { return };
+        let images = self.symmetry.images(self.cursor);
+        let moves = group.iter().map(|&(index, k)| (index, images[k])).collect::<Vec<_>>();
+        self.diagram_mut().move_dots(&moves);
+    }
+
+    /// Puts a dot down under every new finger and moves it along with the
+    /// finger until it lifts, so each finger drags a site of its own. The
+    /// harder a pen presses, the more weight the dot gets in the power
+    /// diagram. In a game, fingers place dots like clicks.
+    fn touch(&mut self, args: &TouchArgs) {
+        let contact = (args.device, args.id);
+        let [x, y] = args.position();
+        let at = [x.clamp(0.0, DEFAULT_WINDOW_WIDTH as f64), y.clamp(0.0, DEFAULT_WINDOW_HEIGHT as f64)];
+        match args.touch {
+            Touch::Start if self.game.is_some() => self.play(at),
+            Touch::Start => {
+                let (color, team) = match self.active_team {
+                    Some(team) => (team_color(team), Some(team)),
+                    None => (self.diagram().palette.color(), None)
+                };
+                let weight = (args.pressure() * self.pen_radius).powi(2);
+                if self.diagram_mut().push_weighted_dot(at, color, team, weight) {
+                    self.touches.insert(contact, (self.active, self.diagram().dots.len() - 1));
+                }
+            }
+            Touch::Move => {
+                if let Some(&(layer, index)) = self.touches.get(&contact) {
+                    if let Some(layer) = self.layers.get_mut(layer) {
+                        layer.diagram.move_dot(index, at);
+                    }
+                }
+            }
+            Touch::End | Touch::Cancel => { self.touches.remove(&contact); }
+        }
+    }
+
+    /// Prints the geometry of the cell under the cursor as JSON, and copies
+    /// it too with Ctrl held. Vertices, area and perimeter are of the part
+    /// inside the window.
+    fn inspect(&mut self) {
+        let diagram = self.diagram();
+        let Some(index) = diagram.nearest_dot(self.cursor) else { return };
+        let cell = &diagram.visible[index];
+        let info = serde_json::json!({
+            "index": index,
+            "site": diagram.dots[index],
+            "vertices": cell.iter().map(|p| [p.0, p.1]).collect::<Vec<_>>(),
+            "area": area(cell),
+            "perimeter": perimeter(cell),
+            "neighbors": diagram.neighbors(index),
+        }).to_string();
+        println!("{}", info);
+        if self.ctrl_held {
+            match clipboard::copy(info.as_bytes(), "text/plain") {
+                Ok(()) => println!("Copied cell {} to clipboard", index),
+                Err(err) => println!("Could not copy cell to clipboard: {}", err)
+            }
+        }
+    }
+
+    fn click(&mut self) {
+        let cursor = self.cursor;
+        if self.game.is_some() {
+            self.play(cursor);
+            return;
+        }
+        if self.pathfinding.is_some() {
+            self.pick_path_end(cursor);
+            return;
+        }
+        // The hyperbolic plane ends at the rim of its disk
+        let hyperbolic = self.diagram().hyperbolic;
+        if self.stamping() {
+            let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+            for dot in self.stamp.dots(cursor) {
+                if (0.0..=width).contains(&dot[0]) && (0.0..=height).contains(&dot[1]) && (!hyperbolic || in_disk(dot)) {
+                    self.insert(dot);
+                }
+            }
+            return;
+        }
+        if !hyperbolic || in_disk(cursor) {
+            if self.bowyer_watson {
+                self.start_insertion(cursor);
+            } else {
+                self.insert(cursor);
+            }
+        }
+    }
+
+    /// Takes the dot nearest to `point` as the start of a new path, or as
+    /// its goal once there is a start, and searches the way between them.
+    fn pick_path_end(&mut self, point: [f64; 2]) {
+        let Some(index) = self.diagram().nearest_dot(point) else { return };
+        match self.path_start {
+            Some(start) if self.path_goal.is_none() && start != index => { self.path_goal = Some(index); },
+            _ => {
+                self.path_start = Some(index);
+                self.path_goal = None;
+            }
+        }
+        self.search_route();
+    }
+
+    /// Plays back the search between the picked dots with the current
+    /// method, if both are picked.
+    fn search_route(&mut self) {
+        let count = self.diagram().dots.len();
+        self.route = match (self.pathfinding, self.path_start, self.path_goal) {
+            (Some((graph, method)), Some(start), Some(goal)) if start < count && goal < count => {
+                Some(Route::new(graph, method, find_path(self.diagram(), graph, method, start, goal)))
+            },
+            _ => None
+        };
+    }
+
+    /// Shows how `dot` gets added before adding it, finishing the one
+    /// shown before at once. Dots that wouldn't change the triangulation
+    /// are added right away.
+    fn start_insertion(&mut self, dot: [f64; 2]) {
+        self.finish_insertion();
+        match self.diagram().cavity(dot) {
+            Some(cavity) => self.insertion = Some(Insertion::new(dot, cavity, self.insertion_delay)),
+            None => self.insert(dot)
+        }
+    }
+
+    fn finish_insertion(&mut self) {
+        if let Some(insertion) = self.insertion.take() {
+            self.insert(insertion.dot);
+        }
+    }
+
+    /// Places a dot for the player whose turn it is and announces the score.
+    fn play(&mut self, dot: [f64; 2]) {
+        let Some(game) = self.game.as_ref() else { return };
+        if game.is_over() {
+            return;
+        }
+        let player = game.current_player();
+        if !self.diagram_mut().add_team_dot(dot, player) {
+            return;
+        }
+        let areas = self.diagram().team_areas();
+        let Some(game) = self.game.as_mut() else { return };
+        game.record_move();
+        self.active_team = Some(game.current_player());
+
+        let window = DEFAULT_WINDOW_WIDTH as f64 * DEFAULT_WINDOW_HEIGHT as f64;
+        let scores = (0..game.players)
+            .map(|p| format!("player {} {:.1}%", p + 1, areas.get(p).unwrap_or(&0.0) / window * 100.0))
+            .collect::<Vec<_>>();
+        println!("Player {} moved, area claimed: {}", player + 1, scores.join(", "));
+        if game.is_over() {
+            match game.leader(&areas) {
+                Some(winner) => println!("Game over, player {} wins", winner + 1),
+                None => println!("Game over, it's a tie")
+            }
+        }
+    }
+
+    fn publish(&mut self) {
+        if (self.observers.is_empty() && self.document_observers.is_empty()) || self.diagram().revision() == self.published_revision {
+            return;
+        }
+        self.published_revision = self.diagram().revision();
+        if !self.observers.is_empty() {
+            let state = state_message(self.diagram());
+            self.observers.retain(|observer| observer.send(state.clone()).is_ok());
+        }
+        if !self.document_observers.is_empty() {
+            let documents = Documents::new(self.diagram(), &self.export_options());
+            self.document_observers.retain(|observer| observer.send(documents.clone()).is_ok());
+        }
+    }
+
+    fn apply(&mut self, command: Command) {
+        match command {
+            Command::AddPoint(dot) => { self.diagram_mut().add_dot(dot); },
+            Command::AddColoredPoint(dot, color) => { self.diagram_mut().add_colored_dot(dot, color); },
+            Command::MovePoint(index, dot) => { self.diagram_mut().move_dot(index, dot); },
+            Command::RemovePoint(index) => { self.diagram_mut().remove_dot(index); },
+            Command::Clear => { self.diagram_mut().clear(); self.trail.clear(); },
+            Command::Jitter(amount) => { self.diagram_mut().jitter(amount); },
+            Command::Relax(steps) => {
+                for _ in 0..steps {
+                    self.relax_step();
+                }
+            }
+            Command::SetRelaxRate(rate) => { self.relax_rate = rate.max(0.0); },
+        }
+    }
+
+    fn relax_continuously(&mut self, elapsed: f64) {
+        if self.relax_rate <= 0.0 {
+            self.relax_due = 0.0;
+            return;
+        }
+        self.relax_due += elapsed * self.relax_rate;
+        // Don't try to catch up after a stall, one step per event is plenty
+        if self.relax_due >= 1.0 {
+            self.relax_due = 0.0;
+            self.relax_step();
+        }
+    }
+
+    fn relax_step(&mut self) {
+        let (range, attractor) = (self.repulsion_range, self.attract.then_some(self.cursor));
+        match self.relaxation {
+            Relaxation::Lloyd => self.diagram_mut().relax(),
+            Relaxation::Repulsion => self.diagram_mut().repel(range, attractor),
+            Relaxation::EqualArea => self.diagram_mut().relax_equal_area(),
+        }
+    }
+
+    /// Puts the dots of the bottom layer where the morph has them now, if
+    /// it moved on since they last were. Edits made in between stay until
+    /// it does.
+    fn show_morph(&mut self) {
+        let Some(morph) = self.morph.as_mut() else { return };
+        if morph.shown == Some(morph.t) {
+            return;
+        }
+        morph.shown = Some(morph.t);
+        let (mut dots, mut colors) = (Vec::new(), Vec::new());
+        let mut grid = Grid::new(&[]);
+        for (dot, color) in morph.dots().into_iter().zip(&morph.colors) {
+            // Sites that split off or merge in start out or end up on top of each other
+            if grid.within(&dots, dot, self.duplicates.distance).is_empty() {
+                grid.insert(dots.len(), dot);
+                dots.push(dot);
+                colors.push(*color);
+            }
+        }
+        let diagram = &mut self.layers[0].diagram;
+        if diagram.dots != dots {
+            let teams = vec![None; dots.len()];
+            diagram.restore(dots, colors, teams);
+        }
+    }
+
+    /// Lets the colors of the active layer drift with noise, or puts back
+    /// the ones they had before.
+    fn toggle_noise(&mut self) {
+        match self.noise.take() {
+            Some(noise) => {
+                if let Some(layer) = self.layers.get_mut(noise.layer) {
+                    let colors = noise.restored(&layer.diagram);
+                    layer.diagram.set_colors(colors);
+                }
+                println!("Noise colors off");
+            }
+            None => {
+                self.noise = Some(NoiseColors::new(self.active, self.diagram(), self.noise_speed));
+                println!("Noise colors on");
+            }
+        }
+    }
+
+    /// Switches to the next of `documents`, or back to exporting the whole
+    /// window after the last.
+    fn next_document(&mut self) {
+        let next = match self.document {
+            None => 0,
+            Some(document) => self.documents.iter().position(|&d| d == document).map_or(0, |i| i + 1),
+        };
+        self.document = self.documents.get(next).copied();
+        match self.document {
+            Some(document) => println!("Exporting the {} document in the middle of the window", document.name()),
+            None => println!("Exporting the whole window"),
+        }
+    }
+
+    fn step_treemap(&mut self, elapsed: f64) {
+        let Some(treemap) = self.treemap.as_mut().filter(|t| !t.done) else { return };
+        self.treemap_due += elapsed * self.treemap_rate;
+        while self.treemap_due >= 1.0 && !treemap.done {
+            self.treemap_due -= 1.0;
+            treemap.step();
+        }
+        if treemap.done {
+            println!("Treemap done after {} steps, {:.1}% of the area off target", treemap.steps, treemap.error() * 100.0);
+        }
+    }
+
+    /// Lays the growth, the sweep and the tour out again when the dots
+    /// changed.
+    fn follow_dots(&mut self) {
+        let diagram = &self.layers[self.active].diagram;
+        let revision = diagram.revision();
+        if let Some(growth) = self.growth.as_mut().filter(|g| g.revision != revision) {
+            growth.lay_out(&diagram.dots, revision);
+        }
+        if let Some(sweep) = self.sweep.as_mut().filter(|s| s.revision != revision) {
+            sweep.lay_out(&diagram.dots, revision);
+        }
+        if let Some(tour) = self.tour.as_mut().filter(|t| t.revision != revision) {
+            *tour = Tour::new(diagram);
+        }
+    }
+
+    /// Adds Steiner points to the active layer until its triangles inside
+    /// the boundary have no angle below the minimum. Dots outside it go.
+    fn refine(&mut self) {
+        let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        let window = vec![[0.0, 0.0], [width, 0.0], [width, height], [0.0, height]];
+        let boundary = self.boundary.as_ref().unwrap_or(&window);
+        let refinement = refine(&self.diagram().dots, boundary, self.min_angle, MAX_REFINED_DOTS);
+        self.history.remember(&self.layers, self.active);
+        self.diagram_mut().replace_dots(refinement.dots);
+        println!("Refined with {} Steiner points to {} dots, smallest angle {:.1} degrees{}", refinement.added,
+            self.diagram().dots.len(), refinement.smallest, if refinement.complete { "" } else { ", stopped at the dot limit" });
+    }
+
+    /// Scatters a quarter more or fewer points over the photo.
+    fn change_lowpoly_points(&mut self, more: bool) {
+        let Some(lowpoly) = self.lowpoly.as_mut() else { return };
+        let count = if more { lowpoly.count + lowpoly.count.div_ceil(4) } else { lowpoly.count * 4 / 5 };
+        lowpoly.set_count(count.max(1));
+        println!("Low-poly with {} points, {} triangles", lowpoly.count, lowpoly.triangles.len());
+    }
+
+    /// Puts the low-poly triangles on the bottom layer.
+    fn show_lowpoly(&mut self) {
+        let Some(lowpoly) = self.lowpoly.as_ref() else { return };
+        let diagram = &mut self.layers[0].diagram;
+        if diagram.polygons != lowpoly.triangles {
+            diagram.restore_with_cells(lowpoly.middles(), lowpoly.colors.clone(), lowpoly.triangles.clone());
+        }
+    }
+
+    /// Puts the treemap's sites and power cells on the bottom layer.
+    fn show_treemap(&mut self) {
+        let Some(treemap) = self.treemap.as_ref() else { return };
+        let diagram = &mut self.layers[0].diagram;
+        if diagram.dots != treemap.sites || diagram.polygons != treemap.cells {
+            // Keep colors changed with `C`
+            let colors = if diagram.colors.len() == treemap.sites.len() { diagram.colors.clone() } else { treemap.colors.clone() };
+            diagram.restore_with_cells(treemap.sites.clone(), colors, treemap.cells.clone());
+        }
+    }
+
+    fn control_sweep(&mut self, key: Key) {
+        let Some(sweep) = self.sweep.as_mut() else { return };
+        match key {
+            Key::Space => sweep.toggle(),
+            Key::Left => sweep.step(false),
+            Key::Right => sweep.step(true),
+            _ => ()
+        }
+    }
+
+    fn control_morph(&mut self, key: Key) {
+        let Some(morph) = self.morph.as_mut() else { return };
+        match key {
+            Key::Space => morph.toggle(),
+            Key::Left => morph.scrub(-SCRUB_STEP),
+            Key::Right => morph.scrub(SCRUB_STEP),
+            _ => ()
+        }
+    }
+
+    /// Whether the command palette is open, so `Escape` closes it rather
+    /// than the window.
+    pub fn command_palette_open(&self) -> bool {
+        self.command_palette.is_some()
+    }
+
+    /// Whether the help is shown, so `Escape` hides it rather than closing
+    /// the window.
+    pub fn help_open(&self) -> bool {
+        self.help.is_some()
+    }
+
+    /// Hands typing, `Backspace`, `Up`, `Down`, `Enter` and `Escape` to the
+    /// open command palette. Returns whether `e` was a key or text meant for
+    /// it, which the rest of the app doesn't see then. Modifiers still are
+    /// for the app to track.
+    fn type_in_palette(&mut self, e: &Event) -> bool {
+        let Some(palette) = self.command_palette.as_mut() else { return false };
+        let modifier = |key: Key| matches!(key, Key::LCtrl | Key::RCtrl | Key::LShift | Key::RShift | Key::LAlt | Key::RAlt);
+        if let Some(text) = e.text_args() {
+            palette.type_text(&text);
+            return true;
+        }
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            match key {
+                Key::Backspace => palette.backspace(),
+                Key::Up | Key::Down => palette.step(key == Key::Down),
+                Key::Escape => self.command_palette = None,
+                _ => ()
+            }
+            return !modifier(key);
+        }
+        if let Some(Button::Keyboard(key)) = e.release_args() {
+            if key == Key::Return {
+                if let Some(action) = palette.picked() {
+                    self.command_palette = None;
+                    self.run_action(action);
+                }
+            }
+            return !modifier(key);
+        }
+        false
+    }
+
+    /// Does what the action does, as if its key was pressed.
+    fn run_action(&mut self, action: &Action) {
+        let held = (self.shift_held, self.ctrl_held);
+        match action.run {
+            Run::Key(key) => { (self.shift_held, self.ctrl_held) = (false, false); self.key(key); },
+            Run::Shift(key) => { (self.shift_held, self.ctrl_held) = (true, false); self.key(key); },
+            Run::Ctrl(key) => { (self.shift_held, self.ctrl_held) = (false, true); self.key(key); },
+            Run::Command(command) => { self.apply(command); },
+        }
+        (self.shift_held, self.ctrl_held) = held;
+    }
+
+    fn key(&mut self, key: Key) {
+        match key {
+            Key::N if self.shift_held => { export_bump(&self.bump_path, self.diagram(), &self.export_options(), &self.bump); },
+            Key::N if self.ctrl_held => { self.toggle_noise(); },
+            Key::N => { self.new_game(); },
+            Key::Space | Key::Left | Key::Right if self.sweep.is_some() => { self.control_sweep(key); },
+            Key::Space | Key::Left | Key::Right if self.morph.is_some() => { self.control_morph(key); },
+            Key::Up | Key::Down if self.lowpoly.is_some() => { self.change_lowpoly_points(key == Key::Up); },
+            Key::Up | Key::Down if self.proximity == Some(ProximityGraph::BetaSkeleton) => {
+                let step = if key == Key::Up { BETA_STEP } else { -BETA_STEP };
+                self.beta = (self.beta + step).clamp(GABRIEL_BETA, MAX_BETA);
+            },
+            Key::Up | Key::Down if self.bowyer_watson => {
+                self.insertion_delay *= if key == Key::Up { 1.5 } else { 1.0 / 1.5 };
+                println!("{:.2} seconds between insertion steps", self.insertion_delay);
+            },
+            Key::R if self.shift_held => { self.refine(); },
+            Key::R => { self.generate(); },
+            Key::U if self.shift_held => {
+                let revision = self.diagram().revision();
+                match self.tour.as_ref().filter(|t| t.revision == revision) {
+                    Some(tour) => export_tour(&self.tour_path, self.diagram(), &self.export_options(), tour),
+                    None => export_tour(&self.tour_path, self.diagram(), &self.export_options(), &Tour::new(self.diagram())),
+                }
+            },
+            Key::U => {
+                let (count, size, spread) = self.clusters;
+                let mut clusters = gaussian_clusters(count, size, spread);
+                for cluster in &mut clusters {
+                    inset(cluster, self.margin);
+                }
+                self.diagram_mut().set_clusters(clusters);
+            },
+            Key::D if self.shift_held => { self.grid_overlay = !self.grid_overlay; },
+            Key::D if self.ctrl_held => { self.next_document(); },
+            Key::D => { self.find_clusters(); },
+            Key::J if self.shift_held => { export_centroids(&self.centroids_path, self.diagram(), &self.export_options()); },
+            Key::J => { self.toggle_kmeans(); },
+            Key::L if self.shift_held => {
+                self.tour = match self.tour {
+                    Some(_) => None,
+                    None => Some(Tour::new(self.diagram())),
+                };
+                println!("Tour {}", if self.tour.is_some() { "on" } else { "off" });
+            },
+            Key::L => { self.lines_only = ! self.lines_only; },
+            Key::O if self.shift_held => {
+                (self.onion, self.onion_cells) = match (self.onion, self.onion_cells) {
+                    (false, _) => (true, false),
+                    (true, false) => (true, true),
+                    (true, true) => (false, false),
+                };
+                match (self.onion, self.onion_cells) {
+                    (false, _) => println!("Convex layers off"),
+                    (true, false) => println!("Convex layers on"),
+                    (true, true) => println!("Convex layers on, cells colored by layer"),
+                }
+            },
+            Key::O => { self.merged = ! self.merged; },
+            Key::Q if self.shift_held => {
+                let hyperbolic = !self.diagram().hyperbolic;
+                self.diagram_mut().set_hyperbolic(hyperbolic);
+                println!("{} plane", if hyperbolic { "Hyperbolic" } else { "Flat" });
+            },
+            Key::Q => {
+                let power = !self.diagram().power;
+                self.diagram_mut().set_power(power);
+            },
+            Key::I if self.shift_held => {
+                self.quality = match self.quality {
+                    None => Some(Quality::MinAngle),
+                    Some(Quality::MinAngle) => Some(Quality::AspectRatio),
+                    Some(Quality::AspectRatio) => None,
+                };
+                println!("Triangle quality {}", self.quality.map_or("off", Quality::name));
+            },
+            Key::I => { self.stats = ! self.stats; },
+            Key::W if self.shift_held => {
+                self.split = match self.split.as_ref() {
+                    None => Some(Split::new(Comparison::Wireframe)),
+                    Some(split) => split.comparison.next().map(Split::new),
+                };
+                match self.split.as_mut() {
+                    Some(split) => {
+                        split.update(&self.layers[self.active].diagram);
+                        let [left, right] = split.comparison.labels(&self.layers[self.active].diagram, self.lines_only);
+                        println!("Side by side: {} and {}", left, right);
+                    }
+                    None => println!("Side by side off"),
+                }
+            },
+            Key::W => {
+                self.history.remember(&self.layers, self.active);
+                let (merge, keep_outside) = (self.collapse_merge, self.collapse_keep_outside);
+                self.diagram_mut().collapse_to_vertices(merge, keep_outside);
+                println!("Collapsed to {} dots", self.diagram().dots.len());
+            },
+            Key::Z if self.ctrl_held => { self.undo(); },
+            Key::Z if self.shift_held => {
+                self.history.remember(&self.layers, self.active);
+                if self.diagram_mut().fit().is_some() {
+                    println!("Fitted {} dots into view", self.diagram().dots.len());
+                }
+            },
+            Key::Z => {
+                self.keyboard_cursor = match self.keyboard_cursor {
+                    Some(_) => None,
+                    None => Some(KeyboardCursor::default()),
+                };
+                println!("Keyboard cursor {}", if self.keyboard_cursor.is_some() { "on" } else { "off" });
+            },
+            Key::Delete if self.keyboard_cursor.is_some() => { self.remove_nearest(); },
+            Key::C if self.ctrl_held => { copy_diagram(self.diagram(), &self.export_options()); },
+            Key::V if self.ctrl_held => { self.paste_dots(); },
+            Key::C if self.shift_held => { self.enclosing_circle = !self.enclosing_circle; },
+            Key::A if self.shift_held => { self.next_theme(); },
+            Key::C => { self.recolor(); },
+            Key::S if self.shift_held => {
+                self.proximity = match self.proximity {
+                    None => Some(ProximityGraph::BetaSkeleton),
+                    Some(ProximityGraph::BetaSkeleton) => Some(ProximityGraph::Urquhart),
+                    Some(ProximityGraph::Urquhart) => None,
+                };
+                println!("Proximity graph {}", self.proximity.map_or("off", ProximityGraph::name));
+            },
+            Key::S => { self.save_dots(); },
+            Key::E if self.shift_held => { export_cut(&self.cut_path, self.diagram(), &self.export_options(), self.kerf); },
+            Key::E => { export_diagram(&self.export_path, self.diagram(), &self.export_options()); },
+            Key::F if self.shift_held => {
+                self.sweep = match self.sweep {
+                    Some(_) => None,
+                    None => Some(Sweep::new(&self.diagram().dots, self.diagram().revision()))
+                };
+                println!("Fortune's algorithm {}", if self.sweep.is_some() { "on" } else { "off" });
+            },
+            Key::G if self.shift_held => {
+                self.growth = match self.growth {
+                    Some(_) => None,
+                    None => Some(Growth::new(&self.diagram().dots, self.diagram().revision(), self.growth_speed))
+                };
+                println!("Growth {}", if self.growth.is_some() { "on" } else { "off" });
+            },
+            Key::G => { export_timelapse(&self.timelapse_path, self.diagram(), &self.export_options()); },
+            Key::P if self.ctrl_held => { self.command_palette = Some(CommandPalette::default()); },
+            Key::F1 => {
+                let pages = help_keys().len().div_ceil(HELP_KEYS_PER_PAGE);
+                self.help = match self.help {
+                    None => Some(0),
+                    Some(page) => Some(page + 1).filter(|&p| p < pages),
+                };
+            },
+            Key::Escape => { self.help = None; },
+            Key::P if self.shift_held => {
+                self.pathfinding = match self.pathfinding {
+                    None => Some((Graph::Delaunay, Method::Dijkstra)),
+                    Some((Graph::Delaunay, Method::Dijkstra)) => Some((Graph::Delaunay, Method::AStar)),
+                    Some((Graph::Delaunay, Method::AStar)) => Some((Graph::Voronoi, Method::Dijkstra)),
+                    Some((Graph::Voronoi, Method::Dijkstra)) => Some((Graph::Voronoi, Method::AStar)),
+                    Some((Graph::Voronoi, Method::AStar)) => None,
+                };
+                match self.pathfinding {
+                    Some((graph, method)) => println!("Shortest paths by {} along {}", method.name(), graph.name()),
+                    None => println!("Shortest paths off")
+                }
+                self.search_route();
+            },
+            Key::P => { self.presentation = ! self.presentation; },
+            Key::T if self.shift_held => { export_tile(&self.tile_path, self.diagram(), &self.export_options(), self.tile_size); },
+            Key::T => { self.trail_mode = ! self.trail_mode; self.trail.clear(); },
+            Key::Tab => { self.next_layer(); },
+            Key::X if self.shift_held => { self.oriented_box = !self.oriented_box; },
+            Key::X => {
+                self.symmetry.mirror_x = !self.symmetry.mirror_x;
+                println!("Mirroring left to right {}", if self.symmetry.mirror_x { "on" } else { "off" });
+            },
+            Key::Y if self.shift_held => { export_edges(&self.edges_path, self.diagram(), &self.export_options()); },
+            Key::Y => {
+                self.symmetry.mirror_y = !self.symmetry.mirror_y;
+                println!("Mirroring top to bottom {}", if self.symmetry.mirror_y { "on" } else { "off" });
+            },
+            Key::K if self.shift_held => { self.run_script(); },
+            Key::K => {
+                self.symmetry.rotations = if self.symmetry.rotations > 1 { 1 } else { self.kaleidoscope };
+                match self.symmetry.rotations {
+                    1 => println!("Kaleidoscope off"),
+                    n => println!("Kaleidoscope on, {} copies around the center", n),
+                }
+            },
+            Key::H if self.shift_held => {
+                self.terrain = match self.terrain {
+                    None => {
+                        self.terrain_seed = random();
+                        Some(Terrain::Noise)
+                    },
+                    Some(Terrain::Noise) => Some(Terrain::Distance),
+                    Some(Terrain::Distance) => None,
+                };
+                println!("Terrain {}", self.terrain.map_or("off", Terrain::name));
+            },
+            Key::H => {
+                self.stamp.next_shape();
+                println!("The stamp is now a {}", self.stamp.shape.name());
+            },
+            Key::B if self.shift_held => {
+                self.bowyer_watson = !self.bowyer_watson;
+                if !self.bowyer_watson {
+                    self.finish_insertion();
+                }
+                println!("Bowyer-Watson insertion {}", if self.bowyer_watson { "on" } else { "off" });
+            },
+            Key::B => {
+                self.tool = self.tool.next();
+                println!("The mouse is now a {} tool", self.tool.name());
+            },
+            Key::F => { self.relax_rate = if self.relax_rate > 0.0 { 0.0 } else { CONTINUOUS_RELAX_RATE }; },
+            Key::M if self.shift_held => {
+                let heights = self.terrain.unwrap_or(Terrain::Noise).heights(self.diagram(), self.terrain_seed);
+                export_heightmap(&self.heightmap_path, self.diagram(), &self.export_options(), &heights);
+            },
+            Key::M => {
+                self.relaxation = match self.relaxation {
+                    Relaxation::Lloyd => Relaxation::Repulsion,
+                    Relaxation::Repulsion => Relaxation::EqualArea,
+                    Relaxation::EqualArea => Relaxation::Lloyd,
+                };
+                println!("Relaxing with {}", self.relaxation.name());
+            },
+            Key::A => {
+                self.attract = !self.attract;
+                println!("Cursor attraction {}", if self.attract { "on" } else { "off" });
+            },
+            Key::V if self.shift_held => { self.triangle_centers = !self.triangle_centers; },
+            Key::V => {
+                let layer = &mut self.layers[self.active];
+                layer.visible = ! layer.visible;
+            },
+            Key::D0 if self.game.is_none() => { self.active_team = None; },
+            Key::D1 | Key::D2 | Key::D3 | Key::D4 | Key::D5 | Key::D6 | Key::D7 | Key::D8 | Key::D9 if self.game.is_none() => {
+                self.active_team = Some(key as usize - Key::D1 as usize);
+            },
+            _ => ()
+        }
+    }
+
+    /// Colors the cells by the cluster DBSCAN puts their dot in.
+    fn find_clusters(&mut self) {
+        let (eps, min_points) = self.dbscan;
+        let (labels, count) = dbscan(&self.diagram().dots, eps, min_points);
+        let noise = labels.iter().filter(|label| label.is_none()).count();
+        self.diagram_mut().paint_groups(&labels, count);
+        println!("Found {} clusters and {} noise dots", count, noise);
+    }
+
+    /// Starts k-means from fresh centroids, or stops it and brings back
+    /// the usual colors.
+    fn toggle_kmeans(&mut self) {
+        if self.kmeans.take().is_some() {
+            self.diagram_mut().recolor();
+            return;
+        }
+        self.kmeans = Some(KMeans::new(&self.diagram().dots, self.kmeans_k));
+        // Show the first assignment right away
+        self.kmeans_due = 1.0;
+    }
+
+    fn kmeans_continuously(&mut self, elapsed: f64) {
+        if self.kmeans.is_none() {
+            return;
+        }
+        self.kmeans_due += elapsed * self.kmeans_rate;
+        if self.kmeans_due < 1.0 {
+            return;
+        }
+        self.kmeans_due = 0.0;
+        let dots = self.diagram().dots.clone();
+        let Some(kmeans) = self.kmeans.as_mut() else { return };
+        let was_converged = kmeans.converged;
+        let changes = kmeans.step(&dots).into_iter().map(|i| (i, kmeans.labels[i])).collect::<Vec<_>>();
+        let (count, steps, converged) = (kmeans.centroids.len(), kmeans.steps, kmeans.converged);
+        if !changes.is_empty() {
+            self.diagram_mut().repaint_groups(&changes, count);
+        }
+        if converged && !was_converged {
+            println!("k-means converged after {} steps", steps);
+        }
+    }
+
+    fn undo(&mut self) {
+        if self.history.undo(&mut self.layers).is_none() {
+            println!("Nothing to undo");
+        }
+    }
+
+    /// Clears the active layer, starting over if a game is on.
+    fn new_game(&mut self) {
+        self.diagram_mut().clear();
+        self.trail.clear();
+        self.insertion = None;
+        self.path_start = None;
+        self.path_goal = None;
+        self.route = None;
+        if let Some(globe) = self.globe.as_mut() {
+            globe.clear();
+        }
+        if self.active == 0 {
+            self.morph = None;
+            self.treemap = None;
+            self.lowpoly = None;
+        }
+        if let Some(game) = self.game.as_mut() {
+            game.reset();
+            self.active_team = Some(game.current_player());
+        }
+    }
+
+    /// Activates the next layer. Moving past a top layer that has dots
+    /// creates a new empty one, otherwise it wraps around to the bottom.
+    fn next_layer(&mut self) {
+        self.trail.clear();
+        if self.active + 1 == self.layers.len() && !self.diagram().dots.is_empty() {
+            let mut layer = Layer::new(self.layers.len(), self.duplicates);
+            layer.diagram.palette = theme_palette(self.theme(), self.layers.len());
+            self.layers.push(layer);
+        }
+        self.active = (self.active + 1) % self.layers.len();
+        println!("Layer {} of {} is active", self.active + 1, self.layers.len());
+    }
+
+    /// Prints the dots of the active layer for `-j`, in the coordinates
+    /// they were loaded in with `--export_coords data`.
+    fn save_dots(&self) {
+        let diagram = self.diagram();
+        let data = self.export_options().data(diagram);
+        let dots = diagram.dots.iter().map(|&d| data.map_or(d, |fit| fit.invert(d))).collect::<Vec<_>>();
+        save_current_dots(&dots, &diagram.metadata);
+    }
+
+    /// Loads a dropped points file, replacing the current dots, or adding to
+    /// them while Shift is held.
+    fn drop_file(&mut self, path: &Path) {
+        if Stream::wanted(path) {
+            self.stream = Some(Stream::start(path.to_path_buf(), self.active, !self.shift_held, self.coords));
+            self.trail.clear();
+            return;
+        }
+        match read_points(path) {
+            Ok(mut points) => {
+                let coords = fit_points(&mut points, self.coords);
+                println!("Loaded {} dots from {}", points.dots.len(), path.display());
+                if self.shift_held {
+                    self.diagram_mut().merge_sites(&points.dots, &points.metadata);
+                } else {
+                    self.diagram_mut().set_sites(points.dots, points.metadata);
+                    self.diagram_mut().coords = coords;
+                }
+                self.trail.clear();
+            }
+            Err(err) => println!("Could not load dots from {}: {}", path.display(), err)
+        }
+    }
+
+    /// Adds the next batch of dots of the file being streamed to its layer,
+    /// with `wait` even if the file is still being read.
+    fn stream_points(&mut self, wait: bool) {
+        let Some(stream) = self.stream.as_mut() else { return };
+        let first = stream.loaded == 0;
+        match stream.next_batch(wait) {
+            None => (),
+            Some(Ok(batch)) => {
+                let diagram = &mut self.layers[stream.layer].diagram;
+                if first && stream.replace {
+                    diagram.set_sites(batch.dots, batch.metadata);
+                    diagram.coords = stream.coords;
+                } else {
+                    diagram.merge_sites(&batch.dots, &batch.metadata);
+                }
+                if stream.done() {
+                    println!("Loaded {} dots from {}", stream.loaded, stream.path.display());
+                    self.stream = None;
+                }
+            }
+            Some(Err(err)) => {
+                println!("Could not load dots from {}: {}", stream.path.display(), err);
+                self.stream = None;
+            }
+        }
+    }
+
+    /// Loads the rest of the file being streamed right away, so scenarios
+    /// come out the same however fast it's read.
+    pub fn finish_loading(&mut self) {
+        while self.stream.is_some() {
+            self.stream_points(true);
+        }
+    }
+
+    /// Adds the dots on the clipboard to the active layer, see
+    /// `points::parse_points`.
+    fn paste_dots(&mut self) {
+        match clipboard::paste().and_then(|text| parse_points(&text)) {
+            Ok(points) => {
+                self.history.remember(&self.layers, self.active);
+                let before = self.diagram().dots.len();
+                self.diagram_mut().merge_sites(&points.dots, &points.metadata);
+                println!("Pasted {} dots, {} of them new", points.dots.len(), self.diagram().dots.len() - before);
+            }
+            Err(err) => println!("Could not paste dots: {}", err)
+        }
+    }
+
+    fn reload_if_changed(&mut self, now: Instant) {
+        let Some(watcher) = self.watcher.as_mut() else { return };
+        if !watcher.poll(now) {
+            return;
+        }
+        match read_points(&watcher.path) {
+            Ok(mut points) => {
+                // Fitted like the first time, so the dots that stayed keep
+                // their places and colors
+                let diagram = &mut self.layers[self.active].diagram;
+                let coords = match diagram.coords {
+                    Some(fit) if self.coords != Coords::Window => {
+                        points.apply(&fit);
+                        Some(fit)
+                    }
+                    _ => fit_points(&mut points, self.coords),
+                };
+                diagram.replace_sites(points.dots, points.metadata);
+                diagram.coords = coords;
+            }
+            // Most likely caught the writer halfway, the next change will fix it
+            Err(err) => println!("Could not reload {}: {}", watcher.path.display(), err)
+        }
+    }
+
+    /// Replaces the dots of the active layer with ones from the next
+    /// generator.
+    fn generate(&mut self) {
+        let count = self.registry.generators.len();
+        let generator = &mut self.registry.generators[self.generator];
+        let mut dots = generator.generate(self.random_count);
+        inset(&mut dots, self.margin);
+        if count > 1 {
+            println!("{} dots, {}", dots.len(), generator.name());
+        }
+        self.generator = (self.generator + 1) % count;
+        self.diagram_mut().set_dots(dots);
+    }
+
+    /// Paints the cells of the active layer with the next colorer.
+    fn recolor(&mut self) {
+        let count = self.registry.colorers.len();
+        let colorer = &mut self.registry.colorers[self.colorer];
+        let colors = colorer.colors(&self.layers[self.active].diagram);
+        if count > 1 {
+            println!("Colored by {}", colorer.name());
+        }
+        self.colorer = (self.colorer + 1) % count;
+        self.diagram_mut().set_colors(colors);
+    }
+
+    /// Starts the script on the dots of the active layer, unless it's
+    /// still running.
+    fn run_script(&mut self) {
+        let dots = self.diagram().dots.clone();
+        match self.script.as_mut() {
+            None => println!("No script to run, see --script"),
+            Some(script) if script.running() => println!("{} is still running", script.path.display()),
+            Some(script) => script.start(&dots),
+        }
+    }
+
+    /// Makes the edits of the script once it's done.
+    fn finish_script(&mut self) {
+        let Some(script) = self.script.as_mut() else { return };
+        let Some(result) = script.finished() else { return };
+        let path = script.path.clone();
+        match result {
+            Ok(edits) => {
+                self.history.remember(&self.layers, self.active);
+                let skipped = script::apply(&edits, self.diagram_mut());
+                println!("Ran {}: {} edits, {} of them skipped, {} dots now", path.display(), edits.len(), skipped, self.diagram().dots.len());
+            },
+            Err(err) => println!("Could not run {}: {}", path.display(), err)
+        }
+    }
+
+    fn export_options(&self) -> ExportOptions {
+        ExportOptions {
+            relief: self.relief,
+            lines_only: self.lines_only,
+            merge_tolerance: self.merged.then_some(self.merge_tolerance),
+            scale: self.export_scale,
+            smoothing: self.smoothing,
+            dots: self.lowpoly.is_none() && self.dot_style().is_some(),
+            dot_style: self.dot_style().unwrap_or_default(),
+            background: self.theme().background,
+            stroke: self.theme().stroke,
+            boundary: self.boundary.clone(),
+            data_coords: self.data_coords,
+            document: self.document,
+        }
+    }
+
+    pub fn draw<G: Graphics>(&self, c: &Context, g: &mut G, now: Instant) {
+        clear(self.theme().background, g);
+        if let Some(split) = self.split.as_ref() {
+            self.draw_split(split, c, g);
+            self.draw_help(c, g);
+            self.draw_command_palette(c, g);
+            return;
+        }
+        if let Some(globe) = self.globe.as_ref() {
+            globe.draw(self.lines_only, c, g);
+            return;
+        }
+
+        for (index, layer) in self.layers.iter().enumerate() {
+            if !layer.visible {
+                continue;
+            }
+            let with_trail = (index == self.active && !self.trail.is_empty())
+                .then(|| self.trail.overlay(&layer.diagram, now));
+            let shown = with_trail.as_ref().unwrap_or(layer.shown());
+            if let Some(sweep) = self.sweep.as_ref().filter(|_| index == self.active) {
+                sweep.draw(c, g);
+                continue;
+            }
+            if let Some(quality) = self.quality.filter(|_| index == self.active) {
+                let corners = self.meshed(&layer.diagram);
+                let triangles = corners.iter().map(|t| t.iter().map(|p| (p[0], p[1])).collect()).collect::<Vec<Vec<Point>>>();
+                let colors = corners.iter().map(|&t| quality_color(quality.goodness(quality.measure(t)))).collect::<Vec<_>>();
+                draw_cells(&triangles, &colors, layer.opacity, c, g);
+                for triangle in &triangles {
+                    draw_outline(triangle, c, g, [0.2, 0.2, 0.2, layer.opacity], 0.5);
+                }
+                continue;
+            }
+            if let Some(terrain) = self.terrain.filter(|_| index == self.active) {
+                let heights = terrain.heights(&layer.diagram, self.terrain_seed);
+                let (shapes, colors): (Vec<_>, Vec<_>) = columns(&layer.diagram, &heights).into_iter().unzip();
+                draw_cells(&shapes, &colors, layer.opacity, c, g);
+                continue;
+            }
+            if self.onion_cells && index == self.active {
+                let layers = convex_layers(&layer.diagram.dots);
+                let colors = layers.depths.iter().map(|&depth| onion_color(depth, layers.hulls.len())).collect::<Vec<_>>();
+                draw_cells(&layer.diagram.visible, &colors, layer.opacity, c, g);
+                continue;
+            }
+            if let Some(growth) = self.growth.as_ref().filter(|_| index == self.active) {
+                let (regions, colors) = growth.regions(&layer.diagram.colors);
+                draw_cells(&regions, &colors, layer.opacity, c, g);
+                continue;
+            }
+            if self.merged {
+                let regions = merge_regions(shown, self.merge_tolerance);
+                if !self.lines_only {
+                    let (cells, colors): (Vec<_>, Vec<_>) = regions.iter()
+                        .flat_map(|region| region.cells.iter().map(|&cell| (shown.polygons[cell].clone(), region.color)))
+                        .unzip();
+                    draw_cells(&cells, &colors, layer.opacity, c, g);
+                }
+                for region in &regions {
+                    let (color, radius) = if self.lines_only { ([0.0, 0.0, 1.0, 1.0], 2.0) } else { ([0.2, 0.2, 0.2, layer.opacity], 1.0) };
+                    for outline in &region.boundary {
+                        draw_outline(outline, c, g, color, radius);
+                    }
+                }
+                continue;
+            }
+            let (polygons, visible) = shown.smoothed(self.smoothing);
+            if self.lines_only {
+                draw_wireframe(&polygons, c, g);
+            } else {
+                match layer.mesh(self.smoothing).filter(|_| with_trail.is_none()) {
+                    Some(mesh) => mesh.draw(c, g),
+                    None => draw_cells(&visible, &shown.colors, layer.opacity, c, g)
+                }
+                if let Some(([r, g_, b, a], width)) = self.theme().stroke {
+                    for poly in polygons.iter() {
+                        draw_outline(poly, c, g, [r, g_, b, a * layer.opacity], width / 2.0);
+                    }
+                }
+            }
+        }
+        // Dots of the active layer go on top of everything, so it's clear where edits go
+        let dot_style = self.dot_style().unwrap_or_default();
+        let inactive_style = DotStyle { fill: [0.5, 0.5, 0.5, 1.0], outline: None, ..dot_style };
+        // Besides when hidden with `--dot_shape none`: the middles of the
+        // low-poly triangles are no sites anyone placed, and the dots of the
+        // terrain would float below its raised cells
+        let hides_dots = |index: usize| self.dot_style().is_none()
+            || (index == 0 && self.lowpoly.is_some()) || (index == self.active && self.terrain.is_some());
+        for (index, layer) in self.layers.iter().enumerate() {
+            if layer.visible && index != self.active && !hides_dots(index) {
+                draw_styled_dots(&layer.shown().dots, &inactive_style, DOT_RESOLUTION, c, g);
+            }
+        }
+        if self.layers[self.active].visible {
+            let diagram = self.diagram();
+            if !hides_dots(self.active) {
+                draw_styled_dots(&self.layers[self.active].shown().dots, &dot_style, DOT_RESOLUTION, c, g);
+            }
+            // The rim of the hyperbolic plane, infinitely far from every dot
+            if diagram.hyperbolic {
+                let (center, radius) = hyperbolic::disk();
+                draw_circle(&center, radius, c, g, [0.0, 0.0, 0.0, 0.5]);
+            }
+            if self.bowyer_watson {
+                for t in diagram.triangles() {
+                    let [a, b, c_] = [t.0, t.1, t.2].map(|i| (diagram.dots[i][0], diagram.dots[i][1]));
+                    draw_outline(&[a, b, c_], c, g, [0.4, 0.4, 0.4, 0.8], 0.5);
+                }
+                if let Some(insertion) = self.insertion.as_ref() {
+                    insertion.draw(c, g);
+                }
+            }
+            if let Some(proximity) = self.proximity {
+                for (a, b) in proximity.edges(diagram, self.beta) {
+                    let (p, q) = (diagram.dots[a], diagram.dots[b]);
+                    graphics::line(SKELETON_COLOR, 1.0, [p[0], p[1], q[0], q[1]], c.transform, g);
+                }
+            }
+            if self.onion {
+                let hulls = convex_layers(&diagram.dots).hulls;
+                for (depth, hull) in hulls.iter().enumerate() {
+                    let [r, g_, b, a] = onion_color(depth, hulls.len());
+                    let corners = hull.iter().map(|p| (p[0], p[1])).collect::<Vec<_>>();
+                    draw_outline(&corners, c, g, [r * ONION_SHADE, g_ * ONION_SHADE, b * ONION_SHADE, a], 1.5);
+                }
+            }
+            if let Some(tour) = self.tour.as_ref() {
+                for (&a, &b) in tour.order.iter().zip(tour.order.iter().cycle().skip(1)) {
+                    let (p, q) = (diagram.dots[a], diagram.dots[b]);
+                    graphics::line(TOUR_COLOR, 1.0, [p[0], p[1], q[0], q[1]], c.transform, g);
+                }
+            }
+            if self.grid_overlay {
+                self.draw_grid(c, g);
+            }
+            if self.triangle_centers {
+                self.draw_triangle_centers(c, g);
+            }
+            if let Some(circle) = enclosing_circle(&diagram.dots).filter(|_| self.enclosing_circle) {
+                draw_circle(&circle.center, circle.radius, c, g, ENCLOSING_CIRCLE_COLOR);
+            }
+            if let Some(found) = oriented_box(&diagram.dots).filter(|_| self.oriented_box) {
+                let corners = found.corners().map(|p| (p[0], p[1]));
+                draw_outline(&corners, c, g, ORIENTED_BOX_COLOR, 1.0);
+            }
+            if let Some(route) = self.route.as_ref() {
+                route.draw(c, g);
+            } else if let Some(start) = self.path_start.filter(|_| self.pathfinding.is_some()).and_then(|i| diagram.dots.get(i)) {
+                draw_marker(start, c, g, route::START);
+            }
+            if let Some(boundary) = self.boundary.as_ref() {
+                let outline = boundary.iter().map(|p| (p[0], p[1])).collect::<Vec<Point>>();
+                draw_outline(&outline, c, g, [0.0, 0.0, 0.0, 0.8], 1.5);
+            }
+            // The circle of each weight, which its dot's cell grows to cover
+            if diagram.power && !diagram.hyperbolic {
+                for (d, &weight) in diagram.dots.iter().zip(&diagram.weights).filter(|(_, &w)| w > 0.0) {
+                    draw_circle(d, weight.sqrt(), c, g, [0.0, 0.0, 0.0, 0.5]);
+                }
+            }
+        }
+
+        // What's outside the document won't be exported
+        if let Some(document) = self.document {
+            let [[left, top], [right, bottom]] = document.frame();
+            let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+            for rect in [[0.0, 0.0, left, height], [right, 0.0, width - right, height], [left, 0.0, right - left, top], [left, bottom, right - left, height - bottom]] {
+                graphics::rectangle(LETTERBOX_COLOR, rect, c.transform, g);
+            }
+        }
+
+        if !self.presentation {
+            // The system cursor doesn't follow the stick or the arrow keys, so show where it is
+            if self.gamepad.used || self.keyboard_cursor.is_some() {
+                const ARM: f64 = 8.0;
+                let [x, y] = self.cursor;
+                for line in [[x - ARM, y, x + ARM, y], [x, y - ARM, x, y + ARM]] {
+                    graphics::line([0.0, 0.0, 0.0, 0.8], 1.0, line, c.transform, g);
+                }
+            }
+            let [x, y] = self.cursor;
+            let outline = if self.erasing() {
+                Some((self.eraser_radius, [0.8, 0.0, 0.0, 0.7]))
+            } else {
+                self.brushing().then_some((self.spray.radius, [0.0, 0.0, 0.0, 0.5]))
+            };
+            if let Some((r, color)) = outline {
+                draw_circle(&[x, y], r, c, g, color);
+            }
+            if self.stamping() && !self.erasing() && !self.alt_held {
+                for d in self.stamp.dots(self.cursor) {
+                    draw_ellipse(&d, c, g, [0.0, 0.0, 0.0, 0.3]);
+                }
+            }
+            if let Some(kmeans) = self.kmeans.as_ref() {
+                for (k, centroid) in kmeans.centroids.iter().enumerate() {
+                    draw_marker(centroid, c, g, group_marker_color(k, kmeans.centroids.len()));
+                }
+            }
+            self.draw_scoreboard(c, g);
+            self.draw_treemap_labels(c, g);
+            self.draw_equal_area_status(c, g);
+            self.draw_quality_status(c, g);
+            self.draw_terrain_status(c, g);
+            self.draw_morph_status(c, g);
+            self.draw_sweep_status(c, g);
+            self.draw_insertion_status(c, g);
+            self.draw_stream_status(c, g);
+            self.draw_route_status(c, g);
+            self.draw_proximity_status(c, g);
+            self.draw_grid_status(c, g);
+            self.draw_tour_status(c, g);
+            self.draw_centers_legend(c, g);
+            self.draw_stats(c, g);
+            self.draw_tooltip(c, g);
+        }
+        self.draw_help(c, g);
+        self.draw_command_palette(c, g);
+    }
+
+    /// The active layer on the left half as it is, and on the right half as
+    /// the comparison has it, with their labels above. Only the cells inside
+    /// the window are drawn, so neither half spills into the other.
+    fn draw_split<G: Graphics>(&self, split: &Split, c: &Context, g: &mut G) {
+        let diagram = self.diagram();
+        let labels = split.comparison.labels(diagram, self.lines_only);
+        let halves = [diagram, split.right(diagram)];
+        for (index, ((shown, corner), label)) in halves.into_iter().zip(compare::corners()).zip(labels).enumerate() {
+            let half = c.trans(corner[0], corner[1]).scale(compare::SCALE, compare::SCALE);
+            let (_, visible) = shown.smoothed(self.smoothing);
+            if self.lines_only != (index == 1 && split.comparison == Comparison::Wireframe) {
+                draw_wireframe(&visible, &half, g);
+            } else {
+                draw_cells(&visible, &shown.colors, 1.0, &half, g);
+                if let Some((color, width)) = self.theme().stroke {
+                    for cell in visible.iter() {
+                        draw_outline(cell, &half, g, color, width / 2.0);
+                    }
+                }
+            }
+            if let Some(style) = self.dot_style() {
+                draw_styled_dots(&shown.dots, &style, DOT_RESOLUTION, &half, g);
+            }
+            let lines = [(None, label)];
+            let width = panel_size(&lines)[0];
+            let middle = corner[0] + DEFAULT_WINDOW_WIDTH as f64 * compare::SCALE / 2.0;
+            draw_panel(&lines, [middle - width / 2.0, corner[1] - panel_size(&lines)[1] - 10.0], c, g);
+        }
+    }
+
+    /// A page of the keys on the left, and which modes are on at the
+    /// top right.
+    fn draw_help<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some(page) = self.help else { return };
+        let keys = help_keys();
+        let pages = keys.len().div_ceil(HELP_KEYS_PER_PAGE);
+        let more = if page + 1 < pages { "F1 for more" } else { "F1 or Escape closes" };
+        let mut lines = vec![(None, format!("Keys, page {} of {}, {}", page + 1, pages, more))];
+        lines.extend(keys.into_iter().skip(page * HELP_KEYS_PER_PAGE).take(HELP_KEYS_PER_PAGE).map(|line| (None, line)));
+        draw_panel(&lines, [10.0, 10.0], c, g);
+
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        let layer = &self.layers[self.active];
+        let mut modes = vec![
+            format!("Layer {} of {}{}", self.active + 1, self.layers.len(), if layer.visible { "" } else { ", hidden" }),
+            format!("{} dots", layer.diagram.dots.len()),
+            format!("Theme: {}", self.theme().name),
+            format!("Cells: {}{}", if layer.diagram.power { "power" } else { "Voronoi" }, if layer.diagram.hyperbolic { ", hyperbolic" } else { "" }),
+            format!("View: {}", if self.lines_only { "wireframe" } else { "polygons" }),
+            format!("Mouse: {} tool", self.tool.name()),
+            format!("Stamp: {}", self.stamp.shape.name()),
+            format!("Relaxing: {}, {}", self.relaxation.name(), if self.relax_rate > 0.0 { "running" } else { "stopped" }),
+            format!("Trail mode: {}", on_off(self.trail_mode)),
+            format!("Mirroring: {}", match (self.symmetry.mirror_x, self.symmetry.mirror_y) {
+                (false, false) => "off",
+                (true, false) => "left to right",
+                (false, true) => "top to bottom",
+                (true, true) => "both ways",
+            }),
+            format!("Kaleidoscope: {}", if self.symmetry.rotations > 1 { format!("{} copies", self.symmetry.rotations) } else { "off".to_string() }),
+            format!("Presentation: {}", on_off(self.presentation)),
+        ];
+        if let Some(team) = self.active_team {
+            modes.push(format!("Placing for team {}", team + 1));
+        }
+        let lines = std::iter::once("Modes".to_string()).chain(modes).map(|line| (None, line)).collect::<Vec<_>>();
+        let width = panel_size(&lines)[0];
+        draw_panel(&lines, [DEFAULT_WINDOW_WIDTH as f64 - width - 10.0, 10.0], c, g);
+    }
+
+    /// The query and the matching actions with their keys, at the top, the
+    /// picked one marked.
+    fn draw_command_palette<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some(palette) = self.command_palette.as_ref() else { return };
+        let matches = palette.matches();
+        // Scroll along so the pick stays in view
+        let first = palette.selected.saturating_sub(command_palette::SHOWN - 1);
+        let mut lines = vec![(None, format!("> {}_", palette.query))];
+        lines.extend(matches.iter().enumerate().skip(first).take(command_palette::SHOWN).map(|(i, action)| {
+            let marker = (i == palette.selected).then_some(PALETTE_PICK_COLOR);
+            match command_palette::shortcut(action.run) {
+                Some(keys) => (marker, format!("{}  {}", action.name, keys)),
+                None => (marker, action.name.to_string()),
+            }
+        }));
+        if matches.is_empty() {
+            lines.push((None, "No matching action".to_string()));
+        }
+        let width = panel_size(&lines)[0];
+        draw_panel(&lines, [(DEFAULT_WINDOW_WIDTH as f64 - width) / 2.0, 10.0], c, g);
+    }
+
+    /// Histograms of the active layer's Delaunay edge lengths and cell areas
+    /// in the bottom right corner. They're worked out every frame, so they
+    /// narrow as the dots relax. The sizes of the smallest circle and box
+    /// around the dots, and how many convex layers they make, go under them
+    /// while those are shown.
+    fn draw_stats<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let width = 320.0;
+        let left = DEFAULT_WINDOW_WIDTH as f64 - width - 10.0;
+        let mut bottom = DEFAULT_WINDOW_HEIGHT as f64 - 10.0;
+        let mut lines = Vec::new();
+        if let Some(circle) = enclosing_circle(&self.diagram().dots).filter(|_| self.enclosing_circle) {
+            lines.push((Some(ENCLOSING_CIRCLE_COLOR), format!("Circle at {:.1}, {:.1}  radius {}",
+                circle.center[0], circle.center[1], short(circle.radius))));
+        }
+        if let Some(found) = oriented_box(&self.diagram().dots).filter(|_| self.oriented_box) {
+            lines.push((Some(ORIENTED_BOX_COLOR), format!("Box {} x {} at {:.1} deg  area {}",
+                short(found.size[0]), short(found.size[1]), found.angle.to_degrees(), short(found.area()))));
+        }
+        if self.onion {
+            let layers = convex_layers(&self.diagram().dots);
+            let outermost = layers.depths.iter().filter(|&&depth| depth == 0).count();
+            lines.push((Some(onion_color(0, layers.hulls.len())), format!("{} convex layers, {} dots on the outermost",
+                layers.hulls.len(), outermost)));
+        }
+        if !lines.is_empty() {
+            bottom -= panel_size(&lines)[1];
+            draw_panel(&lines, [DEFAULT_WINDOW_WIDTH as f64 - panel_size(&lines)[0] - 10.0, bottom], c, g);
+            bottom -= 10.0;
+        }
+        if !self.stats {
+            return;
+        }
+        let histograms = [("Cell areas", cell_areas(self.diagram())), ("Edge lengths", edge_lengths(self.diagram()))];
+        for (title, values) in histograms {
+            bottom -= histogram_size(width)[1];
+            draw_histogram(title, Stats::new(&values).as_ref(), [left, bottom], width, c, g);
+            bottom -= 10.0;
+        }
+    }
+
+    /// The metadata of the dot whose cell is under the cursor, next to the
+    /// cursor, if it has any, in power mode its weight, and the average of
+    /// the `--field` under it.
+    fn draw_tooltip<G: Graphics>(&self, c: &Context, g: &mut G) {
+        const OFFSET: f64 = 16.0;
+        let diagram = self.diagram();
+        if !self.layers[self.active].visible {
+            return;
+        }
+        let Some(index) = diagram.nearest_dot(self.cursor) else { return };
+        let mut lines = diagram.metadata[index].describe().into_iter().map(|line| (None, line)).collect::<Vec<_>>();
+        if diagram.power && !diagram.hyperbolic {
+            let weight = diagram.weights[index];
+            lines.push((None, format!("Weight: {:.0} (radius {:.1})", weight, weight.sqrt())));
+        }
+        if let Some(value) = self.field.as_ref().and_then(|field| field.average(&diagram.visible[index], diagram.dots[index])) {
+            lines.push((None, format!("Field: {:.3}", value)));
+        }
+        let Some(first) = lines.first_mut() else { return };
+        first.0 = Some(diagram.colors[index]);
+        let [width, height] = panel_size(&lines);
+        let [x, y] = self.cursor;
+        // Near the right and bottom edges it goes to the other side of the cursor
+        let left = if x + OFFSET + width > DEFAULT_WINDOW_WIDTH as f64 { x - OFFSET - width } else { x + OFFSET };
+        let top = if y + OFFSET + height > DEFAULT_WINDOW_HEIGHT as f64 { y - OFFSET - height } else { y + OFFSET };
+        draw_panel(&lines, [left.max(0.0), top.max(0.0)], c, g);
+    }
+
+    /// The label of each treemap cell in its middle, with the share of the
+    /// window it covers and the share it should cover.
+    fn draw_treemap_labels<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some(treemap) = self.treemap.as_ref() else { return };
+        if !self.layers[0].visible {
+            return;
+        }
+        for ((cell, label), (share, target)) in treemap.cells.iter().zip(&treemap.labels).zip(treemap.shares().into_iter().zip(&treemap.targets)) {
+            let Some((x, y)) = centroid(cell) else { continue };
+            let lines = [(None, label.clone()), (None, format!("{:.1}% / {:.1}%", share * 100.0, target * 100.0))];
+            let [width, height] = panel_size(&lines);
+            // Cells along the edges would cut their labels off
+            let left = (x - width / 2.0).clamp(0.0, DEFAULT_WINDOW_WIDTH as f64 - width);
+            let top = (y - height / 2.0).clamp(0.0, DEFAULT_WINDOW_HEIGHT as f64 - height);
+            draw_panel(&lines, [left, top], c, g);
+        }
+    }
+
+    /// The Delaunay triangles of a layer by their corners, only those
+    /// inside the `--boundary` if there is one.
+    fn meshed(&self, diagram: &Diagram) -> Vec<[[f64; 2]; 3]> {
+        let mut corners = triangle_corners(diagram);
+        if let Some(boundary) = self.boundary.as_ref() {
+            corners.retain(|[a, b, c]| inside(boundary, [(a[0] + b[0] + c[0]) / 3.0, (a[1] + b[1] + c[1]) / 3.0]));
+        }
+        corners
+    }
+
+    /// The worst triangles of the active layer and what the colors of the
+    /// quality overlay mean, at the top in the middle.
+    fn draw_quality_status<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some(quality) = self.quality else { return };
+        let corners = self.meshed(self.diagram());
+        let smallest = corners.iter().map(|&t| min_angle(t)).fold(f64::INFINITY, f64::min);
+        let worst = corners.iter().map(|&t| aspect_ratio(t)).fold(0.0, f64::max);
+        let mut lines = vec![(None, format!("{} triangles by {}", corners.len(), quality.name()))];
+        if !corners.is_empty() {
+            lines.push((None, format!("Smallest angle {:.1} deg, worst aspect ratio {:.2}", smallest, worst)));
+        }
+        lines.push((Some(quality_color(1.0)), "equilateral".to_string()));
+        lines.push((Some(quality_color(0.0)), "flat".to_string()));
+        let width = panel_size(&lines)[0];
+        draw_panel(&lines, [(DEFAULT_WINDOW_WIDTH as f64 - width) / 2.0, 10.0], c, g);
+    }
+
+    /// What the terrain's heights come from and how much of it is under
+    /// water, at the top in the middle.
+    fn draw_terrain_status<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some(terrain) = self.terrain else { return };
+        let heights = terrain.heights(self.diagram(), self.terrain_seed);
+        let mut lines = vec![(None, format!("Terrain from {}", terrain.name()))];
+        if !heights.is_empty() {
+            let water = heights.iter().filter(|&&h| h < SEA_LEVEL).count();
+            lines.push((None, format!("{} of {} cells under water", water, heights.len())));
+        }
+        lines.push((Some(elevation_color(1.0)), "peaks".to_string()));
+        lines.push((Some(elevation_color(0.5)), "lowlands".to_string()));
+        lines.push((Some(elevation_color(0.0)), "sea".to_string()));
+        let width = panel_size(&lines)[0];
+        draw_panel(&lines, [(DEFAULT_WINDOW_WIDTH as f64 - width) / 2.0, 10.0], c, g);
+    }
+
+    /// While equal-area relaxation runs, how far the cells are from all
+    /// having the same area, in the top right corner.
+    fn draw_equal_area_status<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let diagram = self.diagram();
+        if self.relaxation != Relaxation::EqualArea || self.relax_rate <= 0.0 || diagram.dots.is_empty() {
+            return;
+        }
+        let cells = &diagram.visible;
+        let targets = vec![1.0 / cells.len() as f64; cells.len()];
+        let error = area_error(cells, &targets);
+        let spread = Stats::new(&cells.iter().map(|cell| area(cell)).collect::<Vec<_>>())
+            .map_or(0.0, |stats| stats.std_dev / stats.mean);
+        let state = if error < MAX_ERROR { ", converged" } else { "" };
+        let lines = [(None, format!("Equal areas: {:.1}% of the area off, sizes vary {:.1}%{}", error * 100.0, spread * 100.0, state))];
+        let width = panel_size(&lines)[0];
+        draw_panel(&lines, [DEFAULT_WINDOW_WIDTH as f64 - width - 10.0, 10.0], c, g);
+    }
+
+    fn draw_morph_status<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some(morph) = self.morph.as_ref() else { return };
+        let state = if morph.playing { "playing" } else { "paused" };
+        let lines = [(None, format!("Morph {:.0}% {}, Space plays/pauses, Left/Right scrub", morph.t * 100.0, state))];
+        let height = panel_size(&lines)[1];
+        draw_panel(&lines, [10.0, DEFAULT_WINDOW_HEIGHT as f64 - height - 10.0], c, g);
+    }
+
+    fn draw_stream_status<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some(stream) = self.stream.as_ref() else { return };
+        let lines = [(None, stream.describe())];
+        let height = panel_size(&lines)[1];
+        draw_panel(&lines, [10.0, DEFAULT_WINDOW_HEIGHT as f64 - height - 10.0], c, g);
+    }
+
+    fn draw_insertion_status<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some(insertion) = self.insertion.as_ref() else { return };
+        let lines = [(None, insertion.describe())];
+        let height = panel_size(&lines)[1];
+        draw_panel(&lines, [10.0, DEFAULT_WINDOW_HEIGHT as f64 - height - 10.0], c, g);
+    }
+
+    fn draw_route_status<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some((graph, method)) = self.pathfinding else { return };
+        let text = match (self.route.as_ref(), self.path_start) {
+            (Some(route), _) => route.describe(),
+            (None, None) => format!("{} along {}: click the start dot", method.name(), graph.name()),
+            (None, Some(_)) => format!("{} along {}: click the goal dot", method.name(), graph.name()),
+        };
+        let lines = [(None, text)];
+        let height = panel_size(&lines)[1];
+        draw_panel(&lines, [10.0, DEFAULT_WINDOW_HEIGHT as f64 - height - 10.0], c, g);
+    }
+
+    /// Which proximity graph is showing and how many edges it has, in the
+    /// bottom left corner. For the beta-skeleton also its beta and what
+    /// that comes to.
+    fn draw_proximity_status<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some(proximity) = self.proximity else { return };
+        let edges = proximity.edges(self.diagram(), self.beta).len();
+        if proximity == ProximityGraph::Urquhart {
+            let lines = [(None, format!("Urquhart graph, Delaunay without the longest side of each triangle: {} edges", edges))];
+            let height = panel_size(&lines)[1];
+            draw_panel(&lines, [10.0, DEFAULT_WINDOW_HEIGHT as f64 - height - 10.0], c, g);
+            return;
+        }
+        let near = |beta: f64| (self.beta - beta).abs() < BETA_STEP / 2.0;
+        let known = if near(GABRIEL_BETA) {
+            ", the Gabriel graph"
+        } else if near(RELATIVE_NEIGHBORHOOD_BETA) {
+            ", the relative neighborhood graph"
+        } else {
+            ""
+        };
+        let lines = [(None, format!("Beta-skeleton for beta {:.2}{}: {} edges, Up/Down change beta", self.beta, known, edges))];
+        let height = panel_size(&lines)[1];
+        draw_panel(&lines, [10.0, DEFAULT_WINDOW_HEIGHT as f64 - height - 10.0], c, g);
+    }
+
+    /// The Delaunay triangles of the active layer with their circumcenters
+    /// as rings, centroids as squares and incenters as crosses. The
+    /// circumcircle of the triangle under the cursor is drawn too: it has
+    /// no dot inside, and its center is where the cells of its corners meet.
+    fn draw_triangle_centers<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let triangles = triangle_corners(self.diagram());
+        for &t in &triangles {
+            draw_outline(&t.map(|p| (p[0], p[1])), c, g, [0.3, 0.3, 0.3, 0.6], 0.5);
+        }
+        for &t in &triangles {
+            let [x, y] = centers::centroid(t);
+            let half = CENTER_GLYPH_SIZE / 2.0;
+            graphics::rectangle(CENTROID_COLOR, [x - half, y - half, CENTER_GLYPH_SIZE, CENTER_GLYPH_SIZE], c.transform, g);
+            if let Some([x, y]) = incenter(t) {
+                graphics::line(INCENTER_COLOR, 1.0, [x - half, y - half, x + half, y + half], c.transform, g);
+                graphics::line(INCENTER_COLOR, 1.0, [x - half, y + half, x + half, y - half], c.transform, g);
+            }
+            if let Some(center) = circumcenter(t) {
+                draw_circle(&center, half, c, g, CIRCUMCENTER_COLOR);
+            }
+        }
+        let under_cursor = triangles.into_iter().find(|&t| centers::contains(t, self.cursor));
+        if let Some((t, center)) = under_cursor.and_then(|t| circumcenter(t).map(|center| (t, center))) {
+            let radius = (t[0][0] - center[0]).hypot(t[0][1] - center[1]);
+            draw_circle(&center, radius, c, g, CIRCUMCENTER_COLOR);
+            for corner in t {
+                graphics::line(CIRCUMCENTER_COLOR, 0.5, [center[0], center[1], corner[0], corner[1]], c.transform, g);
+            }
+        }
+    }
+
+    /// How long the tour is, in the bottom left corner.
+    fn draw_tour_status<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some(tour) = self.tour.as_ref() else { return };
+        let lines = [(Some(TOUR_COLOR), format!("Tour through {} dots, {:.0} pixels long, Shift+U exports it to {}",
+            tour.order.len(), tour.length, self.tour_path))];
+        let height = panel_size(&lines)[1];
+        draw_panel(&lines, [10.0, DEFAULT_WINDOW_HEIGHT as f64 - height - 10.0], c, g);
+    }
+
+    /// Which glyph marks which center, in the bottom left corner.
+    fn draw_centers_legend<G: Graphics>(&self, c: &Context, g: &mut G) {
+        if !self.triangle_centers {
+            return;
+        }
+        let lines = [
+            (Some(CIRCUMCENTER_COLOR), "Rings: circumcenters, as far from all three corners, so where their cells meet".to_string()),
+            (Some(CENTROID_COLOR), "Squares: centroids, the centers of mass".to_string()),
+            (Some(INCENTER_COLOR), "Crosses: incenters, as far from all three sides".to_string()),
+        ];
+        let height = panel_size(&lines)[1];
+        draw_panel(&lines, [10.0, DEFAULT_WINDOW_HEIGHT as f64 - height - 10.0], c, g);
+    }
+
+    /// How full the grid of the active layer is, and how much of it the
+    /// search at the cursor looked through for what it found, in the
+    /// bottom left corner.
+    fn draw_grid_status<G: Graphics>(&self, c: &Context, g: &mut G) {
+        if !self.grid_overlay {
+            return;
+        }
+        let grid = self.diagram().grid();
+        let occupied = grid.occupied();
+        let fullest = occupied.iter().map(|&(_, count)| count).max().unwrap_or(0);
+        let (cells, looked_at) = grid.searched(self.cursor, self.eraser_radius);
+        let found = self.diagram().dots_within(self.cursor, self.eraser_radius).len();
+        let lines = [
+            (Some(GRID_COLOR), format!("Grid of {} px cells: {} hold dots, up to {} in one", CELL_SIZE, occupied.len(), fullest)),
+            (Some(GRID_SEARCH_COLOR), format!("At the cursor: {} dots within {} px, {} looked at in {} cells",
+                found, self.eraser_radius, looked_at, cells.len())),
+        ];
+        let height = panel_size(&lines)[1];
+        draw_panel(&lines, [10.0, DEFAULT_WINDOW_HEIGHT as f64 - height - 10.0], c, g);
+    }
+
+    /// The grid of the active layer: its lines, the cells holding dots
+    /// shaded by how many, and the cells a search around the cursor looks
+    /// through, with the dots it finds.
+    fn draw_grid<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let diagram = self.diagram();
+        let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        let occupied = diagram.grid().occupied();
+        let fullest = occupied.iter().map(|&(_, count)| count).max().unwrap_or(1) as f32;
+        for (cell, count) in occupied {
+            let [r, g_, b, a] = GRID_COLOR;
+            graphics::rectangle([r, g_, b, a * 0.5 * count as f32 / fullest], cell, c.transform, g);
+        }
+        for x in (0..).map(|i| i as f64 * CELL_SIZE).take_while(|&x| x <= width) {
+            graphics::line(GRID_COLOR, 0.5, [x, 0.0, x, height], c.transform, g);
+        }
+        for y in (0..).map(|i| i as f64 * CELL_SIZE).take_while(|&y| y <= height) {
+            graphics::line(GRID_COLOR, 0.5, [0.0, y, width, y], c.transform, g);
+        }
+
+        let (cells, _) = diagram.grid().searched(self.cursor, self.eraser_radius);
+        for [x, y, w, h] in cells {
+            draw_outline(&[(x, y), (x + w, y), (x + w, y + h), (x, y + h)], c, g, GRID_SEARCH_COLOR, 1.0);
+        }
+        draw_circle(&self.cursor, self.eraser_radius, c, g, GRID_SEARCH_COLOR);
+        for i in diagram.dots_within(self.cursor, self.eraser_radius) {
+            draw_ellipse(&diagram.dots[i], c, g, GRID_SEARCH_COLOR);
+        }
+    }
+
+    fn draw_sweep_status<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let Some(sweep) = self.sweep.as_ref() else { return };
+        let (done, events) = sweep.progress();
+        let state = if sweep.playing { "playing" } else { "paused" };
+        let lines = [(None, format!("Fortune event {} of {} {}, Space plays/pauses, Left/Right step", done, events, state))];
+        let height = panel_size(&lines)[1];
+        draw_panel(&lines, [10.0, DEFAULT_WINDOW_HEIGHT as f64 - height - 10.0], c, g);
+    }
+
+    /// The share of the window each team's cells cover, once any team has
+    /// dots or a team is selected. In game mode also whose turn it is.
+    fn draw_scoreboard<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let areas = self.diagram().team_areas();
+        if areas.is_empty() && self.active_team.is_none() {
+            return;
+        }
+        let window = DEFAULT_WINDOW_WIDTH as f64 * DEFAULT_WINDOW_HEIGHT as f64;
+        let share = |team: usize| areas.get(team).unwrap_or(&0.0) / window * 100.0;
+
+        let mut lines = Vec::new();
+        if let Some(game) = self.game.as_ref() {
+            lines.push(match (game.is_over(), game.leader(&areas)) {
+                (false, _) => {
+                    let player = game.current_player();
+                    (Some(team_color(player)), format!("Player {} to move, {} moves left", player + 1, game.moves_left()))
+                }
+                (true, Some(winner)) => (Some(team_color(winner)), format!("Game over, player {} wins", winner + 1)),
+                (true, None) => (None, "Game over, it's a tie".to_string())
+            });
+            for player in 0..game.players {
+                lines.push((Some(team_color(player)), format!("Player {}: {:.1}%", player + 1, share(player))));
+            }
+        } else {
+            lines.push(match self.active_team {
+                Some(team) => (Some(team_color(team)), format!("Placing for team {}", team + 1)),
+                None => (None, "Placing uncolored dots".to_string())
+            });
+            for (team, area) in areas.iter().enumerate() {
+                if *area > 0.0 || self.active_team == Some(team) {
+                    lines.push((Some(team_color(team)), format!("Team {}: {:.1}%", team + 1, share(team))));
+                }
+            }
+        }
+        draw_panel(&lines, [10.0, 10.0], c, g);
+    }
+}
+
+/// The actions of the command palette that have a key, key first, so the
+/// help can't fall behind the keys.
+fn help_keys() -> Vec<String> {
+    command_palette::ACTIONS.iter()
+        .filter_map(|action| command_palette::shortcut(action.run).map(|keys| format!("{:<9}{}", keys, action.name)))
+        .collect()
+}
+
+fn copy_diagram(diagram: &Diagram, options: &ExportOptions) {
+    let copied = export::render("svg", diagram, options)
+        .and_then(|svg| clipboard::copy(&svg, "text/plain"));
+    match copied {
+        Ok(()) => println!("Copied diagram to clipboard"),
+        Err(err) => println!("Could not copy diagram to clipboard: {}", err)
+    }
+}
+
+fn export_timelapse(path: &str, diagram: &Diagram, options: &ExportOptions) {
+    match export::export_timelapse(path, diagram, options) {
+        Ok(frames) => println!("Exported time-lapse of {} frames to {}", frames, path),
+        Err(err) => println!("Could not export time-lapse to {}: {}", path, err)
+    }
+}
+
+fn export_cut(path: &str, diagram: &Diagram, options: &ExportOptions, kerf: f64) {
+    match export::export_cut(path, diagram, options, kerf) {
+        Ok(cells) => println!("Exported {} cells for cutting to {}", cells, path),
+        Err(err) => println!("Could not export cells for cutting to {}: {}", path, err)
+    }
+}
+
+fn export_tile(path: &str, diagram: &Diagram, options: &ExportOptions, width: u32) {
+    match export::export_tile(path, diagram, options, width) {
+        Ok(height) => println!("Exported {}x{} tile to {}", width, height, path),
+        Err(err) => println!("Could not export tile to {}: {}", path, err)
+    }
+}
+
+fn export_bump(path: &str, diagram: &Diagram, options: &ExportOptions, bump: &Bump) {
+    let kind = if bump.normals { "normal" } else { "height" };
+    match export::export_bump(path, diagram, options, bump) {
+        Ok(()) => println!("Exported {} map to {}", kind, path),
+        Err(err) => println!("Could not export {} map to {}: {}", kind, path, err)
+    }
+}
+
+fn export_heightmap(path: &str, diagram: &Diagram, options: &ExportOptions, heights: &[f64]) {
+    match export::export_heightmap(path, diagram, options, heights) {
+        Ok(()) => println!("Exported heightmap to {}", path),
+        Err(err) => println!("Could not export heightmap to {}: {}", path, err)
+    }
+}
+
+fn export_tour(path: &str, diagram: &Diagram, options: &ExportOptions, tour: &Tour) {
+    match export::export_tour(path, diagram, options, &tour.order) {
+        Ok(()) => println!("Exported tour of {:.0} pixels through {} dots to {}", tour.length, tour.order.len(), path),
+        Err(err) => println!("Could not export tour to {}: {}", path, err)
+    }
+}
+
+fn export_centroids(path: &str, diagram: &Diagram, options: &ExportOptions) {
+    match export::export_centroids(path, diagram, options) {
+        Ok(count) => println!("Exported {} centroids to {}", count, path),
+        Err(err) => println!("Could not export centroids to {}: {}", path, err)
+    }
+}
+
+fn export_edges(path: &str, diagram: &Diagram, options: &ExportOptions) {
+    match export::export_edges(path, diagram, options) {
+        Ok((edges, lines)) => println!("Exported {} edges as {} polylines to {}", edges, lines, path),
+        Err(err) => println!("Could not export edges to {}: {}", path, err)
+    }
+}
+
+fn export_diagram(path: &str, diagram: &Diagram, options: &ExportOptions) {
+    match export::export(path, diagram, options) {
+        Ok(()) => println!("Exported diagram to {}", path),
+        Err(err) => println!("Could not export diagram to {}: {}", path, err)
+    }
+}
+
+/// The palette `theme` gives the layer at `index`.
+fn theme_palette(theme: &Theme, index: usize) -> Palette {
+    if theme.palette.is_empty() {
+        Layer::palette(index)
+    } else {
+        Palette::List(theme.palette.clone())
+    }
+}
+
+/// From deep blue for the outermost convex layer to yellow for the
+/// innermost of `count`.
+fn onion_color(depth: usize, count: usize) -> [f32; 4] {
+    let t = depth as f32 / (count.max(2) - 1) as f32;
+    hsv(0.65 - 0.5 * t, 0.65, 0.6 + 0.35 * t)
+}
+
+/// Red for flat triangles through yellow to green for equilateral ones.
+fn quality_color(goodness: f64) -> [f32; 4] {
+    hsv(goodness as f32 / 3.0, 0.7, 0.95)
+}
@@ -0,0 +1,63 @@
+//! Audio-reactive input. Reads raw signed 16 bit little-endian mono PCM at
+//! 44.1 kHz from a file or pipe, so any recorder can feed it, for example:
+//!
+//! ```text
+//! arecord -f S16_LE -r 44100 -c 1 | interactive-voronoi --audio -
+//! ```
+//!
+//! Every block of samples jitters the dots by the block's loudness, and an
+//! onset (a block much louder than the recent average) spawns a new dot.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use crate::control::Command;
+use crate::diagram::random_point;
+
+const BLOCK_SAMPLES: usize = 1024;
+/// Blocks quieter than this RMS (of full scale) don't move anything.
+const NOISE_FLOOR: f64 = 0.01;
+/// Jitter in pixels for a full-scale block.
+const MAX_JITTER: f64 = 30.0;
+/// Weight of the newest block in the running loudness average.
+const AVERAGE_WEIGHT: f64 = 0.05;
+
+/// Starts analysing audio from `source` (`-` for standard input) in the
+/// background. An onset is a block whose energy exceeds the running average
+/// times `sensitivity`, so lower values react to more beats.
+pub fn listen(source: &str, sensitivity: f64, commands: Sender<Command>) -> io::Result<()> {
+    let mut input: Box<dyn Read + Send> = if source == "-" {
+        Box::new(io::stdin())
+    } else {
+        Box::new(File::open(source)?)
+    };
+    println!("Listening for audio on {}", source);
+    thread::spawn(move || {
+        let mut block = vec![0u8; BLOCK_SAMPLES * 2];
+        let mut average_energy = 0.0;
+        while input.read_exact(&mut block).is_ok() {
+            let energy = block.chunks_exact(2)
+                .map(|s| (i16::from_le_bytes([s[0], s[1]]) as f64 / i16::MAX as f64).powi(2))
+                .sum::<f64>() / BLOCK_SAMPLES as f64;
+            let rms = energy.sqrt();
+
+            let mut block_commands = Vec::new();
+            if rms > NOISE_FLOOR {
+                block_commands.push(Command::Jitter(rms * MAX_JITTER));
+                if average_energy > 0.0 && energy > average_energy * sensitivity {
+                    block_commands.push(Command::AddPoint(random_point()));
+                }
+            }
+            average_energy += (energy - average_energy) * AVERAGE_WEIGHT;
+
+            for command in block_commands {
+                if commands.send(command).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Ok(())
+}
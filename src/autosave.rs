@@ -0,0 +1,89 @@
+//! Saves the whole session every so often to the temp directory, keeping
+//! the last few saves, so a long session survives a crash or an accidental
+//! close even when the journal was started over since. `--restore-last`
+//! picks up the newest save.
+//!
+//! Saves are journals with one `set` per layer, see `journal`, named
+//! `autosave-0.journal` for the newest up to `autosave-2.journal`.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::diagram::Duplicates;
+use crate::journal::{self, write_layers};
+use crate::model::Layer;
+
+/// How many saves are kept, the oldest dropped for each new one.
+const BACKUPS: usize = 3;
+
+pub struct Autosave {
+    interval: Duration,
+    /// When to save next, if anything changed by then.
+    due: Option<Instant>,
+    /// The revisions of the layers last saved.
+    saved: Vec<u64>,
+}
+
+impl Autosave {
+    pub fn new(seconds: f64) -> Autosave {
+        Autosave { interval: Duration::from_secs_f64(seconds), due: None, saved: Vec::new() }
+    }
+
+    /// Saves the layers if it's time and they changed since the last save.
+    pub fn tick(&mut self, layers: &[Layer], now: Instant) -> io::Result<()> {
+        let due = *self.due.get_or_insert(now + self.interval);
+        if now < due {
+            return Ok(());
+        }
+        self.due = Some(now + self.interval);
+        self.save(layers)
+    }
+
+    /// Saves the layers now unless they didn't change since the last save.
+    pub fn save(&mut self, layers: &[Layer]) -> io::Result<()> {
+        let revisions = layers.iter().map(|l| l.diagram.revision()).collect::<Vec<_>>();
+        if revisions == self.saved {
+            return Ok(());
+        }
+        fs::create_dir_all(directory())?;
+        // Written in full before it takes the newest save's place, so a crash
+        // while saving leaves the saves as they were
+        let written = directory().join("autosave.tmp");
+        let mut file = BufWriter::new(File::create(&written)?);
+        write_layers(&mut file, layers)?;
+        file.flush()?;
+        for i in (1..BACKUPS).rev() {
+            if path(i - 1).exists() {
+                fs::rename(path(i - 1), path(i))?;
+            }
+        }
+        fs::rename(written, path(0))?;
+        self.saved = revisions;
+        Ok(())
+    }
+}
+
+fn directory() -> PathBuf {
+    std::env::temp_dir().join("interactive-voronoi")
+}
+
+fn path(index: usize) -> PathBuf {
+    directory().join(format!("autosave-{}.journal", index))
+}
+
+/// The layers of the newest save that can be read, and where it is.
+pub fn restore_last(duplicates: Duplicates) -> io::Result<(Vec<Layer>, PathBuf)> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, format!("no saves in {}", directory().display()));
+    for path in (0..BACKUPS).map(path).filter(|p| p.exists()) {
+        match journal::recover(&path.to_string_lossy(), duplicates) {
+            Ok(layers) => return Ok((layers, path)),
+            Err(err) => {
+                println!("Could not restore {}: {}", path.display(), err);
+                last_error = err;
+            }
+        }
+    }
+    Err(last_error)
+}
@@ -0,0 +1,191 @@
+//! The smallest shapes around all the dots: their convex hull, the
+//! smallest circle holding them, found with Welzl's algorithm, and the
+//! rectangle of least area, turned to fit them as tightly as possible.
+//! Peeling off one hull after another leaves the convex layers, nested
+//! like the skins of an onion.
+
+use std::cmp::Ordering;
+
+use crate::predicates::orient;
+
+/// How far outside a circle a dot may be and still count as inside, as a
+/// share of the radius, for rounding.
+const TOLERANCE: f64 = 1e-9;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    pub center: [f64; 2],
+    pub radius: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrientedBox {
+    pub center: [f64; 2],
+    /// Along the box's own axes, the longer side first.
+    pub size: [f64; 2],
+    /// Radians from the X axis to the longer side, from 0 up to pi.
+    pub angle: f64,
+}
+
+/// The hulls left after peeling off one after another, the outermost first.
+pub struct ConvexLayers {
+    /// How many hulls lie outside each dot's own.
+    pub depths: Vec<usize>,
+    /// The corners of each hull, as with `convex_hull`.
+    pub hulls: Vec<Vec<[f64; 2]>>,
+}
+
+impl Circle {
+    fn contains(&self, p: [f64; 2]) -> bool {
+        (p[0] - self.center[0]).hypot(p[1] - self.center[1]) <= self.radius * (1.0 + TOLERANCE) + TOLERANCE
+    }
+}
+
+impl OrientedBox {
+    pub fn area(&self) -> f64 {
+        self.size[0] * self.size[1]
+    }
+
+    /// The corners, in order around the box.
+    pub fn corners(&self) -> [[f64; 2]; 4] {
+        let (sin, cos) = self.angle.sin_cos();
+        let [w, h] = [self.size[0] / 2.0, self.size[1] / 2.0];
+        [(-w, -h), (w, -h), (w, h), (-w, h)].map(|(u, v)| [self.center[0] + u * cos - v * sin, self.center[1] + u * sin + v * cos])
+    }
+}
+
+/// The corners of the convex hull, counter-clockwise with y pointing up,
+/// by Andrew's monotone chain. Dots on its sides are left out.
+pub fn convex_hull(dots: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut sorted = dots.to_vec();
+    sorted.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let mut hull: Vec<[f64; 2]> = Vec::with_capacity(2 * sorted.len());
+    for pass in [sorted.clone(), sorted.into_iter().rev().collect()] {
+        let floor = hull.len();
+        for p in pass {
+            while hull.len() >= floor + 2 && orient(hull[hull.len() - 2], hull[hull.len() - 1], p) != Ordering::Greater {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // The last corner of each chain starts the other
+        hull.pop();
+    }
+    hull
+}
+
+/// The convex layers of the dots. Dots on the sides of a hull belong to it
+/// as much as its corners do, so they aren't left for the next one.
+pub fn convex_layers(dots: &[[f64; 2]]) -> ConvexLayers {
+    let mut depths = vec![0; dots.len()];
+    let mut hulls = Vec::new();
+    let mut remaining = (0..dots.len()).collect::<Vec<_>>();
+    while !remaining.is_empty() {
+        let hull = convex_hull(&remaining.iter().map(|&i| dots[i]).collect::<Vec<_>>());
+        // Everything left is inside the hull, so a dot on the line through
+        // one of its sides is on that side
+        let on_hull = |p: [f64; 2]| hull.contains(&p)
+            || (0..hull.len()).any(|i| orient(hull[i], hull[(i + 1) % hull.len()], p) == Ordering::Equal);
+        remaining.retain(|&i| {
+            depths[i] = hulls.len();
+            !on_hull(dots[i])
+        });
+        hulls.push(hull);
+    }
+    ConvexLayers { depths, hulls }
+}
+
+/// The smallest circle with all dots inside or on it, `None` without dots.
+pub fn enclosing_circle(dots: &[[f64; 2]]) -> Option<Circle> {
+    // Only the hull's corners can end up on the circle. Welzl's algorithm
+    // takes expected linear time for points in random order, which a fixed
+    // scramble gives as well as a random one would, without drawing from
+    // the generator that places the dots
+    let mut points = convex_hull(dots);
+    let n = points.len();
+    for i in (1..n).rev() {
+        points.swap(i, (i.wrapping_mul(0x9e37_79b9) >> 7) % (i + 1));
+    }
+
+    let mut circle = Circle { center: *points.first()?, radius: 0.0 };
+    for i in 1..n {
+        if circle.contains(points[i]) {
+            continue;
+        }
+        circle = Circle { center: points[i], radius: 0.0 };
+        for j in 0..i {
+            if circle.contains(points[j]) {
+                continue;
+            }
+            circle = diameter(points[i], points[j]);
+            for k in 0..j {
+                if !circle.contains(points[k]) {
+                    circle = circumcircle(points[i], points[j], points[k]).unwrap_or(circle);
+                }
+            }
+        }
+    }
+    Some(circle)
+}
+
+/// The rectangle of least area with all dots inside or on it, `None`
+/// without dots. One of its sides lies along a side of the convex hull.
+pub fn oriented_box(dots: &[[f64; 2]]) -> Option<OrientedBox> {
+    let hull = convex_hull(dots);
+    if hull.len() < 2 {
+        return hull.first().map(|&center| OrientedBox { center, size: [0.0, 0.0], angle: 0.0 });
+    }
+    let mut best: Option<(f64, OrientedBox)> = None;
+    for i in 0..hull.len() {
+        let (a, b) = (hull[i], hull[(i + 1) % hull.len()]);
+        let length = (b[0] - a[0]).hypot(b[1] - a[1]);
+        if length == 0.0 {
+            continue;
+        }
+        let (ux, uy) = ((b[0] - a[0]) / length, (b[1] - a[1]) / length);
+        let along = |p: &[f64; 2]| p[0] * ux + p[1] * uy;
+        let across = |p: &[f64; 2]| -p[0] * uy + p[1] * ux;
+        let (u0, u1) = hull.iter().map(along).fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+        let (v0, v1) = hull.iter().map(across).fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+        let area = (u1 - u0) * (v1 - v0);
+        if best.as_ref().is_some_and(|(smallest, _)| *smallest <= area) {
+            continue;
+        }
+        let (u, v) = ((u0 + u1) / 2.0, (v0 + v1) / 2.0);
+        let center = [u * ux - v * uy, u * uy + v * ux];
+        let mut angle = uy.atan2(ux);
+        let mut size = [u1 - u0, v1 - v0];
+        if size[1] > size[0] {
+            size.swap(0, 1);
+            angle += std::f64::consts::FRAC_PI_2;
+        }
+        angle = angle.rem_euclid(std::f64::consts::PI);
+        best = Some((area, OrientedBox { center, size, angle }));
+    }
+    best.map(|(_, found)| found)
+}
+
+fn diameter(a: [f64; 2], b: [f64; 2]) -> Circle {
+    Circle {
+        center: [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0],
+        radius: (a[0] - b[0]).hypot(a[1] - b[1]) / 2.0,
+    }
+}
+
+/// `None` for points in a line.
+fn circumcircle(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> Option<Circle> {
+    let d = 2.0 * (a[0] * (b[1] - c[1]) + b[0] * (c[1] - a[1]) + c[0] * (a[1] - b[1]));
+    if d == 0.0 {
+        return None;
+    }
+    let (a2, b2, c2) = (a[0] * a[0] + a[1] * a[1], b[0] * b[0] + b[1] * b[1], c[0] * c[0] + c[1] * c[1]);
+    let center = [
+        (a2 * (b[1] - c[1]) + b2 * (c[1] - a[1]) + c2 * (a[1] - b[1])) / d,
+        (a2 * (c[0] - b[0]) + b2 * (a[0] - c[0]) + c2 * (b[0] - a[0])) / d,
+    ];
+    Some(Circle { center, radius: (center[0] - a[0]).hypot(center[1] - a[1]) })
+}
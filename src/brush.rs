@@ -0,0 +1,85 @@
+//! Tools that act on the diagram while the mouse button is held down.
+
+use crate::diagram::random;
+
+/// Between successive dots of the spray pattern, so they never line up.
+const GOLDEN_ANGLE: f64 = 2.399_963_229_728_653;
+/// Dots in one round of the spray pattern before it starts over at the center.
+const PATTERN_SIZE: usize = 64;
+
+/// What the mouse button does.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tool {
+    /// A click adds one dot.
+    Point,
+    /// Holding the button sprays dots around the cursor.
+    Spray,
+    /// A click adds a whole shape of dots, see `stamp`.
+    Stamp,
+}
+
+impl Tool {
+    pub fn next(self) -> Tool {
+        match self {
+            Tool::Point => Tool::Spray,
+            Tool::Spray => Tool::Stamp,
+            Tool::Stamp => Tool::Point,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Point => "point",
+            Tool::Spray => "spray",
+            Tool::Stamp => "stamp",
+        }
+    }
+}
+
+/// Sprays dots around the cursor at a steady rate.
+pub struct Spray {
+    pub radius: f64,
+    /// Dots per second.
+    pub density: f64,
+    /// From `0.0`, where the dots follow an even sunflower pattern across the
+    /// brush, to `1.0`, where they land anywhere in it at random.
+    pub jitter: f64,
+    /// Dots owed since the last call to `emit`, including fractions.
+    due: f64,
+    /// Position in the sunflower pattern.
+    sprayed: usize,
+}
+
+impl Spray {
+    pub fn new(radius: f64, density: f64, jitter: f64) -> Spray {
+        Spray { radius, density, jitter: jitter.clamp(0.0, 1.0), due: 0.0, sprayed: 0 }
+    }
+
+    /// Call when the button goes down, so the first dot comes right away.
+    pub fn start(&mut self) {
+        self.due = 1.0;
+    }
+
+    /// The dots sprayed around `center` over the last `seconds`.
+    pub fn emit(&mut self, center: [f64; 2], seconds: f64) -> Vec<[f64; 2]> {
+        self.due += seconds * self.density;
+        let count = self.due.floor() as usize;
+        self.due -= count as f64;
+
+        (0..count).map(|_| {
+            let k = self.sprayed % PATTERN_SIZE;
+            self.sprayed += 1;
+            // Equal areas per dot: the radius grows with the square root
+            let even = polar(self.radius * ((k as f64 + 0.5) / PATTERN_SIZE as f64).sqrt(), k as f64 * GOLDEN_ANGLE);
+            let random = polar(self.radius * random::<f64>().sqrt(), random::<f64>() * std::f64::consts::TAU);
+            [
+                center[0] + even[0] + (random[0] - even[0]) * self.jitter,
+                center[1] + even[1] + (random[1] - even[1]) * self.jitter,
+            ]
+        }).collect()
+    }
+}
+
+fn polar(radius: f64, angle: f64) -> [f64; 2] {
+    [radius * angle.cos(), radius * angle.sin()]
+}
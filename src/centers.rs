@@ -0,0 +1,46 @@
+//! The centers of a triangle, each the middle of it in its own sense. The
+//! circumcenter is as far from all three corners, so for a Delaunay
+//! triangle it is where the cells of its corners meet: a corner of the
+//! Voronoi diagram. The centroid is the triangle's center of mass and the
+//! incenter is as far from all three sides.
+
+use std::cmp::Ordering;
+
+use crate::predicates::orient;
+
+/// The point as far from all three corners, `None` for corners in a line.
+pub fn circumcenter([a, b, c]: [[f64; 2]; 3]) -> Option<[f64; 2]> {
+    let d = 2.0 * (a[0] * (b[1] - c[1]) + b[0] * (c[1] - a[1]) + c[0] * (a[1] - b[1]));
+    if d == 0.0 {
+        return None;
+    }
+    let (a2, b2, c2) = (a[0] * a[0] + a[1] * a[1], b[0] * b[0] + b[1] * b[1], c[0] * c[0] + c[1] * c[1]);
+    Some([
+        (a2 * (b[1] - c[1]) + b2 * (c[1] - a[1]) + c2 * (a[1] - b[1])) / d,
+        (a2 * (c[0] - b[0]) + b2 * (a[0] - c[0]) + c2 * (b[0] - a[0])) / d,
+    ])
+}
+
+/// Where the lines from each corner to the middle of the opposite side
+/// cross.
+pub fn centroid([a, b, c]: [[f64; 2]; 3]) -> [f64; 2] {
+    [(a[0] + b[0] + c[0]) / 3.0, (a[1] + b[1] + c[1]) / 3.0]
+}
+
+/// The center of the largest circle inside, the corners weighted by the
+/// length of the side across from them. `None` for a triangle of no size.
+pub fn incenter([a, b, c]: [[f64; 2]; 3]) -> Option<[f64; 2]> {
+    let length = |p: [f64; 2], q: [f64; 2]| (p[0] - q[0]).hypot(p[1] - q[1]);
+    let (wa, wb, wc) = (length(b, c), length(c, a), length(a, b));
+    let total = wa + wb + wc;
+    if total == 0.0 {
+        return None;
+    }
+    Some([(a[0] * wa + b[0] * wb + c[0] * wc) / total, (a[1] * wa + b[1] * wb + c[1] * wc) / total])
+}
+
+/// Whether `p` is inside the triangle or on its sides.
+pub fn contains([a, b, c]: [[f64; 2]; 3], p: [f64; 2]) -> bool {
+    let sides = [orient(a, b, p), orient(b, c, p), orient(c, a, p)];
+    !(sides.contains(&Ordering::Greater) && sides.contains(&Ordering::Less))
+}
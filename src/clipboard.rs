@@ -0,0 +1,69 @@
+//! System clipboard access through the platform's command line tools, so we
+//! don't have to link against every windowing system's clipboard API.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Candidate commands that read clipboard contents of the given MIME type from stdin.
+fn copy_commands(mime: &str) -> Vec<Vec<String>> {
+    let commands: Vec<Vec<&str>> = if cfg!(target_os = "macos") {
+        vec![vec!["pbcopy"]]
+    } else if cfg!(target_os = "windows") {
+        vec![vec!["clip"]]
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        vec![vec!["wl-copy", "--type", mime], vec!["xclip", "-selection", "clipboard", "-t", mime, "-i"]]
+    } else {
+        vec![vec!["xclip", "-selection", "clipboard", "-t", mime, "-i"], vec!["xsel", "--clipboard", "--input"]]
+    };
+    commands.into_iter().map(|c| c.into_iter().map(String::from).collect()).collect()
+}
+
+/// Candidate commands that write the clipboard's text to stdout.
+fn paste_commands() -> Vec<Vec<&'static str>> {
+    if cfg!(target_os = "macos") {
+        vec![vec!["pbpaste"]]
+    } else if cfg!(target_os = "windows") {
+        vec![vec!["powershell", "-NoProfile", "-Command", "Get-Clipboard"]]
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        vec![vec!["wl-paste", "--no-newline"], vec!["xclip", "-selection", "clipboard", "-o"]]
+    } else {
+        vec![vec!["xclip", "-selection", "clipboard", "-o"], vec!["xsel", "--clipboard", "--output"]]
+    }
+}
+
+/// Places `data` on the system clipboard, trying each known tool in turn.
+pub fn copy(data: &[u8], mime: &str) -> io::Result<()> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no clipboard tool available");
+    for command in copy_commands(mime) {
+        let child = Command::new(&command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(err) => { last_error = err; continue; }
+        };
+        child.stdin.take().expect("Clipboard tool stdin is piped").write_all(data)?;
+        if child.wait()?.success() {
+            return Ok(());
+        }
+        last_error = io::Error::other(format!("{} failed", command[0]));
+    }
+    Err(last_error)
+}
+
+/// The text on the system clipboard, trying each known tool in turn.
+pub fn paste() -> io::Result<String> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no clipboard tool available");
+    for command in paste_commands() {
+        let output = Command::new(command[0]).args(&command[1..]).stdin(Stdio::null()).stderr(Stdio::null()).output();
+        match output {
+            Ok(output) if output.status.success() => return Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+            Ok(_) => last_error = io::Error::other(format!("{} failed", command[0])),
+            Err(err) => last_error = err,
+        }
+    }
+    Err(last_error)
+}
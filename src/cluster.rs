@@ -0,0 +1,133 @@
+//! Finding clusters among the dots, for looking at datasets.
+
+use crate::diagram::random;
+use crate::spatial::Grid;
+
+/// Density-based clustering: dots with at least `min_points` dots (themselves
+/// included) within `eps` are core dots, and clusters are what can be reached
+/// from one core dot to the next. Returns every dot's cluster, or `None` for
+/// noise, and the number of clusters.
+pub fn dbscan(dots: &[[f64; 2]], eps: f64, min_points: usize) -> (Vec<Option<usize>>, usize) {
+    let grid = Grid::new(dots);
+    let mut labels = vec![None; dots.len()];
+    let mut visited = vec![false; dots.len()];
+    let mut clusters = 0;
+    for start in 0..dots.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let neighbors = grid.within(dots, dots[start], eps);
+        if neighbors.len() < min_points {
+            // Noise for now, a later cluster may still reach it
+            continue;
+        }
+        let cluster = clusters;
+        clusters += 1;
+        labels[start] = Some(cluster);
+        let mut frontier = neighbors;
+        while let Some(i) = frontier.pop() {
+            if labels[i].is_none() {
+                labels[i] = Some(cluster);
+            }
+            if visited[i] {
+                continue;
+            }
+            visited[i] = true;
+            let reached = grid.within(dots, dots[i], eps);
+            if reached.len() >= min_points {
+                frontier.extend(reached);
+            }
+        }
+    }
+    (labels, clusters)
+}
+
+/// Lloyd's k-means, one step at a time so it can be watched.
+pub struct KMeans {
+    pub centroids: Vec<[f64; 2]>,
+    /// Every dot's cluster as of the last step.
+    pub labels: Vec<usize>,
+    pub steps: usize,
+    /// Whether the last step changed nothing.
+    pub converged: bool,
+}
+
+impl KMeans {
+    /// Picks `k` starting centroids among the dots by k-means++: each one
+    /// at random, but the farther from the ones before, the likelier.
+    pub fn new(dots: &[[f64; 2]], k: usize) -> KMeans {
+        let mut centroids: Vec<[f64; 2]> = Vec::new();
+        if let Some(first) = pick(dots.len(), |_| 1.0) {
+            centroids.push(dots[first]);
+        }
+        while centroids.len() < k.min(dots.len()) {
+            let weight = |i: usize| centroids.iter().map(|c| distance(&dots[i], c)).fold(f64::INFINITY, f64::min);
+            let Some(next) = pick(dots.len(), weight) else { break };
+            centroids.push(dots[next]);
+        }
+        KMeans { centroids, labels: Vec::new(), steps: 0, converged: false }
+    }
+
+    /// Assigns every dot to its nearest centroid, then moves each centroid
+    /// to the middle of its dots. Returns the dots whose cluster changed;
+    /// if the number of dots changed, that's all of them.
+    pub fn step(&mut self, dots: &[[f64; 2]]) -> Vec<usize> {
+        if self.centroids.is_empty() {
+            return Vec::new();
+        }
+        let resized = self.labels.len() != dots.len();
+        self.labels.resize(dots.len(), 0);
+        let mut changed = Vec::new();
+        for (i, dot) in dots.iter().enumerate() {
+            let nearest = (0..self.centroids.len())
+                .min_by(|&a, &b| distance(dot, &self.centroids[a]).total_cmp(&distance(dot, &self.centroids[b])))
+                .unwrap_or(0);
+            if resized || self.labels[i] != nearest {
+                self.labels[i] = nearest;
+                changed.push(i);
+            }
+        }
+
+        let mut sums = vec![[0.0, 0.0, 0.0]; self.centroids.len()];
+        for (dot, &label) in dots.iter().zip(&self.labels) {
+            sums[label][0] += dot[0];
+            sums[label][1] += dot[1];
+            sums[label][2] += 1.0;
+        }
+        let mut moved = false;
+        for (centroid, [x, y, count]) in self.centroids.iter_mut().zip(sums) {
+            // A centroid without dots stays where it is
+            if count > 0.0 {
+                let middle = [x / count, y / count];
+                moved |= middle != *centroid;
+                *centroid = middle;
+            }
+        }
+        self.steps += 1;
+        self.converged = changed.is_empty() && !moved;
+        changed
+    }
+}
+
+/// A random index below `count`, each with a chance proportional to its
+/// weight. `None` if there's nothing to pick.
+fn pick(count: usize, weight: impl Fn(usize) -> f64) -> Option<usize> {
+    let weights = (0..count).map(weight).collect::<Vec<_>>();
+    let total = weights.iter().sum::<f64>();
+    if count == 0 || total <= 0.0 {
+        return None;
+    }
+    let mut left = random::<f64>() * total;
+    for (i, w) in weights.iter().enumerate() {
+        if left < *w {
+            return Some(i);
+        }
+        left -= w;
+    }
+    Some(count - 1)
+}
+
+fn distance(a: &[f64; 2], b: &[f64; 2]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)
+}
@@ -0,0 +1,90 @@
+//! Color helpers. Colors are `[r, g, b, a]` with every channel in `0.0..=1.0`.
+
+use crate::diagram::{random, random_color};
+
+/// Where the colors of new cells come from.
+#[derive(Clone, Default)]
+pub enum Palette {
+    /// Any color at all.
+    #[default]
+    Random,
+    /// Random shades around one hue (in turns).
+    Hue(f32),
+    /// Any of these colors, like those of a theme.
+    List(Vec<[f32; 4]>),
+}
+
+impl Palette {
+    pub fn color(&self) -> [f32; 4] {
+        match self {
+            Palette::Random => random_color(),
+            Palette::List(colors) if !colors.is_empty() => colors[random::<usize>() % colors.len()],
+            Palette::List(_) => random_color(),
+            &Palette::Hue(hue) => hsv(
+                hue + (random::<f32>() - 0.5) * 0.1,
+                0.4 + random::<f32>() * 0.5,
+                0.5 + random::<f32>() * 0.5),
+        }
+    }
+}
+
+/// Converts hue (turns, `0.0..1.0`), saturation and value to an opaque color.
+pub fn hsv(hue: f32, saturation: f32, value: f32) -> [f32; 4] {
+    let h = hue.rem_euclid(1.0) * 6.0;
+    let c = value * saturation;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x)
+    };
+    let m = value - c;
+    [r + m, g + m, b + m, 1.0]
+}
+
+/// Reads a color written `#rrggbb`, or `#rrggbbaa` with its opacity, in hex
+/// like in CSS. The `#` may be left out.
+pub fn parse_color(text: &str) -> Result<[f32; 4], String> {
+    let hex = text.trim().trim_start_matches('#');
+    let bad = || format!("Bad color \"{}\", use #rrggbb or #rrggbbaa", text);
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return Err(bad());
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map(|v| v as f32 / 255.0).map_err(|_| bad());
+    let alpha = if hex.len() == 8 { channel(6)? } else { 1.0 };
+    Ok([channel(0)?, channel(2)?, channel(4)?, alpha])
+}
+
+/// A random shade of the `k`th of `count` hues spread around the color wheel,
+/// for telling groups of cells apart while the cells stay distinct.
+pub fn group_color(k: usize, count: usize) -> [f32; 4] {
+    hsv(k as f32 / count.max(1) as f32, 0.4 + random::<f32>() * 0.4, 0.6 + random::<f32>() * 0.4)
+}
+
+/// The strongest shade of the `k`th of `count` hues, for marking a group.
+pub fn group_marker_color(k: usize, count: usize) -> [f32; 4] {
+    hsv(k as f32 / count.max(1) as f32, 0.9, 0.85)
+}
+
+/// What `group_color` gives the cells that aren't in any group.
+pub const UNGROUPED: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+/// Clearly distinct colors for teams, reused when there are more teams.
+const TEAM_COLORS: [[f32; 4]; 9] = [
+    [0.89, 0.10, 0.11, 1.0],
+    [0.22, 0.49, 0.72, 1.0],
+    [0.30, 0.69, 0.29, 1.0],
+    [1.00, 0.50, 0.00, 1.0],
+    [0.60, 0.31, 0.64, 1.0],
+    [1.00, 0.85, 0.18, 1.0],
+    [0.65, 0.34, 0.16, 1.0],
+    [0.97, 0.51, 0.75, 1.0],
+    [0.10, 0.75, 0.80, 1.0],
+];
+
+pub fn team_color(team: usize) -> [f32; 4] {
+    TEAM_COLORS[team % TEAM_COLORS.len()]
+}
@@ -0,0 +1,32 @@
+//! Coloring a graph so that no two neighbors share a color, with as few
+//! colors as can be managed quickly: DSATUR, which always colors next the
+//! node whose neighbors already have the most different colors, giving it
+//! the first color none of them has. Any map can be colored with four
+//! colors, and on the cells of a diagram DSATUR mostly gets by with those,
+//! now and then needing a fifth.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+/// A color number for every node of the graph with the `neighbors` of each,
+/// counting from zero, different from those of all its neighbors.
+pub fn dsatur(neighbors: &[Vec<usize>]) -> Vec<usize> {
+    let mut colors = vec![None; neighbors.len()];
+    let mut seen = vec![HashSet::new(); neighbors.len()];
+    // Ties go to the node with the most neighbors, then to the first one.
+    // Entries go stale as nodes see more colors, and are skipped then.
+    let mut queue = (0..neighbors.len()).map(|v| (0, neighbors[v].len(), Reverse(v))).collect::<BinaryHeap<_>>();
+    while let Some((saturation, _, Reverse(v))) = queue.pop() {
+        if colors[v].is_some() || saturation != seen[v].len() {
+            continue;
+        }
+        let color = (0..).find(|c| !seen[v].contains(c)).unwrap_or_default();
+        colors[v] = Some(color);
+        for &w in &neighbors[v] {
+            if colors[w].is_none() && seen[w].insert(color) {
+                queue.push((seen[w].len(), neighbors[w].len(), Reverse(w)));
+            }
+        }
+    }
+    colors.into_iter().map(Option::unwrap_or_default).collect()
+}
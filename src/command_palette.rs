@@ -0,0 +1,182 @@
+//! The command palette: every action by name, found by typing a few of its
+//! letters, for when the key is forgotten or there is none. Most actions
+//! are the same as a key press and list it, so the palette teaches the keys
+//! as well.
+
+use piston_window::Key;
+
+use crate::control::Command;
+
+/// How many matches the palette shows at once.
+pub const SHOWN: usize = 12;
+/// What a letter of the query adds to the score when it's the first of a
+/// word of the action's name, or right after the letter before.
+const WORD_START_BONUS: i32 = 6;
+const ADJACENT_BONUS: i32 = 8;
+
+#[derive(Clone, Copy)]
+pub enum Run {
+    Key(Key),
+    Shift(Key),
+    Ctrl(Key),
+    /// Actions without a key of their own.
+    Command(Command),
+}
+
+pub struct Action {
+    pub name: &'static str,
+    pub run: Run,
+}
+
+const fn action(name: &'static str, run: Run) -> Action {
+    Action { name, run }
+}
+
+pub const ACTIONS: &[Action] = &[
+    action("Show keys and modes", Run::Key(Key::F1)),
+    action("Clear the screen", Run::Key(Key::N)),
+    action("Random dots", Run::Key(Key::R)),
+    action("Clusters of random dots", Run::Key(Key::U)),
+    action("Find clusters with DBSCAN", Run::Key(Key::D)),
+    action("Start or stop k-means", Run::Key(Key::J)),
+    action("Switch Voronoi and power diagram", Run::Key(Key::Q)),
+    action("Switch flat and hyperbolic plane", Run::Shift(Key::Q)),
+    action("Merge cells of the same color into regions", Run::Key(Key::O)),
+    action("Toggle statistics", Run::Key(Key::I)),
+    action("Cycle beta-skeleton and Urquhart graph", Run::Shift(Key::S)),
+    action("Toggle smallest enclosing circle", Run::Shift(Key::C)),
+    action("Toggle smallest oriented box", Run::Shift(Key::X)),
+    action("Toggle triangle centers", Run::Shift(Key::V)),
+    action("Toggle spatial grid overlay", Run::Shift(Key::D)),
+    action("Cycle convex layers", Run::Shift(Key::O)),
+    action("Refine mesh", Run::Shift(Key::R)),
+    action("Cycle triangle quality coloring", Run::Shift(Key::I)),
+    action("Collapse dots to cell corners", Run::Key(Key::W)),
+    action("Undo", Run::Ctrl(Key::Z)),
+    action("Toggle keyboard cursor", Run::Key(Key::Z)),
+    action("Toggle wireframe", Run::Key(Key::L)),
+    action("Cycle side-by-side comparison", Run::Shift(Key::W)),
+    action("Change colors", Run::Key(Key::C)),
+    action("Next theme", Run::Shift(Key::A)),
+    action("Copy SVG to clipboard", Run::Ctrl(Key::C)),
+    action("Paste dots from clipboard", Run::Ctrl(Key::V)),
+    action("Toggle noise colors", Run::Ctrl(Key::N)),
+    action("Next document format", Run::Ctrl(Key::D)),
+    action("Print dots to console", Run::Key(Key::S)),
+    action("Toggle presentation mode", Run::Key(Key::P)),
+    action("Export diagram (SVG, PNG, PDF, EPS, mesh)", Run::Key(Key::E)),
+    action("Export laser cutting paths", Run::Shift(Key::E)),
+    action("Export repeating texture", Run::Shift(Key::T)),
+    action("Export normal map", Run::Shift(Key::N)),
+    action("Cycle terrain", Run::Shift(Key::H)),
+    action("Export heightmap", Run::Shift(Key::M)),
+    action("Export centroids", Run::Shift(Key::J)),
+    action("Export edges for pen plotters", Run::Shift(Key::Y)),
+    action("Fit dots into view", Run::Shift(Key::Z)),
+    action("Run script", Run::Shift(Key::K)),
+    action("Toggle TSP tour", Run::Shift(Key::L)),
+    action("Export tour for pen plotters", Run::Shift(Key::U)),
+    action("Export time-lapse", Run::Key(Key::G)),
+    action("Toggle crystal growth", Run::Shift(Key::G)),
+    action("Toggle trail mode", Run::Key(Key::T)),
+    action("Relax one step (Lloyd, repulsion or equal area)", Run::Command(Command::Relax(1))),
+    action("Start or stop relaxing", Run::Key(Key::F)),
+    action("Switch relaxation method (Lloyd, repulsion, equal area)", Run::Key(Key::M)),
+    action("Toggle cursor attraction", Run::Key(Key::A)),
+    action("Switch mouse tool", Run::Key(Key::B)),
+    action("Step through Fortune's algorithm", Run::Shift(Key::F)),
+    action("Cycle shortest path search", Run::Shift(Key::P)),
+    action("Toggle Bowyer-Watson insertion", Run::Shift(Key::B)),
+    action("Switch stamp shape", Run::Key(Key::H)),
+    action("Toggle mirroring left to right", Run::Key(Key::X)),
+    action("Toggle mirroring top to bottom", Run::Key(Key::Y)),
+    action("Toggle kaleidoscope", Run::Key(Key::K)),
+    action("Next layer", Run::Key(Key::Tab)),
+    action("Show or hide layer", Run::Key(Key::V)),
+];
+
+/// What was typed so far and which of the matches is picked.
+#[derive(Default)]
+pub struct CommandPalette {
+    pub query: String,
+    pub selected: usize,
+}
+
+impl CommandPalette {
+    /// The actions matching the query, best first.
+    pub fn matches(&self) -> Vec<&'static Action> {
+        let mut scored = ACTIONS.iter()
+            .filter_map(|action| score(&self.query, action.name).map(|score| (score, action)))
+            .collect::<Vec<_>>();
+        // Stable, so equally good matches keep the order of `ACTIONS`
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        scored.into_iter().map(|(_, action)| action).collect()
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.query.extend(text.chars().filter(|c| !c.is_control()));
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    /// Moves the pick up or down the matches, wrapping around.
+    pub fn step(&mut self, down: bool) {
+        let count = self.matches().len().max(1);
+        self.selected = if down { (self.selected + 1) % count } else { (self.selected + count - 1) % count };
+    }
+
+    pub fn picked(&self) -> Option<&'static Action> {
+        self.matches().get(self.selected).copied()
+    }
+}
+
+/// How well `query` matches `name`, or `None` if its letters don't all
+/// appear in `name` in order. Of all the ways they could appear, takes the
+/// best, where letters at the start of words and right after the one
+/// before count more. Shorter names break ties.
+fn score(query: &str, name: &str) -> Option<i32> {
+    let name = name.to_lowercase().chars().collect::<Vec<_>>();
+    let query = query.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect::<Vec<_>>();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let word_start = |j: usize| j == 0 || !name[j - 1].is_alphanumeric();
+    // The best score for the letters so far with the last of them at each
+    // position of the name, `None` where they can't all be before it
+    let mut best: Vec<Option<i32>> = Vec::new();
+    for (i, &wanted) in query.iter().enumerate() {
+        let mut next = vec![None; name.len()];
+        // The best with the letter before anywhere left of `j`
+        let mut before = (i == 0).then_some(0);
+        for j in 0..name.len() {
+            if name[j] == wanted {
+                let bonus = if word_start(j) { WORD_START_BONUS } else { 0 };
+                let adjacent = if i > 0 && j > 0 { best[j - 1].map(|s| s + ADJACENT_BONUS) } else { None };
+                next[j] = before.max(adjacent).map(|s| s + bonus);
+            }
+            if i > 0 {
+                before = before.max(best[j]);
+            }
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max().map(|score| score - name.len() as i32 / 8)
+}
+
+/// The key that does the same, like `Shift+V`.
+pub fn shortcut(run: Run) -> Option<String> {
+    match run {
+        Run::Key(key) => Some(key_name(key)),
+        Run::Shift(key) => Some(format!("Shift+{}", key_name(key))),
+        Run::Ctrl(key) => Some(format!("Ctrl+{}", key_name(key))),
+        Run::Command(_) => None,
+    }
+}
+
+fn key_name(key: Key) -> String {
+    format!("{:?}", key)
+}
@@ -0,0 +1,120 @@
+//! Side by side comparison: the active layer twice at half size, on the
+//! left as it is and on the right under another setting, so the difference
+//! a setting makes is plain to see. Both halves show the same dots, and
+//! clicks on either edit them.
+
+use piston_window::{Event, Input, Motion, TouchArgs};
+
+use crate::diagram::Diagram;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// Each half is the diagram at this size.
+pub const SCALE: f64 = 0.5;
+/// The Lloyd steps the right half takes the dots through to show them relaxed.
+const RELAXED_STEPS: usize = 20;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Comparison {
+    /// Filled cells next to the wireframe, or the other way around.
+    Wireframe,
+    /// The Voronoi diagram next to the power diagram, or the other way around.
+    Power,
+    /// The dots as they are next to where relaxation takes them.
+    Relaxed,
+}
+
+impl Comparison {
+    /// The comparison after this one, none after the last.
+    pub fn next(self) -> Option<Comparison> {
+        match self {
+            Comparison::Wireframe => Some(Comparison::Power),
+            Comparison::Power => Some(Comparison::Relaxed),
+            Comparison::Relaxed => None,
+        }
+    }
+
+    /// What the left and right half show.
+    pub fn labels(self, diagram: &Diagram, lines_only: bool) -> [String; 2] {
+        let (left, right) = match self {
+            Comparison::Wireframe if lines_only => ("Wireframe", "Filled"),
+            Comparison::Wireframe => ("Filled", "Wireframe"),
+            Comparison::Power if diagram.power => ("Power", "Voronoi"),
+            Comparison::Power => ("Voronoi", "Power"),
+            Comparison::Relaxed => return ["Now".to_string(), format!("After {} Lloyd steps", RELAXED_STEPS)],
+        };
+        [left.to_string(), right.to_string()]
+    }
+}
+
+pub struct Split {
+    pub comparison: Comparison,
+    /// The revision of the diagram the right half was made from.
+    revision: u64,
+    /// What the right half shows, `None` for the same diagram.
+    right: Option<Diagram>,
+}
+
+impl Split {
+    pub fn new(comparison: Comparison) -> Split {
+        Split { comparison, revision: 0, right: None }
+    }
+
+    /// Makes the right half anew if `diagram` changed since.
+    pub fn update(&mut self, diagram: &Diagram) {
+        if self.revision == diagram.revision() {
+            return;
+        }
+        self.revision = diagram.revision();
+        self.right = match self.comparison {
+            Comparison::Wireframe => None,
+            Comparison::Power => {
+                let mut right = diagram.merged(&[], &[]);
+                right.set_power(!diagram.power);
+                Some(right)
+            }
+            Comparison::Relaxed => {
+                let mut right = diagram.merged(&[], &[]);
+                for _ in 0..RELAXED_STEPS {
+                    right.relax();
+                }
+                Some(right)
+            }
+        };
+    }
+
+    /// The diagram the right half shows.
+    pub fn right<'a>(&'a self, diagram: &'a Diagram) -> &'a Diagram {
+        self.right.as_ref().unwrap_or(diagram)
+    }
+}
+
+/// Where the top left corners of the halves are, which are `SCALE` times
+/// the size of the diagram.
+pub fn corners() -> [[f64; 2]; 2] {
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    [[0.0, height / 4.0], [width / 2.0, height / 4.0]]
+}
+
+/// The event with the cursor or touch position on either half moved to
+/// where it is in the diagram.
+pub fn map_event(e: &Event) -> Event {
+    match e {
+        Event::Input(Input::Move(Motion::MouseCursor(p)), timestamp) =>
+            Event::Input(Input::Move(Motion::MouseCursor(to_diagram(*p))), *timestamp),
+        Event::Input(Input::Move(Motion::Touch(args)), timestamp) => {
+            let [x, y] = to_diagram(args.position());
+            let args = TouchArgs { position_3d: [x, y, args.position_3d[2]], ..*args };
+            Event::Input(Input::Move(Motion::Touch(args)), *timestamp)
+        }
+        _ => e.clone()
+    }
+}
+
+fn to_diagram(p: [f64; 2]) -> [f64; 2] {
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    let corner = corners()[usize::from(p[0] >= width / 2.0)];
+    [
+        ((p[0] - corner[0]) / SCALE).clamp(0.0, width),
+        ((p[1] - corner[1]) / SCALE).clamp(0.0, height),
+    ]
+}
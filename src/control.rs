@@ -0,0 +1,24 @@
+//! Commands arriving from outside the window, e.g. over the network. Each
+//! listener runs on its own thread and sends its commands to the app.
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+#[derive(Clone, Copy)]
+pub enum Command {
+    AddPoint([f64; 2]),
+    AddColoredPoint([f64; 2], [f32; 4]),
+    MovePoint(usize, [f64; 2]),
+    RemovePoint(usize),
+    Clear,
+    /// Nudges every dot randomly by up to this many pixels.
+    Jitter(f64),
+    /// Runs this many relaxation steps, of the kind chosen in the app.
+    Relax(usize),
+    /// Keeps relaxing continuously, at this many steps per second.
+    SetRelaxRate(f64),
+}
+
+/// The receiving end lives in the app, every listener gets a sender.
+pub fn command_channel() -> (Sender<Command>, Receiver<Command>) {
+    channel()
+}
@@ -0,0 +1,572 @@
+//! Delaunay triangulation by the Bowyer–Watson algorithm: every dot removes
+//! the triangles whose circumcircles it lies in and fills the hole with
+//! triangles of its own. Every decision goes through the exact predicates,
+//! so co-circular and collinear dots, which symmetric layouts have plenty
+//! of, always give a valid triangulation, and the same one for the same dots
+//! in the same order. Dots can be added one at a time afterwards, which
+//! only touches the triangles around the new one, and taken out again,
+//! which only touches the triangles around the old one.
+
+use std::collections::{HashMap, HashSet};
+use std::cmp::Ordering;
+
+use crate::diagram::Point;
+use crate::predicates::{in_circle, orient};
+
+/// A triangle as indices into the dots, counter-clockwise with y pointing up.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Triangle(pub usize, pub usize, pub usize);
+
+/// How adding a dot changes the triangulation, to show the algorithm at
+/// work. The corners of the triangles may be those of the square around
+/// all dots.
+pub struct Cavity {
+    /// The triangles whose circumcircles were checked for the dot, in the
+    /// order they were, and whether it's inside, which takes them out. The
+    /// first is the one the dot lies in.
+    pub tests: Vec<([[f64; 2]; 3], bool)>,
+    /// The edges around the hole left by the triangles taken out, each of
+    /// which makes a new triangle with the dot.
+    pub border: Vec<[[f64; 2]; 2]>,
+}
+
+/// The corners of the square around all dots come first in `points`.
+const CORNERS: usize = 4;
+/// Resolution of the Hilbert curve the dots are sorted along, in bits per axis.
+const HILBERT_BITS: u32 = 16;
+
+struct Face {
+    corners: [usize; 3],
+    /// The face across the edge opposite each corner, `None` outside the square.
+    neighbors: [Option<usize>; 3],
+}
+
+pub struct Delaunay {
+    points: Vec<[f64; 2]>,
+    faces: Vec<Face>,
+    /// A face at each point, `None` for dots that were left out.
+    face_at: Vec<Option<usize>>,
+    /// The search for the face a new dot lies in starts here.
+    last: usize,
+}
+
+impl Delaunay {
+    /// Triangulates the dots inside the square around `center` reaching
+    /// `radius` in every direction. Dots outside it, and dots at the exact
+    /// place of an earlier one, are left out and get no triangles or cell.
+    pub fn new(dots: &[[f64; 2]], center: [f64; 2], radius: f64) -> Delaunay {
+        let [x, y] = center;
+        let mut points = vec![[x - radius, y - radius], [x + radius, y - radius], [x + radius, y + radius], [x - radius, y + radius]];
+        points.extend_from_slice(dots);
+        let mut delaunay = Delaunay {
+            faces: vec![
+                Face { corners: [0, 1, 3], neighbors: [Some(1), None, None] },
+                Face { corners: [2, 3, 1], neighbors: [Some(0), None, None] },
+            ],
+            face_at: vec![Some(0), Some(0), Some(1), Some(0)],
+            last: 0,
+            points,
+        };
+        delaunay.face_at.resize(delaunay.points.len(), None);
+        for dot in insertion_order(dots) {
+            delaunay.insert(dot + CORNERS);
+        }
+        delaunay
+    }
+
+    /// Adds a dot after the ones already there. Returns the dots whose cells
+    /// changed, the new one included, or nothing if it was left out.
+    pub fn add(&mut self, dot: [f64; 2]) -> Vec<usize> {
+        self.points.push(dot);
+        self.face_at.push(None);
+        self.insert(self.points.len() - 1).into_iter()
+            .filter(|&point| point >= CORNERS)
+            .map(|point| point - CORNERS)
+            .collect()
+    }
+
+    /// Takes out the dots at `dots`, filling the hole each leaves with the
+    /// Delaunay triangles of the dots around it. The dots after them move
+    /// up, like in `Vec::retain`. Returns the dots whose cells changed, by
+    /// their new indices, or `None` if a hole couldn't be filled, which
+    /// leaves the triangulation broken, to be made anew.
+    pub fn remove(&mut self, dots: &[usize]) -> Option<Vec<usize>> {
+        // Holes are dug in order of the dots, so the same dots always give
+        // the same triangles, the set is only there to look them up
+        let mut order = dots.iter().map(|&dot| dot + CORNERS).collect::<Vec<_>>();
+        order.sort_unstable();
+        order.dedup();
+        let removed = order.iter().copied().collect::<HashSet<_>>();
+        let mut free = Vec::new();
+        let mut changed = HashSet::new();
+        for &point in &order {
+            if self.face_at[point].is_some() {
+                changed.extend(self.dig(point, &mut free)?);
+            }
+        }
+
+        let point_index = self.compact(free, &removed);
+
+        let mut changed = changed.into_iter()
+            .filter_map(|point| point_index[point])
+            .filter(|&point| point >= CORNERS)
+            .map(|point| point - CORNERS)
+            .collect::<Vec<_>>();
+        changed.sort_unstable();
+        Some(changed)
+    }
+
+    /// Drops the faces in `free` and the points in `removed`, and renumbers
+    /// the rest. Returns the new index of every point, `None` for the
+    /// removed ones.
+    fn compact(&mut self, free: Vec<usize>, removed: &HashSet<usize>) -> Vec<Option<usize>> {
+        let mut face_index = vec![None; self.faces.len()];
+        let free = free.into_iter().collect::<HashSet<_>>();
+        let mut kept = 0;
+        for (face, index) in face_index.iter_mut().enumerate() {
+            if !free.contains(&face) {
+                *index = Some(kept);
+                kept += 1;
+            }
+        }
+        let mut face = 0;
+        self.faces.retain(|_| { face += 1; face_index[face - 1].is_some() });
+        let mut point_index = vec![None; self.points.len()];
+        let mut kept = 0;
+        for (point, index) in point_index.iter_mut().enumerate() {
+            if !removed.contains(&point) {
+                *index = Some(kept);
+                kept += 1;
+            }
+        }
+        for face in &mut self.faces {
+            face.corners = face.corners.map(|c| point_index[c].expect("faces only have corners that are left"));
+            face.neighbors = face.neighbors.map(|n| n.and_then(|n| face_index[n]));
+        }
+        let mut point = 0;
+        self.points.retain(|_| { point += 1; point_index[point - 1].is_some() });
+        let mut point = 0;
+        self.face_at.retain(|_| { point += 1; point_index[point - 1].is_some() });
+        for face in self.face_at.iter_mut() {
+            *face = face.and_then(|f| face_index[f]);
+        }
+        self.last = face_index[self.last].unwrap_or(0);
+        point_index
+    }
+
+    /// Moves the dot at `dot` to `to`, taking it out and putting it back in
+    /// at its new place under the same index. Returns the dots whose cells
+    /// changed, or `None` if the hole it left couldn't be filled, which
+    /// leaves the triangulation broken, to be made anew.
+    pub fn move_dot(&mut self, dot: usize, to: [f64; 2]) -> Option<Vec<usize>> {
+        let point = dot + CORNERS;
+        let mut changed = HashSet::from([point]);
+        if self.face_at[point].is_some() {
+            let mut free = Vec::new();
+            changed.extend(self.dig(point, &mut free)?);
+            self.compact(free, &HashSet::new());
+        }
+        self.points[point] = to;
+        changed.extend(self.insert(point));
+        let mut changed = changed.into_iter()
+            .filter(|&point| point >= CORNERS)
+            .map(|point| point - CORNERS)
+            .collect::<Vec<_>>();
+        changed.sort_unstable();
+        Some(changed)
+    }
+
+    /// Takes the faces around `point` out and fills their hole without it,
+    /// leaving the slots of the two faces fewer in `free`. Returns the
+    /// points around it, or `None` if the hole has no Delaunay ear.
+    fn dig(&mut self, point: usize, free: &mut Vec<usize>) -> Option<Vec<usize>> {
+        let start = self.face_at[point]?;
+        let mut star = Vec::new();
+        let mut face = start;
+        loop {
+            star.push(face);
+            let Face { corners, neighbors } = &self.faces[face];
+            let at = corners.iter().position(|&c| c == point)?;
+            face = neighbors[(at + 1) % 3]?;
+            if face == start {
+                break;
+            }
+        }
+
+        // The edges around the star go counter-clockwise like the faces
+        let border = self.border(&star);
+        let beyond = border.iter().map(|&(a, b, beyond)| ((a, b), beyond)).collect::<HashMap<_, _>>();
+        let next = border.iter().map(|&(a, b, _)| (a, b)).collect::<HashMap<_, _>>();
+        let mut ring = vec![border[0].0];
+        while ring.len() < border.len() {
+            ring.push(*next.get(ring.last()?)?);
+        }
+        let around = ring.clone();
+        let triangles = self.fill(ring)?;
+
+        let (slots, rest) = star.split_at(triangles.len());
+        free.extend_from_slice(rest);
+        let mut edges = HashMap::new();
+        for (&slot, &corners) in slots.iter().zip(&triangles) {
+            self.faces[slot] = Face { corners, neighbors: [None; 3] };
+            for i in 0..3 {
+                edges.insert((corners[(i + 1) % 3], corners[(i + 2) % 3]), (slot, i));
+            }
+            for corner in corners {
+                self.face_at[corner] = Some(slot);
+            }
+        }
+        for (&(a, b), &(slot, i)) in &edges {
+            self.faces[slot].neighbors[i] = match beyond.get(&(a, b)) {
+                Some(&beyond) => {
+                    if let Some(beyond) = beyond {
+                        let across = &mut self.faces[beyond];
+                        let opposite = (0..3).find(|&j| across.corners[j] != a && across.corners[j] != b)?;
+                        across.neighbors[opposite] = Some(slot);
+                    }
+                    beyond
+                }
+                None => Some(edges.get(&(b, a))?.0)
+            };
+        }
+        self.face_at[point] = None;
+        self.last = slots[0];
+        Some(around)
+    }
+
+    /// Triangles filling the counter-clockwise polygon `ring`, each of
+    /// which has none of its corners in its circumcircle, cut off as ears
+    /// one by one. The hole a dot leaves always has such ears.
+    fn fill(&self, mut ring: Vec<usize>) -> Option<Vec<[usize; 3]>> {
+        let corners = ring.clone();
+        let mut triangles = Vec::new();
+        while ring.len() > 3 {
+            let n = ring.len();
+            let ear = (0..n).find(|&i| {
+                let [a, b, c] = [ring[i], ring[(i + 1) % n], ring[(i + 2) % n]];
+                let [pa, pb, pc] = [a, b, c].map(|corner| self.points[corner]);
+                orient(pa, pb, pc) == Ordering::Greater && corners.iter()
+                    .filter(|&&d| d != a && d != b && d != c)
+                    .all(|&d| in_circle(pa, pb, pc, self.points[d]) != Ordering::Greater)
+            })?;
+            triangles.push([ring[ear], ring[(ear + 1) % n], ring[(ear + 2) % n]]);
+            ring.remove((ear + 1) % n);
+        }
+        triangles.push([ring[0], ring[1], ring[2]]);
+        Some(triangles)
+    }
+
+    /// Returns the points whose cells changed.
+    fn insert(&mut self, point: usize) -> Vec<usize> {
+        let p = self.points[point];
+        let Some(face) = self.locate(p) else { return Vec::new() };
+        if self.faces[face].corners.iter().any(|&c| self.points[c] == p) {
+            return Vec::new();
+        }
+
+        let hole = self.hole(face, p, |_, _| ());
+        let border = self.border(&hole);
+
+        // Fills the hole with a fan of faces around the point, reusing the
+        // slots of the faces that were removed
+        let mut slots = hole.into_iter();
+        let mut starting_at = HashMap::new();
+        for &(a, b, beyond) in &border {
+            let new = Face { corners: [point, a, b], neighbors: [beyond, None, None] };
+            let face = match slots.next() {
+                Some(slot) => { self.faces[slot] = new; slot }
+                None => { self.faces.push(new); self.faces.len() - 1 }
+            };
+            if let Some(beyond) = beyond {
+                let across = &mut self.faces[beyond];
+                let opposite = (0..3).find(|&j| across.corners[j] != a && across.corners[j] != b).unwrap();
+                across.neighbors[opposite] = Some(face);
+            }
+            starting_at.insert(a, face);
+            self.face_at[a] = Some(face);
+            self.face_at[b] = Some(face);
+            self.face_at[point] = Some(face);
+            self.last = face;
+        }
+        for &(a, b, _) in &border {
+            let (face, next) = (starting_at[&a], starting_at[&b]);
+            self.faces[face].neighbors[1] = Some(next);
+            self.faces[next].neighbors[2] = Some(face);
+        }
+        border.iter().map(|&(a, _, _)| a).chain([point]).collect()
+    }
+
+    /// The faces whose circumcircles contain `p`, which always include
+    /// `face`, the one it lies in, and form a connected hole around it.
+    /// `tested` hears of every face checked on the way and whether it's in.
+    fn hole(&self, face: usize, p: [f64; 2], mut tested: impl FnMut(usize, bool)) -> Vec<usize> {
+        let mut hole = vec![face];
+        tested(face, true);
+        let mut i = 0;
+        while i < hole.len() {
+            for next in self.faces[hole[i]].neighbors.into_iter().flatten() {
+                if hole.contains(&next) {
+                    continue;
+                }
+                let inside = self.in_circumcircle(next, p);
+                tested(next, inside);
+                if inside {
+                    hole.push(next);
+                }
+            }
+            i += 1;
+        }
+        hole
+    }
+
+    /// The border of a hole, as edges with the face beyond each of them.
+    fn border(&self, hole: &[usize]) -> Vec<(usize, usize, Option<usize>)> {
+        let mut border = Vec::new();
+        for &face in hole {
+            let Face { corners, neighbors } = &self.faces[face];
+            for i in 0..3 {
+                if !neighbors[i].is_some_and(|n| hole.contains(&n)) {
+                    border.push((corners[(i + 1) % 3], corners[(i + 2) % 3], neighbors[i]));
+                }
+            }
+        }
+        border
+    }
+
+    /// What adding `dot` would do, without doing it, or `None` if it would
+    /// be left out.
+    pub fn cavity(&self, dot: [f64; 2]) -> Option<Cavity> {
+        let face = self.locate(dot)?;
+        if self.faces[face].corners.iter().any(|&c| self.points[c] == dot) {
+            return None;
+        }
+        let corners = |face: usize| self.faces[face].corners.map(|c| self.points[c]);
+        let mut tests = Vec::<([[f64; 2]; 3], bool)>::new();
+        let hole = self.hole(face, dot, |face, inside| {
+            // Faces next to several faces of the hole are checked again
+            // from each, with the same outcome
+            if !tests.iter().any(|(t, _)| *t == corners(face)) {
+                tests.push((corners(face), inside));
+            }
+        });
+        let border = self.border(&hole).into_iter().map(|(a, b, _)| [self.points[a], self.points[b]]).collect();
+        Some(Cavity { tests, border })
+    }
+
+    /// The face `p` lies in or on the edge of, found by walking towards it
+    /// from the face added last, or `None` if it's outside the square.
+    fn locate(&self, p: [f64; 2]) -> Option<usize> {
+        let mut face = self.last;
+        'walk: loop {
+            let Face { corners, neighbors } = &self.faces[face];
+            for i in 0..3 {
+                let (a, b) = (self.points[corners[(i + 1) % 3]], self.points[corners[(i + 2) % 3]]);
+                if orient(a, b, p) == Ordering::Less {
+                    face = neighbors[i]?;
+                    continue 'walk;
+                }
+            }
+            return Some(face);
+        }
+    }
+
+    fn in_circumcircle(&self, face: usize, p: [f64; 2]) -> bool {
+        let [a, b, c] = self.faces[face].corners.map(|corner| self.points[corner]);
+        in_circle(a, b, c, p) == Ordering::Greater
+    }
+
+    /// The triangles between the dots, leaving out those that reach the
+    /// corners of the square.
+    pub fn triangles(&self) -> Vec<Triangle> {
+        self.faces.iter()
+            .filter(|face| face.corners.iter().all(|&c| c >= CORNERS))
+            .map(|face| Triangle(face.corners[0] - CORNERS, face.corners[1] - CORNERS, face.corners[2] - CORNERS))
+            .collect()
+    }
+
+    /// The Voronoi cell of every dot, see `cell`.
+    pub fn voronoi_cells(&self) -> Vec<Vec<Point>> {
+        (0..self.points.len() - CORNERS).map(|dot| self.cell(dot)).collect()
+    }
+
+    /// The Voronoi cell of a dot, as the circumcenters of the faces around
+    /// it in order. Cells at the edge of the diagram reach far out, towards
+    /// the corners of the square. Dots that were left out have none.
+    pub fn cell(&self, dot: usize) -> Vec<Point> {
+        let point = dot + CORNERS;
+        let Some(start) = self.face_at[point] else { return Vec::new() };
+        let mut cell = Vec::new();
+        let mut face = start;
+        loop {
+            let Face { corners, neighbors } = &self.faces[face];
+            cell.push(self.circumcenter(&self.faces[face]));
+            let at = corners.iter().position(|&c| c == point).unwrap();
+            face = neighbors[(at + 1) % 3].unwrap();
+            if face == start {
+                return cell;
+            }
+        }
+    }
+
+    fn circumcenter(&self, face: &Face) -> Point {
+        let [a, b, c] = face.corners.map(|corner| self.points[corner]);
+        let (bx, by) = (b[0] - a[0], b[1] - a[1]);
+        let (cx, cy) = (c[0] - a[0], c[1] - a[1]);
+        let (b_length, c_length) = (bx * bx + by * by, cx * cx + cy * cy);
+        // Never zero, the exact predicates leave no flat faces
+        let scale = 0.5 / (bx * cy - by * cx);
+        (a[0] + (cy * b_length - by * c_length) * scale, a[1] + (bx * c_length - cx * b_length) * scale)
+    }
+}
+
+/// The dots in the order they get inserted: along a Hilbert curve through
+/// their bounding box, so each is close to the one before and finding the
+/// face it lies in takes only a few steps.
+fn insertion_order(dots: &[[f64; 2]]) -> Vec<usize> {
+    let (mut min, mut max) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+    for dot in dots {
+        for axis in 0..2 {
+            min[axis] = min[axis].min(dot[axis]);
+            max[axis] = max[axis].max(dot[axis]);
+        }
+    }
+    let cells = (1u32 << HILBERT_BITS) as f64 - 1.0;
+    let scale = cells / (max[0] - min[0]).max(max[1] - min[1]).max(f64::MIN_POSITIVE);
+    let mut order = (0..dots.len())
+        .map(|i| {
+            let [x, y] = [0, 1].map(|axis| ((dots[i][axis] - min[axis]) * scale).clamp(0.0, cells) as u32);
+            (hilbert_index(x, y), i)
+        })
+        .collect::<Vec<_>>();
+    order.sort_unstable();
+    order.into_iter().map(|(_, i)| i).collect()
+}
+
+/// How far along the Hilbert curve the grid cell at `x`, `y` is.
+fn hilbert_index(mut x: u32, mut y: u32) -> u64 {
+    let last = (1 << HILBERT_BITS) - 1;
+    let mut index = 0;
+    let mut s = 1 << (HILBERT_BITS - 1);
+    while s > 0 {
+        let (rx, ry) = ((x & s) > 0, (y & s) > 0);
+        index += (s as u64 * s as u64) * ((3 * rx as u64) ^ ry as u64);
+        // Turns the quadrant so the curve runs through it the right way
+        if !ry {
+            if rx {
+                (x, y) = (last - x, last - y);
+            }
+            (x, y) = (y, x);
+        }
+        s >>= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagram::{random, seed_random};
+
+    const CENTER: [f64; 2] = [500.0, 500.0];
+    const RADIUS: f64 = 2000.0;
+
+    /// The triangles, each starting at its smallest corner, in order.
+    fn normalized(delaunay: &Delaunay) -> Vec<[usize; 3]> {
+        let mut triangles = delaunay.triangles().into_iter()
+            .map(|Triangle(a, b, c)| {
+                let corners = [a, b, c];
+                let first = (0..3).min_by_key(|&i| corners[i]).unwrap();
+                [corners[first], corners[(first + 1) % 3], corners[(first + 2) % 3]]
+            })
+            .collect::<Vec<_>>();
+        triangles.sort_unstable();
+        triangles
+    }
+
+    /// Whether no dot lies inside the circumcircle of any face.
+    fn is_delaunay(delaunay: &Delaunay) -> bool {
+        (0..delaunay.faces.len()).all(|face| {
+            delaunay.points.iter().all(|&p| !delaunay.in_circumcircle(face, p))
+        })
+    }
+
+    fn remaining(dots: &[[f64; 2]], removed: &[usize]) -> Vec<[f64; 2]> {
+        dots.iter().enumerate()
+            .filter(|(i, _)| !removed.contains(i))
+            .map(|(_, &dot)| dot)
+            .collect()
+    }
+
+    #[test]
+    fn removal_matches_fresh_triangulation() {
+        seed_random(7);
+        let dots = (0..200).map(|_| [random::<f64>() * 1000.0, random::<f64>() * 1000.0]).collect::<Vec<_>>();
+        let removed = [3, 17, 18, 64, 120, 121, 122, 199];
+        let mut delaunay = Delaunay::new(&dots, CENTER, RADIUS);
+        delaunay.remove(&removed).unwrap();
+        let fresh = Delaunay::new(&remaining(&dots, &removed), CENTER, RADIUS);
+        assert!(is_delaunay(&delaunay));
+        assert_eq!(normalized(&delaunay), normalized(&fresh));
+    }
+
+    #[test]
+    fn cocircular_removal_stays_delaunay() {
+        // A grid, where every four neighbours share a circle
+        let dots = (0..64).map(|i| [(i % 8) as f64 * 100.0, (i / 8) as f64 * 100.0]).collect::<Vec<_>>();
+        let removed = [9, 10, 27, 36, 45, 63];
+        let mut delaunay = Delaunay::new(&dots, CENTER, RADIUS);
+        delaunay.remove(&removed).unwrap();
+        let fresh = Delaunay::new(&remaining(&dots, &removed), CENTER, RADIUS);
+        assert!(is_delaunay(&delaunay));
+        assert_eq!(delaunay.triangles().len(), fresh.triangles().len());
+        for dot in 0..dots.len() - removed.len() {
+            let mut cell = delaunay.cell(dot);
+            let mut fresh_cell = fresh.cell(dot);
+            for cell in [&mut cell, &mut fresh_cell] {
+                cell.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                cell.dedup_by(|a, b| (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9);
+            }
+            assert_eq!(cell.len(), fresh_cell.len(), "cell of dot {}", dot);
+        }
+    }
+
+    #[test]
+    fn removal_ignores_the_order_of_the_dots() {
+        let dots = (0..64).map(|i| [(i % 8) as f64 * 100.0, (i / 8) as f64 * 100.0]).collect::<Vec<_>>();
+        let mut forward = Delaunay::new(&dots, CENTER, RADIUS);
+        let mut backward = Delaunay::new(&dots, CENTER, RADIUS);
+        forward.remove(&[9, 10, 18, 27, 36]).unwrap();
+        backward.remove(&[36, 27, 18, 10, 9, 9]).unwrap();
+        assert_eq!(normalized(&forward), normalized(&backward));
+    }
+
+    #[test]
+    fn adding_matches_fresh_triangulation() {
+        seed_random(11);
+        let dots = (0..200).map(|_| [random::<f64>() * 1000.0, random::<f64>() * 1000.0]).collect::<Vec<_>>();
+        let mut delaunay = Delaunay::new(&dots[..50], CENTER, RADIUS);
+        for &dot in &dots[50..] {
+            assert!(!delaunay.add(dot).is_empty());
+        }
+        assert!(delaunay.add(dots[0]).is_empty());
+        let fresh = Delaunay::new(&dots, CENTER, RADIUS);
+        assert!(is_delaunay(&delaunay));
+        assert_eq!(normalized(&delaunay), normalized(&fresh));
+    }
+
+    #[test]
+    fn moving_matches_fresh_triangulation() {
+        seed_random(13);
+        let mut dots = (0..200).map(|_| [random::<f64>() * 1000.0, random::<f64>() * 1000.0]).collect::<Vec<_>>();
+        let mut delaunay = Delaunay::new(&dots, CENTER, RADIUS);
+        for dot in [0, 5, 99, 150, 199] {
+            let to = [random::<f64>() * 1000.0, random::<f64>() * 1000.0];
+            let changed = delaunay.move_dot(dot, to).unwrap();
+            assert!(changed.contains(&dot));
+            dots[dot] = to;
+        }
+        let fresh = Delaunay::new(&dots, CENTER, RADIUS);
+        assert!(is_delaunay(&delaunay));
+        assert_eq!(normalized(&delaunay), normalized(&fresh));
+    }
+}
@@ -0,0 +1,268 @@
+//! Where random dots land: evenly everywhere, or more often where a density
+//! function is high, so random layouts can have some structure.
+
+use std::path::Path;
+
+use crate::diagram::{random, random_point};
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// Grid points per side when looking for the highest density of an expression.
+const PROBE_STEPS: usize = 64;
+/// Candidates tried per dot before taking the last one anyway.
+const MAX_TRIES: usize = 10_000;
+
+pub enum Density {
+    Uniform,
+    /// Densest in the middle of the window, fading to nothing in the corners.
+    Radial,
+    /// Darker pixels are denser. The image is stretched over the window.
+    Image { width: usize, height: usize, darkness: Vec<f64> },
+    /// `max` is at least the highest value anywhere in the window.
+    Expression { expr: Expr, max: f64 },
+}
+
+impl Density {
+    /// `uniform`, `radial`, the path of a grayscale PNG image, or an
+    /// expression in `x`, `y` and `r` like `1 - r` or `sin(x * 10) + 1`.
+    pub fn parse(spec: &str) -> Result<Density, String> {
+        match spec {
+            "uniform" => return Ok(Density::Uniform),
+            "radial" => return Ok(Density::Radial),
+            _ => ()
+        }
+        if Path::new(spec).is_file() {
+            return load_image(spec);
+        }
+        let expr = Expr::parse(spec)?;
+        let probed = (0..=PROBE_STEPS).flat_map(|i| (0..=PROBE_STEPS).map(move |j| (i, j)))
+            .map(|(i, j)| expr.eval(i as f64 / PROBE_STEPS as f64, j as f64 / PROBE_STEPS as f64))
+            .fold(0.0, f64::max);
+        if probed <= 0.0 {
+            return Err(format!("\"{}\" isn't positive anywhere", spec));
+        }
+        // Peaks between the grid points may be a little higher
+        Ok(Density::Expression { expr, max: probed * 1.1 })
+    }
+
+    /// A random point in the window, more likely where the density is high.
+    pub fn sample(&self) -> [f64; 2] {
+        if let Density::Uniform = self {
+            return random_point();
+        }
+        let max = match self {
+            Density::Expression { max, .. } => *max,
+            _ => 1.0
+        };
+        let mut point = random_point();
+        for _ in 0..MAX_TRIES {
+            let (x, y) = (point[0] / DEFAULT_WINDOW_WIDTH as f64, point[1] / DEFAULT_WINDOW_HEIGHT as f64);
+            if random::<f64>() * max < self.at(x, y) {
+                break;
+            }
+            point = random_point();
+        }
+        point
+    }
+
+    /// The density at `x` and `y`, both from 0 to 1 across the window.
+    fn at(&self, x: f64, y: f64) -> f64 {
+        match self {
+            Density::Uniform => 1.0,
+            Density::Radial => 1.0 - radius(x, y),
+            Density::Image { width, height, darkness } => {
+                let column = ((x * *width as f64) as usize).min(width - 1);
+                let row = ((y * *height as f64) as usize).min(height - 1);
+                darkness[row * width + column]
+            }
+            Density::Expression { expr, .. } => expr.eval(x, y).max(0.0),
+        }
+    }
+}
+
+/// `count` clusters of `size` dots each, normally distributed with standard
+/// deviation `spread` around centers anywhere in the window. Dots that would
+/// land outside the window are picked again.
+pub fn gaussian_clusters(count: usize, size: usize, spread: f64) -> Vec<Vec<[f64; 2]>> {
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    (0..count).map(|_| {
+        let center = random_point();
+        (0..size).map(|_| {
+            let mut point = center;
+            for _ in 0..MAX_TRIES {
+                let [dx, dy] = gaussian_pair();
+                point = [center[0] + dx * spread, center[1] + dy * spread];
+                if (0.0..width).contains(&point[0]) && (0.0..height).contains(&point[1]) {
+                    break;
+                }
+            }
+            point
+        }).collect()
+    }).collect()
+}
+
+/// Squeezes dots anywhere in the window into the part of it at least
+/// `margin` pixels away from its border, so no cells are cut into slivers
+/// there.
+pub fn inset(dots: &mut [[f64; 2]], margin: f64) {
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    let margin = margin.clamp(0.0, width.min(height) / 2.0);
+    for dot in dots {
+        dot[0] = margin + dot[0] * (width - 2.0 * margin) / width;
+        dot[1] = margin + dot[1] * (height - 2.0 * margin) / height;
+    }
+}
+
+/// Two independent standard normal numbers, by the Box-Muller transform.
+fn gaussian_pair() -> [f64; 2] {
+    // Never zero, so the logarithm stays finite
+    let length = (-2.0 * (1.0 - random::<f64>()).ln()).sqrt();
+    let angle = random::<f64>() * std::f64::consts::TAU;
+    [length * angle.cos(), length * angle.sin()]
+}
+
+fn load_image(path: &str) -> Result<Density, String> {
+    let image = image::open(path).map_err(|err| format!("Can't load {}: {}", path, err))?.to_luma8();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let darkness = image.pixels().map(|p| 1.0 - p.0[0] as f64 / 255.0).collect::<Vec<_>>();
+    if darkness.iter().all(|&d| d <= 0.0) {
+        return Err(format!("{} is white all over", path));
+    }
+    Ok(Density::Image { width, height, darkness })
+}
+
+/// Distance from the center, 1 in the corners.
+fn radius(x: f64, y: f64) -> f64 {
+    ((x - 0.5).powi(2) + (y - 0.5).powi(2)).sqrt() / 0.5f64.sqrt()
+}
+
+/// A formula of numbers, the variables `x`, `y` and `r` (see `radius`),
+/// `+ - * / ^`, parentheses and the functions `sin cos exp abs sqrt`.
+pub enum Expr {
+    Number(f64),
+    X,
+    Y,
+    R,
+    Negate(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(fn(f64) -> f64, Box<Expr>),
+}
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Expr, String> {
+        let mut parser = Parser { chars: text.chars().filter(|c| !c.is_whitespace()).collect(), at: 0 };
+        let expr = parser.sum()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(c) => Err(format!("Unexpected \"{}\" in \"{}\"", c, text))
+        }
+    }
+
+    pub fn eval(&self, x: f64, y: f64) -> f64 {
+        match self {
+            Expr::Number(n) => *n,
+            Expr::X => x,
+            Expr::Y => y,
+            Expr::R => radius(x, y),
+            Expr::Negate(e) => -e.eval(x, y),
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.eval(x, y), b.eval(x, y));
+                match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    '/' => a / b,
+                    _ => a.powf(b),
+                }
+            }
+            Expr::Call(f, e) => f(e.eval(x, y)),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.at += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.power()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.at += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.power()?));
+        }
+        Ok(expr)
+    }
+
+    /// `^` binds tighter than a leading minus, and to the right.
+    fn power(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some('-') {
+            self.at += 1;
+            return Ok(Expr::Negate(Box::new(self.power()?)));
+        }
+        let base = self.atom()?;
+        if self.peek() == Some('^') {
+            self.at += 1;
+            return Ok(Expr::Binary('^', Box::new(base), Box::new(self.power()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let start = self.at;
+        match self.peek() {
+            Some('(') => {
+                self.at += 1;
+                let expr = self.sum()?;
+                if self.peek() != Some(')') {
+                    return Err("Missing \")\"".to_string());
+                }
+                self.at += 1;
+                Ok(expr)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+                    self.at += 1;
+                }
+                let number = self.chars[start..self.at].iter().collect::<String>();
+                number.parse().map(Expr::Number).map_err(|_| format!("Bad number \"{}\"", number))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                    self.at += 1;
+                }
+                let name = self.chars[start..self.at].iter().collect::<String>();
+                let function: fn(f64) -> f64 = match name.as_str() {
+                    "x" => return Ok(Expr::X),
+                    "y" => return Ok(Expr::Y),
+                    "r" => return Ok(Expr::R),
+                    "sin" => f64::sin,
+                    "cos" => f64::cos,
+                    "exp" => f64::exp,
+                    "abs" => f64::abs,
+                    "sqrt" => f64::sqrt,
+                    _ => return Err(format!("Unknown name \"{}\"", name))
+                };
+                if self.peek() != Some('(') {
+                    return Err(format!("Missing \"(\" after {}", name));
+                }
+                Ok(Expr::Call(function, Box::new(self.atom()?)))
+            }
+            Some(c) => Err(format!("Unexpected \"{}\"", c)),
+            None => Err("Unexpected end".to_string()),
+        }
+    }
+}
@@ -0,0 +1,1031 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rand::distributions::{Distribution, Standard};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::color::{group_color, team_color, Palette, UNGROUPED};
+use crate::delaunay::{Cavity, Delaunay, Triangle};
+use crate::density::Density;
+use crate::fit::Fit;
+use crate::geometry::{area, centroid, chaikin, clip_to_rect};
+use crate::hyperbolic::{self, hyperbolic_cells};
+use crate::metadata::Metadata;
+use crate::power::{fit_step, power_cells};
+use crate::spatial::Grid;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+pub type Point = (f64, f64);
+/// Cells as polygons, either those of a diagram or computed from them.
+pub type Cells<'a> = Cow<'a, [Vec<Point>]>;
+
+/// How close dots have to be by default to count as one.
+pub const DEFAULT_DUPLICATE_DISTANCE: f64 = 0.001;
+
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The sites of the diagram together with their colors and the Voronoi
+/// cells computed from them. `colors[i]`, `teams[i]`, `weights[i]`,
+/// `metadata[i]`, `ids[i]` and `polygons[i]` belong to `dots[i]`.
+#[derive(Default)]
+pub struct Diagram {
+    pub dots: Vec<[f64; 2]>,
+    pub colors: Vec<[f32; 4]>,
+    /// The team owning each dot, if any. Team dots are drawn in the team's color.
+    pub teams: Vec<Option<usize>>,
+    pub polygons: Vec<Vec<Point>>,
+    /// The part of each cell inside the window, which is what gets drawn.
+    pub visible: Vec<Vec<Point>>,
+    /// Colors for new dots come from here.
+    pub palette: Palette,
+    /// Finds dots by position, rebuilt whenever the cells are.
+    grid: Grid,
+    /// The Delaunay triangulation of the dots while the cells are their
+    /// Voronoi cells, so that a new dot only changes the cells around it.
+    triangulation: Option<Delaunay>,
+    /// The power diagram weight of each dot, in square pixels: a dot of
+    /// weight `r²` claims the space of a circle of radius `r` more than one
+    /// of weight zero. Only shapes the cells in power mode and while
+    /// equal-area relaxation runs.
+    pub weights: Vec<f64>,
+    /// The label, category and value each dot was loaded with, if any.
+    pub metadata: Vec<Metadata>,
+    /// A number for each dot that stays with it whatever happens to the
+    /// others, through moves, removals and undo, and is never given to
+    /// another dot. Colors, like everything else about a dot, go with it,
+    /// and exports write it so cells can be told apart between edits.
+    pub ids: Vec<u64>,
+    /// How the dots were scaled and moved into the window when they were
+    /// loaded, see `fit::Coords`, for exports in their own coordinates.
+    /// `None` while they are in the window's.
+    pub coords: Option<Fit>,
+    /// Whether the cells are the power cells of the weights, cut to the
+    /// window, instead of the Voronoi cells.
+    pub power: bool,
+    /// Whether the cells are those of the hyperbolic plane in the Poincaré
+    /// disk, see `hyperbolic`, rather than those of the flat window. Goes
+    /// before `power`.
+    pub hyperbolic: bool,
+    /// Whether `polygons` are power cells right now, which they also are
+    /// after equal-area relaxation.
+    power_cells: bool,
+    /// Which dots count as one. Of dots that coincide only the first is
+    /// kept, whichever way they came in, so the triangulation never sees two
+    /// dots at the same place.
+    pub duplicates: Duplicates,
+    /// Changes whenever the dots or colors change, and is never reused,
+    /// not even by another diagram.
+    revision: u64,
+}
+
+impl Diagram {
+    pub fn new() -> Diagram {
+        Diagram::default()
+    }
+
+    /// Replaces all dots, giving them new colors.
+    pub fn set_dots(&mut self, dots: Vec<[f64; 2]>) {
+        self.set_sites(dots, Vec::new());
+    }
+
+    /// Like `set_dots`, with the metadata of each dot.
+    pub fn set_sites(&mut self, dots: Vec<[f64; 2]>, metadata: Vec<Metadata>) {
+        self.teams = vec![None; dots.len()];
+        self.weights.clear();
+        self.metadata = metadata;
+        self.ids.clear();
+        self.coords = None;
+        self.dots = dots;
+        self.recolor();
+        self.update();
+    }
+
+    pub fn clear(&mut self) {
+        self.dots.clear();
+        self.colors.clear();
+        self.teams.clear();
+        self.weights.clear();
+        self.metadata.clear();
+        self.ids.clear();
+        self.coords = None;
+        self.polygons.clear();
+        self.visible.clear();
+        self.grid = Grid::default();
+        self.triangulation = None;
+        self.touch();
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn touch(&mut self) {
+        self.revision = NEXT_REVISION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Adds a dot unless there already is one at the same place.
+    /// Returns whether the dot was added.
+    pub fn add_dot(&mut self, dot: [f64; 2]) -> bool {
+        self.add_colored_dot(dot, self.palette.color())
+    }
+
+    pub fn add_colored_dot(&mut self, dot: [f64; 2], color: [f32; 4]) -> bool {
+        self.push_dot(dot, color, None)
+    }
+
+    /// Adds a dot owned by `team`, in the team's color.
+    pub fn add_team_dot(&mut self, dot: [f64; 2], team: usize) -> bool {
+        self.push_dot(dot, team_color(team), Some(team))
+    }
+
+    pub fn push_dot(&mut self, dot: [f64; 2], color: [f32; 4], team: Option<usize>) -> bool {
+        self.push_weighted_dot(dot, color, team, 0.0)
+    }
+
+    /// Like `push_dot`, with a power diagram weight. A dot on top of an
+    /// existing one isn't added, but may add its weight to it, see
+    /// `Duplicates`.
+    pub fn push_weighted_dot(&mut self, dot: [f64; 2], color: [f32; 4], team: Option<usize>, weight: f64) -> bool {
+        if let Some(existing) = self.coinciding(dot, None) {
+            if self.duplicates.accumulate && weight != 0.0 {
+                self.weights[existing] += weight;
+                self.update();
+            }
+            return false;
+        }
+        self.push_site(dot, color, team, weight, Metadata::default());
+        self.settle();
+        true
+    }
+
+    /// Adds a dot unless it coincides with one that is already there.
+    /// Returns whether it was added. While the cells are Voronoi cells,
+    /// the ones around it are computed right away, otherwise `settle` has
+    /// to compute all of them.
+    fn push_site(&mut self, dot: [f64; 2], color: [f32; 4], team: Option<usize>, weight: f64, metadata: Metadata) -> bool {
+        if self.coinciding(dot, None).is_some() {
+            return false;
+        }
+        self.dots.push(dot);
+        self.colors.push(color);
+        self.teams.push(team);
+        self.weights.push(weight);
+        self.metadata.push(metadata);
+        self.ids.push(new_id());
+        self.grid.insert(self.dots.len() - 1, dot);
+        let Some(triangulation) = self.triangulation.as_mut() else { return true };
+        // Only the cells around the new dot change, which makes adding dots
+        // to big diagrams quick
+        self.polygons.push(Vec::new());
+        self.visible.push(Vec::new());
+        for index in triangulation.add(dot) {
+            let cell = triangulation.cell(index);
+            self.visible[index] = visible_part(&cell);
+            self.polygons[index] = cell;
+        }
+        true
+    }
+
+    /// Puts the dot at `index` at `dot`. While the cells are Voronoi cells,
+    /// the ones around its old and new place are computed right away,
+    /// otherwise `settle` has to compute all of them.
+    fn relocate(&mut self, index: usize, dot: [f64; 2]) {
+        let from = std::mem::replace(&mut self.dots[index], dot);
+        self.grid.relocate(index, from, dot);
+        let Some(triangulation) = self.triangulation.as_mut() else { return };
+        let Some(changed) = triangulation.move_dot(index, dot) else {
+            self.triangulation = None;
+            return;
+        };
+        for index in changed {
+            let cell = triangulation.cell(index);
+            self.visible[index] = visible_part(&cell);
+            self.polygons[index] = cell;
+        }
+    }
+
+    /// Finishes adding or moving dots with `push_site` or `relocate`,
+    /// computing all cells if those couldn't.
+    fn settle(&mut self) {
+        if self.triangulation.is_some() {
+            self.touch();
+        } else {
+            self.update();
+        }
+    }
+
+    /// Adds dots that belong together, like the mirror images of one dot,
+    /// all in the same color. Dots that are already there are skipped.
+    /// Returns whether any dot was added.
+    pub fn add_group(&mut self, dots: &[[f64; 2]], color: [f32; 4], team: Option<usize>) -> bool {
+        let mut added = false;
+        for &dot in dots {
+            added |= self.push_site(dot, color, team, 0.0, Metadata::default());
+        }
+        if added {
+            self.settle();
+        }
+        added
+    }
+
+    /// Adds several dots at once, skipping the ones that are already there.
+    pub fn merge(&mut self, dots: &[[f64; 2]]) {
+        self.merge_sites(dots, &[]);
+    }
+
+    /// Like `merge`, with the metadata of each dot, or of the first few.
+    pub fn merge_sites(&mut self, dots: &[[f64; 2]], metadata: &[Metadata]) {
+        let mut added = false;
+        for (i, &dot) in dots.iter().enumerate() {
+            let color = self.palette.color();
+            added |= self.push_site(dot, color, None, 0.0, metadata.get(i).cloned().unwrap_or_default());
+        }
+        if added {
+            self.settle();
+        }
+    }
+
+    /// Like `restore` without teams, but taking `cells` as the cells of the
+    /// dots instead of their Voronoi cells, for layouts like the power
+    /// diagram of a treemap.
+    pub fn restore_with_cells(&mut self, dots: Vec<[f64; 2]>, colors: Vec<[f32; 4]>, cells: Vec<Vec<Point>>) {
+        self.teams = vec![None; dots.len()];
+        self.weights = vec![0.0; dots.len()];
+        self.metadata = vec![Metadata::default(); dots.len()];
+        self.ids = dots.iter().map(|_| new_id()).collect();
+        self.dots = dots;
+        self.colors = colors;
+        self.set_cells(cells);
+        self.grid = Grid::new(&self.dots);
+        self.triangulation = None;
+        self.power_cells = false;
+        self.touch();
+    }
+
+    /// Replaces all dots along with their colors and teams, as they were saved.
+    /// Keeps `metadata` and `ids`, which have to be put back first if the
+    /// dots were saved with theirs. Dots that stay where they were keep their
+    /// weights, new ones get none.
+    pub fn restore(&mut self, dots: Vec<[f64; 2]>, colors: Vec<[f32; 4]>, teams: Vec<Option<usize>>) {
+        // Undoing additions only takes dots out, which is quicker than
+        // computing all cells anew
+        if let Some(removed) = self.removed_from(&dots) {
+            let (metadata, ids) = (std::mem::take(&mut self.metadata), std::mem::take(&mut self.ids));
+            self.remove_dots(&removed);
+            self.colors = colors;
+            self.teams = teams;
+            (self.metadata, self.ids) = (metadata, ids);
+            self.update_ids();
+            return;
+        }
+        let key = |d: &[f64; 2]| (d[0].to_bits(), d[1].to_bits());
+        let old = self.dots.iter().zip(&self.weights).map(|(d, &w)| (key(d), w)).collect::<HashMap<_, _>>();
+        self.weights = dots.iter().map(|d| old.get(&key(d)).copied().unwrap_or(0.0)).collect();
+        self.dots = dots;
+        self.colors = colors;
+        self.teams = teams;
+        self.update();
+    }
+
+    /// The diagram as if the window wrapped around at its edges, like the
+    /// surface of a torus: every dot also repeats a window's width and
+    /// height away on all sides. Only the copies within half a window of it
+    /// are kept, since those are the closest to every point inside, so the
+    /// cells in the window come out the same along opposite edges.
+    pub fn periodic(&self) -> Diagram {
+        let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        let near = |d: [f64; 2]| (-width / 2.0..=1.5 * width).contains(&d[0]) && (-height / 2.0..=1.5 * height).contains(&d[1]);
+        let (mut dots, mut colors, mut teams, mut metadata) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for (i, d) in self.dots.iter().enumerate() {
+            for dx in [-width, 0.0, width] {
+                for dy in [-height, 0.0, height] {
+                    let copy = [d[0] + dx, d[1] + dy];
+                    if near(copy) {
+                        dots.push(copy);
+                        colors.push(self.colors[i]);
+                        teams.push(self.teams[i]);
+                        metadata.push(self.metadata[i].clone());
+                    }
+                }
+            }
+        }
+        let mut periodic = Diagram { metadata, ..Diagram::new() };
+        periodic.restore(dots, colors, teams);
+        periodic
+    }
+
+    /// Swaps in a new set of dots. Dots that were already there keep their
+    /// color, team and metadata, so reloading a slightly changed file
+    /// doesn't repaint everything.
+    pub fn replace_dots(&mut self, dots: Vec<[f64; 2]>) {
+        let key = |d: &[f64; 2]| (d[0].to_bits(), d[1].to_bits());
+        let old = self.dots.iter().zip(&self.metadata).map(|(d, m)| (key(d), m)).collect::<HashMap<_, _>>();
+        let metadata = dots.iter().map(|d| old.get(&key(d)).map(|&m| m.clone()).unwrap_or_default()).collect();
+        self.replace_sites(dots, metadata);
+    }
+
+    /// Like `replace_dots`, with new metadata for all dots.
+    pub fn replace_sites(&mut self, dots: Vec<[f64; 2]>, metadata: Vec<Metadata>) {
+        let key = |d: &[f64; 2]| (d[0].to_bits(), d[1].to_bits());
+        let old = self.dots.iter().enumerate().map(|(i, d)| (key(d), (self.colors[i], self.teams[i], self.ids[i]))).collect::<HashMap<_, _>>();
+        let ((colors, teams), ids) = dots.iter()
+            .map(|d| {
+                let (color, team, id) = old.get(&key(d)).copied().unwrap_or_else(|| (self.palette.color(), None, new_id()));
+                ((color, team), id)
+            })
+            .unzip();
+        self.colors = colors;
+        self.teams = teams;
+        self.ids = ids;
+        self.weights.clear();
+        self.metadata = metadata;
+        self.dots = dots;
+        self.update();
+    }
+
+    /// Moves the dot at `index`, unless that would put it on top of another one.
+    pub fn move_dot(&mut self, index: usize, dot: [f64; 2]) -> bool {
+        if index >= self.dots.len() {
+            return false;
+        }
+        if self.coinciding(dot, Some(index)).is_some() {
+            return false;
+        }
+        self.relocate(index, dot);
+        self.settle();
+        true
+    }
+
+    /// Moves several dots at once, skipping the moves that would put a dot
+    /// on top of another one. Returns whether any dot moved. Dots moved on
+    /// top of each other in the same call become one.
+    pub fn move_dots(&mut self, moves: &[(usize, [f64; 2])]) -> bool {
+        let (mut moved, mut ends) = (Vec::new(), Vec::new());
+        let (mut grid, mut merged) = (Grid::default(), false);
+        for &(index, dot) in moves {
+            if index >= self.dots.len() || self.dots[index] == dot {
+                continue;
+            }
+            if self.coinciding(dot, Some(index)).is_none() {
+                merged |= !grid.within(&ends, dot, self.duplicates.distance).is_empty();
+                grid.insert(ends.len(), dot);
+                ends.push(dot);
+                moved.push(index);
+            }
+        }
+        if moved.is_empty() {
+            return false;
+        }
+        if merged {
+            // Dots that land on each other are left to `update` to merge
+            for (&index, &dot) in moved.iter().zip(&ends) {
+                self.dots[index] = dot;
+            }
+            self.update();
+        } else {
+            for (&index, &dot) in moved.iter().zip(&ends) {
+                self.relocate(index, dot);
+            }
+            self.settle();
+        }
+        true
+    }
+
+    /// Scales and moves all dots into view, see `fit::Fit`, and adds that
+    /// to `coords`. Returns how, or `None` without dots.
+    pub fn fit(&mut self) -> Option<Fit> {
+        let fit = Fit::new(&self.dots)?;
+        for dot in &mut self.dots {
+            *dot = fit.apply(*dot);
+        }
+        self.coords = Some(self.coords.map_or(fit, |coords| coords.then(&fit)));
+        self.update();
+        Some(fit)
+    }
+
+    pub fn remove_dot(&mut self, index: usize) -> bool {
+        if index >= self.dots.len() {
+            return false;
+        }
+        self.remove_dots(&[index]);
+        true
+    }
+
+    /// Takes out the dots at `indices`. While the cells are Voronoi cells,
+    /// only the ones around the dots are computed anew, so erasing and
+    /// undoing stay quick in big diagrams.
+    fn remove_dots(&mut self, indices: &[usize]) {
+        let mut keep = vec![true; self.dots.len()];
+        for &i in indices {
+            keep[i] = false;
+        }
+        self.retain(&keep);
+        let Some(changed) = self.triangulation.as_mut().and_then(|t| t.remove(indices)) else {
+            self.update();
+            return;
+        };
+        let mut kept = keep.iter();
+        self.polygons.retain(|_| *kept.next().unwrap_or(&true));
+        let mut kept = keep.iter();
+        self.visible.retain(|_| *kept.next().unwrap_or(&true));
+        if let Some(triangulation) = &self.triangulation {
+            for index in changed {
+                let cell = triangulation.cell(index);
+                self.visible[index] = visible_part(&cell);
+                self.polygons[index] = cell;
+            }
+        }
+        self.grid = Grid::new(&self.dots);
+        self.touch();
+    }
+
+    /// The indices of the dots to take out to be left with `dots`, if they
+    /// are the current ones in the same order with some missing.
+    fn removed_from(&self, dots: &[[f64; 2]]) -> Option<Vec<usize>> {
+        if self.triangulation.is_none() || dots.len() >= self.dots.len() {
+            return None;
+        }
+        let mut left = dots.iter().peekable();
+        let removed = (0..self.dots.len())
+            .filter(|&i| if left.peek() == Some(&&self.dots[i]) { left.next(); false } else { true })
+            .collect::<Vec<_>>();
+        left.peek().is_none().then_some(removed)
+    }
+
+    /// The grid that finds the dots by position.
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// The indices of the dots within `radius` of `center`.
+    pub fn dots_within(&self, center: [f64; 2], radius: f64) -> Vec<usize> {
+        self.grid.within(&self.dots, center, radius)
+    }
+
+    /// Removes all dots within `radius` of `center`. Returns how many there were.
+    pub fn remove_within(&mut self, center: [f64; 2], radius: f64) -> usize {
+        let doomed = self.dots_within(center, radius);
+        if doomed.is_empty() {
+            return 0;
+        }
+        self.remove_dots(&doomed);
+        doomed.len()
+    }
+
+    /// Colors the cells by group, `count` groups in all, and the cells
+    /// without a group gray.
+    pub fn paint_groups(&mut self, groups: &[Option<usize>], count: usize) {
+        self.colors = groups.iter().map(|group| group.map_or(UNGROUPED, |k| group_color(k, count))).collect();
+        self.touch();
+    }
+
+    /// Like `paint_groups`, for only some of the dots, so the other cells
+    /// keep their shades. `changes` holds the index and group of each.
+    pub fn repaint_groups(&mut self, changes: &[(usize, usize)], count: usize) {
+        for &(index, group) in changes {
+            if let Some(color) = self.colors.get_mut(index) {
+                *color = group_color(group, count);
+            }
+        }
+        self.touch();
+    }
+
+    /// Gives every dot a new color, except that team dots keep their team's.
+    pub fn recolor(&mut self) {
+        self.colors = self.palette_colors();
+        self.touch();
+    }
+
+    /// New colors from the palette for every dot, but the team's for team dots.
+    pub fn palette_colors(&self) -> Vec<[f32; 4]> {
+        self.teams.iter().map(|team| match team {
+            Some(team) => team_color(*team),
+            None => self.palette.color()
+        }).collect()
+    }
+
+    /// Sets the power diagram weight of the dot at `index`, at least zero,
+    /// growing or shrinking its cell in power mode.
+    pub fn set_weight(&mut self, index: usize, weight: f64) -> bool {
+        if index >= self.dots.len() {
+            return false;
+        }
+        self.weights[index] = weight.max(0.0);
+        self.update();
+        true
+    }
+
+    /// Puts back the weights of all dots, as saved along with them.
+    pub fn set_weights(&mut self, weights: Vec<f64>) {
+        if weights.len() == self.dots.len() && weights != self.weights {
+            self.weights = weights;
+            self.update();
+        }
+    }
+
+    /// Paints the cells in `colors`, one for each dot.
+    pub fn set_colors(&mut self, colors: Vec<[f32; 4]>) {
+        if colors.len() == self.dots.len() {
+            self.colors = colors;
+            self.touch();
+        }
+    }
+
+    /// The visible area of the cells owned by each team, indexed by team.
+    pub fn team_areas(&self) -> Vec<f64> {
+        let mut areas = Vec::new();
+        for (team, cell) in self.teams.iter().zip(&self.visible) {
+            if let Some(team) = *team {
+                if areas.len() <= team {
+                    areas.resize(team + 1, 0.0);
+                }
+                areas[team] += area(cell);
+            }
+        }
+        areas
+    }
+
+    /// Replaces the dots with groups of dots. Every group gets its own hue,
+    /// so the cells show which group their dot is from.
+    pub fn set_clusters(&mut self, clusters: Vec<Vec<[f64; 2]>>) {
+        let (mut dots, mut colors) = (Vec::new(), Vec::new());
+        let count = clusters.len();
+        for (k, cluster) in clusters.into_iter().enumerate() {
+            for dot in cluster {
+                dots.push(dot);
+                colors.push(group_color(k, count));
+            }
+        }
+        self.teams = vec![None; dots.len()];
+        self.weights.clear();
+        self.metadata.clear();
+        self.ids.clear();
+        self.dots = dots;
+        self.colors = colors;
+        self.update();
+    }
+
+    /// Replaces the dots with `num` random ones, spread according to `density`.
+    pub fn randomize(&mut self, num: usize, density: &Density) {
+        self.set_dots((0..num).map(|_| density.sample()).collect());
+    }
+
+    /// One step of Lloyd relaxation: every dot moves to the centroid of its
+    /// cell, as far as it's visible in the window. Repeating this spreads the
+    /// dots out evenly.
+    pub fn relax(&mut self) {
+        for (dot, poly) in self.dots.iter_mut().zip(&self.visible) {
+            if let Some(c) = centroid(poly) {
+                *dot = [c.0, c.1];
+            }
+        }
+        self.update();
+    }
+
+    /// One step of capacity-constrained relaxation: like `relax`, but the
+    /// cells become power cells whose weights are tuned so that all cells
+    /// get closer to the same area. Spreads the dots out like blue noise.
+    pub fn relax_equal_area(&mut self) {
+        if self.dots.is_empty() {
+            return;
+        }
+        if !self.power_cells {
+            self.polygons = power_cells(&self.dots, &self.weights);
+        }
+        let targets = vec![1.0 / self.dots.len() as f64; self.dots.len()];
+        let cells = fit_step(&mut self.dots, &mut self.weights, &self.polygons, &targets);
+        self.set_cells(cells);
+        self.grid = Grid::new(&self.dots);
+        self.triangulation = None;
+        self.power_cells = true;
+        self.touch();
+    }
+
+    /// Replaces the dots with the corners of their cells, which over and
+    /// over again refines the diagram into fractal-like patterns. Corners
+    /// within `merge` pixels of each other become one dot. Corners outside
+    /// the window are left out, unless `keep_outside`, which keeps those up
+    /// to half the window's size beyond it.
+    pub fn collapse_to_vertices(&mut self, merge: f64, keep_outside: bool) {
+        // Neighboring cells share their corners, at least these have to become one
+        const SAME_CORNER: f64 = 0.001;
+        let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        // Further out the corners would leave the area triangulate works in
+        let (margin_x, margin_y) = if keep_outside { (width / 2.0, height / 2.0) } else { (0.0, 0.0) };
+        let corners = self.polygons.iter().flatten()
+            .map(|&(x, y)| [x, y])
+            .filter(|p| (-margin_x..=width + margin_x).contains(&p[0]) && (-margin_y..=height + margin_y).contains(&p[1]))
+            .collect::<Vec<_>>();
+        let grid = Grid::new(&corners);
+        let mut merged = vec![false; corners.len()];
+        let mut dots = Vec::new();
+        for (i, corner) in corners.iter().enumerate() {
+            if merged[i] {
+                continue;
+            }
+            for j in grid.within(&corners, *corner, merge.max(SAME_CORNER)) {
+                merged[j] = true;
+            }
+            dots.push(*corner);
+        }
+        self.set_dots(dots);
+    }
+
+    /// One step of force-directed relaxation: dots closer than `range` to
+    /// each other or to the window's edge push apart, the closer the harder.
+    /// Dots further apart don't interact, so clusters spread out but keep
+    /// their rough shape. With an `attractor`, all dots are also pulled
+    /// towards it.
+    pub fn repel(&mut self, range: f64, attractor: Option<[f64; 2]>) {
+        const STEP: f64 = 0.1;
+        const ATTRACTION: f64 = 0.02;
+        let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        let range = range.max(1.0);
+
+        let mut moves = vec![[0.0; 2]; self.dots.len()];
+        for (i, d) in self.dots.iter().enumerate() {
+            let neighbours = self.dots_within(*d, range);
+            let mut push = |away: [f64; 2], distance: f64| {
+                let strength = (1.0 - distance / range) * range * STEP;
+                moves[i][0] += away[0] * strength;
+                moves[i][1] += away[1] * strength;
+            };
+            for j in neighbours {
+                if j == i {
+                    continue;
+                }
+                let (dx, dy) = (d[0] - self.dots[j][0], d[1] - self.dots[j][1]);
+                let distance = (dx * dx + dy * dy).sqrt();
+                if distance >= range {
+                    continue;
+                }
+                if distance > 0.0 {
+                    push([dx / distance, dy / distance], distance);
+                } else {
+                    let angle = random::<f64>() * std::f64::consts::TAU;
+                    push([angle.cos(), angle.sin()], 0.0);
+                }
+            }
+            for (away, distance) in [([1.0, 0.0], d[0]), ([-1.0, 0.0], width - d[0]), ([0.0, 1.0], d[1]), ([0.0, -1.0], height - d[1])] {
+                if distance < range {
+                    push(away, distance.max(0.0));
+                }
+            }
+            if let Some(a) = attractor {
+                moves[i][0] += (a[0] - d[0]) * ATTRACTION;
+                moves[i][1] += (a[1] - d[1]) * ATTRACTION;
+            }
+        }
+
+        for (dot, m) in self.dots.iter_mut().zip(moves) {
+            dot[0] = (dot[0] + m[0]).clamp(0.0, width);
+            dot[1] = (dot[1] + m[1]).clamp(0.0, height);
+        }
+        self.update();
+    }
+
+    /// Moves every dot randomly by up to `amount` in each direction, keeping
+    /// them inside the window.
+    pub fn jitter(&mut self, amount: f64) {
+        for dot in self.dots.iter_mut() {
+            dot[0] = (dot[0] + (random::<f64>() * 2.0 - 1.0) * amount).clamp(0.0, DEFAULT_WINDOW_WIDTH as f64);
+            dot[1] = (dot[1] + (random::<f64>() * 2.0 - 1.0) * amount).clamp(0.0, DEFAULT_WINDOW_HEIGHT as f64);
+        }
+        self.update();
+    }
+
+    /// A copy of this diagram with extra sites appended.
+    pub fn merged(&self, dots: &[[f64; 2]], colors: &[[f32; 4]]) -> Diagram {
+        let mut merged = Diagram {
+            dots: self.dots.clone(),
+            colors: self.colors.clone(),
+            teams: self.teams.clone(),
+            weights: self.weights.clone(),
+            metadata: self.metadata.clone(),
+            ids: self.ids.clone(),
+            power: self.power,
+            hyperbolic: self.hyperbolic,
+            palette: self.palette.clone(),
+            duplicates: self.duplicates,
+            ..Diagram::default()
+        };
+        for (dot, color) in dots.iter().zip(colors) {
+            merged.dots.push(*dot);
+            merged.colors.push(*color);
+            merged.teams.push(None);
+            merged.weights.push(0.0);
+            merged.metadata.push(Metadata::default());
+        }
+        merged.update();
+        merged
+    }
+
+    /// A copy of this diagram with at most one dot per `spacing` by
+    /// `spacing` square, standing in for all dots in it with their mean
+    /// position, color and weight. Looks much the same from afar as the
+    /// original with many more dots than pixels, and draws faster.
+    pub fn thinned(&self, spacing: f64) -> Diagram {
+        let mut squares = HashMap::new();
+        let mut sums: Vec<([f64; 2], [f32; 4], f64, usize)> = Vec::new();
+        for (i, dot) in self.dots.iter().enumerate() {
+            let square = ((dot[0] / spacing).floor() as i64, (dot[1] / spacing).floor() as i64);
+            let at = *squares.entry(square).or_insert_with(|| {
+                sums.push(([0.0; 2], [0.0; 4], 0.0, 0));
+                sums.len() - 1
+            });
+            let (position, color, weight, count) = &mut sums[at];
+            position[0] += dot[0];
+            position[1] += dot[1];
+            for (sum, channel) in color.iter_mut().zip(self.colors[i]) {
+                *sum += channel;
+            }
+            *weight += self.weights[i];
+            *count += 1;
+        }
+
+        let mut thinned = Diagram {
+            power: self.power,
+            hyperbolic: self.hyperbolic,
+            palette: self.palette.clone(),
+            duplicates: self.duplicates,
+            ..Diagram::default()
+        };
+        for ([x, y], color, weight, count) in sums {
+            let n = count as f64;
+            thinned.dots.push([x / n, y / n]);
+            thinned.colors.push(color.map(|channel| channel / count as f32));
+            thinned.teams.push(None);
+            thinned.weights.push(weight / n);
+        }
+        thinned.update();
+        thinned
+    }
+
+    /// Recomputes the cells after the dots changed.
+    pub fn update(&mut self) {
+        // Dots added without a weight or metadata get none, and new ids
+        self.weights.resize(self.dots.len(), 0.0);
+        self.metadata.resize(self.dots.len(), Metadata::default());
+        self.update_ids();
+        self.grid = Grid::new(&self.dots);
+        if self.drop_duplicates() {
+            self.grid = Grid::new(&self.dots);
+        }
+        if self.hyperbolic {
+            self.triangulation = None;
+            self.set_cells(hyperbolic_cells(&self.dots));
+        } else if self.power {
+            self.triangulation = None;
+            self.set_cells(power_cells(&self.dots, &self.weights));
+        } else {
+            let triangulation = triangulate(&self.dots);
+            self.set_cells(triangulation.voronoi_cells());
+            self.triangulation = Some(triangulation);
+        }
+        self.power_cells = self.power && !self.hyperbolic;
+        self.touch();
+    }
+
+    /// Takes `cells` as the cells of the dots, clipping them to the window.
+    fn set_cells(&mut self, cells: Vec<Vec<Point>>) {
+        self.visible = visible_parts(&cells);
+        self.polygons = cells;
+    }
+
+    /// The first dot other than `except` that a dot at `dot` would coincide
+    /// with, see `duplicates`.
+    pub fn coinciding(&self, dot: [f64; 2], except: Option<usize>) -> Option<usize> {
+        self.grid.within(&self.dots, dot, self.duplicates.distance).into_iter().filter(|&i| Some(i) != except).min()
+    }
+
+    /// Drops every dot that coincides with an earlier one, handing its
+    /// weight on if the weights accumulate. Returns whether there were any.
+    fn drop_duplicates(&mut self) -> bool {
+        let mut keep = vec![true; self.dots.len()];
+        for i in 0..self.dots.len() {
+            let first = self.grid.within(&self.dots, self.dots[i], self.duplicates.distance).into_iter()
+                .filter(|&j| j < i && keep[j])
+                .min();
+            if let Some(first) = first {
+                keep[i] = false;
+                if self.duplicates.accumulate {
+                    self.weights[first] += self.weights[i];
+                }
+            }
+        }
+        if keep.iter().all(|&k| k) {
+            return false;
+        }
+        self.retain(&keep);
+        true
+    }
+
+    /// Keeps only the dots whose entry in `keep` is true, cells aside.
+    fn retain(&mut self, keep: &[bool]) {
+        let mut kept = keep.iter();
+        self.dots.retain(|_| *kept.next().unwrap_or(&true));
+        let mut kept = keep.iter();
+        self.colors.retain(|_| *kept.next().unwrap_or(&true));
+        let mut kept = keep.iter();
+        self.teams.retain(|_| *kept.next().unwrap_or(&true));
+        let mut kept = keep.iter();
+        self.weights.retain(|_| *kept.next().unwrap_or(&true));
+        let mut kept = keep.iter();
+        self.metadata.retain(|_| *kept.next().unwrap_or(&true));
+        let mut kept = keep.iter();
+        self.ids.retain(|_| *kept.next().unwrap_or(&true));
+    }
+
+    /// Gives the dots without an id new ones.
+    fn update_ids(&mut self) {
+        self.ids.truncate(self.dots.len());
+        while self.ids.len() < self.dots.len() {
+            self.ids.push(new_id());
+        }
+    }
+
+    /// Switches between power cells and Voronoi cells, see `power`.
+    pub fn set_power(&mut self, power: bool) {
+        self.power = power;
+        self.update();
+    }
+
+    /// Switches between the hyperbolic plane and the flat one, see
+    /// `hyperbolic`.
+    pub fn set_hyperbolic(&mut self, hyperbolic: bool) {
+        self.hyperbolic = hyperbolic;
+        self.update();
+    }
+
+    /// The cells with their corners cut off `iterations` times, see
+    /// `chaikin`, once whole and once cut to the window like `polygons` and
+    /// `visible`. Without iterations, those themselves.
+    pub fn smoothed(&self, iterations: usize) -> (Cells<'_>, Cells<'_>) {
+        if iterations == 0 {
+            return (Cow::Borrowed(&self.polygons), Cow::Borrowed(&self.visible));
+        }
+        let smoothed = self.polygons.iter().map(|poly| chaikin(poly, iterations)).collect::<Vec<_>>();
+        let visible = visible_parts(&smoothed);
+        (Cow::Owned(smoothed), Cow::Owned(visible))
+    }
+
+    /// The Delaunay triangles of the dots, as indices into `dots`.
+    pub fn triangles(&self) -> Vec<Triangle> {
+        match &self.triangulation {
+            Some(triangulation) => triangulation.triangles(),
+            None => triangulate(&self.dots).triangles()
+        }
+    }
+
+    /// What adding `dot` would do to the Delaunay triangulation, see
+    /// `Delaunay::cavity`.
+    pub fn cavity(&self, dot: [f64; 2]) -> Option<Cavity> {
+        match &self.triangulation {
+            Some(triangulation) => triangulation.cavity(dot),
+            None => triangulate(&self.dots).cavity(dot)
+        }
+    }
+
+    /// The dot whose cell `point` is in: the closest one, or in power mode
+    /// the one with the smallest power distance.
+    pub fn nearest_dot(&self, point: [f64; 2]) -> Option<usize> {
+        if self.hyperbolic {
+            let distance = |i: usize| hyperbolic::distance(self.dots[i], point);
+            return (0..self.dots.len()).filter(|&i| distance(i).is_finite()).min_by(|&a, &b| distance(a).total_cmp(&distance(b)));
+        }
+        let distance = |i: usize| {
+            let d = self.dots[i];
+            let weight = if self.power { self.weights[i] } else { 0.0 };
+            (d[0] - point[0]).powi(2) + (d[1] - point[1]).powi(2) - weight
+        };
+        (0..self.dots.len()).min_by(|&a, &b| distance(a).total_cmp(&distance(b)))
+    }
+
+    /// The dots whose cells share an edge with the cell of dot `index`, in order.
+    pub fn neighbors(&self, index: usize) -> Vec<usize> {
+        let mut neighbors = self.triangles().into_iter()
+            .filter(|t| [t.0, t.1, t.2].contains(&index))
+            .flat_map(|t| [t.0, t.1, t.2])
+            .filter(|&i| i != index)
+            .collect::<Vec<_>>();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+}
+
+/// The ways of spreading the dots out, see `Diagram::relax`, `Diagram::repel`
+/// and `Diagram::relax_equal_area`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Relaxation {
+    Lloyd,
+    Repulsion,
+    EqualArea,
+}
+
+impl Relaxation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Relaxation::Lloyd => "Lloyd's algorithm",
+            Relaxation::Repulsion => "repulsion",
+            Relaxation::EqualArea => "equal areas",
+        }
+    }
+}
+
+impl FromStr for Relaxation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Relaxation, String> {
+        match s {
+            "lloyd" => Ok(Relaxation::Lloyd),
+            "repulsion" => Ok(Relaxation::Repulsion),
+            "equal_area" => Ok(Relaxation::EqualArea),
+            _ => Err(format!("Unknown relaxation \"{}\", use lloyd, repulsion or equal_area", s))
+        }
+    }
+}
+
+/// When dots count as one, see `Diagram::duplicates`.
+#[derive(Clone, Copy)]
+pub struct Duplicates {
+    /// Dots at most this far apart, in pixels, coincide. Even at zero, dots
+    /// at the exact same place do.
+    pub distance: f64,
+    /// Whether a dot that coincides with an earlier one adds its power
+    /// diagram weight to that one's, instead of being dropped along with it.
+    pub accumulate: bool,
+}
+
+impl Default for Duplicates {
+    fn default() -> Duplicates {
+        Duplicates { distance: DEFAULT_DUPLICATE_DISTANCE, accumulate: false }
+    }
+}
+
+impl Duplicates {
+    pub fn coincide(&self, a: [f64; 2], b: [f64; 2]) -> bool {
+        let (dx, dy) = (a[0] - b[0], a[1] - b[1]);
+        dx * dx + dy * dy <= self.distance * self.distance
+    }
+}
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Makes every random dot and color from now on reproducible.
+pub fn seed_random(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Like `rand::random`, but drawn from the seedable generator.
+pub fn random<T>() -> T where Standard: Distribution<T> {
+    RNG.with(|rng| rng.borrow_mut().gen())
+}
+
+pub fn random_point() -> [f64; 2] {
+    [random::<f64>() * DEFAULT_WINDOW_WIDTH as f64, random::<f64>() * DEFAULT_WINDOW_HEIGHT as f64]
+}
+
+/// An id no dot had before, see `Diagram::ids`.
+fn new_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+pub fn random_color() -> [f32; 4] {
+    [random::<f32>(), random::<f32>(), random::<f32>(), 1.0]
+}
+
+/// The part of a cell that lies inside the window.
+pub fn visible_part(poly: &[Point]) -> Vec<Point> {
+    clip_to_rect(poly, (0.0, 0.0), (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64))
+}
+
+/// The visible part of every cell, clipped on all cores, since big
+/// diagrams have a lot of cells.
+fn visible_parts(cells: &[Vec<Point>]) -> Vec<Vec<Point>> {
+    // Fewer cells than this aren't worth a thread
+    const MIN_CHUNK: usize = 4096;
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = cells.len().div_ceil(threads).max(MIN_CHUNK);
+    std::thread::scope(|scope| {
+        let clipping = cells.chunks(chunk)
+            .map(|chunk| scope.spawn(|| chunk.iter().map(|cell| visible_part(cell)).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        clipping.into_iter().flat_map(|thread| thread.join().unwrap()).collect()
+    })
+}
+
+fn triangulate(dots: &[[f64;2]]) -> Delaunay {
+    Delaunay::new(
+        dots,
+        [DEFAULT_WINDOW_WIDTH as f64 / 2.0, DEFAULT_WINDOW_HEIGHT as f64 / 2.0],
+        std::f64::consts::SQRT_2 * std::cmp::max(DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT) as f64)
+}
+
@@ -0,0 +1,322 @@
+//! Comparing and merging saved sessions, journals or autosaves, layer by
+//! layer, so two people can annotate copies of the same dots and bring
+//! their work together.
+//!
+//! Sessions don't keep track of which dot is which, so dots are matched up
+//! by where they are: a dot at the same place in both is the same dot, then
+//! a dot that is gone and one that is new with the same color and metadata
+//! are the same dot moved, the closest ones first. A dot that was moved and
+//! recolored at once shows up as removed and added.
+
+use std::collections::HashMap;
+use std::io;
+
+use interactive_voronoi::diagram::Duplicates;
+use interactive_voronoi::metadata::Metadata;
+
+use crate::journal::{self, write_layers};
+use crate::model::Layer;
+
+/// A dot with everything a session saves about it.
+#[derive(Clone, PartialEq)]
+struct Site {
+    dot: [f64; 2],
+    color: [f32; 4],
+    team: Option<usize>,
+    metadata: Metadata,
+    weight: f64,
+}
+
+impl Site {
+    /// Whether the two differ in anything but where they are.
+    fn looks_like(&self, other: &Site) -> bool {
+        self.color == other.color && self.team == other.team && self.metadata == other.metadata && self.weight == other.weight
+    }
+}
+
+/// Which dots of the old sites became which of the new ones.
+struct Matching {
+    /// The index of each old dot among the new ones, `None` if it's gone.
+    new_index: Vec<Option<usize>>,
+    /// The new dots that aren't any of the old ones.
+    added: Vec<usize>,
+}
+
+/// What became of the dots of a layer between two sessions, for `--diff`.
+pub fn diff(old_path: &str, new_path: &str, duplicates: Duplicates) -> io::Result<String> {
+    let (old, new) = (read(old_path, duplicates)?, read(new_path, duplicates)?);
+    let mut report = String::new();
+    for index in 0..old.len().max(new.len()) {
+        let (old, new) = (layer(&old, index), layer(&new, index));
+        let matching = matching(old, new);
+        let mut lines = Vec::new();
+        let (mut moved, mut recolored, mut changed) = (0, 0, 0);
+        for (i, j) in matching.new_index.iter().enumerate() {
+            let Some(j) = *j else {
+                lines.push(format!("  removed {}", describe(&old[i])));
+                continue;
+            };
+            let (before, after) = (&old[i], &new[j]);
+            if before.dot != after.dot {
+                moved += 1;
+                lines.push(format!("  moved {} to {}", position(before.dot), position(after.dot)));
+            }
+            if before.color != after.color {
+                recolored += 1;
+                lines.push(format!("  recolored {} from {} to {}", position(after.dot), hex(&before.color), hex(&after.color)));
+            } else if !before.looks_like(after) {
+                changed += 1;
+                lines.push(format!("  changed the team, metadata or weight of {}", position(after.dot)));
+            }
+        }
+        for &j in &matching.added {
+            lines.push(format!("  added {}", describe(&new[j])));
+        }
+        let removed = matching.new_index.iter().filter(|j| j.is_none()).count();
+        report += &format!("Layer {}: {} added, {} removed, {} moved, {} recolored, {} otherwise changed\n",
+                           index, matching.added.len(), removed, moved, recolored, changed);
+        for line in lines {
+            report += &line;
+            report.push('\n');
+        }
+    }
+    Ok(report)
+}
+
+/// Merges the changes two sessions made to the same base session into a
+/// new session at `out_path`, for `--merge`. Where both changed where a dot
+/// is or how it looks differently, or one removed a dot the other changed,
+/// the dot stays as it was in the base and the conflict is reported.
+pub fn merge(base_path: &str, ours_path: &str, theirs_path: &str, out_path: &str, duplicates: Duplicates) -> io::Result<String> {
+    let base = read(base_path, duplicates)?;
+    let ours = read(ours_path, duplicates)?;
+    let theirs = read(theirs_path, duplicates)?;
+    let mut report = String::new();
+    let mut layers = Vec::new();
+    for index in 0..base.len().max(ours.len()).max(theirs.len()) {
+        let (base, ours, theirs) = (layer(&base, index), layer(&ours, index), layer(&theirs, index));
+        let (our_matching, their_matching) = (matching(base, ours), matching(base, theirs));
+        let mut merged = Vec::new();
+        let mut conflicts = Vec::new();
+        for (i, original) in base.iter().enumerate() {
+            let (our, their) = (our_matching.new_index[i].map(|j| &ours[j]), their_matching.new_index[i].map(|j| &theirs[j]));
+            match (our, their) {
+                (None, None) => (),
+                (None, Some(kept)) | (Some(kept), None) => {
+                    if kept != original {
+                        conflicts.push(format!("  {} was removed on one side and changed on the other", position(original.dot)));
+                        merged.push(original.clone());
+                    }
+                }
+                (Some(our), Some(their)) => {
+                    let mut site = original.clone();
+                    match merged_change(original.dot, our.dot, their.dot) {
+                        Some(dot) => site.dot = dot,
+                        None => conflicts.push(format!("  {} was moved to {} and to {}", position(original.dot), position(our.dot), position(their.dot))),
+                    }
+                    let look = if our.looks_like(original) { their } else { our };
+                    if our.looks_like(original) || their.looks_like(original) || our.looks_like(their) {
+                        site = Site { dot: site.dot, ..look.clone() };
+                    } else {
+                        conflicts.push(format!("  {} was recolored or changed differently on both sides", position(original.dot)));
+                    }
+                    merged.push(site);
+                }
+            }
+        }
+        let ours_added = our_matching.added.iter().map(|&j| ours[j].clone()).collect::<Vec<_>>();
+        let theirs_added = their_matching.added.iter().map(|&j| &theirs[j])
+            .filter(|s| !ours_added.iter().any(|o| o.dot == s.dot))
+            .cloned()
+            .collect::<Vec<_>>();
+        report += &format!("Layer {}: {} dots, {} added here, {} added there, {} conflicts\n",
+                           index, merged.len() + ours_added.len() + theirs_added.len(), ours_added.len(), theirs_added.len(), conflicts.len());
+        for conflict in conflicts {
+            report += &conflict;
+            report.push('\n');
+        }
+        merged.extend(ours_added);
+        merged.extend(theirs_added);
+        layers.push(to_layer(index, merged, duplicates));
+    }
+    let mut file = std::fs::File::create(out_path)?;
+    write_layers(&mut file, &layers)?;
+    report += &format!("Merged into {}\n", out_path);
+    Ok(report)
+}
+
+/// Where both sides agree a dot went, or where the side that moved it put
+/// it, `None` if they moved it to different places.
+fn merged_change(original: [f64; 2], ours: [f64; 2], theirs: [f64; 2]) -> Option<[f64; 2]> {
+    if ours == original || ours == theirs {
+        Some(theirs)
+    } else if theirs == original {
+        Some(ours)
+    } else {
+        None
+    }
+}
+
+/// Matches up the old and new dots, see the module documentation.
+fn matching(old: &[Site], new: &[Site]) -> Matching {
+    let key = |d: [f64; 2]| (d[0].to_bits(), d[1].to_bits());
+    let mut at = HashMap::<_, Vec<usize>>::new();
+    for (j, site) in new.iter().enumerate().rev() {
+        at.entry(key(site.dot)).or_default().push(j);
+    }
+    let mut new_index = old.iter().map(|site| at.get_mut(&key(site.dot)).and_then(|js| js.pop())).collect::<Vec<_>>();
+    let mut taken = vec![false; new.len()];
+    for &j in new_index.iter().flatten() {
+        taken[j] = true;
+    }
+
+    // The gone and new dots that look alike, closest first
+    let mut candidates = Vec::new();
+    for (i, _) in new_index.iter().enumerate().filter(|(_, j)| j.is_none()) {
+        for (j, _) in taken.iter().enumerate().filter(|(_, &t)| !t) {
+            if old[i].looks_like(&new[j]) {
+                let distance = (old[i].dot[0] - new[j].dot[0]).hypot(old[i].dot[1] - new[j].dot[1]);
+                candidates.push((distance, i, j));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (_, i, j) in candidates {
+        if new_index[i].is_none() && !taken[j] {
+            new_index[i] = Some(j);
+            taken[j] = true;
+        }
+    }
+    let added = (0..new.len()).filter(|&j| !taken[j]).collect();
+    Matching { new_index, added }
+}
+
+fn read(path: &str, duplicates: Duplicates) -> io::Result<Vec<Vec<Site>>> {
+    let layers = journal::recover(path, duplicates)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path, err)))?;
+    Ok(layers.iter().map(|layer| {
+        let d = &layer.diagram;
+        (0..d.dots.len())
+            .map(|i| Site { dot: d.dots[i], color: d.colors[i], team: d.teams[i], metadata: d.metadata[i].clone(), weight: d.weights[i] })
+            .collect()
+    }).collect())
+}
+
+/// The sites of layer `index`, none if the session doesn't have it.
+fn layer(layers: &[Vec<Site>], index: usize) -> &[Site] {
+    layers.get(index).map_or(&[], Vec::as_slice)
+}
+
+fn to_layer(index: usize, sites: Vec<Site>, duplicates: Duplicates) -> Layer {
+    let mut layer = Layer::new(index, duplicates);
+    let d = &mut layer.diagram;
+    d.metadata = sites.iter().map(|s| s.metadata.clone()).collect();
+    d.restore(sites.iter().map(|s| s.dot).collect(), sites.iter().map(|s| s.color).collect(), sites.iter().map(|s| s.team).collect());
+    d.set_weights(sites.iter().map(|s| s.weight).collect());
+    layer
+}
+
+fn describe(site: &Site) -> String {
+    match &site.metadata.label {
+        Some(label) => format!("{} \"{}\"", position(site.dot), label),
+        None => position(site.dot),
+    }
+}
+
+fn position(dot: [f64; 2]) -> String {
+    format!("({:.1}, {:.1})", dot[0], dot[1])
+}
+
+fn hex(c: &[f32; 4]) -> String {
+    let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(c[0]), byte(c[1]), byte(c[2]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+    fn site(x: f64, y: f64, color: [f32; 4]) -> Site {
+        Site { dot: [x, y], color, team: None, metadata: Metadata::default(), weight: 0.0 }
+    }
+
+    /// Writes a session of one layer with `sites` to a file of its own.
+    fn session(name: &str, sites: &[Site]) -> String {
+        let path = temp_path(name);
+        let layers = [to_layer(0, sites.to_vec(), Duplicates::default())];
+        write_layers(&mut std::fs::File::create(&path).unwrap(), &layers).unwrap();
+        path
+    }
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir().join(format!("voronoi-diff-test-{}-{}", std::process::id(), name)).to_string_lossy().into_owned()
+    }
+
+    fn base() -> Vec<Site> {
+        vec![site(100.0, 100.0, RED), site(300.0, 200.0, RED), site(500.0, 400.0, BLUE)]
+    }
+
+    #[test]
+    fn diff_finds_every_kind_of_change() {
+        let old = session("diff-old", &base());
+        let mut changed = base();
+        changed[0].dot = [120.0, 110.0];
+        changed[1].color = BLUE;
+        changed[2].weight = 100.0;
+        changed.push(site(700.0, 300.0, RED));
+        let new = session("diff-new", &changed);
+        let report = diff(&old, &new, Duplicates::default()).unwrap();
+        assert!(report.starts_with("Layer 0: 1 added, 0 removed, 1 moved, 1 recolored, 1 otherwise changed\n"), "{}", report);
+
+        let same = diff(&old, &old, Duplicates::default()).unwrap();
+        assert_eq!(same, "Layer 0: 0 added, 0 removed, 0 moved, 0 recolored, 0 otherwise changed\n");
+    }
+
+    #[test]
+    fn merge_keeps_the_changes_of_both_sides() {
+        let base_path = session("merge-base", &base());
+        let mut ours = base();
+        ours[0].dot = [120.0, 110.0];
+        ours.push(site(700.0, 300.0, RED));
+        let mut theirs = base();
+        theirs[1].color = BLUE;
+        theirs.remove(2);
+        let (ours_path, theirs_path) = (session("merge-ours", &ours), session("merge-theirs", &theirs));
+        let out = temp_path("merge-out");
+
+        let report = merge(&base_path, &ours_path, &theirs_path, &out, Duplicates::default()).unwrap();
+        assert!(report.contains("0 conflicts"), "{}", report);
+        let merged = read(&out, Duplicates::default()).unwrap();
+        let expected = vec![site(120.0, 110.0, RED), site(300.0, 200.0, BLUE), site(700.0, 300.0, RED)];
+        assert!(merged[0] == expected);
+        // The merged session is like ours with their changes on top
+        assert!(diff(&theirs_path, &out, Duplicates::default()).unwrap().starts_with("Layer 0: 1 added, 0 removed, 1 moved, 0 recolored"));
+    }
+
+    #[test]
+    fn merge_of_unchanged_copies_is_the_base() {
+        let base_path = session("unchanged-base", &base());
+        let out = temp_path("unchanged-out");
+        merge(&base_path, &base_path, &base_path, &out, Duplicates::default()).unwrap();
+        assert!(read(&out, Duplicates::default()).unwrap()[0] == base());
+    }
+
+    #[test]
+    fn conflicting_changes_keep_the_base() {
+        let base_path = session("conflict-base", &base());
+        let (mut ours, mut theirs) = (base(), base());
+        ours[0].dot = [120.0, 110.0];
+        theirs[0].dot = [80.0, 90.0];
+        ours[1].color = BLUE;
+        theirs.remove(1);
+        let (ours_path, theirs_path) = (session("conflict-ours", &ours), session("conflict-theirs", &theirs));
+        let out = temp_path("conflict-out");
+
+        let report = merge(&base_path, &ours_path, &theirs_path, &out, Duplicates::default()).unwrap();
+        assert!(report.contains("2 conflicts"), "{}", report);
+        assert!(read(&out, Duplicates::default()).unwrap()[0] == base());
+    }
+}
@@ -0,0 +1,64 @@
+//! The document the picture exports are composed for, like an A4 page or a
+//! square, with a size and aspect ratio of its own rather than the
+//! window's. It shows as the biggest part of the window with its aspect
+//! ratio, in the middle, and exports of pictures cut off everything
+//! around it.
+
+use std::str::FromStr;
+
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// Sizes known by name, in PostScript points for the paper formats.
+pub const PRESETS: [(&str, Document); 4] = [
+    ("a4", Document { width: 595.0, height: 842.0 }),
+    ("a4_landscape", Document { width: 842.0, height: 595.0 }),
+    ("letter", Document { width: 612.0, height: 792.0 }),
+    ("square", Document { width: 1080.0, height: 1080.0 }),
+];
+
+/// Width and height of the exported page, in pixels for PNG exports before
+/// `--export-scale`, and in the units of the page for the vector formats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Document {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Document {
+    /// The part of the window the document shows, its top left and bottom
+    /// right corners.
+    pub fn frame(&self) -> [[f64; 2]; 2] {
+        let (window_width, window_height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        let scale = (window_width / self.width).min(window_height / self.height);
+        let (width, height) = (self.width * scale, self.height * scale);
+        let left = (window_width - width) / 2.0;
+        let top = (window_height - height) / 2.0;
+        [[left, top], [left + width, top + height]]
+    }
+
+    /// Its name among `PRESETS`, or its size.
+    pub fn name(&self) -> String {
+        match PRESETS.iter().find(|(_, d)| d == self) {
+            Some((name, _)) => name.to_string(),
+            None => format!("{}x{}", self.width, self.height),
+        }
+    }
+}
+
+impl FromStr for Document {
+    type Err = String;
+
+    /// One of `PRESETS` by name, or `WIDTHxHEIGHT` like `1920x1080`.
+    fn from_str(s: &str) -> Result<Document, String> {
+        if let Some((_, document)) = PRESETS.iter().find(|(name, _)| *name == s) {
+            return Ok(*document);
+        }
+        let bad = || format!("Unknown document size \"{}\", use WIDTHxHEIGHT, a4, a4_landscape, letter or square", s);
+        let (width, height) = s.split_once('x').ok_or_else(bad)?;
+        let (width, height): (f64, f64) = (width.trim().parse().map_err(|_| bad())?, height.trim().parse().map_err(|_| bad())?);
+        if !(width > 0.0 && height > 0.0 && width.is_finite() && height.is_finite()) {
+            return Err(bad());
+        }
+        Ok(Document { width, height })
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use graphics::{Context, Graphics, BACK_END_MAX_VERTEX_COUNT};
+
+use crate::adjacency::{point_key, polygon_edges};
+use crate::diagram::Point;
+use crate::geometry::{ear_triangles, is_convex};
+use crate::stats::{short, Stats};
+use crate::text::{draw_text, text_size, GLYPH_HEIGHT};
+
+/// Screen pixels per font pixel for overlay text.
+pub const TEXT_SCALE: f64 = 2.0;
+const PANEL_PADDING: f64 = 8.0;
+const LINE_SPACING: f64 = 6.0;
+const HISTOGRAM_BARS_HEIGHT: f64 = 80.0;
+const DOT_RADIUS: f64 = 4.0;
+/// Corners of the dots on screen, fewer than `graphics::ellipse` gives
+/// them, which nobody sees at their size but adds up with many dots.
+pub const DOT_RESOLUTION: u32 = 16;
+/// Corners of the dots in exports, as many as `graphics::ellipse` gives
+/// them, so that drawing them all in one call leaves the pixels the same.
+pub const EXPORT_DOT_RESOLUTION: u32 = 128;
+const WIREFRAME_COLOR: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+const WIREFRAME_WIDTH: f64 = 2.0;
+/// Corners of the round line ends, as many as `graphics::line` gives them.
+const WIREFRAME_CAP_RESOLUTION: u32 = 64;
+/// Width of the outline around circle and square dots.
+pub const DOT_OUTLINE_WIDTH: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DotShape {
+    Circle,
+    Square,
+    Cross,
+}
+
+/// How the dots of the diagram look, on screen and in exports.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DotStyle {
+    pub shape: DotShape,
+    /// Half the width of the dot.
+    pub radius: f64,
+    pub fill: [f32; 4],
+    /// Drawn around circles and squares. Crosses are all outline already.
+    pub outline: Option<[f32; 4]>,
+}
+
+impl Default for DotStyle {
+    fn default() -> DotStyle {
+        DotStyle { shape: DotShape::Circle, radius: DOT_RADIUS, fill: [0.0, 0.0, 0.0, 1.0], outline: None }
+    }
+}
+
+impl DotStyle {
+    /// How thick the arms of a cross are.
+    pub fn cross_width(&self) -> f64 {
+        (self.radius / 2.0).max(1.0)
+    }
+
+    /// The corners of a square dot at `center`, or the two arms of a cross
+    /// from end to end. Circles have none.
+    pub fn outlines(&self, center: [f64; 2]) -> Vec<Vec<Point>> {
+        let ([x, y], r) = (center, self.radius);
+        match self.shape {
+            DotShape::Circle => Vec::new(),
+            DotShape::Square => vec![vec![(x - r, y - r), (x + r, y - r), (x + r, y + r), (x - r, y + r)]],
+            DotShape::Cross => vec![vec![(x - r, y - r), (x + r, y + r)], vec![(x - r, y + r), (x + r, y - r)]],
+        }
+    }
+}
+
+impl FromStr for DotShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DotShape, String> {
+        match s {
+            "circle" => Ok(DotShape::Circle),
+            "square" => Ok(DotShape::Square),
+            "cross" => Ok(DotShape::Cross),
+            _ => Err(format!("Unknown dot shape \"{}\", use circle, square, cross or none", s))
+        }
+    }
+}
+
+/// The cells in the wireframe view, closed, with a single draw call.
+/// Neighboring cells share their corners exactly, so a border they share
+/// is the same pair of corners in both and is drawn only once.
+pub fn draw_wireframe<G: Graphics>(
+    polygons: &[Vec<Point>],
+    c: &Context,
+    g: &mut G,
+) {
+    let mut drawn = HashSet::new();
+    g.tri_list(&c.draw_state, &WIREFRAME_COLOR, |f| {
+        for (a, b) in polygons.iter().flat_map(|poly| polygon_edges(poly)) {
+            let (ka, kb) = (point_key(a), point_key(b));
+            if ka == kb || !drawn.insert((ka.min(kb), ka.max(kb))) {
+                continue;
+            }
+            graphics::triangulation::with_round_border_line_tri_list(
+                WIREFRAME_CAP_RESOLUTION, c.transform, [a.0, a.1, b.0, b.1], WIREFRAME_WIDTH, |vertices| f(vertices));
+        }
+    });
+}
+
+pub fn draw_polygon<G: Graphics>(
+    poly: &[Point],
+    c: &Context,
+    g: &mut G,
+    color: [f32; 4]
+) {
+    let mut polygon_points: Vec<[f64; 2]> = Vec::new();
+
+    for p in poly {
+        polygon_points.push([p.0, p.1]);
+    }
+
+    graphics::polygon(
+        color,
+        polygon_points.as_slice(),
+        c.transform,
+        g
+    )
+}
+
+pub fn draw_ellipse<G: Graphics>(
+    cursor: &[f64; 2],
+    c: &Context,
+    g: &mut G,
+    color: [f32; 4]
+) {
+    graphics::ellipse(
+        color,
+        graphics::ellipse::circle(cursor[0], cursor[1], DOT_RADIUS),
+        c.transform,
+        g
+    );
+}
+
+/// Filled cells, each in its color with the alpha scaled by `opacity`, in
+/// as few draw calls as the back end allows instead of one per cell. Cells
+/// drawn again unchanged are better kept as a `CellMesh`.
+pub fn draw_cells<G: Graphics>(
+    cells: &[Vec<Point>],
+    colors: &[[f32; 4]],
+    opacity: f32,
+    c: &Context,
+    g: &mut G,
+) {
+    CellMesh::new(cells, colors, opacity).draw(c, g);
+}
+
+/// Filled cells cut into triangles once, with a color for every corner, so
+/// that drawing them again only has to hand the buffers to the back end.
+/// Convex cells are fanned from their first corner like `graphics::polygon`
+/// does, concave ones, like those with curved edges, cut into ears.
+#[derive(Default)]
+pub struct CellMesh {
+    vertices: Vec<[f64; 2]>,
+    colors: Vec<[f32; 4]>,
+}
+
+impl CellMesh {
+    pub fn new(cells: &[Vec<Point>], colors: &[[f32; 4]], opacity: f32) -> CellMesh {
+        let mut mesh = CellMesh::default();
+        for (cell, &[r, g, b, a]) in cells.iter().zip(colors) {
+            let triangles = if is_convex(cell) {
+                (1..cell.len().saturating_sub(1)).map(|i| [0, i, i + 1]).collect()
+            } else {
+                ear_triangles(cell)
+            };
+            for triangle in triangles {
+                mesh.vertices.extend(triangle.map(|i| [cell[i].0, cell[i].1]));
+            }
+            mesh.colors.resize(mesh.vertices.len(), [r, g, b, a * opacity]);
+        }
+        mesh
+    }
+
+    /// All triangles in as few draw calls as the back end allows, which
+    /// takes as many whole triangles as fit in `BACK_END_MAX_VERTEX_COUNT`.
+    pub fn draw<G: Graphics>(&self, c: &Context, g: &mut G) {
+        use graphics::triangulation::{tx, ty};
+
+        const CHUNK: usize = BACK_END_MAX_VERTEX_COUNT / 3 * 3;
+        let m = c.transform;
+        let mut transformed = Vec::with_capacity(CHUNK.min(self.vertices.len()));
+        g.tri_list_c(&c.draw_state, |f| {
+            for (vertices, colors) in self.vertices.chunks(CHUNK).zip(self.colors.chunks(CHUNK)) {
+                transformed.clear();
+                transformed.extend(vertices.iter().map(|&[x, y]| [tx(m, x, y), ty(m, x, y)]));
+                f(&transformed, colors);
+            }
+        });
+    }
+}
+
+/// All dots in one color with a single draw call, with `DOT_RESOLUTION`
+/// corners.
+pub fn draw_dots<G: Graphics>(
+    dots: &[[f64; 2]],
+    c: &Context,
+    g: &mut G,
+    color: [f32; 4]
+) {
+    g.tri_list(&c.draw_state, &color, |f| {
+        for d in dots {
+            let rect = graphics::ellipse::circle(d[0], d[1], DOT_RADIUS);
+            graphics::triangulation::with_ellipse_tri_list(DOT_RESOLUTION, c.transform, rect, |vertices| f(vertices));
+        }
+    });
+}
+
+/// The dots of the diagram in `style`, circles with `resolution` corners.
+/// Circles and squares go in a single draw call, their outlines and crosses
+/// take one per dot.
+pub fn draw_styled_dots<G: Graphics>(
+    dots: &[[f64; 2]],
+    style: &DotStyle,
+    resolution: u32,
+    c: &Context,
+    g: &mut G
+) {
+    match style.shape {
+        DotShape::Circle => g.tri_list(&c.draw_state, &style.fill, |f| {
+            for d in dots {
+                let rect = graphics::ellipse::circle(d[0], d[1], style.radius);
+                graphics::triangulation::with_ellipse_tri_list(resolution, c.transform, rect, |vertices| f(vertices));
+            }
+        }),
+        DotShape::Square => g.tri_list(&c.draw_state, &style.fill, |f| {
+            for d in dots {
+                let (r, side) = (style.radius, 2.0 * style.radius);
+                f(&graphics::triangulation::rect_tri_list_xy(c.transform, [d[0] - r, d[1] - r, side, side]));
+            }
+        }),
+        DotShape::Cross => {
+            for d in dots {
+                for arm in style.outlines(*d) {
+                    let (a, b) = (arm[0], arm[1]);
+                    graphics::line(style.fill, style.cross_width() / 2.0, [a.0, a.1, b.0, b.1], c.transform, g);
+                }
+            }
+        }
+    }
+    let Some(outline) = style.outline.filter(|_| style.shape != DotShape::Cross) else { return };
+    for d in dots {
+        match style.shape {
+            DotShape::Circle => graphics::Ellipse::new_border(outline, DOT_OUTLINE_WIDTH / 2.0)
+                .draw(graphics::ellipse::circle(d[0], d[1], style.radius), &c.draw_state, c.transform, g),
+            _ => for square in style.outlines(*d) {
+                draw_outline(&square, c, g, outline, DOT_OUTLINE_WIDTH / 2.0);
+            }
+        }
+    }
+}
+
+/// The outline of a circle.
+pub fn draw_circle<G: Graphics>(
+    center: &[f64; 2],
+    radius: f64,
+    c: &Context,
+    g: &mut G,
+    color: [f32; 4]
+) {
+    graphics::Ellipse::new_border(color, 1.0)
+        .draw(graphics::ellipse::circle(center[0], center[1], radius), &c.draw_state, c.transform, g);
+}
+
+/// The outline of a closed loop of points.
+pub fn draw_outline<G: Graphics>(
+    points: &[Point],
+    c: &Context,
+    g: &mut G,
+    color: [f32; 4],
+    radius: f64
+) {
+    for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+        graphics::line(color, radius, [a.0, a.1, b.0, b.1], c.transform, g);
+    }
+}
+
+/// A diamond with a black outline, standing out from cells and dots.
+pub fn draw_marker<G: Graphics>(
+    center: &[f64; 2],
+    c: &Context,
+    g: &mut G,
+    color: [f32; 4]
+) {
+    const SIZE: f64 = 9.0;
+    let [x, y] = *center;
+    let corners = [[x, y - SIZE], [x + SIZE, y], [x, y + SIZE], [x - SIZE, y]];
+    graphics::polygon(color, &corners, c.transform, g);
+    for i in 0..corners.len() {
+        let (a, b) = (corners[i], corners[(i + 1) % corners.len()]);
+        graphics::line([0.0, 0.0, 0.0, 1.0], 1.0, [a[0], a[1], b[0], b[1]], c.transform, g);
+    }
+}
+
+/// The size of the box `draw_panel` draws for these lines.
+pub fn panel_size(lines: &[(Option<[f32; 4]>, String)]) -> [f64; 2] {
+    let line_height = GLYPH_HEIGHT * TEXT_SCALE;
+    let text_width = lines.iter().map(|(_, t)| text_size(t, TEXT_SCALE)[0]).fold(0.0, f64::max);
+    [
+        text_width + swatch_width(lines) + 2.0 * PANEL_PADDING,
+        lines.len() as f64 * (line_height + LINE_SPACING) - LINE_SPACING + 2.0 * PANEL_PADDING,
+    ]
+}
+
+fn swatch_width(lines: &[(Option<[f32; 4]>, String)]) -> f64 {
+    if lines.iter().any(|(s, _)| s.is_some()) { GLYPH_HEIGHT * TEXT_SCALE + LINE_SPACING } else { 0.0 }
+}
+
+/// A translucent box with lines of text at `pos`, each line optionally
+/// preceded by a color swatch. Returns the size of the box.
+pub fn draw_panel<G: Graphics>(
+    lines: &[(Option<[f32; 4]>, String)],
+    pos: [f64; 2],
+    c: &Context,
+    g: &mut G,
+) -> [f64; 2] {
+    let line_height = GLYPH_HEIGHT * TEXT_SCALE;
+    let swatch = swatch_width(lines);
+    let size = panel_size(lines);
+    graphics::rectangle([1.0, 1.0, 1.0, 0.8], [pos[0], pos[1], size[0], size[1]], c.transform, g);
+
+    for (i, (color, text)) in lines.iter().enumerate() {
+        let top = pos[1] + PANEL_PADDING + i as f64 * (line_height + LINE_SPACING);
+        if let Some(color) = color {
+            graphics::rectangle(*color, [pos[0] + PANEL_PADDING, top, line_height, line_height], c.transform, g);
+        }
+        draw_text(text, [pos[0] + PANEL_PADDING + swatch, top], TEXT_SCALE, [0.0, 0.0, 0.0, 1.0], c, g);
+    }
+    size
+}
+
+pub fn histogram_size(width: f64) -> [f64; 2] {
+    [width, 2.0 * PANEL_PADDING + GLYPH_HEIGHT * TEXT_SCALE + 3.0 * LINE_SPACING + 2.0 * GLYPH_HEIGHT + HISTOGRAM_BARS_HEIGHT]
+}
+
+/// A translucent box at `pos`, `width` wide, with `title`, the summary of
+/// `stats` and a bar per histogram bin labeled with the lowest and highest
+/// value. Returns the size of the box.
+pub fn draw_histogram<G: Graphics>(
+    title: &str,
+    stats: Option<&Stats>,
+    pos: [f64; 2],
+    width: f64,
+    c: &Context,
+    g: &mut G,
+) -> [f64; 2] {
+    let title_height = GLYPH_HEIGHT * TEXT_SCALE;
+    let small_height = GLYPH_HEIGHT;
+    let size = histogram_size(width);
+    graphics::rectangle([1.0, 1.0, 1.0, 0.8], [pos[0], pos[1], size[0], size[1]], c.transform, g);
+
+    let black = [0.0, 0.0, 0.0, 1.0];
+    let left = pos[0] + PANEL_PADDING;
+    let mut top = pos[1] + PANEL_PADDING;
+    draw_text(title, [left, top], TEXT_SCALE, black, c, g);
+    top += title_height + LINE_SPACING;
+    let Some(stats) = stats else {
+        draw_text("no data", [left, top], 1.0, black, c, g);
+        return size;
+    };
+    draw_text(&stats.summary(), [left, top], 1.0, black, c, g);
+    top += small_height + LINE_SPACING;
+
+    let inner = width - 2.0 * PANEL_PADDING;
+    let bar_width = inner / stats.histogram.len() as f64;
+    let tallest = stats.histogram.iter().copied().max().unwrap_or(0).max(1) as f64;
+    for (i, &n) in stats.histogram.iter().enumerate() {
+        let height = n as f64 / tallest * HISTOGRAM_BARS_HEIGHT;
+        let x = left + i as f64 * bar_width;
+        graphics::rectangle([0.2, 0.4, 0.8, 1.0], [x, top + HISTOGRAM_BARS_HEIGHT - height, bar_width - 1.0, height], c.transform, g);
+    }
+    top += HISTOGRAM_BARS_HEIGHT + LINE_SPACING;
+
+    let max = short(stats.max);
+    draw_text(&short(stats.min), [left, top], 1.0, black, c, g);
+    draw_text(&max, [left + inner - text_size(&max, 1.0)[0], top], 1.0, black, c, g);
+    size
+}
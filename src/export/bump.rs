@@ -0,0 +1,150 @@
+//! Height and normal maps for materials like cracked ground or cobblestones,
+//! worked out from how far each pixel is from the edges of its cell. Edges
+//! that only come from the cell being cut off at the window's border are
+//! left out, so the border doesn't turn into a crack.
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+
+use crate::diagram::{Diagram, Point};
+use crate::raster::Canvas;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+use super::image::write_png;
+use super::ExportOptions;
+
+/// What the height at a pixel comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BumpField {
+    /// The distance to the nearest edge, rising over the bevel width and
+    /// flat beyond it, like rounded cobblestones with cracks between them.
+    Edges,
+    /// How far the pixel is from its dot compared to the nearest edge, so
+    /// each cell is a cone with its top at the dot.
+    Sites,
+}
+
+pub struct Bump {
+    pub field: BumpField,
+    /// Write the normals of the surface rather than its height.
+    pub normals: bool,
+    /// How many pixels of the window the edges take to rise to full height.
+    pub bevel: f64,
+}
+
+/// Writes the map as a PNG the size of the window times `options.scale`.
+pub fn export_bump(path: &str, diagram: &Diagram, options: &ExportOptions, bump: &Bump) -> io::Result<()> {
+    let image = render_bump(diagram, options, bump)?;
+    let mut w = BufWriter::new(File::create(path)?);
+    write_png(&mut w, &image)
+}
+
+/// The height map in gray, white at the top, or the normal map in the
+/// OpenGL convention: X to the right in red, Y up in green, Z out of the
+/// image in blue.
+pub fn render_bump(diagram: &Diagram, options: &ExportOptions, bump: &Bump) -> io::Result<Canvas> {
+    if diagram.dots.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "there are no dots yet"));
+    }
+    let width = (DEFAULT_WINDOW_WIDTH as f64 * options.scale).round() as u32;
+    let height = (DEFAULT_WINDOW_HEIGHT as f64 * options.scale).round() as u32;
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the export scale leaves no pixels"));
+    }
+    let (sx, sy) = (width as f64 / DEFAULT_WINDOW_WIDTH as f64, height as f64 / DEFAULT_WINDOW_HEIGHT as f64);
+    let heights = heights(diagram, options, bump, width as usize, height as usize, (sx, sy));
+
+    let mut image = Canvas::new(width, height);
+    let (w, h) = (width as usize, height as usize);
+    for y in 0..h {
+        for x in 0..w {
+            let at = |x: usize, y: usize| heights[y * w + x] * bump.bevel;
+            let rgb = if bump.normals {
+                // Slopes in window pixels, Y turned up
+                let (left, right) = (x.saturating_sub(1), (x + 1).min(w - 1));
+                let (top, bottom) = (y.saturating_sub(1), (y + 1).min(h - 1));
+                let dx = (at(right, y) - at(left, y)) * sx / (right - left).max(1) as f64;
+                let dy = (at(x, top) - at(x, bottom)) * sy / (bottom - top).max(1) as f64;
+                let length = (dx * dx + dy * dy + 1.0).sqrt();
+                [-dx / length, -dy / length, 1.0 / length].map(|n| byte(n * 0.5 + 0.5))
+            } else {
+                [byte(heights[y * w + x]); 3]
+            };
+            let i = (y * w + x) * 4;
+            image.pixels[i..i + 3].copy_from_slice(&rgb);
+            image.pixels[i + 3] = 255;
+        }
+    }
+    Ok(image)
+}
+
+/// The height of every pixel between 0 and 1, row by row. Pixels outside
+/// all cells stay at 0.
+fn heights(diagram: &Diagram, options: &ExportOptions, bump: &Bump, width: usize, height: usize, (sx, sy): (f64, f64)) -> Vec<f64> {
+    let (_, visible) = diagram.smoothed(options.smoothing);
+    let mut heights = vec![0.0; width * height];
+    for (i, cell) in visible.iter().enumerate().filter(|(_, cell)| cell.len() >= 3) {
+        let edges = (0..cell.len())
+            .map(|j| (cell[j], cell[(j + 1) % cell.len()]))
+            .filter(|&(a, b)| !on_border(a, b))
+            .collect::<Vec<_>>();
+        let dot = (diagram.dots[i][0], diagram.dots[i][1]);
+
+        let (left, top) = cell.iter().fold((f64::INFINITY, f64::INFINITY), |(x, y), p| (x.min(p.0), y.min(p.1)));
+        let (right, bottom) = cell.iter().fold((f64::NEG_INFINITY, f64::NEG_INFINITY), |(x, y), p| (x.max(p.0), y.max(p.1)));
+        let columns = ((left * sx).floor().max(0.0) as usize)..((right * sx).ceil().min(width as f64) as usize);
+        for py in ((top * sy).floor().max(0.0) as usize)..((bottom * sy).ceil().min(height as f64) as usize) {
+            for px in columns.clone() {
+                // The middle of the pixel, in window coordinates
+                let p = ((px as f64 + 0.5) / sx, (py as f64 + 0.5) / sy);
+                if !inside(cell, p) {
+                    continue;
+                }
+                let edge = edges.iter().map(|&(a, b)| segment_distance(p, a, b)).fold(f64::INFINITY, f64::min);
+                heights[py * width + px] = match bump.field {
+                    BumpField::Edges => {
+                        // A quarter circle, steep at the edge and level on top
+                        let t = 1.0 - (edge / bump.bevel).min(1.0);
+                        (1.0 - t * t).sqrt()
+                    }
+                    BumpField::Sites if edge.is_finite() => {
+                        let site = (p.0 - dot.0).hypot(p.1 - dot.1);
+                        if edge + site > 0.0 { edge / (edge + site) } else { 1.0 }
+                    }
+                    BumpField::Sites => 1.0,
+                };
+            }
+        }
+    }
+    heights
+}
+
+/// Whether the edge runs along the border of the window.
+fn on_border(a: Point, b: Point) -> bool {
+    const EPSILON: f64 = 1e-6;
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    [(a.0, b.0, 0.0), (a.0, b.0, width), (a.1, b.1, 0.0), (a.1, b.1, height)].iter()
+        .any(|&(u, v, line)| (u - line).abs() < EPSILON && (v - line).abs() < EPSILON)
+}
+
+/// Whether `p` is inside the polygon, by the even-odd rule.
+fn inside(polygon: &[Point], p: Point) -> bool {
+    let mut inside = false;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx * dx + dy * dy;
+    let t = if length > 0.0 { (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length).clamp(0.0, 1.0) } else { 0.0 };
+    (p.0 - a.0 - t * dx).hypot(p.1 - a.1 - t * dy)
+}
+
+fn byte(v: f64) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
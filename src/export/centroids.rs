@@ -0,0 +1,46 @@
+//! The centroid of every cell, for stippling and TSP art made elsewhere.
+//! After a few rounds of relaxation the dots sit on these already, and the
+//! centroids are where the next round would move them.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::diagram::Diagram;
+use crate::geometry::{area, centroid};
+use crate::metadata::{self, dots_json};
+use super::ExportOptions;
+
+/// Writes the centroids of the cells as far as they're in the window, in
+/// the order of their dots. A `.csv` file gets `x,y,area` lines under a
+/// header, anything else a JSON array of `[x, y]` pairs. Both can be read
+/// back with `-j`, and both keep the metadata of the dots if they have
+/// some. A cell with no area in the window keeps its dot. With
+/// `data_coords`, both are in the coordinates the dots were loaded in.
+/// Returns how many were written.
+pub fn export_centroids(path: &str, diagram: &Diagram, options: &ExportOptions) -> io::Result<usize> {
+    let data = options.data(diagram);
+    let cells = diagram.dots.iter().zip(&diagram.visible)
+        .map(|(dot, poly)| match centroid(poly) {
+            Some(c) => ([c.0, c.1], area(poly)),
+            None => (*dot, 0.0),
+        })
+        .map(|(c, area)| (data.map_or(c, |fit| fit.invert(c)), options.area(diagram, area)))
+        .collect::<Vec<_>>();
+    let is_csv = Path::new(path).extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+    let mut w = BufWriter::new(File::create(path)?);
+    let with_metadata = metadata::any(&diagram.metadata);
+    if is_csv {
+        writeln!(w, "x,y,area{}", if with_metadata { format!(",{}", metadata::FIELDS.join(",")) } else { String::new() })?;
+        for (([x, y], area), metadata) in cells.iter().zip(&diagram.metadata) {
+            let columns = if with_metadata { format!(",{}", metadata.csv_columns()) } else { String::new() };
+            writeln!(w, "{},{},{}{}", x, y, area, columns)?;
+        }
+    } else {
+        let centroids = cells.iter().map(|&(c, _)| c).collect::<Vec<_>>();
+        serde_json::to_writer(&mut w, &dots_json(&centroids, &diagram.metadata))?;
+        writeln!(w)?;
+    }
+    w.flush()?;
+    Ok(cells.len())
+}
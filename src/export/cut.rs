@@ -0,0 +1,83 @@
+//! The cells as cutting paths for a laser cutter or CNC router, to make a
+//! puzzle out of the diagram: outlines only, each numbered so the pieces
+//! can be told apart, and grown by half the kerf, the width of material
+//! the cut burns away, so every piece comes out at the size of its cell.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::diagram::{Diagram, Point};
+use crate::geometry::{centroid, offset};
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+use super::ExportOptions;
+
+/// Pure red hairlines are what most laser software takes for cuts.
+const CUT_COLOR: &str = "#ff0000";
+const CUT_WIDTH: f64 = 0.1;
+/// Black text is engraved.
+const LABEL_COLOR: &str = "#000000";
+const LABEL_SIZE: f64 = 10.0;
+/// Room around a piece in its own file.
+const MARGIN: f64 = 5.0;
+
+/// Writes all cells into one SVG if `path` ends in `.svg`, otherwise one SVG
+/// per cell into the directory `path`. Returns the number of cells.
+pub fn export_cut(path: &str, diagram: &Diagram, options: &ExportOptions, kerf: f64) -> io::Result<usize> {
+    let pieces = pieces(diagram, options, kerf);
+    if pieces.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "there are no cells yet"));
+    }
+    let svg = Path::new(path).extension().is_some_and(|e| e.eq_ignore_ascii_case("svg"));
+
+    if svg {
+        let mut w = BufWriter::new(File::create(path)?);
+        let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        write_header(&mut w, [0.0, 0.0, width, height], options.scale)?;
+        for (number, outline) in &pieces {
+            write_piece(&mut w, *number, outline)?;
+        }
+        writeln!(w, "</svg>")?;
+    } else {
+        std::fs::create_dir_all(path)?;
+        for (number, outline) in &pieces {
+            let file = Path::new(path).join(format!("cell_{:05}.svg", number));
+            let mut w = BufWriter::new(File::create(file)?);
+            let (left, top) = outline.iter().fold((f64::INFINITY, f64::INFINITY), |(x, y), p| (x.min(p.0), y.min(p.1)));
+            let (right, bottom) = outline.iter().fold((f64::NEG_INFINITY, f64::NEG_INFINITY), |(x, y), p| (x.max(p.0), y.max(p.1)));
+            let bounds = [left - MARGIN, top - MARGIN, right - left + 2.0 * MARGIN, bottom - top + 2.0 * MARGIN];
+            write_header(&mut w, bounds, options.scale)?;
+            write_piece(&mut w, *number, outline)?;
+            writeln!(w, "</svg>")?;
+        }
+    }
+    Ok(pieces.len())
+}
+
+/// The cells inside the window, numbered from 1 in the order of their
+/// dots, each grown by half the kerf.
+fn pieces(diagram: &Diagram, options: &ExportOptions, kerf: f64) -> Vec<(usize, Vec<Point>)> {
+    let (_, visible) = diagram.smoothed(options.smoothing);
+    visible.iter().enumerate()
+        .filter(|(_, cell)| cell.len() >= 3)
+        .map(|(i, cell)| (i + 1, offset(cell, kerf / 2.0)))
+        .collect()
+}
+
+fn write_header<W: Write>(w: &mut W, [x, y, width, height]: [f64; 4], scale: f64) -> io::Result<()> {
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(w, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        width * scale, height * scale, x, y, width, height)
+}
+
+/// A group with the outline of the cell and its number in the middle.
+fn write_piece<W: Write>(w: &mut W, number: usize, outline: &[Point]) -> io::Result<()> {
+    let d = outline.iter().map(|p| format!("{},{}", p.0, p.1)).collect::<Vec<_>>().join(" L");
+    writeln!(w, r#"<g id="cell-{}">"#, number)?;
+    writeln!(w, r#"<path d="M{}Z" fill="none" stroke="{}" stroke-width="{}"/>"#, d, CUT_COLOR, CUT_WIDTH)?;
+    if let Some((x, y)) = centroid(outline) {
+        writeln!(w, r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{}" text-anchor="middle" dominant-baseline="central" fill="{}">{}</text>"#,
+            x, y, LABEL_SIZE, LABEL_COLOR, number)?;
+    }
+    writeln!(w, "</g>")
+}
@@ -0,0 +1,95 @@
+//! DXF export for CAD tools such as AutoCAD and Fusion.
+//!
+//! The file is in the old R12 ASCII flavor, which every tool reads: the
+//! cells are closed polylines, the Delaunay edges lines and the dots points,
+//! each kind on a layer of its own so they can be hidden separately. Like
+//! PostScript, DXF is Y-up, so the diagram is flipped to keep it upright.
+
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use crate::diagram::{Diagram, Point};
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+use super::ExportOptions;
+
+/// Layer names with their AutoCAD color index.
+const CELLS: (&str, u8) = ("CELLS", 5);
+const DELAUNAY: (&str, u8) = ("DELAUNAY", 8);
+const SITES: (&str, u8) = ("SITES", 7);
+
+/// One group: a code saying what the value means, then the value.
+fn group<W: Write>(w: &mut W, code: u16, value: impl Display) -> io::Result<()> {
+    writeln!(w, "{:>3}", code)?;
+    writeln!(w, "{}", value)
+}
+
+fn coordinates<W: Write>(w: &mut W, first_code: u16, p: Point) -> io::Result<()> {
+    group(w, first_code, p.0)?;
+    group(w, first_code + 10, DEFAULT_WINDOW_HEIGHT as f64 - p.1)?;
+    group(w, first_code + 20, 0.0)
+}
+
+pub fn write_dxf<W: Write>(w: &mut W, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    group(w, 0, "SECTION")?;
+    group(w, 2, "HEADER")?;
+    group(w, 9, "$ACADVER")?;
+    group(w, 1, "AC1009")?;
+    group(w, 9, "$EXTMIN")?;
+    coordinates(w, 10, (0.0, DEFAULT_WINDOW_HEIGHT as f64))?;
+    group(w, 9, "$EXTMAX")?;
+    coordinates(w, 10, (DEFAULT_WINDOW_WIDTH as f64, 0.0))?;
+    group(w, 0, "ENDSEC")?;
+
+    group(w, 0, "SECTION")?;
+    group(w, 2, "TABLES")?;
+    group(w, 0, "TABLE")?;
+    group(w, 2, "LAYER")?;
+    group(w, 70, 3)?;
+    for (name, color) in [CELLS, DELAUNAY, SITES] {
+        group(w, 0, "LAYER")?;
+        group(w, 2, name)?;
+        group(w, 70, 0)?;
+        group(w, 62, color)?;
+        group(w, 6, "CONTINUOUS")?;
+    }
+    group(w, 0, "ENDTAB")?;
+    group(w, 0, "ENDSEC")?;
+
+    group(w, 0, "SECTION")?;
+    group(w, 2, "ENTITIES")?;
+    let (_, visible) = diagram.smoothed(options.smoothing);
+    for cell in visible.iter().filter(|cell| cell.len() >= 3) {
+        group(w, 0, "POLYLINE")?;
+        group(w, 8, CELLS.0)?;
+        // Vertices follow, and the polyline is closed
+        group(w, 66, 1)?;
+        group(w, 70, 1)?;
+        coordinates(w, 10, (0.0, DEFAULT_WINDOW_HEIGHT as f64))?;
+        for &p in cell {
+            group(w, 0, "VERTEX")?;
+            group(w, 8, CELLS.0)?;
+            coordinates(w, 10, p)?;
+        }
+        group(w, 0, "SEQEND")?;
+        group(w, 8, CELLS.0)?;
+    }
+    let mut edges = diagram.triangles().into_iter()
+        .flat_map(|t| [(t.0, t.1), (t.1, t.2), (t.2, t.0)])
+        .map(|(a, b)| (a.min(b), a.max(b)))
+        .collect::<Vec<_>>();
+    edges.sort_unstable();
+    edges.dedup();
+    for (a, b) in edges {
+        group(w, 0, "LINE")?;
+        group(w, 8, DELAUNAY.0)?;
+        coordinates(w, 10, (diagram.dots[a][0], diagram.dots[a][1]))?;
+        coordinates(w, 11, (diagram.dots[b][0], diagram.dots[b][1]))?;
+    }
+    for d in diagram.dots.iter().filter(|_| options.dots) {
+        group(w, 0, "POINT")?;
+        group(w, 8, SITES.0)?;
+        coordinates(w, 10, (d[0], d[1]))?;
+    }
+    group(w, 0, "ENDSEC")?;
+    group(w, 0, "EOF")
+}
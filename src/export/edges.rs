@@ -0,0 +1,118 @@
+//! The edges of the cells as strokes for pen plotters: every edge once,
+//! even where two cells share it, clipped to the window, and joined into
+//! polylines wherever they meet, so the pen goes over each line once and is
+//! lifted as rarely as possible.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::adjacency::polygon_edges;
+use crate::diagram::{Diagram, Point};
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+use super::ExportOptions;
+
+/// Corners closer than this are the same corner. Neighboring cells are cut
+/// to the window each on their own, so their corners on its edge can differ
+/// in the last bits.
+const SAME_CORNER: f64 = 1e-6;
+const STROKE_COLOR: &str = "#000000";
+const STROKE_WIDTH: f64 = 0.5;
+
+/// Writes the polylines to `path`: a `.json` file gets an array of them,
+/// each an array of `[x, y]` pairs, a `.csv` file `polyline,x,y` lines
+/// under a header, and anything else an SVG with a path for each. Returns
+/// how many edges and polylines were written.
+pub fn export_edges(path: &str, diagram: &Diagram, options: &ExportOptions) -> io::Result<(usize, usize)> {
+    let (_, visible) = diagram.smoothed(options.smoothing);
+    let edges = unique_edges(&visible);
+    if edges.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "there are no cells yet"));
+    }
+    let lines = polylines(&edges);
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).unwrap_or_default();
+    let mut w = BufWriter::new(File::create(path)?);
+    match extension.as_str() {
+        "json" => {
+            let lines = lines.iter().map(|line| line.iter().map(|p| [p.0, p.1]).collect::<Vec<_>>()).collect::<Vec<_>>();
+            serde_json::to_writer(&mut w, &lines)?;
+            writeln!(w)?;
+        }
+        "csv" => {
+            writeln!(w, "polyline,x,y")?;
+            for (number, line) in lines.iter().enumerate() {
+                for p in line {
+                    writeln!(w, "{},{},{}", number, p.0, p.1)?;
+                }
+            }
+        }
+        _ => {
+            let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+            writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+            writeln!(w, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+                width * options.scale, height * options.scale, width, height)?;
+            for line in &lines {
+                let d = line.iter().map(|p| format!("{},{}", p.0, p.1)).collect::<Vec<_>>().join(" L");
+                writeln!(w, r#"<path d="M{}" fill="none" stroke="{}" stroke-width="{}"/>"#, d, STROKE_COLOR, STROKE_WIDTH)?;
+            }
+            writeln!(w, "</svg>")?;
+        }
+    }
+    w.flush()?;
+    Ok((edges.len(), lines.len()))
+}
+
+fn corner_key(p: &Point) -> (i64, i64) {
+    ((p.0 / SAME_CORNER).round() as i64, (p.1 / SAME_CORNER).round() as i64)
+}
+
+/// The edges of all cells, those two cells share only once, and none of
+/// no length.
+fn unique_edges(cells: &[Vec<Point>]) -> Vec<(Point, Point)> {
+    let mut seen = HashMap::new();
+    let mut edges = Vec::new();
+    for cell in cells.iter().filter(|cell| cell.len() >= 3) {
+        for (a, b) in polygon_edges(cell) {
+            let (ka, kb) = (corner_key(a), corner_key(b));
+            if ka == kb {
+                continue;
+            }
+            let unordered = if ka < kb { (ka, kb) } else { (kb, ka) };
+            seen.entry(unordered).or_insert_with(|| {
+                edges.push((*a, *b));
+            });
+        }
+    }
+    edges
+}
+
+/// Joins the edges end to end into as few polylines as a walk from corner to
+/// corner gives. Walks start where an odd number of edges meet, like the
+/// window's edge or a loose end, so closed loops are only left for last.
+fn polylines(edges: &[(Point, Point)]) -> Vec<Vec<Point>> {
+    let mut at: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, (a, b)) in edges.iter().enumerate() {
+        at.entry(corner_key(a)).or_default().push(i);
+        at.entry(corner_key(b)).or_default().push(i);
+    }
+    let mut used = vec![false; edges.len()];
+    let mut starts = edges.iter().flat_map(|(a, b)| [*a, *b]).collect::<Vec<_>>();
+    starts.sort_by_key(|p| at[&corner_key(p)].len().is_multiple_of(2));
+
+    let mut lines = Vec::new();
+    for start in starts {
+        let mut line = vec![start];
+        let mut corner = start;
+        while let Some(&next) = at[&corner_key(&corner)].iter().find(|&&i| !used[i]) {
+            used[next] = true;
+            let (a, b) = edges[next];
+            corner = if corner_key(&a) == corner_key(&corner) { b } else { a };
+            line.push(corner);
+        }
+        if line.len() > 1 {
+            lines.push(line);
+        }
+    }
+    lines
+}
@@ -0,0 +1,42 @@
+//! GeoJSON export for web maps and notebooks: the cells as polygon features
+//! with their index, the id of their dot, see `Diagram::ids`, their dot,
+//! area, color and metadata. Like shapefiles, the
+//! coordinates are Y-up, so the diagram is flipped to keep it upright,
+//! unless it is written in the coordinates the dots were loaded in. `-j`
+//! reads the dots and their metadata back from it.
+
+use std::io::{self, Write};
+
+use crate::diagram::Diagram;
+use crate::geometry::{area, signed_area};
+use super::ExportOptions;
+
+pub fn write_geojson<W: Write>(w: &mut W, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    let (_, visible) = diagram.smoothed(options.smoothing);
+    let flip = options.y_up(diagram);
+    let features = visible.iter().enumerate().filter(|(_, cell)| cell.len() >= 3).map(|(i, cell)| {
+        let mut ring = cell.iter().map(flip).collect::<Vec<_>>();
+        // Outer rings go counter-clockwise, unlike in shapefiles
+        if signed_area(&ring) < 0.0 {
+            ring.reverse();
+        }
+        ring.push(ring[0]);
+        let site = flip(&(diagram.dots[i][0], diagram.dots[i][1]));
+        let mut properties = serde_json::json!({ "index": i, "id": diagram.ids[i], "site": [site.0, site.1], "area": options.area(diagram, area(cell)), "color": hex(&diagram.colors[i]) });
+        if let (Some(properties), Some(metadata)) = (properties.as_object_mut(), diagram.metadata.get(i)) {
+            properties.extend(metadata.to_json());
+        }
+        serde_json::json!({
+            "type": "Feature",
+            "geometry": { "type": "Polygon", "coordinates": [ring.iter().map(|p| [p.0, p.1]).collect::<Vec<_>>()] },
+            "properties": properties,
+        })
+    }).collect::<Vec<_>>();
+    serde_json::to_writer(&mut *w, &serde_json::json!({ "type": "FeatureCollection", "features": features }))?;
+    writeln!(w)
+}
+
+fn hex(c: &[f32; 4]) -> String {
+    let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(c[0]), byte(c[1]), byte(c[2]))
+}
@@ -0,0 +1,56 @@
+//! The adjacency graph of the cells: which cells border on which, and
+//! along which edge. For map coloring, pathfinding and other graph work.
+//! Cells carry the metadata of their dots.
+
+use std::io::{self, Write};
+
+use crate::adjacency::shared_edges;
+use crate::diagram::Diagram;
+use super::ExportOptions;
+
+/// For every cell, its dot and its id, see `Diagram::ids`, and its
+/// neighbors with the edge shared with each.
+pub fn write_json<W: Write>(w: &mut W, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    let data = options.data(diagram);
+    let place = |p: [f64; 2]| data.map_or(p, |fit| fit.invert(p));
+    let mut neighbors = vec![Vec::new(); diagram.dots.len()];
+    for edge in shared_edges(&diagram.polygons) {
+        let (a, b) = edge.cells;
+        let ends = [place([edge.ends.0 .0, edge.ends.0 .1]), place([edge.ends.1 .0, edge.ends.1 .1])];
+        neighbors[a].push(serde_json::json!({ "cell": b, "edge": ends }));
+        neighbors[b].push(serde_json::json!({ "cell": a, "edge": ends }));
+    }
+    let cells = diagram.dots.iter().zip(neighbors).enumerate()
+        .map(|(index, (&site, neighbors))| {
+            let mut cell = serde_json::json!({ "index": index, "id": diagram.ids[index], "site": place(site), "neighbors": neighbors });
+            if let (Some(cell), Some(metadata)) = (cell.as_object_mut(), diagram.metadata.get(index)) {
+                cell.extend(metadata.to_json());
+            }
+            cell
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_writer_pretty(&mut *w, &serde_json::json!({ "cells": cells }))?;
+    writeln!(w)
+}
+
+/// An undirected Graphviz graph with a node per cell, placed at its dot
+/// (Y up, as Graphviz has it), and an edge per pair of neighbors. The
+/// shared edge's endpoints are in the `shared` attribute, the metadata of
+/// the dots in attributes of the nodes named like its fields.
+pub fn write_dot<W: Write>(w: &mut W, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    let flip = options.y_up(diagram);
+    writeln!(w, "graph voronoi {{")?;
+    for (index, d) in diagram.dots.iter().enumerate() {
+        let metadata = diagram.metadata.get(index).map(|m| m.fields()).unwrap_or_default().into_iter()
+            .map(|(name, text)| format!(", {}=\"{}\"", name, text.replace('"', "\\\"")))
+            .collect::<String>();
+        let (x, y) = flip(&(d[0], d[1]));
+        writeln!(w, "  {} [pos=\"{},{}!\"{}];", index, x, y, metadata)?;
+    }
+    for edge in shared_edges(&diagram.polygons) {
+        let ((a, b), (p, q)) = (edge.cells, edge.ends);
+        let (p, q) = (flip(&p), flip(&q));
+        writeln!(w, "  {} -- {} [shared=\"{},{} {},{}\"];", a, b, p.0, p.1, q.0, q.1)?;
+    }
+    writeln!(w, "}}")
+}
@@ -0,0 +1,46 @@
+//! The heights of `terrain` as a grayscale image, black for the lowest cell
+//! and white for the highest, for game engines to build a landscape from.
+//! Cells are filled without smoothing their edges, so every pixel holds the
+//! exact height of one cell.
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+
+use graphics::{clear, Transformed};
+
+use crate::diagram::Diagram;
+use crate::draw::draw_cells;
+use crate::raster::Canvas;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+use super::image::write_png;
+use super::ExportOptions;
+
+/// Writes a PNG the size of the window times `options.scale`, with
+/// `heights` from 0 to 1 for each dot's cell.
+pub fn export_heightmap(path: &str, diagram: &Diagram, options: &ExportOptions, heights: &[f64]) -> io::Result<()> {
+    let image = render_heightmap(diagram, options, heights)?;
+    let mut w = BufWriter::new(File::create(path)?);
+    write_png(&mut w, &image)
+}
+
+pub fn render_heightmap(diagram: &Diagram, options: &ExportOptions, heights: &[f64]) -> io::Result<Canvas> {
+    if diagram.dots.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "there are no dots yet"));
+    }
+    let width = (DEFAULT_WINDOW_WIDTH as f64 * options.scale).round() as u32;
+    let height = (DEFAULT_WINDOW_HEIGHT as f64 * options.scale).round() as u32;
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the export scale leaves no pixels"));
+    }
+
+    let mut image = Canvas::new(width, height);
+    let c = image.context().scale(width as f64 / DEFAULT_WINDOW_WIDTH as f64, height as f64 / DEFAULT_WINDOW_HEIGHT as f64);
+    clear([0.0, 0.0, 0.0, 1.0], &mut image);
+    let grays = heights.iter().map(|&h| {
+        let level = (h.clamp(0.0, 1.0) * 255.0).round() as f32 / 255.0;
+        [level, level, level, 1.0]
+    }).collect::<Vec<_>>();
+    let (_, visible) = diagram.smoothed(options.smoothing);
+    draw_cells(&visible, &grays, 1.0, &c, &mut image);
+    Ok(image)
+}
@@ -0,0 +1,69 @@
+//! The PNG encoder for software-rendered frames, written by hand to keep
+//! the dependencies down; PNG data goes uncompressed.
+
+use std::io::{self, Write};
+
+use crate::raster::Canvas;
+
+/// Writes the canvas as an 8 bit RGB PNG, dropping the alpha channel.
+pub fn write_png<W: Write>(w: &mut W, canvas: &Canvas) -> io::Result<()> {
+    let mut ihdr = Vec::new();
+    ihdr.extend_from_slice(&canvas.width.to_be_bytes());
+    ihdr.extend_from_slice(&canvas.height.to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    // Every row starts with filter type 0, no filtering
+    let mut raw = Vec::with_capacity((canvas.width as usize * 3 + 1) * canvas.height as usize);
+    for row in canvas.pixels.chunks_exact(canvas.width as usize * 4) {
+        raw.push(0);
+        for pixel in row.chunks_exact(4) {
+            raw.extend_from_slice(&pixel[..3]);
+        }
+    }
+
+    w.write_all(b"\x89PNG\r\n\x1a\n")?;
+    write_chunk(w, b"IHDR", &ihdr)?;
+    write_chunk(w, b"IDAT", &zlib_stored(&raw))?;
+    write_chunk(w, b"IEND", &[])
+}
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    let crc = crc32(&[&kind[..], data].concat());
+    w.write_all(&crc.to_be_bytes())
+}
+
+/// A zlib stream of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let blocks = data.chunks(0xffff).collect::<Vec<_>>();
+    for (i, block) in blocks.iter().enumerate() {
+        out.push((i + 1 == blocks.len()) as u8);
+        out.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    if blocks.is_empty() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
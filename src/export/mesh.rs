@@ -0,0 +1,78 @@
+//! Delaunay mesh export for 3D tools such as Blender.
+//!
+//! The mesh is written in a right-handed, Z-up frame: screen Y is flipped so
+//! the diagram isn't mirrored, and every face is wound counter-clockwise so
+//! its normal points up.
+
+use std::io::{self, Write};
+
+use crate::diagram::Diagram;
+use crate::refine::inside;
+use super::ExportOptions;
+
+/// Vertices as `[x, y, z]` and faces as vertex index triples.
+fn build_mesh(diagram: &Diagram, options: &ExportOptions) -> (Vec<[f64; 3]>, Vec<[usize; 3]>) {
+    let vertices = diagram.dots.iter().zip(&diagram.colors).map(|(d, c)| {
+        [d[0], -d[1], options.relief * luminance(c)]
+    }).collect::<Vec<_>>();
+
+    let faces = diagram.triangles().iter().filter(|t| {
+        let middle = [0, 1].map(|axis| (diagram.dots[t.0][axis] + diagram.dots[t.1][axis] + diagram.dots[t.2][axis]) / 3.0);
+        options.boundary.as_ref().is_none_or(|boundary| inside(boundary, middle))
+    }).map(|t| {
+        let (a, b, c) = (vertices[t.0], vertices[t.1], vertices[t.2]);
+        let cross = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+        if cross >= 0.0 { [t.0, t.1, t.2] } else { [t.0, t.2, t.1] }
+    }).collect();
+
+    (vertices, faces)
+}
+
+/// Relative luminance of a cell color, used as its height in the relief.
+fn luminance(color: &[f32; 4]) -> f64 {
+    (0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]) as f64
+}
+
+fn to_byte(channel: f32) -> u8 {
+    (channel.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+pub fn write_obj<W: Write>(w: &mut W, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    let (vertices, faces) = build_mesh(diagram, options);
+
+    writeln!(w, "# interactive-voronoi Delaunay mesh")?;
+    writeln!(w, "o voronoi")?;
+    for v in &vertices {
+        writeln!(w, "v {} {} {}", v[0], v[1], v[2])?;
+    }
+    // OBJ indices start at one
+    for f in &faces {
+        writeln!(w, "f {} {} {}", f[0] + 1, f[1] + 1, f[2] + 1)?;
+    }
+    Ok(())
+}
+
+pub fn write_ply<W: Write>(w: &mut W, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    let (vertices, faces) = build_mesh(diagram, options);
+
+    writeln!(w, "ply")?;
+    writeln!(w, "format ascii 1.0")?;
+    writeln!(w, "comment interactive-voronoi Delaunay mesh")?;
+    writeln!(w, "element vertex {}", vertices.len())?;
+    writeln!(w, "property float x")?;
+    writeln!(w, "property float y")?;
+    writeln!(w, "property float z")?;
+    writeln!(w, "property uchar red")?;
+    writeln!(w, "property uchar green")?;
+    writeln!(w, "property uchar blue")?;
+    writeln!(w, "element face {}", faces.len())?;
+    writeln!(w, "property list uchar int vertex_indices")?;
+    writeln!(w, "end_header")?;
+    for (v, c) in vertices.iter().zip(&diagram.colors) {
+        writeln!(w, "{} {} {} {} {} {}", v[0], v[1], v[2], to_byte(c[0]), to_byte(c[1]), to_byte(c[2]))?;
+    }
+    for f in &faces {
+        writeln!(w, "3 {} {} {}", f[0], f[1], f[2])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use std::io;
+use std::path::Path;
+
+use crate::diagram::{Diagram, Point};
+use crate::document::Document;
+use crate::draw::DotStyle;
+use crate::fit::Fit;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+mod bump;
+mod centroids;
+mod cut;
+mod dxf;
+mod edges;
+mod geojson;
+mod graph;
+mod heightmap;
+mod image;
+mod mesh;
+mod png;
+mod shapefile;
+mod tile;
+mod timelapse;
+mod tour;
+mod vector;
+
+pub use bump::{export_bump, render_bump, Bump, BumpField};
+pub use centroids::export_centroids;
+pub use cut::export_cut;
+pub use edges::export_edges;
+pub use heightmap::{export_heightmap, render_heightmap};
+pub use png::render_image;
+pub use tile::export_tile;
+pub use timelapse::export_timelapse;
+pub use tour::export_tour;
+
+/// The top left and bottom right corners of the window.
+const WINDOW: [[f64; 2]; 2] = [[0.0, 0.0], [DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64]];
+
+/// Knobs shared by the exporters. Not every format uses every option.
+pub struct ExportOptions {
+    /// Height of the brightest cell when extruding the mesh into a 2.5D relief.
+    /// Zero keeps the mesh flat.
+    pub relief: f64,
+    /// Outline the cells instead of filling them, like the wireframe view.
+    pub lines_only: bool,
+    /// Merge neighboring cells with colors this close into regions, see
+    /// `regions::merge_regions`.
+    pub merge_tolerance: Option<f32>,
+    /// Size of SVG and PNG exports as a multiple of the window's.
+    pub scale: f64,
+    /// How often the corners of the cells are cut off, see
+    /// `Diagram::smoothed`. Merged regions keep theirs.
+    pub smoothing: usize,
+    /// Draw the dots on top of the cells.
+    pub dots: bool,
+    /// What they look like.
+    pub dot_style: DotStyle,
+    /// Painted behind the cells.
+    pub background: [f32; 4],
+    /// Color and width of the lines around filled cells, see `Theme`.
+    pub stroke: Option<([f32; 4], f64)>,
+    /// Mesh exports leave out the triangles whose middle is outside this
+    /// polygon, like those across a dent in a refined mesh's boundary.
+    pub boundary: Option<Vec<[f64; 2]>>,
+    /// Write the coordinates the dots were loaded in, see `Diagram::coords`,
+    /// rather than the window's. Only the formats for data rather than
+    /// pictures do: GeoJSON, shapefiles, the cell graph and centroids.
+    pub data_coords: bool,
+    /// The page the picture formats, SVG, PNG, EPS and PDF, are cut to
+    /// instead of the window, see `document`.
+    pub document: Option<Document>,
+}
+
+impl ExportOptions {
+    /// How to map the window back into the coordinates the dots were
+    /// loaded in, if `data_coords` asks for them and they weren't in the
+    /// window's already.
+    pub fn data(&self, diagram: &Diagram) -> Option<Fit> {
+        diagram.coords.filter(|_| self.data_coords)
+    }
+
+    /// A point of the window for a Y-up format: flipped upside down, or in
+    /// the coordinates the dots were loaded in, as they were, see `data`.
+    fn y_up(&self, diagram: &Diagram) -> impl Fn(&Point) -> Point + Copy {
+        let data = self.data(diagram);
+        move |p: &Point| match data {
+            Some(fit) => {
+                let [x, y] = fit.invert([p.0, p.1]);
+                (x, y)
+            }
+            None => (p.0, DEFAULT_WINDOW_HEIGHT as f64 - p.1)
+        }
+    }
+
+    /// The part of the window the picture formats show, its top left and
+    /// bottom right corners, and the width and height of their page.
+    fn page(&self) -> ([[f64; 2]; 2], [f64; 2]) {
+        match self.document {
+            Some(document) => (document.frame(), [document.width, document.height]),
+            None => (WINDOW, [DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64])
+        }
+    }
+
+    /// An area in the window in the units of the coordinates written.
+    fn area(&self, diagram: &Diagram, area: f64) -> f64 {
+        self.data(diagram).map_or(area, |fit| fit.invert_area(area))
+    }
+}
+
+/// Writes the diagram to `path`, picking the format from the file extension.
+pub fn export(path: &str, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    // Shapefiles come as several files next to each other
+    if extension == "shp" {
+        return shapefile::export_shapefile(path, diagram, options);
+    }
+    std::fs::write(path, render(&extension, diagram, options)?)
+}
+
+/// Renders the diagram in the given format, named by its usual file extension.
+pub fn render(format: &str, diagram: &Diagram, options: &ExportOptions) -> io::Result<Vec<u8>> {
+    let mut w = Vec::new();
+    match format {
+        "obj" => mesh::write_obj(&mut w, diagram, options)?,
+        "ply" => mesh::write_ply(&mut w, diagram, options)?,
+        "dxf" => dxf::write_dxf(&mut w, diagram, options)?,
+        "svg" => vector::write_svg(&mut w, diagram, options)?,
+        "png" => png::write_png_image(&mut w, diagram, options)?,
+        "eps" => vector::write_eps(&mut w, diagram, options)?,
+        "pdf" => vector::write_pdf(&mut w, diagram, options)?,
+        "json" => graph::write_json(&mut w, diagram, options)?,
+        "geojson" => geojson::write_geojson(&mut w, diagram, options)?,
+        "dot" | "gv" => graph::write_dot(&mut w, diagram, options)?,
+        _ => return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown export format \"{}\", use one of: obj, ply, dxf, shp, geojson, svg, png, eps, pdf, json, dot", format)))
+    }
+    Ok(w)
+}
@@ -0,0 +1,95 @@
+//! Raster export of the diagram as it is drawn on screen, rendered off
+//! screen by the software renderer. Every output pixel averages a grid of
+//! samples, so edges come out smooth at any scale.
+
+use std::io::{self, Write};
+
+use graphics::{clear, Context, Graphics, Transformed};
+
+use crate::diagram::Diagram;
+use crate::draw::{draw_cells, draw_outline, draw_styled_dots, draw_wireframe, EXPORT_DOT_RESOLUTION};
+use crate::raster::Canvas;
+use crate::regions::{merge_regions, Region};
+use super::image::write_png;
+use super::vector::{REGION_BORDER_COLOR, REGION_BORDER_WIDTH};
+use super::ExportOptions;
+
+/// Samples per output pixel along each axis.
+const SUPERSAMPLING: u32 = 3;
+/// Output rows rendered at a time, so the samples of a big export never
+/// have to fit in memory all at once.
+const STRIP_ROWS: u32 = 256;
+
+pub fn write_png_image<W: Write>(w: &mut W, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    write_png(w, &render_image(diagram, options)?)
+}
+
+/// The diagram as the PNG export draws it, `options.scale` times the size of
+/// the window, or of the document if there is one, in RGBA pixels.
+pub fn render_image(diagram: &Diagram, options: &ExportOptions) -> io::Result<Canvas> {
+    let (frame, [width, height]) = options.page();
+    let width = (width * options.scale).round() as u32;
+    let height = (height * options.scale).round() as u32;
+    render_sized(diagram, options, frame, width, height)
+}
+
+/// The diagram with the part `frame` of the window, its top left and
+/// bottom right corners, stretched to `width` by `height` pixels.
+pub(super) fn render_sized(diagram: &Diagram, options: &ExportOptions, frame: [[f64; 2]; 2], width: u32, height: u32) -> io::Result<Canvas> {
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the export scale leaves no pixels"));
+    }
+
+    let regions = options.merge_tolerance.map(|tolerance| merge_regions(diagram, tolerance));
+    let mut image = Canvas::new(width, height);
+    for top in (0..height).step_by(STRIP_ROWS as usize) {
+        let rows = STRIP_ROWS.min(height - top);
+        let mut strip = Canvas::new(width * SUPERSAMPLING, rows * SUPERSAMPLING);
+        let [[left, upper], [right, lower]] = frame;
+        let samples_x = width as f64 / (right - left) * SUPERSAMPLING as f64;
+        let samples_y = height as f64 / (lower - upper) * SUPERSAMPLING as f64;
+        let c = strip.context().trans(0.0, -((top * SUPERSAMPLING) as f64)).scale(samples_x, samples_y).trans(-left, -upper);
+        clear(options.background, &mut strip);
+        draw_diagram(diagram, regions.as_deref(), options, &c, &mut strip);
+
+        let strip = strip.downsample(SUPERSAMPLING);
+        let start = top as usize * width as usize * 4;
+        image.pixels[start..start + strip.pixels.len()].copy_from_slice(&strip.pixels);
+    }
+    Ok(image)
+}
+
+/// The cells, or the `regions` merged from them, and the dots unless left
+/// out, in window coordinates.
+pub fn draw_diagram<G: Graphics>(diagram: &Diagram, regions: Option<&[Region]>, options: &ExportOptions, c: &Context, g: &mut G) {
+    let lines_only = options.lines_only;
+    if let Some(regions) = regions {
+        if !lines_only {
+            let (cells, colors): (Vec<_>, Vec<_>) = regions.iter()
+                .flat_map(|region| region.cells.iter().map(|&cell| (diagram.polygons[cell].clone(), region.color)))
+                .unzip();
+            draw_cells(&cells, &colors, 1.0, c, g);
+        }
+        for region in regions {
+            let (color, radius) = if lines_only { ([0.0, 0.0, 1.0, 1.0], 2.0) } else { (REGION_BORDER_COLOR, REGION_BORDER_WIDTH) };
+            for outline in &region.boundary {
+                draw_outline(outline, c, g, color, radius);
+            }
+        }
+    } else {
+        let (polygons, _) = diagram.smoothed(options.smoothing);
+        if lines_only {
+            draw_wireframe(&polygons, c, g);
+        } else {
+            draw_cells(&polygons, &diagram.colors, 1.0, c, g);
+            if let Some((color, width)) = options.stroke {
+                for poly in polygons.iter() {
+                    draw_outline(poly, c, g, color, width / 2.0);
+                }
+            }
+        }
+    }
+    if options.dots {
+        draw_styled_dots(&diagram.dots, &options.dot_style, EXPORT_DOT_RESOLUTION, c, g);
+    }
+}
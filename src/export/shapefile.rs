@@ -0,0 +1,206 @@
+//! ESRI Shapefile export for GIS tools such as QGIS and ArcGIS.
+//!
+//! A shapefile only holds one kind of geometry, so there are two bundles of
+//! three files each: the cells as polygons, and the dots as points next to
+//! them with `_sites` added to the name. The `.shp` file has the shapes, the
+//! `.shx` file where each one starts, and the `.dbf` file a table with the
+//! attributes of each, with `LABEL`, `CATEGORY` and `VALUE` columns if the
+//! dots have metadata. GIS coordinates are Y-up, so the diagram is flipped
+//! to keep it upright, unless it is written in the coordinates the dots
+//! were loaded in.
+
+use std::io;
+use std::path::Path;
+
+use crate::diagram::{Diagram, Point};
+use crate::geometry::{area, signed_area};
+use crate::metadata::{self, Metadata};
+use super::ExportOptions;
+
+const FILE_CODE: i32 = 9994;
+const VERSION: i32 = 1000;
+const POINT: i32 = 1;
+const POLYGON: i32 = 5;
+/// The main file and index header, in bytes.
+const HEADER_LENGTH: usize = 100;
+
+enum Field {
+    /// A number with this many digits after the point.
+    Number(&'static str, u8, u8),
+    Text(&'static str, u8),
+}
+
+enum Value {
+    Number(f64),
+    Text(String),
+}
+
+/// Writes `path`, which should end in `.shp`, along with the `.shx` and
+/// `.dbf` files of the cells, and the same three for the dots.
+pub fn export_shapefile(path: &str, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    let (_, visible) = diagram.smoothed(options.smoothing);
+    let flip = options.y_up(diagram);
+
+    let cells = visible.iter().enumerate().filter(|(_, cell)| cell.len() >= 3).collect::<Vec<_>>();
+    let rings = cells.iter().map(|(_, cell)| {
+        let mut ring = cell.iter().map(flip).collect::<Vec<_>>();
+        // Outer rings go clockwise
+        if signed_area(&ring) > 0.0 {
+            ring.reverse();
+        }
+        ring.push(ring[0]);
+        ring
+    }).collect::<Vec<_>>();
+    let with_metadata = metadata::any(&diagram.metadata);
+    let add_metadata_fields = |mut fields: Vec<Field>| {
+        if with_metadata {
+            fields.extend([Field::Text("LABEL", 80), Field::Text("CATEGORY", 40), Field::Number("VALUE", 20, 6)]);
+        }
+        fields
+    };
+    let add_metadata_values = |mut row: Vec<Value>, i: usize| {
+        if with_metadata {
+            row.extend(metadata_values(&diagram.metadata[i]));
+        }
+        row
+    };
+
+    let cell_fields = add_metadata_fields(vec![Field::Number("INDEX", 10, 0), Field::Number("AREA", 18, 3), Field::Text("COLOR", 7)]);
+    let cell_rows = cells.iter().map(|&(i, cell)| {
+        add_metadata_values(vec![Value::Number(i as f64), Value::Number(options.area(diagram, area(cell))), Value::Text(hex(&diagram.colors[i]))], i)
+    }).collect::<Vec<_>>();
+    write_bundle(Path::new(path), POLYGON, &rings, &cell_fields, &cell_rows)?;
+
+    let sites = diagram.dots.iter().map(|d| vec![flip(&(d[0], d[1]))]).collect::<Vec<_>>();
+    let site_fields = add_metadata_fields(vec![Field::Number("INDEX", 10, 0), Field::Number("X", 18, 3), Field::Number("Y", 18, 3), Field::Text("COLOR", 7)]);
+    let site_rows = sites.iter().zip(&diagram.colors).enumerate().map(|(i, (site, c))| {
+        add_metadata_values(vec![Value::Number(i as f64), Value::Number(site[0].0), Value::Number(site[0].1), Value::Text(hex(c))], i)
+    }).collect::<Vec<_>>();
+    let stem = Path::new(path).file_stem().and_then(|s| s.to_str()).unwrap_or("voronoi");
+    write_bundle(&Path::new(path).with_file_name(format!("{}_sites.shp", stem)), POINT, &sites, &site_fields, &site_rows)
+}
+
+/// The `LABEL`, `CATEGORY` and `VALUE` of a dot, blank where unset.
+fn metadata_values(metadata: &Metadata) -> [Value; 3] {
+    [
+        Value::Text(metadata.label.clone().unwrap_or_default()),
+        Value::Text(metadata.category.clone().unwrap_or_default()),
+        metadata.value.map_or_else(|| Value::Text(String::new()), Value::Number),
+    ]
+}
+
+fn hex(c: &[f32; 4]) -> String {
+    let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(c[0]), byte(c[1]), byte(c[2]))
+}
+
+/// The `.shp`, `.shx` and `.dbf` files for shapes that are each a single
+/// point or a single ring.
+fn write_bundle(path: &Path, shape_type: i32, shapes: &[Vec<Point>], fields: &[Field], rows: &[Vec<Value>]) -> io::Result<()> {
+    let contents = shapes.iter().map(|points| shape_content(shape_type, points)).collect::<Vec<_>>();
+    let all = shapes.iter().flatten().copied().collect::<Vec<_>>();
+    let bounds = bounds(&all);
+
+    let mut shp = header(shape_type, HEADER_LENGTH + contents.iter().map(|c| 8 + c.len()).sum::<usize>(), bounds);
+    let mut shx = header(shape_type, HEADER_LENGTH + 8 * contents.len(), bounds);
+    for (number, content) in contents.iter().enumerate() {
+        // Offsets and lengths count 16-bit words
+        shx.extend(((shp.len() / 2) as i32).to_be_bytes());
+        shx.extend(((content.len() / 2) as i32).to_be_bytes());
+        shp.extend((number as i32 + 1).to_be_bytes());
+        shp.extend(((content.len() / 2) as i32).to_be_bytes());
+        shp.extend(content);
+    }
+    std::fs::write(path, shp)?;
+    std::fs::write(path.with_extension("shx"), shx)?;
+    std::fs::write(path.with_extension("dbf"), table(fields, rows))
+}
+
+fn shape_content(shape_type: i32, points: &[Point]) -> Vec<u8> {
+    let mut content = shape_type.to_le_bytes().to_vec();
+    if shape_type == POINT {
+        content.extend(points[0].0.to_le_bytes());
+        content.extend(points[0].1.to_le_bytes());
+        return content;
+    }
+    for v in bounds(points) {
+        content.extend(v.to_le_bytes());
+    }
+    // One part, starting at the first point
+    content.extend(1i32.to_le_bytes());
+    content.extend((points.len() as i32).to_le_bytes());
+    content.extend(0i32.to_le_bytes());
+    for p in points {
+        content.extend(p.0.to_le_bytes());
+        content.extend(p.1.to_le_bytes());
+    }
+    content
+}
+
+/// The smallest and largest X and Y.
+fn bounds(points: &[Point]) -> [f64; 4] {
+    if points.is_empty() {
+        return [0.0; 4];
+    }
+    points.iter().fold([f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY], |[x0, y0, x1, y1], p| {
+        [x0.min(p.0), y0.min(p.1), x1.max(p.0), y1.max(p.1)]
+    })
+}
+
+/// The header of the `.shp` and `.shx` files, `length` bytes long in all.
+fn header(shape_type: i32, length: usize, bounds: [f64; 4]) -> Vec<u8> {
+    let mut header = FILE_CODE.to_be_bytes().to_vec();
+    header.extend([0; 20]);
+    header.extend(((length / 2) as i32).to_be_bytes());
+    header.extend(VERSION.to_le_bytes());
+    header.extend(shape_type.to_le_bytes());
+    for v in bounds {
+        header.extend(v.to_le_bytes());
+    }
+    // No Z or M values
+    header.extend([0; 32]);
+    header
+}
+
+/// A dBase III table.
+fn table(fields: &[Field], rows: &[Vec<Value>]) -> Vec<u8> {
+    let width = |field: &Field| match field { Field::Number(_, width, _) | Field::Text(_, width) => *width };
+    let header_length = 32 + 32 * fields.len() + 1;
+    let record_length = 1 + fields.iter().map(|f| width(f) as usize).sum::<usize>();
+
+    // Version, then the date of the last change as years since 1900,
+    // month and day, left at the start of 1970 so exports don't differ
+    let mut table = vec![0x03, 70, 1, 1];
+    table.extend((rows.len() as u32).to_le_bytes());
+    table.extend((header_length as u16).to_le_bytes());
+    table.extend((record_length as u16).to_le_bytes());
+    table.extend([0; 20]);
+    for field in fields {
+        let (name, kind, decimals) = match field {
+            Field::Number(name, _, decimals) => (name, b'N', *decimals),
+            Field::Text(name, _) => (name, b'C', 0),
+        };
+        let mut descriptor = [0u8; 32];
+        descriptor[..name.len()].copy_from_slice(name.as_bytes());
+        descriptor[11] = kind;
+        descriptor[16] = width(field);
+        descriptor[17] = decimals;
+        table.extend(descriptor);
+    }
+    table.push(0x0d);
+    for row in rows {
+        // Not deleted
+        table.push(b' ');
+        for (field, value) in fields.iter().zip(row) {
+            let width = width(field) as usize;
+            let text = match (field, value) {
+                (Field::Number(_, _, decimals), Value::Number(v)) => format!("{:>width$.decimals$}", v, decimals = *decimals as usize),
+                (_, Value::Text(text)) => format!("{:<width$}", text),
+                (_, Value::Number(v)) => format!("{:<width$}", v),
+            };
+            table.extend(&text.as_bytes()[..width.min(text.len())]);
+        }
+    }
+    table.push(0x1a);
+    table
+}
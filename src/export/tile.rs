@@ -0,0 +1,27 @@
+//! A seamless texture: the window as one tile of the diagram wrapped
+//! around like a torus, see `Diagram::periodic`, so copies of the image
+//! placed side by side continue each other's cells across the edges.
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+
+use crate::diagram::Diagram;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+use super::image::write_png;
+use super::png::render_sized;
+use super::{ExportOptions, WINDOW};
+
+/// Writes the tile as a PNG `width` pixels wide. Its height keeps the
+/// window's aspect ratio as closely as whole pixels allow, since the tile
+/// only repeats seamlessly if it spans exactly one window each way. Returns
+/// the height.
+pub fn export_tile(path: &str, diagram: &Diagram, options: &ExportOptions, width: u32) -> io::Result<u32> {
+    if diagram.dots.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "there are no dots yet"));
+    }
+    let height = (width as f64 * DEFAULT_WINDOW_HEIGHT as f64 / DEFAULT_WINDOW_WIDTH as f64).round() as u32;
+    let image = render_sized(&diagram.periodic(), options, WINDOW, width, height)?;
+    let mut w = BufWriter::new(File::create(path)?);
+    write_png(&mut w, &image)?;
+    Ok(height)
+}
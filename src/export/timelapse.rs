@@ -0,0 +1,86 @@
+//! A time-lapse of how the diagram grew: one frame for the state after each
+//! dot was added, in the order they were added.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use graphics::clear;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageError, RgbaImage};
+
+use crate::diagram::Diagram;
+use crate::raster::Canvas;
+use crate::regions::merge_regions;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+use super::image::write_png;
+use super::png::draw_diagram;
+use super::ExportOptions;
+
+/// How long each frame of the GIF shows, in milliseconds.
+const FRAME_DELAY: u32 = 100;
+/// The finished diagram stays up a little longer before the loop restarts.
+const LAST_FRAME_DELAY: u32 = 2000;
+
+/// Writes an animated GIF if `path` ends in `.gif`, otherwise numbered PNG
+/// frames into the directory `path`. Returns the number of frames.
+pub fn export_timelapse(path: &str, diagram: &Diagram, options: &ExportOptions) -> io::Result<usize> {
+    let count = diagram.dots.len();
+    if count == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "there are no dots yet"));
+    }
+    let gif = Path::new(path).extension().is_some_and(|e| e.eq_ignore_ascii_case("gif"));
+
+    // The frames are rendered from a copy the dots are added to one by one,
+    // which only touches the cells around each new dot
+    let mut partial = Diagram::new();
+    partial.duplicates = diagram.duplicates;
+    partial.hyperbolic = diagram.hyperbolic;
+    partial.power = diagram.power;
+    let next_frame = |partial: &mut Diagram, frame: usize| {
+        let dot = frame - 1;
+        partial.push_weighted_dot(diagram.dots[dot], diagram.colors[dot], diagram.teams[dot], diagram.weights[dot]);
+        render_frame(partial, options)
+    };
+
+    if gif {
+        let mut file = BufWriter::new(File::create(path)?);
+        {
+            let mut encoder = GifEncoder::new(&mut file);
+            encoder.set_repeat(Repeat::Infinite).map_err(gif_error)?;
+            for frame in 1..=count {
+                let delay = if frame == count { LAST_FRAME_DELAY } else { FRAME_DELAY };
+                let canvas = next_frame(&mut partial, frame);
+                let image = RgbaImage::from_raw(canvas.width, canvas.height, canvas.pixels)
+                    .expect("the canvas has four bytes per pixel");
+                let frame = Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(delay, 1));
+                encoder.encode_frame(frame).map_err(gif_error)?;
+            }
+        }
+        file.flush()?;
+    } else {
+        std::fs::create_dir_all(path)?;
+        for frame in 1..=count {
+            let file = Path::new(path).join(format!("frame_{:05}.png", frame));
+            let mut w = BufWriter::new(File::create(file)?);
+            write_png(&mut w, &next_frame(&mut partial, frame))?;
+        }
+    }
+    Ok(count)
+}
+
+fn gif_error(err: ImageError) -> io::Error {
+    match err {
+        ImageError::IoError(err) => err,
+        err => io::Error::other(err)
+    }
+}
+
+fn render_frame(partial: &Diagram, options: &ExportOptions) -> Canvas {
+    let mut canvas = Canvas::new(DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT);
+    let c = canvas.context();
+    clear([1.0; 4], &mut canvas);
+    let regions = options.merge_tolerance.map(|tolerance| merge_regions(partial, tolerance));
+    draw_diagram(partial, regions.as_deref(), options, &c, &mut canvas);
+    canvas
+}
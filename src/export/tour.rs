@@ -0,0 +1,28 @@
+//! A tour through the dots as an SVG for pen plotters: one closed path,
+//! stroked and never filled, so the pen goes down once and draws it all.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::diagram::Diagram;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+use super::ExportOptions;
+
+const STROKE_WIDTH: f64 = 1.0;
+
+/// Writes the dots joined in `order`, back to the first at the end, the
+/// size of the window times `options.scale`.
+pub fn export_tour(path: &str, diagram: &Diagram, options: &ExportOptions, order: &[usize]) -> io::Result<()> {
+    if order.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "there are no dots yet"));
+    }
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(w, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        width * options.scale, height * options.scale, width, height)?;
+    let d = order.iter().map(|&i| format!("{},{}", diagram.dots[i][0], diagram.dots[i][1])).collect::<Vec<_>>().join(" L");
+    writeln!(w, r#"<path d="M{}Z" fill="none" stroke="black" stroke-width="{}" stroke-linejoin="round"/>"#, d, STROKE_WIDTH)?;
+    writeln!(w, "</svg>")?;
+    w.flush()
+}
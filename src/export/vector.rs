@@ -0,0 +1,283 @@
+//! Vector export of the diagram as it is drawn on screen.
+//!
+//! The diagram is first flattened into a list of [`Shape`]s, which each
+//! backend then translates into its own drawing operators. SVG keeps the
+//! screen's Y-down coordinates, PostScript and PDF flip to their Y-up pages.
+//! SVG also keeps the metadata of the dots, in `data-` attributes and a
+//! tooltip `<title>` on their cells.
+
+use std::io::{self, Write};
+
+use crate::diagram::{Diagram, Point};
+use crate::draw::{DotShape, DOT_OUTLINE_WIDTH};
+use crate::metadata::Metadata;
+use crate::regions::merge_regions;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+use super::ExportOptions;
+
+const OUTLINE_COLOR: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+const OUTLINE_WIDTH: f64 = 2.0;
+/// Between merged regions, see `ExportOptions::merge_tolerance`.
+pub const REGION_BORDER_COLOR: [f32; 4] = [0.2, 0.2, 0.2, 1.0];
+pub const REGION_BORDER_WIDTH: f64 = 1.5;
+
+enum Shape {
+    /// A cell, of the dot at `site`.
+    Polygon { points: Vec<Point>, fill: Option<[f32; 4]>, stroke: Option<([f32; 4], f64)>, site: usize },
+    /// Several closed loops filled together by the even-odd rule, so inner loops make holes.
+    Path { loops: Vec<Vec<Point>>, fill: Option<[f32; 4]>, stroke: Option<([f32; 4], f64)> },
+    Circle { center: Point, radius: f64, fill: [f32; 4], stroke: Option<([f32; 4], f64)> },
+}
+
+fn shapes(diagram: &Diagram, options: &ExportOptions) -> Vec<Shape> {
+    let mut shapes = Vec::new();
+    if let Some(tolerance) = options.merge_tolerance {
+        for region in merge_regions(diagram, tolerance) {
+            shapes.push(if options.lines_only {
+                Shape::Path { loops: region.boundary, fill: None, stroke: Some((OUTLINE_COLOR, OUTLINE_WIDTH)) }
+            } else {
+                Shape::Path { loops: region.boundary, fill: Some(region.color), stroke: Some((REGION_BORDER_COLOR, REGION_BORDER_WIDTH)) }
+            });
+        }
+    }
+    let (polygons, _) = diagram.smoothed(options.smoothing);
+    for (site, (poly, color)) in polygons.iter().zip(&diagram.colors).enumerate().filter(|_| options.merge_tolerance.is_none()) {
+        shapes.push(if options.lines_only {
+            Shape::Polygon { points: poly.clone(), fill: None, stroke: Some((OUTLINE_COLOR, OUTLINE_WIDTH)), site }
+        } else {
+            Shape::Polygon { points: poly.clone(), fill: Some(*color), stroke: options.stroke, site }
+        });
+    }
+    let style = &options.dot_style;
+    let outline = style.outline.map(|color| (color, DOT_OUTLINE_WIDTH));
+    for d in diagram.dots.iter().filter(|_| options.dots) {
+        shapes.push(match style.shape {
+            DotShape::Circle => Shape::Circle { center: (d[0], d[1]), radius: style.radius, fill: style.fill, stroke: outline },
+            DotShape::Square => Shape::Path { loops: style.outlines(*d), fill: Some(style.fill), stroke: outline },
+            DotShape::Cross => Shape::Path { loops: style.outlines(*d), fill: None, stroke: Some((style.fill, style.cross_width())) },
+        });
+    }
+    shapes
+}
+
+/// The metadata as `data-label`, `data-category` and `data-value` attributes.
+fn svg_data(metadata: &Metadata) -> String {
+    metadata.fields().into_iter().map(|(name, text)| format!(r#" data-{}="{}""#, name, xml_escape(&text))).collect()
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn svg_color(c: &[f32; 4]) -> String {
+    let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(c[0]), byte(c[1]), byte(c[2]))
+}
+
+pub fn write_svg<W: Write>(w: &mut W, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    let ([[left, top], [right, bottom]], [width, height]) = options.page();
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(w, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        width * options.scale, height * options.scale, left, top, right - left, bottom - top)?;
+    writeln!(w, r#"<rect x="{}" y="{}" width="100%" height="100%" fill="{}"/>"#, left, top, svg_color(&options.background))?;
+    for shape in shapes(diagram, options) {
+        match shape {
+            Shape::Polygon { points, fill, stroke, site } => {
+                let points = points.iter().map(|p| format!("{},{}", p.0, p.1)).collect::<Vec<_>>().join(" ");
+                let fill = fill.map(|c| svg_color(&c)).unwrap_or_else(|| "none".to_string());
+                let stroke = stroke.map(|(c, width)| format!(r#" stroke="{}" stroke-width="{}""#, svg_color(&c), width)).unwrap_or_default();
+                match diagram.metadata.get(site).filter(|m| !m.is_empty()) {
+                    Some(metadata) => writeln!(w, r#"<polygon points="{}" fill="{}"{}{}><title>{}</title></polygon>"#,
+                        points, fill, stroke, svg_data(metadata), xml_escape(&metadata.describe().join("\n")))?,
+                    None => writeln!(w, r#"<polygon points="{}" fill="{}"{}/>"#, points, fill, stroke)?,
+                }
+            }
+            Shape::Path { loops, fill, stroke } => {
+                let d = loops.iter()
+                    .map(|l| format!("M{}Z", l.iter().map(|p| format!("{},{}", p.0, p.1)).collect::<Vec<_>>().join(" L")))
+                    .collect::<Vec<_>>().join(" ");
+                let fill = fill.map(|c| svg_color(&c)).unwrap_or_else(|| "none".to_string());
+                let stroke = stroke.map(|(c, width)| format!(r#" stroke="{}" stroke-width="{}""#, svg_color(&c), width)).unwrap_or_default();
+                writeln!(w, r#"<path d="{}" fill="{}" fill-rule="evenodd"{}/>"#, d, fill, stroke)?;
+            }
+            Shape::Circle { center, radius, fill, stroke } => {
+                let stroke = stroke.map(|(c, width)| format!(r#" stroke="{}" stroke-width="{}""#, svg_color(&c), width)).unwrap_or_default();
+                writeln!(w, r#"<circle cx="{}" cy="{}" r="{}" fill="{}"{}/>"#, center.0, center.1, radius, svg_color(&fill), stroke)?;
+            }
+        }
+    }
+    writeln!(w, "</svg>")
+}
+
+/// Operator names for the PostScript and PDF backends, which share the same
+/// path and painting model.
+struct PathOps {
+    move_to: &'static str,
+    line_to: &'static str,
+    curve_to: &'static str,
+    close: &'static str,
+    fill_color: fn(&[f32; 4]) -> String,
+    stroke_color: fn(&[f32; 4]) -> String,
+    line_width: &'static str,
+    /// Scales by the first number and then moves by the other two.
+    transform: fn(f64, f64, f64) -> String,
+    fill: &'static str,
+    even_odd_fill: &'static str,
+    stroke: &'static str,
+}
+
+const POSTSCRIPT_OPS: PathOps = PathOps {
+    move_to: "moveto",
+    line_to: "lineto",
+    curve_to: "curveto",
+    close: "closepath",
+    fill_color: |c| format!("{} {} {} setrgbcolor", c[0], c[1], c[2]),
+    stroke_color: |c| format!("{} {} {} setrgbcolor", c[0], c[1], c[2]),
+    line_width: "setlinewidth",
+    transform: |scale, x, y| format!("[{} 0 0 {} {} {}] concat", scale, scale, x, y),
+    fill: "fill",
+    even_odd_fill: "eofill",
+    stroke: "stroke",
+};
+
+const PDF_OPS: PathOps = PathOps {
+    move_to: "m",
+    line_to: "l",
+    curve_to: "c",
+    close: "h",
+    fill_color: |c| format!("{} {} {} rg", c[0], c[1], c[2]),
+    stroke_color: |c| format!("{} {} {} RG", c[0], c[1], c[2]),
+    line_width: "w",
+    transform: |scale, x, y| format!("{} 0 0 {} {} {} cm", scale, scale, x, y),
+    fill: "f",
+    even_odd_fill: "f*",
+    stroke: "S",
+};
+
+fn page_operators(diagram: &Diagram, options: &ExportOptions, ops: &PathOps) -> String {
+    let height = DEFAULT_WINDOW_HEIGHT as f64;
+    let flip = |p: Point| (p.0, height - p.1);
+    let mut out = String::new();
+    let mut line = |s: String| { out.push_str(&s); out.push('\n'); };
+
+    // The document's frame of the window fills the page
+    if options.document.is_some() {
+        let ([[left, _], [right, bottom]], [page_width, _]) = options.page();
+        let scale = page_width / (right - left);
+        line((ops.transform)(scale, -left * scale, -(height - bottom) * scale));
+    }
+    line((ops.fill_color)(&options.background));
+    line(format!("0 0 {m} {w} 0 {l} {w} {h} {l} 0 {h} {l} {c}",
+        m = ops.move_to, l = ops.line_to, c = ops.close, w = DEFAULT_WINDOW_WIDTH, h = height));
+    line(ops.fill.to_string());
+
+    for shape in shapes(diagram, options) {
+        match shape {
+            Shape::Polygon { points, fill, stroke, .. } => {
+                paint(&[points], fill, stroke, ops.fill, ops, &flip).into_iter().for_each(&mut line);
+            }
+            Shape::Path { loops, fill, stroke } => {
+                paint(&loops, fill, stroke, ops.even_odd_fill, ops, &flip).into_iter().for_each(&mut line);
+            }
+            Shape::Circle { center, radius, fill, stroke } => {
+                // Four cubic Béziers approximate the circle closely enough for dots
+                let (cx, cy) = flip(center);
+                let k = 0.552_284_75 * radius;
+                let path = format!(
+                    "{x0} {cy} {m} {x0} {a} {b} {y1} {cx} {y1} {c} {d} {y1} {x1} {a} {x1} {cy} {c} \
+                     {x1} {e} {d} {y0} {cx} {y0} {c} {b} {y0} {x0} {e} {x0} {cy} {c} {h}",
+                    x0 = cx - radius, x1 = cx + radius, y0 = cy - radius, y1 = cy + radius,
+                    a = cy + k, b = cx - k, d = cx + k, e = cy - k,
+                    cx = cx, cy = cy, m = ops.move_to, c = ops.curve_to, h = ops.close);
+                line((ops.fill_color)(&fill));
+                line(path.clone());
+                line(ops.fill.to_string());
+                if let Some((color, width)) = stroke {
+                    line((ops.stroke_color)(&color));
+                    line(format!("{} {}", width, ops.line_width));
+                    line(path);
+                    line(ops.stroke.to_string());
+                }
+            }
+        }
+    }
+    out
+}
+
+/// The lines that fill and outline a path of closed loops, filling with
+/// `fill_op`. Nothing for a path without points.
+fn paint(
+    loops: &[Vec<Point>],
+    fill: Option<[f32; 4]>,
+    stroke: Option<([f32; 4], f64)>,
+    fill_op: &str,
+    ops: &PathOps,
+    flip: &impl Fn(Point) -> Point,
+) -> Vec<String> {
+    let mut path = Vec::new();
+    for points in loops.iter().filter(|l| !l.is_empty()) {
+        for (i, p) in points.iter().enumerate() {
+            let (x, y) = flip(*p);
+            let op = if i == 0 { ops.move_to } else { ops.line_to };
+            path.push(format!("{} {} {}", x, y, op));
+        }
+        path.push(ops.close.to_string());
+    }
+    if path.is_empty() {
+        return Vec::new();
+    }
+    let path = path.join(" ");
+    let mut lines = Vec::new();
+    if let Some(c) = fill {
+        lines.push((ops.fill_color)(&c));
+        lines.push(path.clone());
+        lines.push(fill_op.to_string());
+    }
+    if let Some((c, width)) = stroke {
+        lines.push((ops.stroke_color)(&c));
+        lines.push(format!("{} {}", width, ops.line_width));
+        lines.push(path);
+        lines.push(ops.stroke.to_string());
+    }
+    lines
+}
+
+pub fn write_eps<W: Write>(w: &mut W, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    let (_, [width, height]) = options.page();
+    writeln!(w, "%!PS-Adobe-3.0 EPSF-3.0")?;
+    writeln!(w, "%%BoundingBox: 0 0 {} {}", width.ceil(), height.ceil())?;
+    writeln!(w, "%%Creator: interactive-voronoi")?;
+    writeln!(w, "%%EndComments")?;
+    writeln!(w, "gsave")?;
+    writeln!(w, "newpath 0 0 moveto {w} 0 lineto {w} {h} lineto 0 {h} lineto closepath clip newpath", w = width, h = height)?;
+    write!(w, "{}", page_operators(diagram, options, &POSTSCRIPT_OPS))?;
+    writeln!(w, "grestore")?;
+    writeln!(w, "showpage")?;
+    writeln!(w, "%%EOF")
+}
+
+pub fn write_pdf<W: Write>(w: &mut W, diagram: &Diagram, options: &ExportOptions) -> io::Result<()> {
+    let (_, [width, height]) = options.page();
+    let content = page_operators(diagram, options, &PDF_OPS);
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents 4 0 R /Resources << >> >>", width, height),
+        format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content),
+    ];
+
+    // The cross-reference table needs the byte offset of every object
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).as_bytes());
+    w.write_all(&pdf)
+}
@@ -0,0 +1,117 @@
+//! A grid of numbers loaded from a file, like temperatures or population
+//! counts, stretched over the window. Painting each cell by the average of
+//! the field under it turns the diagram into bins of that data, the cells
+//! of the dots deciding where one bin ends and the next begins.
+//!
+//! The field is a CSV file with a number in every column of every row, or
+//! a grayscale image whose brighter pixels have higher values. Empty or
+//! unreadable CSV columns have no value and are left out of the averages.
+
+use std::path::Path;
+
+use crate::color::{hsv, UNGROUPED};
+use crate::diagram::Point;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+pub struct Field {
+    columns: usize,
+    rows: usize,
+    /// Row by row, NaN where there is no value.
+    values: Vec<f64>,
+    /// The lowest and highest value anywhere, the ends of the color ramp.
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Field {
+    /// Reads a `.csv` or `.txt` file of numbers, or an image of any format
+    /// the `image` crate knows.
+    pub fn open(path: &Path) -> Result<Field, String> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+        let (columns, values) = if extension == "csv" || extension == "txt" {
+            let text = std::fs::read_to_string(path).map_err(|err| format!("Can't load {}: {}", path.display(), err))?;
+            let lines = text.lines().filter(|line| !line.trim().is_empty()).collect::<Vec<_>>();
+            let columns = lines.iter().map(|line| line.split(',').count()).max().unwrap_or(0);
+            let mut values = Vec::with_capacity(columns * lines.len());
+            for line in lines {
+                let row = line.split(',').map(|v| v.trim().parse().unwrap_or(f64::NAN)).collect::<Vec<f64>>();
+                values.extend(row.iter().copied().chain(std::iter::repeat(f64::NAN)).take(columns));
+            }
+            (columns, values)
+        } else {
+            let image = image::open(path).map_err(|err| format!("Can't load {}: {}", path.display(), err))?.to_luma8();
+            (image.width() as usize, image.pixels().map(|p| p.0[0] as f64 / 255.0).collect())
+        };
+        let (low, high) = values.iter().filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &v| (low.min(v), high.max(v)));
+        if low > high {
+            return Err(format!("{} has no numbers", path.display()));
+        }
+        Ok(Field { columns, rows: values.len() / columns, values, low, high })
+    }
+
+    /// The size of a grid cell in the window.
+    fn step(&self) -> [f64; 2] {
+        [DEFAULT_WINDOW_WIDTH as f64 / self.columns as f64, DEFAULT_WINDOW_HEIGHT as f64 / self.rows as f64]
+    }
+
+    /// The value at a point of the window, `None` outside it or where the
+    /// grid has none.
+    pub fn at(&self, p: [f64; 2]) -> Option<f64> {
+        let [width, height] = self.step();
+        let (column, row) = ((p[0] / width).floor(), (p[1] / height).floor());
+        if column < 0.0 || row < 0.0 || column >= self.columns as f64 || row >= self.rows as f64 {
+            return None;
+        }
+        Some(self.values[row as usize * self.columns + column as usize]).filter(|v| v.is_finite())
+    }
+
+    /// The average of the values whose grid cells have their middle inside
+    /// the convex polygon, or for polygons too small to have any, the value
+    /// at `dot`.
+    pub fn average(&self, poly: &[Point], dot: [f64; 2]) -> Option<f64> {
+        let [width, height] = self.step();
+        let (mut total, mut count) = (0.0, 0);
+        if poly.len() >= 3 {
+            let top = poly.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+            let bottom = poly.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+            let first_row = (top / height - 0.5).ceil().max(0.0) as usize;
+            let last_row = ((bottom / height - 0.5).ceil().max(0.0) as usize).min(self.rows);
+            for row in first_row..last_row {
+                let middle = (row as f64 + 0.5) * height;
+                let (mut left, mut right) = (f64::INFINITY, f64::NEG_INFINITY);
+                for (i, p) in poly.iter().enumerate() {
+                    let q = poly[(i + 1) % poly.len()];
+                    if (p.1 <= middle) != (q.1 <= middle) {
+                        let x = p.0 + (middle - p.1) / (q.1 - p.1) * (q.0 - p.0);
+                        left = left.min(x);
+                        right = right.max(x);
+                    }
+                }
+                if left > right {
+                    continue;
+                }
+                let first = (left / width - 0.5).ceil().max(0.0) as usize;
+                let last = ((right / width - 0.5).ceil().max(0.0) as usize).min(self.columns);
+                for &v in &self.values[row * self.columns + first.min(last)..row * self.columns + last] {
+                    if v.is_finite() {
+                        total += v;
+                        count += 1;
+                    }
+                }
+            }
+        }
+        if count == 0 {
+            return self.at(dot);
+        }
+        Some(total / count as f64)
+    }
+
+    /// Blue for the lowest value of the field through green to red for the
+    /// highest, and gray without a value.
+    pub fn color(&self, value: Option<f64>) -> [f32; 4] {
+        let Some(value) = value else { return UNGROUPED };
+        let t = ((value - self.low) / (self.high - self.low).max(f64::EPSILON)).clamp(0.0, 1.0);
+        hsv(0.66 * (1.0 - t as f32), 0.6, 0.95)
+    }
+}
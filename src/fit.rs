@@ -0,0 +1,120 @@
+//! Fitting dots whose coordinates don't match the window into view, like
+//! longitudes and latitudes or values between 0 and 1: the box around them
+//! is scaled, keeping its shape, to fill the window up to a margin, and
+//! moved to its middle. The diagram keeps the fit, so exports can map the
+//! dots back into the coordinates they were loaded in, see `Coords`.
+
+use std::str::FromStr;
+
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// How far the fitted dots stay from the edges of the window, in pixels.
+pub const MARGIN: f64 = 20.0;
+
+/// What the coordinates of loaded dots are in, for `--coords`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Coords {
+    /// Pixels of the window, taken as they are.
+    Window,
+    /// Shares of the window's width and height, from 0 to 1.
+    Normalized,
+    /// Any units, fitted into view, see `Fit::new`.
+    Data,
+}
+
+impl Coords {
+    /// How dots in these coordinates get into the window, `None` if they
+    /// are there already.
+    pub fn fit(self, dots: &[[f64; 2]]) -> Option<Fit> {
+        match self {
+            Coords::Window => None,
+            Coords::Normalized => Some(Fit::normalized()),
+            Coords::Data => Fit::new(dots),
+        }
+    }
+}
+
+impl FromStr for Coords {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Coords, String> {
+        match s {
+            "window" => Ok(Coords::Window),
+            "normalized" => Ok(Coords::Normalized),
+            "data" => Ok(Coords::Data),
+            _ => Err(format!("Unknown coordinates \"{}\", use window, normalized or data", s))
+        }
+    }
+}
+
+/// Scaling by `scale` along each axis, then moving by `offset`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fit {
+    pub scale: [f64; 2],
+    pub offset: [f64; 2],
+}
+
+impl Fit {
+    /// The fit of the box around `dots` into the window, or `None` without
+    /// dots. Dots all in a line only get moved along it, a single dot only
+    /// to the middle.
+    pub fn new(dots: &[[f64; 2]]) -> Option<Fit> {
+        let [low, high] = bounding_box(dots)?;
+        let window = [DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64];
+        let scale = (0..2)
+            .filter(|&axis| high[axis] > low[axis])
+            .map(|axis| (window[axis] - 2.0 * MARGIN) / (high[axis] - low[axis]))
+            .fold(f64::INFINITY, f64::min);
+        let scale = if scale.is_finite() { scale } else { 1.0 };
+        let offset = [0, 1].map(|axis| window[axis] / 2.0 - (low[axis] + high[axis]) / 2.0 * scale);
+        Some(Fit { scale: [scale; 2], offset })
+    }
+
+    /// From shares of the window's width and height to pixels.
+    pub fn normalized() -> Fit {
+        Fit { scale: [DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64], offset: [0.0; 2] }
+    }
+
+    /// This fit followed by `next`.
+    pub fn then(&self, next: &Fit) -> Fit {
+        Fit {
+            scale: [0, 1].map(|axis| self.scale[axis] * next.scale[axis]),
+            offset: [0, 1].map(|axis| self.offset[axis] * next.scale[axis] + next.offset[axis]),
+        }
+    }
+
+    /// Where `dot` ends up in the window.
+    pub fn apply(&self, dot: [f64; 2]) -> [f64; 2] {
+        [0, 1].map(|axis| dot[axis] * self.scale[axis] + self.offset[axis])
+    }
+
+    /// Where a dot in the window came from.
+    pub fn invert(&self, dot: [f64; 2]) -> [f64; 2] {
+        [0, 1].map(|axis| (dot[axis] - self.offset[axis]) / self.scale[axis])
+    }
+
+    /// An area in the window in square units of where the dots came from.
+    pub fn invert_area(&self, area: f64) -> f64 {
+        area / (self.scale[0] * self.scale[1]).abs()
+    }
+}
+
+/// The lowest and the highest coordinates of the dots, leaving out ones
+/// that aren't finite, or `None` without dots.
+pub fn bounding_box(dots: &[[f64; 2]]) -> Option<[[f64; 2]; 2]> {
+    let mut low = [f64::INFINITY; 2];
+    let mut high = [f64::NEG_INFINITY; 2];
+    for dot in dots.iter().filter(|d| d[0].is_finite() && d[1].is_finite()) {
+        for axis in 0..2 {
+            low[axis] = low[axis].min(dot[axis]);
+            high[axis] = high[axis].max(dot[axis]);
+        }
+    }
+    (low[0] <= high[0]).then_some([low, high])
+}
+
+/// How many of the dots are outside the window.
+pub fn outside(dots: &[[f64; 2]]) -> usize {
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    dots.iter().filter(|d| !((0.0..=width).contains(&d[0]) && (0.0..=height).contains(&d[1]))).count()
+}
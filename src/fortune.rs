@@ -0,0 +1,346 @@
+//! Fortune's sweepline algorithm for the Voronoi diagram, run up to any
+//! position of the sweep line so every step of it can be shown.
+//!
+//! A horizontal line sweeps down the window. Every point above it that is
+//! closer to some dot than to the line already knows its cell, and the
+//! border of that area, the beach line, is made of parabolic arcs, one
+//! focused on each dot above the line. The points where neighboring arcs
+//! meet trace the Voronoi edges. The beach line changes only at events: a
+//! dot reached by the sweep line splits the arc above it with a new arc
+//! (a site event), and an arc squeezed to nothing by its neighbors leaves a
+//! Voronoi vertex behind (a circle event), once the sweep line has passed
+//! the whole circle through its dot and the dots of its neighbors.
+//!
+//! The computation runs with y pointing up, where the sweep line moves
+//! towards lower y, and converts from and to window coordinates at the
+//! edges.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::diagram::Point;
+use crate::predicates::orient;
+
+/// Pixels between the points the arcs of the beach line are drawn with, at
+/// most.
+const ARC_STEP: f64 = 4.0;
+
+/// The state of the algorithm with the sweep line at some height, in window
+/// coordinates.
+pub struct Snapshot {
+    pub sweep: f64,
+    /// The traced parts of the Voronoi edges, those still growing ending at
+    /// the beach line.
+    pub edges: Vec<[Point; 2]>,
+    /// The Voronoi vertices found so far.
+    pub vertices: Vec<Point>,
+    /// The arcs of the beach line as points along them, left to right.
+    pub beach: Vec<Vec<Point>>,
+    /// The circles of the circle events waiting for the sweep line, as
+    /// center and radius. Each event happens when the sweep line reaches
+    /// the bottom of its circle.
+    pub circles: Vec<(Point, f64)>,
+}
+
+/// Where the sweep line is at each event of the whole run, top to bottom.
+pub fn event_positions(dots: &[[f64; 2]]) -> Vec<f64> {
+    let mut fortune = Fortune::new(dots);
+    fortune.run(f64::NEG_INFINITY);
+    fortune.happened.iter().map(|&y| -y).collect()
+}
+
+/// Runs the algorithm over the dots until the sweep line is at `sweep`,
+/// processing every event at or above it. `width` is how far to the right
+/// the beach line is drawn, from 0.
+pub fn snapshot(dots: &[[f64; 2]], sweep: f64, width: f64) -> Snapshot {
+    let mut fortune = Fortune::new(dots);
+    let line = -sweep;
+    fortune.run(line);
+
+    let edges = fortune.traces.iter().enumerate().filter_map(|(i, trace)| {
+        let end = match trace.end {
+            Some(end) => end,
+            None => {
+                // Still growing, so its breakpoint is on the beach line
+                let k = fortune.beach.windows(2).position(|pair| pair[0].trace_right == Some(i))?;
+                let (left, right) = (fortune.sites[fortune.beach[k].site], fortune.sites[fortune.beach[k + 1].site]);
+                let x = breakpoint(left, right, line);
+                (x, beach_height(left, right, x, line))
+            }
+        };
+        // Edges between dots at the same height come from infinitely far
+        // up, or from the top of the window as far as anyone can see
+        let start = trace.start.unwrap_or((end.0, end.1.max(0.0)));
+        Some([window(start), window(end)])
+    }).collect();
+
+    let mut beach = Vec::new();
+    for (k, arc) in fortune.beach.iter().enumerate() {
+        let site = fortune.sites[arc.site];
+        if site.1 <= line {
+            continue;
+        }
+        let from = if k == 0 { 0.0 } else { breakpoint(fortune.sites[fortune.beach[k - 1].site], site, line).max(0.0) };
+        let to = if k + 1 == fortune.beach.len() { width } else { breakpoint(site, fortune.sites[fortune.beach[k + 1].site], line).min(width) };
+        if from >= to {
+            continue;
+        }
+        let pieces = ((to - from) / ARC_STEP).ceil().max(1.0) as usize;
+        beach.push((0..=pieces).map(|i| {
+            let x = from + (to - from) * i as f64 / pieces as f64;
+            window((x, parabola(site, x, line)))
+        }).collect());
+    }
+
+    let circles = fortune.events.iter()
+        .filter_map(|event| match event.kind {
+            Kind::Circle { arc, center } if fortune.is_pending(arc, event.id) => {
+                Some((window(center), center.1 - event.y))
+            }
+            _ => None
+        })
+        .collect();
+
+    Snapshot { sweep, edges, vertices: fortune.vertices.iter().map(|&v| window(v)).collect(), beach, circles }
+}
+
+/// Between window coordinates and those with y pointing up.
+fn window(p: Point) -> Point {
+    (p.0, -p.1)
+}
+
+/// The height of the parabola of points as far from `site` as from the
+/// sweep line at `line`, at `x`.
+fn parabola(site: Point, x: f64, line: f64) -> f64 {
+    ((x - site.0).powi(2) + site.1 * site.1 - line * line) / (2.0 * (site.1 - line))
+}
+
+/// The height of the beach line at breakpoint `x` between the arcs of
+/// `left` and `right`, from whichever parabola isn't squeezed into a ray.
+fn beach_height(left: Point, right: Point, x: f64, line: f64) -> f64 {
+    if left.1 > line {
+        parabola(left, x, line)
+    } else if right.1 > line {
+        parabola(right, x, line)
+    } else {
+        line
+    }
+}
+
+/// Where the arc of `left` meets the arc of `right` to its right.
+fn breakpoint(left: Point, right: Point, line: f64) -> f64 {
+    // A dot on the sweep line has a parabola squeezed into a ray up from it
+    if left.1 == line {
+        return left.0;
+    }
+    if right.1 == line {
+        return right.0;
+    }
+    let (dl, dr) = (2.0 * (left.1 - line), 2.0 * (right.1 - line));
+    // The difference of the parabolas, a x² + b x + c, is below zero left
+    // of the breakpoint, where the left arc is the lower one, and above
+    // right of it
+    let a = 1.0 / dl - 1.0 / dr;
+    let b = -2.0 * left.0 / dl + 2.0 * right.0 / dr;
+    let c = (left.0 * left.0 + left.1 * left.1 - line * line) / dl - (right.0 * right.0 + right.1 * right.1 - line * line) / dr;
+    if a.abs() < 1e-12 {
+        return -c / b;
+    }
+    (-b + (b * b - 4.0 * a * c).max(0.0).sqrt()) / (2.0 * a)
+}
+
+fn circumcenter(a: Point, b: Point, c: Point) -> Point {
+    let d = 2.0 * (a.0 * (b.1 - c.1) + b.0 * (c.1 - a.1) + c.0 * (a.1 - b.1));
+    let (a2, b2, c2) = (a.0 * a.0 + a.1 * a.1, b.0 * b.0 + b.1 * b.1, c.0 * c.0 + c.1 * c.1);
+    ((a2 * (b.1 - c.1) + b2 * (c.1 - a.1) + c2 * (a.1 - b.1)) / d, (a2 * (c.0 - b.0) + b2 * (a.0 - c.0) + c2 * (b.0 - a.0)) / d)
+}
+
+struct Arc {
+    site: usize,
+    /// Tells arcs apart after the beach line changed around them.
+    id: usize,
+    /// The circle event that would remove this arc, if any.
+    circle: Option<usize>,
+    /// The edge traced by the breakpoint with the next arc.
+    trace_right: Option<usize>,
+}
+
+/// A Voronoi edge as far as it was traced. Every breakpoint traces one, so
+/// an edge found at a site event is traced in two halves going both ways.
+struct Trace {
+    /// `None` for edges reaching infinitely far up.
+    start: Option<Point>,
+    end: Option<Point>,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Site(usize),
+    Circle { arc: usize, center: Point },
+}
+
+struct Event {
+    y: f64,
+    id: usize,
+    kind: Kind,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Event) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Event) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Event {
+    /// Higher events come first, and among events at the same height
+    /// circle events before sites, otherwise in the order they were made,
+    /// which for sites is left to right.
+    fn cmp(&self, other: &Event) -> Ordering {
+        let is_circle = |e: &Event| matches!(e.kind, Kind::Circle { .. });
+        self.y.total_cmp(&other.y)
+            .then_with(|| is_circle(self).cmp(&is_circle(other)))
+            .then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+struct Fortune {
+    sites: Vec<Point>,
+    beach: Vec<Arc>,
+    events: BinaryHeap<Event>,
+    traces: Vec<Trace>,
+    vertices: Vec<Point>,
+    /// The heights of the events processed so far, in order.
+    happened: Vec<f64>,
+    next_id: usize,
+}
+
+impl Fortune {
+    fn new(dots: &[[f64; 2]]) -> Fortune {
+        let sites = dots.iter().map(|d| window((d[0], d[1]))).collect::<Vec<_>>();
+        // Site events at the same height go left to right
+        let mut order = (0..sites.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| sites[b].1.total_cmp(&sites[a].1).then(sites[a].0.total_cmp(&sites[b].0)));
+        let events = order.iter().enumerate().map(|(id, &site)| Event { y: sites[site].1, id, kind: Kind::Site(site) }).collect();
+        Fortune { sites, beach: Vec::new(), events, traces: Vec::new(), vertices: Vec::new(), happened: Vec::new(), next_id: dots.len() }
+    }
+
+    fn id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Processes every event at or above `line`.
+    fn run(&mut self, line: f64) {
+        while self.events.peek().is_some_and(|e| e.y >= line) {
+            let event = self.events.pop().unwrap();
+            match event.kind {
+                Kind::Site(site) => self.add_site(site),
+                Kind::Circle { arc, center } => {
+                    if !self.is_pending(arc, event.id) {
+                        continue;
+                    }
+                    self.remove_arc(arc, center, event.y);
+                }
+            }
+            self.happened.push(event.y);
+        }
+    }
+
+    /// Whether the circle event still stands, not replaced by a change of
+    /// the beach line around its arc.
+    fn is_pending(&self, arc: usize, event: usize) -> bool {
+        self.beach.iter().any(|a| a.id == arc && a.circle == Some(event))
+    }
+
+    fn add_site(&mut self, site: usize) {
+        let p = self.sites[site];
+        let id = self.id();
+        let new_arc = Arc { site, id, circle: None, trace_right: None };
+        if self.beach.is_empty() {
+            self.beach.push(new_arc);
+            return;
+        }
+        let line = p.1;
+        let k = (0..self.beach.len())
+            .find(|&k| k + 1 == self.beach.len() || breakpoint(self.sites[self.beach[k].site], self.sites[self.beach[k + 1].site], line) >= p.0)
+            .unwrap();
+        let above = self.sites[self.beach[k].site];
+
+        if above.1 == line {
+            // Only dots at the height of the first ones so far, whose arcs
+            // are side by side with an edge straight up between each two
+            let at = if p.0 < above.0 { k } else { k + 1 };
+            let trace = self.traces.len();
+            self.traces.push(Trace { start: None, end: None });
+            let mut new_arc = new_arc;
+            if at == k {
+                new_arc.trace_right = Some(trace);
+            } else {
+                new_arc.trace_right = self.beach[k].trace_right;
+                self.beach[k].trace_right = Some(trace);
+            }
+            self.beach.insert(at, new_arc);
+            return;
+        }
+
+        // Split the arc above the dot in two, with the new arc in between
+        self.beach[k].circle = None;
+        let start = (p.0, parabola(above, p.0, line));
+        let (left_trace, right_trace) = (self.traces.len(), self.traces.len() + 1);
+        self.traces.push(Trace { start: Some(start), end: None });
+        self.traces.push(Trace { start: Some(start), end: None });
+        let right_half = Arc { site: self.beach[k].site, id: self.id(), circle: None, trace_right: self.beach[k].trace_right };
+        self.beach[k].trace_right = Some(left_trace);
+        self.beach.insert(k + 1, Arc { trace_right: Some(right_trace), ..new_arc });
+        self.beach.insert(k + 2, right_half);
+        self.check_circle(k, line);
+        self.check_circle(k + 2, line);
+    }
+
+    fn remove_arc(&mut self, arc: usize, center: Point, line: f64) {
+        let k = self.beach.iter().position(|a| a.id == arc).unwrap();
+        self.vertices.push(center);
+        let left = self.beach[k - 1].trace_right.unwrap();
+        let right = self.beach[k].trace_right.unwrap();
+        self.traces[left].end = Some(center);
+        self.traces[right].end = Some(center);
+        let trace = self.traces.len();
+        self.traces.push(Trace { start: Some(center), end: None });
+        self.beach.remove(k);
+        self.beach[k - 1].trace_right = Some(trace);
+        self.beach[k - 1].circle = None;
+        self.beach[k].circle = None;
+        self.check_circle(k - 1, line);
+        self.check_circle(k, line);
+    }
+
+    /// Adds the circle event of arc `k`, if its neighbors close in on it.
+    fn check_circle(&mut self, k: usize, line: f64) {
+        if k == 0 || k + 1 >= self.beach.len() {
+            return;
+        }
+        let [a, b, c] = [k - 1, k, k + 1].map(|i| self.beach[i].site);
+        if a == c {
+            return;
+        }
+        let [pa, pb, pc] = [a, b, c].map(|s| self.sites[s]);
+        // The breakpoints converge only if the dots turn clockwise
+        if orient([pa.0, pa.1], [pb.0, pb.1], [pc.0, pc.1]) != Ordering::Less {
+            return;
+        }
+        let center = circumcenter(pa, pb, pc);
+        let radius = (pb.0 - center.0).hypot(pb.1 - center.1);
+        let id = self.id();
+        self.beach[k].circle = Some(id);
+        let arc = self.beach[k].id;
+        self.events.push(Event { y: (center.1 - radius).min(line), id, kind: Kind::Circle { arc, center } });
+    }
+}
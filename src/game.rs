@@ -0,0 +1,45 @@
+/// Players take turns placing dots, each claiming the cell around it. When
+/// everyone has used up their moves, whoever's cells cover the most area wins.
+pub struct Game {
+    pub players: usize,
+    pub moves_per_player: usize,
+    moves_made: usize,
+}
+
+impl Game {
+    pub fn new(players: usize, moves_per_player: usize) -> Game {
+        Game { players, moves_per_player, moves_made: 0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.moves_made = 0;
+    }
+
+    /// The player whose turn it is, counting from zero.
+    pub fn current_player(&self) -> usize {
+        self.moves_made % self.players
+    }
+
+    pub fn moves_left(&self) -> usize {
+        self.players * self.moves_per_player - self.moves_made
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.moves_left() == 0
+    }
+
+    /// Passes the turn on to the next player.
+    pub fn record_move(&mut self) {
+        if !self.is_over() {
+            self.moves_made += 1;
+        }
+    }
+
+    /// The player with the most area, `None` on a tie. `areas` is indexed by player.
+    pub fn leader(&self, areas: &[f64]) -> Option<usize> {
+        let area = |player: usize| areas.get(player).copied().unwrap_or(0.0);
+        let best = (0..self.players).max_by(|&a, &b| area(a).total_cmp(&area(b)))?;
+        let tied = (0..self.players).filter(|&p| area(p) == area(best)).count() > 1;
+        (!tied).then_some(best)
+    }
+}
@@ -0,0 +1,134 @@
+//! Gamepad input read from a Linux joystick device such as `/dev/input/js0`,
+//! for installations where a keyboard and mouse are impractical. Presses
+//! and stick movements become piston controller events, which the app
+//! handles like any other input:
+//!
+//! * The left stick moves the cursor.
+//! * A works like the mouse button, B removes the dot nearest the cursor.
+//! * The left trigger shrinks the spray brush, the right one grows it.
+//! * The right shoulder button switches tools like `B`, the left one the
+//!   relaxation like `M`.
+//!
+//! Buttons and axes are numbered the way the Linux xpad driver numbers those
+//! of an Xbox controller.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use piston_window::{Button, ButtonArgs, ButtonState, ControllerAxisArgs, ControllerButton, Event, Input, Motion, MouseButton};
+
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+pub const BUTTON_A: u8 = 0;
+pub const BUTTON_B: u8 = 1;
+pub const LEFT_SHOULDER: u8 = 4;
+pub const RIGHT_SHOULDER: u8 = 5;
+const STICK_X: u8 = 0;
+const STICK_Y: u8 = 1;
+const LEFT_TRIGGER: u8 = 2;
+const RIGHT_TRIGGER: u8 = 5;
+
+/// Sticks rarely rest at exactly zero, closer to it than this is centered.
+const DEAD_ZONE: f64 = 0.15;
+/// How fast the stick moves the cursor when pushed all the way.
+const CURSOR_SPEED: f64 = 600.0;
+/// How many times bigger a fully pressed trigger makes the brush per second.
+const BRUSH_GROWTH: f64 = 2.0;
+const MIN_BRUSH_RADIUS: f64 = 5.0;
+const MAX_BRUSH_RADIUS: f64 = 300.0;
+
+/// Event types of the joystick API, see `linux/joystick.h`.
+const JS_EVENT_BUTTON: u8 = 0x01;
+const JS_EVENT_AXIS: u8 = 0x02;
+/// Set on the events reporting the state the device was in when opened.
+const JS_EVENT_INIT: u8 = 0x80;
+
+/// Starts reading the joystick device in the background, sending its input
+/// as controller events.
+pub fn listen(device: &str, events: Sender<Event>) -> io::Result<()> {
+    let mut input = File::open(device)?;
+    println!("Listening for gamepad input on {}", device);
+    thread::spawn(move || {
+        // Each event is a u32 timestamp, an i16 value, a u8 type and a u8 number
+        let mut raw = [0u8; 8];
+        while input.read_exact(&mut raw).is_ok() {
+            let value = i16::from_ne_bytes([raw[4], raw[5]]);
+            let (kind, number) = (raw[6], raw[7]);
+            let input = match kind & !JS_EVENT_INIT {
+                // Buttons that are up at the start would read as releases
+                JS_EVENT_BUTTON if kind & JS_EVENT_INIT == 0 => {
+                    let state = if value != 0 { ButtonState::Press } else { ButtonState::Release };
+                    Input::Button(ButtonArgs { state, button: Button::Controller(ControllerButton::new(0, number)), scancode: None })
+                }
+                JS_EVENT_AXIS => Input::Move(Motion::ControllerAxis(ControllerAxisArgs::new(0, number, value as f64 / i16::MAX as f64))),
+                _ => continue
+            };
+            if events.send(Event::Input(input, None)).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// The event with button A turned into the left mouse button, or `None` for
+/// other events.
+pub fn as_mouse(e: &Event) -> Option<Event> {
+    match e {
+        Event::Input(Input::Button(args @ ButtonArgs { button: Button::Controller(b), .. }), timestamp) if b.button == BUTTON_A => {
+            let args = ButtonArgs { button: Button::Mouse(MouseButton::Left), ..*args };
+            Some(Event::Input(Input::Button(args), *timestamp))
+        }
+        _ => None
+    }
+}
+
+/// Where the stick and triggers are, kept between events.
+#[derive(Default)]
+pub struct Gamepad {
+    stick: [f64; 2],
+    /// From 0 when released to 1 when pressed all the way.
+    triggers: [f64; 2],
+    /// Whether the stick was ever moved, so there is a cursor to show.
+    pub used: bool,
+}
+
+impl Gamepad {
+    pub fn axis(&mut self, args: &ControllerAxisArgs) {
+        match args.axis {
+            STICK_X => self.stick[0] = args.position,
+            STICK_Y => self.stick[1] = args.position,
+            // Triggers rest at -1
+            LEFT_TRIGGER => self.triggers[0] = (args.position + 1.0) / 2.0,
+            RIGHT_TRIGGER => self.triggers[1] = (args.position + 1.0) / 2.0,
+            _ => return
+        }
+        self.used |= self.stick.iter().any(|v| v.abs() > DEAD_ZONE);
+    }
+
+    /// Whether the stick or a trigger is pushed, so things change over time.
+    pub fn active(&self) -> bool {
+        self.stick.iter().chain(&self.triggers).any(|v| v.abs() > DEAD_ZONE)
+    }
+
+    /// Where the stick takes the cursor in `seconds`, staying in the window.
+    pub fn move_cursor(&self, cursor: [f64; 2], seconds: f64) -> [f64; 2] {
+        let speed = |v: f64| if v.abs() > DEAD_ZONE { v * CURSOR_SPEED } else { 0.0 };
+        [
+            (cursor[0] + speed(self.stick[0]) * seconds).clamp(0.0, DEFAULT_WINDOW_WIDTH as f64),
+            (cursor[1] + speed(self.stick[1]) * seconds).clamp(0.0, DEFAULT_WINDOW_HEIGHT as f64),
+        ]
+    }
+
+    /// How big the triggers make a brush of `radius` in `seconds`.
+    pub fn resize_brush(&self, radius: f64, seconds: f64) -> f64 {
+        let pressed = |v: f64| if v > DEAD_ZONE { v } else { 0.0 };
+        let growth = pressed(self.triggers[1]) - pressed(self.triggers[0]);
+        if growth == 0.0 {
+            return radius;
+        }
+        (radius * BRUSH_GROWTH.powf(growth * seconds)).clamp(MIN_BRUSH_RADIUS, MAX_BRUSH_RADIUS)
+    }
+}
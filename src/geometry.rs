@@ -0,0 +1,211 @@
+//! Plain polygon helpers for the Voronoi cells.
+
+use crate::diagram::Point;
+
+/// Clips a convex or concave polygon to an axis-aligned rectangle
+/// (Sutherland–Hodgman).
+pub fn clip_to_rect(poly: &[Point], min: Point, max: Point) -> Vec<Point> {
+    let inside: [&dyn Fn(Point) -> bool; 4] = [
+        &|p| p.0 >= min.0,
+        &|p| p.0 <= max.0,
+        &|p| p.1 >= min.1,
+        &|p| p.1 <= max.1,
+    ];
+    let intersect: [&dyn Fn(Point, Point) -> Point; 4] = [
+        &|a, b| at_x(a, b, min.0),
+        &|a, b| at_x(a, b, max.0),
+        &|a, b| at_y(a, b, min.1),
+        &|a, b| at_y(a, b, max.1),
+    ];
+
+    let mut output = poly.to_vec();
+    for (inside, intersect) in inside.iter().zip(intersect.iter()) {
+        let input = std::mem::take(&mut output);
+        for (i, &current) in input.iter().enumerate() {
+            let previous = input[(i + input.len() - 1) % input.len()];
+            match (inside(previous), inside(current)) {
+                (true, true) => output.push(current),
+                (true, false) => output.push(intersect(previous, current)),
+                (false, true) => { output.push(intersect(previous, current)); output.push(current); },
+                (false, false) => ()
+            }
+        }
+    }
+    output
+}
+
+/// Clips a convex polygon to the half-plane where `normal · p <= offset`.
+pub fn clip_to_half_plane(poly: &[Point], normal: Point, offset: f64) -> Vec<Point> {
+    let outside = |p: Point| normal.0 * p.0 + normal.1 * p.1 - offset;
+    let mut output = Vec::new();
+    for (i, &current) in poly.iter().enumerate() {
+        let previous = poly[(i + poly.len() - 1) % poly.len()];
+        let (a, b) = (outside(previous), outside(current));
+        if (a <= 0.0) != (b <= 0.0) {
+            let t = a / (a - b);
+            output.push((previous.0 + t * (current.0 - previous.0), previous.1 + t * (current.1 - previous.1)));
+        }
+        if b <= 0.0 {
+            output.push(current);
+        }
+    }
+    output
+}
+
+fn at_x(a: Point, b: Point, x: f64) -> Point {
+    let t = (x - a.0) / (b.0 - a.0);
+    (x, a.1 + t * (b.1 - a.1))
+}
+
+fn at_y(a: Point, b: Point, y: f64) -> Point {
+    let t = (y - a.1) / (b.1 - a.1);
+    (a.0 + t * (b.0 - a.0), y)
+}
+
+/// Rounds off the corners of a closed polygon by cutting each of them off
+/// `iterations` times (Chaikin's algorithm): every edge is replaced by the
+/// points a quarter and three quarters along it, doubling the points.
+pub fn chaikin(poly: &[Point], iterations: usize) -> Vec<Point> {
+    let mut smoothed = poly.to_vec();
+    for _ in 0..iterations {
+        smoothed = smoothed.iter().zip(smoothed.iter().cycle().skip(1))
+            .flat_map(|(a, b)| [
+                (0.75 * a.0 + 0.25 * b.0, 0.75 * a.1 + 0.25 * b.1),
+                (0.25 * a.0 + 0.75 * b.0, 0.25 * a.1 + 0.75 * b.1),
+            ])
+            .collect();
+    }
+    smoothed
+}
+
+/// Whether the polygon bends the same way at every corner. Straight
+/// corners don't count either way.
+pub fn is_convex(poly: &[Point]) -> bool {
+    let mut turns = 0.0;
+    for i in 0..poly.len() {
+        let turn = turn(poly[i], poly[(i + 1) % poly.len()], poly[(i + 2) % poly.len()]);
+        if turn * turns < 0.0 {
+            return false;
+        }
+        if turn != 0.0 {
+            turns = turn;
+        }
+    }
+    true
+}
+
+/// Triangles covering a simple polygon that may be concave, as indices into
+/// it, found by cutting off one corner after another whose triangle holds
+/// no other corner (ear clipping).
+pub fn ear_triangles(poly: &[Point]) -> Vec<[usize; 3]> {
+    let orientation = signed_area(poly).signum();
+    let mut left = (0..poly.len()).collect::<Vec<_>>();
+    let mut triangles = Vec::new();
+    while left.len() > 3 {
+        let n = left.len();
+        let corner = |i: usize| (left[(i + n - 1) % n], left[i], left[(i + 1) % n]);
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = corner(i);
+            turn(poly[a], poly[b], poly[c]) * orientation > 0.0
+                && left.iter().all(|&p| [a, b, c].contains(&p) || !in_triangle(poly[p], poly[a], poly[b], poly[c]))
+        });
+        match ear {
+            Some(i) => {
+                let (a, b, c) = corner(i);
+                triangles.push([a, b, c]);
+                left.remove(i);
+            }
+            None => {
+                // Only straight corners or rounding trouble left, which a
+                // fan covers well enough
+                match (0..n).find(|&i| { let (a, b, c) = corner(i); turn(poly[a], poly[b], poly[c]) == 0.0 }) {
+                    Some(straight) => { left.remove(straight); }
+                    None => break
+                }
+            }
+        }
+    }
+    triangles.extend((1..left.len().saturating_sub(1)).map(|i| [left[0], left[i], left[i + 1]]));
+    triangles
+}
+
+/// The polygon grown by `distance` on every side, or shrunk for a negative
+/// one, with its corners extended until the sides meet. Corners sharper
+/// than about 30 degrees are cut short at four times the distance.
+pub fn offset(poly: &[Point], distance: f64) -> Vec<Point> {
+    const MITER_LIMIT: f64 = 4.0;
+    let mut corners = poly.to_vec();
+    corners.dedup();
+    if corners.len() > 1 && corners.first() == corners.last() {
+        corners.pop();
+    }
+    if corners.len() < 3 || distance == 0.0 {
+        return corners;
+    }
+    let orientation = signed_area(&corners).signum();
+    let n = corners.len();
+    // The outward normal of the side from each corner to the next
+    let normals = (0..n).map(|i| {
+        let (a, b) = (corners[i], corners[(i + 1) % n]);
+        let length = (b.0 - a.0).hypot(b.1 - a.1);
+        (orientation * (b.1 - a.1) / length, orientation * (a.0 - b.0) / length)
+    }).collect::<Vec<_>>();
+    (0..n).map(|i| {
+        let (before, after) = (normals[(i + n - 1) % n], normals[i]);
+        let (x, y) = (before.0 + after.0, before.1 + after.1);
+        let length = x.hypot(y);
+        if length < f64::EPSILON {
+            return (corners[i].0 + after.0 * distance, corners[i].1 + after.1 * distance);
+        }
+        let (x, y) = (x / length, y / length);
+        let stretch = 1.0 / (x * after.0 + y * after.1).max(1.0 / MITER_LIMIT);
+        (corners[i].0 + x * distance * stretch, corners[i].1 + y * distance * stretch)
+    }).collect()
+}
+
+/// Positive if `a`, `b`, `c` turn counter-clockwise in Y-up coordinates.
+fn turn(a: Point, b: Point, c: Point) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+fn in_triangle(p: Point, a: Point, b: Point, c: Point) -> bool {
+    let (x, y, z) = (turn(a, b, p), turn(b, c, p), turn(c, a, p));
+    (x >= 0.0 && y >= 0.0 && z >= 0.0) || (x <= 0.0 && y <= 0.0 && z <= 0.0)
+}
+
+/// Signed area, positive for counter-clockwise polygons in Y-up coordinates.
+pub fn signed_area(poly: &[Point]) -> f64 {
+    let mut sum = 0.0;
+    for (i, a) in poly.iter().enumerate() {
+        let b = poly[(i + 1) % poly.len()];
+        sum += a.0 * b.1 - b.0 * a.1;
+    }
+    sum / 2.0
+}
+
+pub fn area(poly: &[Point]) -> f64 {
+    signed_area(poly).abs()
+}
+
+pub fn perimeter(poly: &[Point]) -> f64 {
+    poly.iter().enumerate().map(|(i, a)| {
+        let b = poly[(i + 1) % poly.len()];
+        ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+    }).sum()
+}
+
+/// The center of mass of the polygon, `None` if it has no area.
+pub fn centroid(poly: &[Point]) -> Option<Point> {
+    let area = signed_area(poly);
+    if area.abs() < f64::EPSILON {
+        return None;
+    }
+    let (mut x, mut y) = (0.0, 0.0);
+    for (i, a) in poly.iter().enumerate() {
+        let b = poly[(i + 1) % poly.len()];
+        let cross = a.0 * b.1 - b.0 * a.1;
+        x += (a.0 + b.0) * cross;
+        y += (a.1 + b.1) * cross;
+    }
+    Some((x / (6.0 * area), y / (6.0 * area)))
+}
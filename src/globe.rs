@@ -0,0 +1,256 @@
+//! The globe of `--globe`: sites on a sphere with their spherical Voronoi
+//! cells, seen from afar as an orthographic projection. Dragging turns it,
+//! clicking puts a site on the point under the cursor.
+
+use std::io;
+use std::path::Path;
+
+use graphics::{Context, Graphics};
+
+use crate::diagram::{random_color, Point};
+use crate::draw::{draw_cells, draw_circle, draw_dots, draw_outline};
+use crate::points::read_dots;
+use crate::sphere::{dot, from_lat_lon, normalize, spherical_cells, Vector};
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// Space between the globe and the top and bottom of the window.
+const MARGIN: f64 = 20.0;
+/// How far, in pixels, the mouse has to move with the button down for a
+/// drag rather than a click.
+const DRAG_DISTANCE: f64 = 3.0;
+/// The longest arc, in radians, drawn as one straight piece.
+const MAX_ARC: f64 = 0.03;
+/// Sites closer than this, as the cosine of the angle between them, are one.
+const SAME_SITE: f64 = 1.0 - 1e-12;
+
+struct Drag {
+    last: [f64; 2],
+    moved: bool,
+}
+
+pub struct Globe {
+    sites: Vec<Vector>,
+    colors: Vec<[f32; 4]>,
+    cells: Vec<Vec<Vector>>,
+    /// Turns the sphere into the view, where x points right, y up and z
+    /// towards the viewer. Rows are the view's axes.
+    rotation: [Vector; 3],
+    /// The part of each cell facing the viewer, in window coordinates.
+    shown: Vec<Vec<Point>>,
+    drag: Option<Drag>,
+}
+
+impl Globe {
+    pub fn new(sites: &[Vector]) -> Globe {
+        let mut globe = Globe {
+            sites: Vec::new(),
+            colors: Vec::new(),
+            cells: Vec::new(),
+            rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            shown: Vec::new(),
+            drag: None,
+        };
+        for &site in sites {
+            if !globe.has_site(site) {
+                globe.sites.push(site);
+                globe.colors.push(random_color());
+            }
+        }
+        globe.update();
+        globe
+    }
+
+    fn has_site(&self, site: Vector) -> bool {
+        self.sites.iter().any(|&s| dot(s, site) > SAME_SITE)
+    }
+
+    pub fn clear(&mut self) {
+        self.sites.clear();
+        self.colors.clear();
+        self.update();
+    }
+
+    /// Adds a site at the point of the globe under `p`, if there is one.
+    pub fn add(&mut self, p: [f64; 2]) {
+        let Some(site) = self.at(p) else { return };
+        if !self.has_site(site) {
+            self.sites.push(site);
+            self.colors.push(random_color());
+            self.update();
+        }
+    }
+
+    pub fn press(&mut self, p: [f64; 2]) {
+        self.drag = Some(Drag { last: p, moved: false });
+    }
+
+    /// Turns the globe along with the mouse once it moved far enough from
+    /// where the button went down.
+    pub fn drag(&mut self, p: [f64; 2]) {
+        let Some(drag) = self.drag.as_mut() else { return };
+        let (dx, dy) = (p[0] - drag.last[0], p[1] - drag.last[1]);
+        if !drag.moved && dx.hypot(dy) < DRAG_DISTANCE {
+            return;
+        }
+        drag.moved = true;
+        drag.last = p;
+        let (_, radius) = disk();
+        // Right turns about the view's y axis, down about its x axis, so
+        // the front follows the mouse
+        let (sin, cos) = (dx / radius).sin_cos();
+        let around_y = [[cos, 0.0, sin], [0.0, 1.0, 0.0], [-sin, 0.0, cos]];
+        let (sin, cos) = (dy / radius).sin_cos();
+        let around_x = [[1.0, 0.0, 0.0], [0.0, cos, -sin], [0.0, sin, cos]];
+        self.rotation = multiply(&around_x, &multiply(&around_y, &self.rotation));
+        self.show();
+    }
+
+    /// Ends a drag. A press that never moved is a click, which adds a site.
+    pub fn release(&mut self, p: [f64; 2]) {
+        if self.drag.take().is_some_and(|drag| !drag.moved) {
+            self.add(p);
+        }
+    }
+
+    fn update(&mut self) {
+        self.cells = spherical_cells(&self.sites);
+        self.show();
+    }
+
+    /// Projects the cells as the globe is turned now.
+    fn show(&mut self) {
+        let toward_viewer = self.rotation[2];
+        let nearest = (0..self.sites.len()).max_by(|&a, &b| dot(self.sites[a], toward_viewer).total_cmp(&dot(self.sites[b], toward_viewer)));
+        self.shown = self.cells.iter().enumerate().map(|(i, cell)| {
+            let arcs = arcs(cell).into_iter().map(|v| self.turn(v)).collect::<Vec<_>>();
+            let front = front_part(&arcs);
+            // A cell whose edges are all on the back, or which has none,
+            // either covers the whole front or none of it
+            if front.is_empty() && nearest == Some(i) {
+                limb(0.0, std::f64::consts::TAU)
+            } else {
+                front
+            }.into_iter().map(to_window).collect()
+        }).collect();
+    }
+
+    fn turn(&self, v: Vector) -> Vector {
+        self.rotation.map(|row| dot(row, v))
+    }
+
+    /// The point of the sphere under `p` in the window, if the globe is there.
+    fn at(&self, p: [f64; 2]) -> Option<Vector> {
+        let (center, radius) = disk();
+        let (x, y) = ((p[0] - center[0]) / radius, (center[1] - p[1]) / radius);
+        let z2 = 1.0 - x * x - y * y;
+        if z2 <= 0.0 {
+            return None;
+        }
+        // The rotation's inverse is its transpose
+        let view = [x, y, z2.sqrt()];
+        let [r0, r1, r2] = self.rotation;
+        Some(normalize([0, 1, 2].map(|i| r0[i] * view[0] + r1[i] * view[1] + r2[i] * view[2])))
+    }
+
+    pub fn draw<G: Graphics>(&self, lines_only: bool, c: &Context, g: &mut G) {
+        if lines_only {
+            for cell in &self.shown {
+                draw_outline(cell, c, g, [0.0, 0.0, 1.0, 1.0], 2.0);
+            }
+        } else {
+            draw_cells(&self.shown, &self.colors, 1.0, c, g);
+        }
+        let (center, radius) = disk();
+        draw_circle(&center, radius, c, g, [0.0, 0.0, 0.0, 0.5]);
+        let dots = self.sites.iter()
+            .map(|&site| self.turn(site))
+            .filter(|v| v[2] > 0.0)
+            .map(|v| { let (x, y) = to_window(v); [x, y] })
+            .collect::<Vec<_>>();
+        draw_dots(&dots, c, g, [0.0, 0.0, 0.0, 1.0]);
+    }
+}
+
+/// Reads sites from a CSV or JSON file of latitude and longitude pairs in
+/// degrees, see `points::read_dots`.
+pub fn read_lat_lon(path: &Path) -> io::Result<Vec<Vector>> {
+    let pairs = read_dots(path)?;
+    if let Some([lat, lon]) = pairs.iter().find(|[lat, lon]| lat.abs() > 90.0 || !lon.is_finite()) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{},{} is no latitude and longitude", lat, lon)));
+    }
+    Ok(pairs.iter().map(|&[lat, lon]| from_lat_lon(lat, lon)).collect())
+}
+
+/// The center and radius of the globe in the window.
+fn disk() -> ([f64; 2], f64) {
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    ([width / 2.0, height / 2.0], width.min(height) / 2.0 - MARGIN)
+}
+
+fn to_window(v: Vector) -> Point {
+    let (center, radius) = disk();
+    (center[0] + radius * v[0], center[1] - radius * v[1])
+}
+
+fn multiply(a: &[Vector; 3], b: &[Vector; 3]) -> [Vector; 3] {
+    a.map(|row| [0, 1, 2].map(|j| row[0] * b[0][j] + row[1] * b[1][j] + row[2] * b[2][j]))
+}
+
+/// The corners of a cell with points in between along the arcs joining
+/// them, close enough to look round.
+fn arcs(cell: &[Vector]) -> Vec<Vector> {
+    let mut points = Vec::new();
+    for (&a, &b) in cell.iter().zip(cell.iter().cycle().skip(1)) {
+        let angle = dot(a, b).clamp(-1.0, 1.0).acos();
+        let pieces = (angle / MAX_ARC).ceil().max(1.0) as usize;
+        for i in 0..pieces {
+            let t = i as f64 / pieces as f64;
+            points.push(normalize([0, 1, 2].map(|k| a[k] * (1.0 - t) + b[k] * t)));
+        }
+    }
+    points
+}
+
+/// The part of a turned cell with `z >= 0`, facing the viewer. Where it
+/// passes behind the globe, the rim of the globe closes it.
+fn front_part(cell: &[Vector]) -> Vec<Vector> {
+    // A convex cell crosses the rim at most once each way
+    let mut front = Vec::new();
+    let (mut leaving, mut entering) = (None, None);
+    for (i, &current) in cell.iter().enumerate() {
+        let previous = cell[(i + cell.len() - 1) % cell.len()];
+        if (previous[2] >= 0.0) != (current[2] >= 0.0) {
+            let t = previous[2] / (previous[2] - current[2]);
+            let crossing = [0, 1].map(|k| previous[k] + t * (current[k] - previous[k]));
+            let on_rim = normalize([crossing[0], crossing[1], 0.0]);
+            if current[2] < 0.0 {
+                leaving = Some(front.len());
+            } else {
+                entering = Some(on_rim);
+            }
+            front.push(on_rim);
+        }
+        if current[2] >= 0.0 {
+            front.push(current);
+        }
+    }
+    let (Some(leaving), Some(entering)) = (leaving, entering) else { return front };
+    // Counter-clockwise along the rim from where the cell leaves the front
+    // to where it comes back
+    let angle = |v: Vector| v[1].atan2(v[0]);
+    let from = angle(front[leaving]);
+    let gap = (angle(entering) - from).rem_euclid(std::f64::consts::TAU);
+    let rim = limb(from, gap);
+    front.splice(leaving + 1..leaving + 1, rim);
+    front
+}
+
+/// Points along the rim counter-clockwise from angle `from`, `span` radians
+/// long, without either end.
+fn limb(from: f64, span: f64) -> Vec<Vector> {
+    let pieces = (span / MAX_ARC).ceil() as usize;
+    (1..pieces).map(|i| {
+        let angle = from + span * i as f64 / pieces as f64;
+        [angle.cos(), angle.sin(), 0.0]
+    }).collect()
+}
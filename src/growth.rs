@@ -0,0 +1,98 @@
+//! Crystal growth: the region of every site floods outward from it at a
+//! speed of its own, and each point of the window goes to the region that
+//! reaches it first. Once all have run into each other, the regions are the
+//! multiplicatively weighted Voronoi diagram of the sites, whose edges are
+//! arcs of circles wherever the speeds on both sides differ.
+
+use crate::diagram::{random, Point};
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// Side of the squares of the grid the regions are made of, in pixels.
+const SQUARE: f64 = 2.0;
+/// The speeds of the sites are spread this many times below and above the
+/// average.
+const SPEED_SPREAD: f64 = 2.0;
+
+pub struct Growth {
+    /// The revision of the diagram whose dots are growing.
+    pub revision: u64,
+    /// How fast the region of each site grows, as a multiple of `speed`.
+    speeds: Vec<f64>,
+    columns: usize,
+    /// The seconds until each square of the grid is reached, row by row.
+    arrival: Vec<f64>,
+    /// The site that reaches each square first.
+    owner: Vec<usize>,
+    elapsed: f64,
+    /// Average pixels per second.
+    speed: f64,
+}
+
+impl Growth {
+    pub fn new(dots: &[[f64; 2]], revision: u64, speed: f64) -> Growth {
+        let columns = (DEFAULT_WINDOW_WIDTH as f64 / SQUARE).ceil() as usize;
+        let mut growth = Growth { revision, speeds: Vec::new(), columns, arrival: Vec::new(), owner: Vec::new(), elapsed: 0.0, speed };
+        growth.lay_out(dots, revision);
+        growth
+    }
+
+    /// Works out the regions again for changed dots, without starting over.
+    /// Dots that are new get a speed of their own, the others keep theirs.
+    pub fn lay_out(&mut self, dots: &[[f64; 2]], revision: u64) {
+        self.revision = revision;
+        while self.speeds.len() < dots.len() {
+            self.speeds.push(SPEED_SPREAD.powf(2.0 * random::<f64>() - 1.0));
+        }
+        let rows = (DEFAULT_WINDOW_HEIGHT as f64 / SQUARE).ceil() as usize;
+        self.arrival = vec![f64::INFINITY; self.columns * rows];
+        self.owner = vec![0; self.columns * rows];
+        for row in 0..rows {
+            for column in 0..self.columns {
+                let (x, y) = ((column as f64 + 0.5) * SQUARE, (row as f64 + 0.5) * SQUARE);
+                let square = row * self.columns + column;
+                for (i, d) in dots.iter().enumerate() {
+                    let seconds = (d[0] - x).hypot(d[1] - y) / (self.speeds[i] * self.speed);
+                    if seconds < self.arrival[square] {
+                        self.arrival[square] = seconds;
+                        self.owner[square] = i;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn advance(&mut self, seconds: f64) {
+        self.elapsed += seconds;
+    }
+
+    /// Whether every square has been reached, or there are no sites to
+    /// reach any.
+    pub fn done(&self) -> bool {
+        self.arrival.iter().all(|&a| a <= self.elapsed || a.is_infinite())
+    }
+
+    /// The parts of the regions grown so far, as rectangles of squares in a
+    /// row that belong to the same site, each with the color of its site.
+    pub fn regions(&self, colors: &[[f32; 4]]) -> (Vec<Vec<Point>>, Vec<[f32; 4]>) {
+        let (mut rectangles, mut rectangle_colors) = (Vec::new(), Vec::new());
+        for (row, squares) in self.arrival.chunks(self.columns).enumerate() {
+            let owners = &self.owner[row * self.columns..(row + 1) * self.columns];
+            let mut column = 0;
+            while column < self.columns {
+                if squares[column] > self.elapsed {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < self.columns && squares[column] <= self.elapsed && owners[column] == owners[start] {
+                    column += 1;
+                }
+                let (top, bottom) = (row as f64 * SQUARE, (row + 1) as f64 * SQUARE);
+                let (left, right) = (start as f64 * SQUARE, column as f64 * SQUARE);
+                rectangles.push(vec![(left, top), (right, top), (right, bottom), (left, bottom)]);
+                rectangle_colors.push(colors.get(owners[start]).copied().unwrap_or([0.0, 0.0, 0.0, 1.0]));
+            }
+        }
+        (rectangles, rectangle_colors)
+    }
+}
@@ -0,0 +1,46 @@
+//! Going back to how a layer was before a change, with `Ctrl+Z`.
+
+use crate::metadata::Metadata;
+use crate::model::Layer;
+
+/// Changes remembered at most, the oldest are forgotten first.
+const MAX_UNDO: usize = 50;
+
+/// The dots of a layer before a change.
+struct Saved {
+    layer: usize,
+    dots: Vec<[f64; 2]>,
+    colors: Vec<[f32; 4]>,
+    teams: Vec<Option<usize>>,
+    metadata: Vec<Metadata>,
+    ids: Vec<u64>,
+    weights: Vec<f64>,
+}
+
+#[derive(Default)]
+pub struct History {
+    saved: Vec<Saved>,
+}
+
+impl History {
+    /// Remembers the dots of layer `index` as they are, call right before changing them.
+    pub fn remember(&mut self, layers: &[Layer], index: usize) {
+        let d = &layers[index].diagram;
+        self.saved.push(Saved { layer: index, dots: d.dots.clone(), colors: d.colors.clone(), teams: d.teams.clone(), metadata: d.metadata.clone(), ids: d.ids.clone(), weights: d.weights.clone() });
+        if self.saved.len() > MAX_UNDO {
+            self.saved.remove(0);
+        }
+    }
+
+    /// Puts the last remembered layer back the way it was. Returns the
+    /// index of that layer, `None` if there was nothing to undo.
+    pub fn undo(&mut self, layers: &mut [Layer]) -> Option<usize> {
+        let saved = self.saved.pop()?;
+        let layer = layers.get_mut(saved.layer)?;
+        layer.diagram.metadata = saved.metadata;
+        layer.diagram.ids = saved.ids;
+        layer.diagram.restore(saved.dots, saved.colors, saved.teams);
+        layer.diagram.set_weights(saved.weights);
+        Some(saved.layer)
+    }
+}
@@ -0,0 +1,103 @@
+//! A tiny read-only HTTP server with the live diagram, for dashboards and
+//! notebooks to pull:
+//!
+//! * `/diagram.svg`, the diagram as drawn, as `E` would export it
+//! * `/points.json`, the dots of the active layer, `[[x, y], ...]`, as `-j`
+//!   reads them, or objects with their metadata if they have some
+//! * `/cells.geojson`, the cells as GeoJSON features, see `export`
+//!
+//! They are rendered whenever the diagram changes, so requests don't wait
+//! for the app.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use interactive_voronoi::diagram::Diagram;
+use interactive_voronoi::export::{render, ExportOptions};
+use interactive_voronoi::metadata::dots_json;
+
+/// The diagram in every format served.
+#[derive(Clone, Default)]
+pub struct Documents {
+    svg: Vec<u8>,
+    points: String,
+    cells: Vec<u8>,
+}
+
+impl Documents {
+    pub fn new(diagram: &Diagram, options: &ExportOptions) -> Documents {
+        Documents {
+            svg: render("svg", diagram, options).unwrap_or_default(),
+            points: dots_json(&diagram.dots, &diagram.metadata).to_string(),
+            cells: render("geojson", diagram, options).unwrap_or_default(),
+        }
+    }
+}
+
+/// Serves the latest documents arriving on `documents` on the TCP port in
+/// the background.
+pub fn serve(port: u16, documents: Receiver<Documents>) -> io::Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Serving the diagram over HTTP on port {}", port);
+    let latest = Arc::new(Mutex::new(Documents::default()));
+
+    let update = latest.clone();
+    thread::spawn(move || {
+        for documents in documents {
+            *update.lock().expect("HTTP documents poisoned") = documents;
+        }
+    });
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let latest = latest.clone();
+            thread::spawn(move || {
+                if let Err(err) = handle_request(stream, &latest) {
+                    println!("HTTP request failed: {}", err);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn handle_request(mut stream: TcpStream, latest: &Mutex<Documents>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers don't matter, but have to be read before answering
+    let mut header = String::new();
+    for _ in 0..100 {
+        header.clear();
+        if reader.read_line(&mut header)? <= 2 {
+            break;
+        }
+    }
+
+    let mut words = request_line.split_whitespace();
+    let (method, target) = (words.next().unwrap_or_default(), words.next().unwrap_or_default());
+    let path = target.split('?').next().unwrap_or_default();
+    if method != "GET" && method != "HEAD" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"Only GET and HEAD\n", false);
+    }
+    let documents = latest.lock().expect("HTTP documents poisoned").clone();
+    match path {
+        "/diagram.svg" => respond(&mut stream, "200 OK", "image/svg+xml", &documents.svg, method == "HEAD"),
+        "/points.json" => respond(&mut stream, "200 OK", "application/json", documents.points.as_bytes(), method == "HEAD"),
+        "/cells.geojson" => respond(&mut stream, "200 OK", "application/geo+json", &documents.cells, method == "HEAD"),
+        _ => respond(&mut stream, "404 Not Found", "text/plain", b"Try /diagram.svg, /points.json or /cells.geojson\n", method == "HEAD")
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8], head_only: bool) -> io::Result<()> {
+    // Pages on other origins, like notebooks, may fetch the diagram too
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status, content_type, body.len())?;
+    if !head_only {
+        stream.write_all(body)?;
+    }
+    stream.flush()
+}
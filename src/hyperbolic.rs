@@ -0,0 +1,119 @@
+//! Voronoi diagrams of the hyperbolic plane, shown in the Poincaré disk
+//! model: the whole plane fits into a disk, distances grow without bound
+//! towards its rim, and straight lines are arcs meeting the rim at right
+//! angles. The cells are computed in the Klein model of the same plane,
+//! where straight lines are straight chords, so the edges between cells are
+//! too, and only then bent into the Poincaré disk for drawing.
+
+use std::f64::consts::TAU;
+
+use crate::diagram::Point;
+use crate::geometry::clip_to_half_plane;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// Space between the disk and the top and bottom of the window.
+const MARGIN: f64 = 20.0;
+/// Corners of the polygon standing in for the rim, enough to keep within a
+/// pixel of the circle.
+const RIM_CORNERS: usize = 1024;
+/// The longest straight piece, in pixels, an arc is drawn with.
+const MAX_SEGMENT: f64 = 3.0;
+
+/// The center and radius of the disk in the window.
+pub fn disk() -> ([f64; 2], f64) {
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    ([width / 2.0, height / 2.0], width.min(height) / 2.0 - MARGIN)
+}
+
+pub fn in_disk(p: [f64; 2]) -> bool {
+    norm2(to_unit(p)) < 1.0
+}
+
+/// The hyperbolic distance between two points of the window, infinite if
+/// either is outside the disk.
+pub fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    let (a, b) = (to_unit(a), to_unit(b));
+    if norm2(a) >= 1.0 || norm2(b) >= 1.0 {
+        return f64::INFINITY;
+    }
+    let d = (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2);
+    (1.0 + 2.0 * d / ((1.0 - norm2(a)) * (1.0 - norm2(b)))).acosh()
+}
+
+/// The hyperbolic Voronoi cell of each dot, in window coordinates, with its
+/// edges drawn as the arcs they are in the Poincaré disk. Dots outside the
+/// disk have none.
+pub fn hyperbolic_cells(dots: &[[f64; 2]]) -> Vec<Vec<Point>> {
+    let sites = dots.iter().map(|&d| in_disk(d).then(|| poincare_to_klein(to_unit(d)))).collect::<Vec<_>>();
+    let rim = (0..RIM_CORNERS)
+        .map(|i| {
+            let angle = i as f64 * TAU / RIM_CORNERS as f64;
+            (angle.cos(), angle.sin())
+        })
+        .collect::<Vec<_>>();
+    sites.iter().enumerate().map(|(i, site)| {
+        let Some(p) = *site else { return Vec::new() };
+        let mut others = sites.iter().enumerate()
+            .filter(|&(j, _)| j != i)
+            .filter_map(|(_, site)| *site)
+            .collect::<Vec<_>>();
+        // The nearest sites cut the most off, which leaves fewer corners
+        // for the others to cut
+        let away = |q: &Point| (q.0 - p.0).powi(2) + (q.1 - p.1).powi(2);
+        others.sort_unstable_by(|q, r| away(q).total_cmp(&away(r)));
+
+        let a = (1.0 - norm2(p)).sqrt();
+        let mut cell = rim.clone();
+        for q in others {
+            if cell.is_empty() {
+                break;
+            }
+            // A point x is closer to p than to q where
+            // (1 - p·x) / a < (1 - q·x) / b, which is linear in x
+            let b = (1.0 - norm2(q)).sqrt();
+            cell = clip_to_half_plane(&cell, (q.0 / b - p.0 / a, q.1 / b - p.1 / a), 1.0 / b - 1.0 / a);
+        }
+        bend(&cell)
+    }).collect()
+}
+
+/// A polygon of the Klein disk in the window, every edge an arc of the
+/// Poincaré disk made of enough pieces to look round.
+fn bend(poly: &[Point]) -> Vec<Point> {
+    let mut bent = Vec::new();
+    for (&a, &b) in poly.iter().zip(poly.iter().cycle().skip(1)) {
+        let (start, end) = (from_unit(klein_to_poincare(a)), from_unit(klein_to_poincare(b)));
+        let pieces = ((end.0 - start.0).hypot(end.1 - start.1) / MAX_SEGMENT).ceil().max(1.0) as usize;
+        bent.push(start);
+        for i in 1..pieces {
+            let t = i as f64 / pieces as f64;
+            bent.push(from_unit(klein_to_poincare((a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)))));
+        }
+    }
+    bent
+}
+
+fn norm2(p: Point) -> f64 {
+    p.0 * p.0 + p.1 * p.1
+}
+
+/// A point of the window in the Poincaré disk as the unit disk.
+fn to_unit(p: [f64; 2]) -> Point {
+    let (center, radius) = disk();
+    ((p[0] - center[0]) / radius, (p[1] - center[1]) / radius)
+}
+
+fn from_unit(z: Point) -> Point {
+    let (center, radius) = disk();
+    (center[0] + radius * z.0, center[1] + radius * z.1)
+}
+
+fn poincare_to_klein(z: Point) -> Point {
+    let scale = 2.0 / (1.0 + norm2(z));
+    (scale * z.0, scale * z.1)
+}
+
+fn klein_to_poincare(k: Point) -> Point {
+    let scale = 1.0 / (1.0 + (1.0 - norm2(k)).max(0.0).sqrt());
+    (scale * k.0, scale * k.1)
+}
@@ -0,0 +1,106 @@
+//! Adding a dot the way Bowyer–Watson does, one step at a time: the
+//! triangles whose circumcircles contain the new dot are found by walking
+//! out from the one it lies in, taken out, and the hole they leave is
+//! filled with triangles from the dot to its border.
+
+use graphics::{Context, Graphics};
+
+use crate::delaunay::Cavity;
+use crate::diagram::Point;
+use crate::draw::{draw_circle, draw_ellipse, draw_outline, draw_polygon};
+
+const BAD: [f32; 4] = [0.9, 0.1, 0.1, 1.0];
+const GOOD: [f32; 4] = [0.1, 0.6, 0.1, 1.0];
+
+pub struct Insertion {
+    pub dot: [f64; 2],
+    cavity: Cavity,
+    /// How many steps have been shown: first one per circumcircle test,
+    /// then the hole, then the triangles filling it.
+    step: usize,
+    /// Seconds until the next step.
+    due: f64,
+}
+
+impl Insertion {
+    pub fn new(dot: [f64; 2], cavity: Cavity, delay: f64) -> Insertion {
+        Insertion { dot, cavity, step: 1, due: delay }
+    }
+
+    fn steps(&self) -> usize {
+        self.cavity.tests.len() + 2
+    }
+
+    /// Moves on a step every `delay` seconds. Returns whether the last one
+    /// has been shown long enough for the dot to be added for real.
+    pub fn advance(&mut self, seconds: f64, delay: f64) -> bool {
+        self.due -= seconds;
+        while self.due <= 0.0 {
+            if self.step == self.steps() {
+                return true;
+            }
+            self.step += 1;
+            self.due += delay;
+        }
+        false
+    }
+
+    pub fn draw<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let tests = &self.cavity.tests;
+        let corners = |t: &[[f64; 2]; 3]| t.iter().map(|p| (p[0], p[1])).collect::<Vec<Point>>();
+        if self.step <= tests.len() {
+            for (triangle, inside) in &tests[..self.step] {
+                if *inside {
+                    draw_polygon(&corners(triangle), c, g, [BAD[0], BAD[1], BAD[2], 0.3]);
+                } else {
+                    draw_outline(&corners(triangle), c, g, GOOD, 2.0);
+                }
+            }
+            let (triangle, inside) = &tests[self.step - 1];
+            if let Some((center, radius)) = circumcircle(triangle) {
+                draw_circle(&center, radius, c, g, if *inside { BAD } else { GOOD });
+            }
+        } else {
+            // The triangles taken out leave a hole
+            for (triangle, _) in tests.iter().filter(|(_, inside)| *inside) {
+                draw_polygon(&corners(triangle), c, g, [1.0, 1.0, 1.0, 0.85]);
+            }
+            let [x, y] = self.dot;
+            for &[a, b] in &self.cavity.border {
+                graphics::line(BAD, 2.0, [a[0], a[1], b[0], b[1]], c.transform, g);
+                if self.step == self.steps() {
+                    for p in [a, b] {
+                        graphics::line(GOOD, 1.0, [x, y, p[0], p[1]], c.transform, g);
+                    }
+                }
+            }
+        }
+        draw_ellipse(&self.dot, c, g, BAD);
+    }
+
+    /// What's going on, for the status panel.
+    pub fn describe(&self) -> String {
+        let tests = &self.cavity.tests;
+        if self.step <= tests.len() {
+            let inside = if tests[self.step - 1].1 { "inside, taken out" } else { "outside, kept" };
+            format!("Circumcircle {} of {}: dot {}", self.step, tests.len(), inside)
+        } else if self.step < self.steps() {
+            format!("{} triangles taken out, leaving a hole", tests.iter().filter(|(_, inside)| *inside).count())
+        } else {
+            format!("Hole filled with {} new triangles", self.cavity.border.len())
+        }
+    }
+}
+
+fn circumcircle([a, b, c]: &[[f64; 2]; 3]) -> Option<([f64; 2], f64)> {
+    let d = 2.0 * (a[0] * (b[1] - c[1]) + b[0] * (c[1] - a[1]) + c[0] * (a[1] - b[1]));
+    if d == 0.0 {
+        return None;
+    }
+    let (a2, b2, c2) = (a[0] * a[0] + a[1] * a[1], b[0] * b[0] + b[1] * b[1], c[0] * c[0] + c[1] * c[1]);
+    let center = [
+        (a2 * (b[1] - c[1]) + b2 * (c[1] - a[1]) + c2 * (a[1] - b[1])) / d,
+        (a2 * (c[0] - b[0]) + b2 * (a[0] - c[0]) + c2 * (b[0] - a[0])) / d,
+    ];
+    Some((center, (center[0] - a[0]).hypot(center[1] - a[1])))
+}
@@ -0,0 +1,325 @@
+//! An append-only log of every change to the dots, so a session that
+//! crashed or was closed by accident can be restored with `--recover`.
+//!
+//! Each line is a JSON object naming the layer and what happened to it:
+//!
+//! ```text
+//! {"layer":0,"op":"add","dot":[10.0,20.0],"color":[1.0,0.0,0.0,1.0],"team":null}
+//! {"layer":0,"op":"move","index":3,"dot":[10.0,20.0]}
+//! {"layer":0,"op":"remove","index":3}
+//! {"layer":0,"op":"weight","index":3,"weight":400.0}
+//! {"layer":0,"op":"clear"}
+//! {"layer":0,"op":"set","dots":[...],"colors":[...],"teams":[...],"metadata":[...]}
+//! ```
+//!
+//! Changes that aren't a single add, move or remove, like randomizing or
+//! relaxing, are written as a `set` of the whole layer. Its `metadata` holds
+//! an object for each dot, as `metadata::Metadata::to_json` writes it, and
+//! is left out when no dot has any, and so are its `weights`, the power
+//! diagram weight of each dot, when all are zero.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::diagram::{Diagram, Duplicates};
+use crate::metadata::{self, Metadata};
+use crate::model::Layer;
+
+/// What the journal last wrote for a layer, to tell what changed since.
+struct Snapshot {
+    revision: u64,
+    dots: Vec<[f64; 2]>,
+    colors: Vec<[f32; 4]>,
+    teams: Vec<Option<usize>>,
+    metadata: Vec<Metadata>,
+    weights: Vec<f64>,
+}
+
+impl Snapshot {
+    fn of(layer: &Layer) -> Snapshot {
+        let d = &layer.diagram;
+        Snapshot { revision: d.revision(), dots: d.dots.clone(), colors: d.colors.clone(), teams: d.teams.clone(), metadata: d.metadata.clone(), weights: d.weights.clone() }
+    }
+}
+
+pub struct Journal {
+    file: File,
+    snapshots: Vec<Snapshot>,
+}
+
+impl Journal {
+    /// Starts a fresh journal. Whatever the last session left is kept as
+    /// `PATH.1`, so a relaunch after a crash doesn't lose it, unless that
+    /// session never wrote anything.
+    pub fn create(path: &str, layers: &[Layer]) -> io::Result<Journal> {
+        if fs::metadata(path).is_ok_and(|m| m.len() > 0) {
+            fs::rename(path, format!("{}.1", path))?;
+        }
+        let mut journal = Journal { file: File::create(path)?, snapshots: Vec::new() };
+        journal.record(layers)?;
+        Ok(journal)
+    }
+
+    /// Continues the journal of a recovered session, which `layers` must
+    /// have been read from.
+    pub fn resume(path: &str, layers: &[Layer]) -> io::Result<Journal> {
+        let file = OpenOptions::new().append(true).open(path)?;
+        Ok(Journal { file, snapshots: layers.iter().map(Snapshot::of).collect() })
+    }
+
+    /// Writes down how the layers changed since the last call.
+    pub fn record(&mut self, layers: &[Layer]) -> io::Result<()> {
+        for (index, layer) in layers.iter().enumerate() {
+            if self.snapshots.get(index).is_some_and(|s| s.revision == layer.diagram.revision()) {
+                continue;
+            }
+            let current = Snapshot::of(layer);
+            let entry = match self.snapshots.get(index) {
+                Some(last) => change(last, &current),
+                None if current.dots.is_empty() => None,
+                None => Some(set(&current))
+            };
+            if let Some(mut entry) = entry {
+                entry["layer"] = json!(index);
+                writeln!(self.file, "{}", entry)?;
+            }
+            if index < self.snapshots.len() {
+                self.snapshots[index] = current;
+            } else {
+                self.snapshots.push(current);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the layers as a journal of their own, one `set` per layer, which
+/// `recover` reads like any other.
+pub fn write_layers<W: Write>(w: &mut W, layers: &[Layer]) -> io::Result<()> {
+    for (index, layer) in layers.iter().enumerate() {
+        let mut entry = set(&Snapshot::of(layer));
+        entry["layer"] = json!(index);
+        writeln!(w, "{}", entry)?;
+    }
+    Ok(())
+}
+
+/// The entry that turns `last` into `current`, if anything changed.
+fn change(last: &Snapshot, current: &Snapshot) -> Option<Value> {
+    let same_weight = |i: usize, j: usize| last.weights.get(i) == current.weights.get(j);
+    let same = |i: usize, j: usize| {
+        last.dots[i] == current.dots[j] && last.colors[i] == current.colors[j] && last.teams[i] == current.teams[j]
+            && last.metadata[i] == current.metadata[j] && same_weight(i, j)
+    };
+    let (old, new) = (last.dots.len(), current.dots.len());
+
+    if new == 0 {
+        return (old > 0).then(|| json!({"op": "clear"}));
+    }
+    if new == old + 1 && (0..old).all(|i| same(i, i)) && current.metadata[old].is_empty() && current.weights.get(old) == Some(&0.0) {
+        return Some(json!({
+            "op": "add",
+            "dot": current.dots[old],
+            "color": current.colors[old],
+            "team": current.teams[old],
+        }));
+    }
+    if new + 1 == old {
+        let removed = (0..new).find(|&i| !same(i, i)).unwrap_or(new);
+        if (removed..new).all(|i| same(i + 1, i)) {
+            return Some(json!({"op": "remove", "index": removed}));
+        }
+    }
+    if new == old {
+        let changed = (0..new).filter(|&i| !same(i, i)).collect::<Vec<_>>();
+        match changed[..] {
+            [] => return None,
+            [i] if last.colors[i] == current.colors[i] && last.teams[i] == current.teams[i] && last.metadata[i] == current.metadata[i] => {
+                if !same_weight(i, i) {
+                    if last.dots[i] == current.dots[i] {
+                        return Some(json!({"op": "weight", "index": i, "weight": current.weights[i]}));
+                    }
+                } else {
+                    return Some(json!({"op": "move", "index": i, "dot": current.dots[i]}));
+                }
+            }
+            _ => ()
+        }
+    }
+    Some(set(current))
+}
+
+fn set(snapshot: &Snapshot) -> Value {
+    let mut entry = json!({"op": "set", "dots": snapshot.dots, "colors": snapshot.colors, "teams": snapshot.teams});
+    if metadata::any(&snapshot.metadata) {
+        entry["metadata"] = snapshot.metadata.iter().map(|m| Value::Object(m.to_json())).collect();
+    }
+    if snapshot.weights.iter().any(|&w| w != 0.0) {
+        entry["weights"] = json!(snapshot.weights);
+    }
+    entry
+}
+
+/// Replays a journal into the layers it describes, treating coinciding
+/// dots as the session did. A damaged last line, as a crash in the middle of
+/// writing would leave, is skipped.
+pub fn recover(path: &str, duplicates: Duplicates) -> io::Result<Vec<Layer>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut layers = vec![Layer::new(0, duplicates)];
+    let lines = text.lines().filter(|line| !line.trim().is_empty()).collect::<Vec<_>>();
+
+    for (number, line) in lines.iter().enumerate() {
+        let bad_line = |what: &str| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Journal line {}: {}", number + 1, what));
+        let entry = match serde_json::from_str::<Value>(line) {
+            Ok(entry) => entry,
+            Err(_) if number + 1 == lines.len() => {
+                println!("Skipped the unfinished last entry of {}", path);
+                break;
+            }
+            Err(err) => return Err(bad_line(&err.to_string()))
+        };
+        let index: usize = get(&entry, "layer").map_err(|e| bad_line(&e))?;
+        while layers.len() <= index {
+            layers.push(Layer::new(layers.len(), duplicates));
+        }
+        apply(&mut layers[index].diagram, &entry).map_err(|e| bad_line(&e))?;
+    }
+    Ok(layers)
+}
+
+fn apply(diagram: &mut Diagram, entry: &Value) -> Result<(), String> {
+    match get::<String>(entry, "op")?.as_str() {
+        "add" => { diagram.push_dot(get(entry, "dot")?, get(entry, "color")?, get(entry, "team")?); },
+        "move" => { diagram.move_dot(get(entry, "index")?, get(entry, "dot")?); },
+        "remove" => { diagram.remove_dot(get(entry, "index")?); },
+        "weight" => { diagram.set_weight(get(entry, "index")?, get(entry, "weight")?); },
+        "clear" => diagram.clear(),
+        "set" => {
+            diagram.metadata = match entry.get("metadata") {
+                Some(Value::Array(objects)) => objects.iter()
+                    .map(|o| o.as_object().map(Metadata::from_json).unwrap_or_default())
+                    .collect(),
+                Some(_) => return Err("bad metadata: not an array".to_string()),
+                None => Vec::new()
+            };
+            diagram.ids.clear();
+            diagram.restore(get(entry, "dots")?, get(entry, "colors")?, get(entry, "teams")?);
+            if entry.get("weights").is_some() {
+                diagram.set_weights(get(entry, "weights")?);
+            }
+        },
+        op => return Err(format!("unknown op {}", op))
+    }
+    Ok(())
+}
+
+fn get<T: DeserializeOwned>(entry: &Value, key: &str) -> Result<T, String> {
+    let value = entry.get(key).ok_or_else(|| format!("missing {}", key))?;
+    T::deserialize(value).map_err(|err| format!("bad {}: {}", key, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+    /// A journal file of its own for each test, gone before it starts.
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("voronoi-journal-test-{}-{}", std::process::id(), name));
+        let path = path.to_string_lossy().into_owned();
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(format!("{}.1", path));
+        path
+    }
+
+    fn ops(path: &str) -> Vec<String> {
+        fs::read_to_string(path).unwrap().lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["op"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn assert_same(recovered: &[Layer], layers: &[Layer]) {
+        assert_eq!(recovered.len(), layers.len());
+        for (r, l) in recovered.iter().zip(layers) {
+            assert_eq!(r.diagram.dots, l.diagram.dots);
+            assert_eq!(r.diagram.colors, l.diagram.colors);
+            assert_eq!(r.diagram.teams, l.diagram.teams);
+            assert_eq!(r.diagram.weights, l.diagram.weights);
+            assert_eq!(r.diagram.metadata, l.diagram.metadata);
+        }
+    }
+
+    #[test]
+    fn replays_every_op() {
+        let path = temp_path("ops");
+        let mut layers = vec![Layer::new(0, Duplicates::default())];
+        let mut journal = Journal::create(&path, &layers).unwrap();
+        let mut step = |layers: &mut Vec<Layer>, change: &dyn Fn(&mut Diagram)| {
+            change(&mut layers[0].diagram);
+            journal.record(layers).unwrap();
+            assert_same(&recover(&path, Duplicates::default()).unwrap(), layers);
+        };
+
+        step(&mut layers, &|d| { d.push_dot([100.0, 100.0], RED, None); });
+        step(&mut layers, &|d| { d.push_dot([300.0, 200.0], BLUE, Some(1)); });
+        step(&mut layers, &|d| { d.push_dot([500.0, 400.0], RED, None); });
+        step(&mut layers, &|d| { d.move_dot(0, [150.0, 120.0]); });
+        step(&mut layers, &|d| { d.set_weight(1, 400.0); });
+        step(&mut layers, &|d| { d.remove_dot(2); });
+        step(&mut layers, &|d| d.clear());
+        step(&mut layers, &|d| {
+            d.metadata = vec![Metadata { label: Some("Oslo".to_string()), ..Metadata::default() }, Metadata::default()];
+            d.restore(vec![[10.0, 20.0], [600.0, 300.0]], vec![BLUE, RED], vec![None, Some(2)]);
+            d.set_weights(vec![0.0, 250.0]);
+        });
+        assert_eq!(ops(&path), ["add", "add", "add", "move", "weight", "remove", "clear", "set"]);
+    }
+
+    #[test]
+    fn skips_an_unfinished_last_line() {
+        let path = temp_path("truncated");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, r#"{{"layer":0,"op":"add","dot":[10.0,20.0],"color":[1.0,0.0,0.0,1.0],"team":null}}"#).unwrap();
+        write!(file, r#"{{"layer":0,"op":"add","dot":[30.0,"#).unwrap();
+        drop(file);
+        let layers = recover(&path, Duplicates::default()).unwrap();
+        assert_eq!(layers[0].diagram.dots, [[10.0, 20.0]]);
+    }
+
+    #[test]
+    fn damage_before_the_last_line_is_an_error() {
+        let path = temp_path("damaged");
+        fs::write(&path, "{\"layer\":0,\"op\":\"add\",\n{\"layer\":0,\"op\":\"clear\"}\n").unwrap();
+        assert!(recover(&path, Duplicates::default()).is_err());
+    }
+
+    #[test]
+    fn keeps_the_last_journal_aside() {
+        let path = temp_path("rotation");
+        let mut layers = vec![Layer::new(0, Duplicates::default())];
+        let mut journal = Journal::create(&path, &layers).unwrap();
+        layers[0].diagram.push_dot([100.0, 100.0], RED, None);
+        journal.record(&layers).unwrap();
+        drop(journal);
+        let crashed = fs::read_to_string(&path).unwrap();
+
+        // A new session keeps the old journal as PATH.1, and an untouched
+        // one leaves that be
+        let empty = vec![Layer::new(0, Duplicates::default())];
+        drop(Journal::create(&path, &empty).unwrap());
+        assert_eq!(fs::read_to_string(format!("{}.1", path)).unwrap(), crashed);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        drop(Journal::create(&path, &empty).unwrap());
+        assert_eq!(fs::read_to_string(format!("{}.1", path)).unwrap(), crashed);
+
+        let recovered = recover(&format!("{}.1", path), Duplicates::default()).unwrap();
+        assert_same(&recovered, &layers);
+    }
+}
@@ -0,0 +1,75 @@
+//! Moving the cursor with the arrow keys, so the app can be used without a
+//! pointing device. A tap moves the cursor a little for fine positioning,
+//! holding a key moves it faster and faster. Enter works like the mouse
+//! button.
+
+use piston_window::{Button, ButtonArgs, Event, Input, Key, MouseButton};
+
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// How far a single tap moves the cursor.
+const TAP_STEP: f64 = 2.0;
+const START_SPEED: f64 = 60.0;
+/// How much faster the cursor gets every second an arrow key is held.
+const ACCELERATION: f64 = 600.0;
+const MAX_SPEED: f64 = 900.0;
+
+/// The arrow keys held down and for how long.
+#[derive(Default)]
+pub struct KeyboardCursor {
+    /// Left, right, up and down.
+    held: [bool; 4],
+    held_for: f64,
+}
+
+impl KeyboardCursor {
+    /// Notes an arrow key going down or up. Returns the cursor moved by a
+    /// tap if it went down, or `None` if `key` isn't an arrow key.
+    pub fn arrow(&mut self, key: Key, down: bool, cursor: [f64; 2]) -> Option<[f64; 2]> {
+        let (index, direction) = match key {
+            Key::Left => (0, [-1.0, 0.0]),
+            Key::Right => (1, [1.0, 0.0]),
+            Key::Up => (2, [0.0, -1.0]),
+            Key::Down => (3, [0.0, 1.0]),
+            _ => return None
+        };
+        self.held[index] = down;
+        if !self.moving() {
+            self.held_for = 0.0;
+        }
+        let step = if down { TAP_STEP } else { 0.0 };
+        Some(clamp_to_window([cursor[0] + direction[0] * step, cursor[1] + direction[1] * step]))
+    }
+
+    pub fn moving(&self) -> bool {
+        self.held.iter().any(|&h| h)
+    }
+
+    /// Where the held keys take the cursor in `seconds`.
+    pub fn move_cursor(&mut self, cursor: [f64; 2], seconds: f64) -> [f64; 2] {
+        if !self.moving() {
+            return cursor;
+        }
+        self.held_for += seconds;
+        let speed = (START_SPEED + ACCELERATION * self.held_for).min(MAX_SPEED);
+        let axis = |minus: bool, plus: bool| (plus as i32 - minus as i32) as f64;
+        let [left, right, up, down] = self.held;
+        clamp_to_window([cursor[0] + axis(left, right) * speed * seconds, cursor[1] + axis(up, down) * speed * seconds])
+    }
+}
+
+/// The event with Enter turned into the left mouse button, or `None` for
+/// other events.
+pub fn as_mouse(e: &Event) -> Option<Event> {
+    match e {
+        Event::Input(Input::Button(args @ ButtonArgs { button: Button::Keyboard(Key::Return), .. }), timestamp) => {
+            let args = ButtonArgs { button: Button::Mouse(MouseButton::Left), ..*args };
+            Some(Event::Input(Input::Button(args), *timestamp))
+        }
+        _ => None
+    }
+}
+
+fn clamp_to_window(p: [f64; 2]) -> [f64; 2] {
+    [p[0].clamp(0.0, DEFAULT_WINDOW_WIDTH as f64), p[1].clamp(0.0, DEFAULT_WINDOW_HEIGHT as f64)]
+}
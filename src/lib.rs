@@ -0,0 +1,42 @@
+//! The diagram and everything needed to draw and export it, without a
+//! window. The app is built on top of this, and so are the golden image
+//! tests, which render diagrams with `export::render_image` and compare the
+//! pixels against reference images.
+
+pub mod adjacency;
+pub mod bounds;
+pub mod centers;
+pub mod color;
+pub mod coloring;
+pub mod delaunay;
+pub mod density;
+pub mod diagram;
+pub mod document;
+pub mod draw;
+pub mod export;
+pub mod field;
+pub mod fit;
+pub mod fortune;
+pub mod geometry;
+pub mod hyperbolic;
+pub mod lowpoly;
+pub mod metadata;
+pub mod pathfinding;
+pub mod plugin;
+pub mod power;
+pub mod predicates;
+pub mod raster;
+pub mod refine;
+pub mod proximity;
+pub mod regions;
+pub mod spatial;
+pub mod sphere;
+pub mod stats;
+pub mod stipple;
+pub mod terrain;
+pub mod text;
+pub mod theme;
+pub mod tour;
+
+pub static DEFAULT_WINDOW_HEIGHT: u32 = 720;
+pub static DEFAULT_WINDOW_WIDTH:  u32 = 1280;
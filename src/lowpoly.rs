@@ -0,0 +1,248 @@
+//! Low-poly art from a photo: points scattered over it, more of them where
+//! the photo has edges, joined into their Delaunay triangles, and each
+//! triangle filled with the average color of the photo under it.
+
+use std::path::Path;
+
+use image::imageops::FilterType;
+use image::RgbImage;
+
+use crate::delaunay::Delaunay;
+use crate::diagram::{random, Diagram, Point};
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// How likely a point lands on a flat part of the photo compared to its
+/// sharpest edge, so smooth areas still get a few triangles.
+const EDGE_FLOOR: f64 = 0.05;
+/// Points along the border of the photo, as a multiple of the spacing the
+/// points inside would have if they were spread evenly.
+const BORDER_SPACING: f64 = 2.0;
+
+/// A photo scaled to fit the window, keeping its aspect ratio, and centered.
+pub struct Photo {
+    pixels: RgbImage,
+    /// Where its top left corner is in the window.
+    origin: [f64; 2],
+}
+
+impl Photo {
+    /// Reads a PNG or JPEG file.
+    pub fn open(path: &Path) -> Result<Photo, String> {
+        let image = image::open(path).map_err(|err| format!("Can't load {}: {}", path.display(), err))?.to_rgb8();
+        let (window_width, window_height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        let scale = (window_width / image.width() as f64).min(window_height / image.height() as f64);
+        let width = ((image.width() as f64 * scale).round() as u32).max(1);
+        let height = ((image.height() as f64 * scale).round() as u32).max(1);
+        Ok(Photo {
+            pixels: image::imageops::resize(&image, width, height, FilterType::Triangle),
+            origin: [((window_width - width as f64) / 2.0).floor(), ((window_height - height as f64) / 2.0).floor()],
+        })
+    }
+
+    fn width(&self) -> f64 {
+        self.pixels.width() as f64
+    }
+
+    fn height(&self) -> f64 {
+        self.pixels.height() as f64
+    }
+
+    /// The color of the pixel at column `x` and row `y` of the photo.
+    fn color(&self, x: u32, y: u32) -> [f64; 3] {
+        self.pixels.get_pixel(x, y).0.map(|v| v as f64 / 255.0)
+    }
+
+    fn brightness(&self, x: u32, y: u32) -> f64 {
+        let [r, g, b] = self.color(x, y);
+        0.299 * r + 0.587 * g + 0.114 * b
+    }
+
+    /// The left, top, right and bottom of the photo in the window.
+    pub fn bounds(&self) -> [f64; 4] {
+        [self.origin[0], self.origin[1], self.origin[0] + self.width(), self.origin[1] + self.height()]
+    }
+
+    /// How dark the photo is at a point of the window, from 0 for white to
+    /// 1 for black, and 0 outside it.
+    pub fn darkness(&self, p: [f64; 2]) -> f64 {
+        let [x, y] = [p[0] - self.origin[0], p[1] - self.origin[1]];
+        if x < 0.0 || y < 0.0 || x >= self.width() || y >= self.height() {
+            return 0.0;
+        }
+        1.0 - self.brightness(x as u32, y as u32)
+    }
+
+    /// The pixels whose centers are inside the convex polygon, given in
+    /// window coordinates, as their centers in the window and their colors.
+    pub fn pixels_inside(&self, poly: &[Point]) -> Vec<([f64; 2], [f64; 3])> {
+        let mut pixels = Vec::new();
+        if poly.len() < 3 {
+            return pixels;
+        }
+        let poly = poly.iter().map(|p| [p.0 - self.origin[0], p.1 - self.origin[1]]).collect::<Vec<_>>();
+        let top = poly.iter().map(|p| p[1]).fold(f64::INFINITY, f64::min);
+        let bottom = poly.iter().map(|p| p[1]).fold(f64::NEG_INFINITY, f64::max);
+        for y in (top.floor().max(0.0) as u32)..(bottom.ceil().min(self.height()) as u32) {
+            let middle = y as f64 + 0.5;
+            let (mut left, mut right) = (f64::INFINITY, f64::NEG_INFINITY);
+            for (i, p) in poly.iter().enumerate() {
+                let q = poly[(i + 1) % poly.len()];
+                if (p[1] <= middle) != (q[1] <= middle) {
+                    let x = p[0] + (middle - p[1]) / (q[1] - p[1]) * (q[0] - p[0]);
+                    left = left.min(x);
+                    right = right.max(x);
+                }
+            }
+            if left > right {
+                continue;
+            }
+            let first = (left - 0.5).ceil().max(0.0) as u32;
+            let last = ((right - 0.5).ceil().max(0.0) as u32).min(self.pixels.width());
+            for x in first..last {
+                pixels.push(([self.origin[0] + x as f64 + 0.5, self.origin[1] + middle], self.color(x, y)));
+            }
+        }
+        pixels
+    }
+
+    /// How much the brightness changes around each pixel, row by row.
+    fn edges(&self) -> Vec<f64> {
+        let (width, height) = (self.pixels.width(), self.pixels.height());
+        let mut edges = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let dx = self.brightness((x + 1).min(width - 1), y) - self.brightness(x.saturating_sub(1), y);
+                let dy = self.brightness(x, (y + 1).min(height - 1)) - self.brightness(x, y.saturating_sub(1));
+                edges.push(dx.hypot(dy));
+            }
+        }
+        edges
+    }
+
+    /// The average color of the pixels whose centers are inside the
+    /// triangle, given in window coordinates, or of the pixel under its
+    /// middle if it's too thin to hold any.
+    fn average(&self, corners: [[f64; 2]; 3]) -> [f32; 4] {
+        let corners = corners.map(|[x, y]| [x - self.origin[0], y - self.origin[1]]);
+        let [a, b, c] = corners;
+        let side = |p: [f64; 2], q: [f64; 2], r: [f64; 2]| (q[0] - p[0]) * (r[1] - p[1]) - (q[1] - p[1]) * (r[0] - p[0]);
+        let orientation = side(a, b, c).signum();
+        let range = |axis: usize, size: f64| {
+            let low = corners.iter().map(|p| p[axis]).fold(f64::INFINITY, f64::min);
+            let high = corners.iter().map(|p| p[axis]).fold(f64::NEG_INFINITY, f64::max);
+            (low.floor().max(0.0) as u32)..(high.ceil().min(size) as u32)
+        };
+        let (mut total, mut count) = ([0.0; 3], 0);
+        for y in range(1, self.height()) {
+            for x in range(0, self.width()) {
+                let p = [x as f64 + 0.5, y as f64 + 0.5];
+                if [side(a, b, p), side(b, c, p), side(c, a, p)].iter().all(|&s| s * orientation >= 0.0) {
+                    let color = self.color(x, y);
+                    for i in 0..3 {
+                        total[i] += color[i];
+                    }
+                    count += 1;
+                }
+            }
+        }
+        if count == 0 {
+            let x = ((a[0] + b[0] + c[0]) / 3.0).clamp(0.0, self.width() - 1.0) as u32;
+            let y = ((a[1] + b[1] + c[1]) / 3.0).clamp(0.0, self.height() - 1.0) as u32;
+            (total, count) = (self.color(x, y), 1);
+        }
+        let [r, g, b] = total.map(|t| (t / count as f64) as f32);
+        [r, g, b, 1.0]
+    }
+}
+
+pub struct LowPoly {
+    photo: Photo,
+    /// The edge strength of the pixels added up row by row, to pick pixels
+    /// with a chance that grows with it.
+    cumulative: Vec<f64>,
+    /// The random points drawn so far. Fewer points take the first ones, so
+    /// changing the count only adds or removes a few triangles.
+    samples: Vec<[f64; 2]>,
+    /// Points inside the photo, not counting those along its border.
+    pub count: usize,
+    pub triangles: Vec<Vec<Point>>,
+    pub colors: Vec<[f32; 4]>,
+}
+
+impl LowPoly {
+    pub fn new(photo: Photo, count: usize) -> LowPoly {
+        let edges = photo.edges();
+        let sharpest = edges.iter().copied().fold(0.0, f64::max).max(f64::MIN_POSITIVE);
+        let cumulative = edges.iter()
+            .scan(0.0, |total, &edge| {
+                *total += EDGE_FLOOR + edge / sharpest;
+                Some(*total)
+            })
+            .collect();
+        let mut lowpoly = LowPoly { photo, cumulative, samples: Vec::new(), count: 0, triangles: Vec::new(), colors: Vec::new() };
+        lowpoly.set_count(count);
+        lowpoly
+    }
+
+    /// Triangulates again with `count` points inside the photo.
+    pub fn set_count(&mut self, count: usize) {
+        self.count = count;
+        while self.samples.len() < count {
+            let sample = self.sample();
+            self.samples.push(sample);
+        }
+        let mut points = self.border();
+        points.extend_from_slice(&self.samples[..count]);
+        let center = [DEFAULT_WINDOW_WIDTH as f64 / 2.0, DEFAULT_WINDOW_HEIGHT as f64 / 2.0];
+        let radius = DEFAULT_WINDOW_WIDTH.max(DEFAULT_WINDOW_HEIGHT) as f64;
+        let corners = Delaunay::new(&points, center, radius).triangles().into_iter()
+            .map(|t| [points[t.0], points[t.1], points[t.2]])
+            .collect::<Vec<_>>();
+        self.colors = corners.iter().map(|&c| self.photo.average(c)).collect();
+        self.triangles = corners.iter().map(|c| c.iter().map(|p| (p[0], p[1])).collect()).collect();
+    }
+
+    /// A random point of the photo, likelier on its edges.
+    fn sample(&self) -> [f64; 2] {
+        let total = self.cumulative.last().copied().unwrap_or(0.0);
+        let target = random::<f64>() * total;
+        let pixel = self.cumulative.partition_point(|&c| c <= target).min(self.cumulative.len() - 1);
+        let width = self.photo.pixels.width() as usize;
+        [
+            self.photo.origin[0] + (pixel % width) as f64 + random::<f64>(),
+            self.photo.origin[1] + (pixel / width) as f64 + random::<f64>(),
+        ]
+    }
+
+    /// The corners of the photo and points evenly along its sides, so the
+    /// triangles cover all of it.
+    fn border(&self) -> Vec<[f64; 2]> {
+        let [left, top] = self.photo.origin;
+        let (width, height) = (self.photo.width(), self.photo.height());
+        let spacing = BORDER_SPACING * (width * height / self.count.max(1) as f64).sqrt();
+        let mut points = Vec::new();
+        for (start, step, length) in [([left, top], [1.0, 0.0], width), ([left + width, top], [0.0, 1.0], height),
+                                      ([left + width, top + height], [-1.0, 0.0], width), ([left, top + height], [0.0, -1.0], height)] {
+            let pieces = (length / spacing).ceil().max(1.0) as usize;
+            for i in 0..pieces {
+                let along = length * i as f64 / pieces as f64;
+                points.push([start[0] + step[0] * along, start[1] + step[1] * along]);
+            }
+        }
+        points
+    }
+
+    /// The triangles as the cells of a diagram, each with its middle for
+    /// the dot, ready to be drawn and exported.
+    pub fn diagram(&self) -> Diagram {
+        let mut diagram = Diagram::new();
+        diagram.restore_with_cells(self.middles(), self.colors.clone(), self.triangles.clone());
+        diagram
+    }
+
+    pub fn middles(&self) -> Vec<[f64; 2]> {
+        self.triangles.iter()
+            .map(|t| [(t[0].0 + t[1].0 + t[2].0) / 3.0, (t[0].1 + t[1].1 + t[2].1) / 3.0])
+            .collect()
+    }
+}
@@ -1,245 +1,897 @@
 use touch_visualizer::TouchVisualizer;
-use graphics::{ Context, Graphics };
 use piston_window::*;
-use delaunay2d::Delaunay2D;
-use serde_json;
-
-static DEFAULT_WINDOW_HEIGHT: u32 = 720;
-static DEFAULT_WINDOW_WIDTH:  u32 = 1280;
-
-type Point = (f64, f64);
+use std::path::Path;
+use std::time::Instant;
+
+mod app;
+mod audio;
+mod autosave;
+mod brush;
+mod clipboard;
+mod command_palette;
+mod compare;
+mod cluster;
+mod control;
+mod diff;
+mod game;
+mod gamepad;
+mod globe;
+mod growth;
+mod history;
+mod http;
+mod insertion;
+mod journal;
+mod keyboard;
+mod midi;
+mod model;
+mod morph;
+mod noise;
+mod osc;
+mod points;
+mod record;
+mod route;
+mod scenario;
+mod script;
+mod stamp;
+mod stream;
+mod sweep;
+mod symmetry;
+mod trail;
+mod treemap;
+mod view;
+mod watch;
+mod websocket;
+
+use interactive_voronoi::{bounds, centers, color, delaunay, density, diagram, document, draw, export, field, fit, fortune, geometry, hyperbolic, lowpoly, metadata, pathfinding, plugin, power, proximity, raster, refine, regions, spatial, sphere, stats, stipple, terrain, theme, tour};
+use interactive_voronoi::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+use app::App;
+use record::{Player, Recorder};
+use view::View;
 
 struct Settings {
     lines_only: bool,
+    /// The themes `Shift+A` cycles through, the first drawn with the dots
+    /// of `--dot_shape` and the like.
+    themes: Vec<theme::Theme>,
+    /// The one to start with.
+    theme: usize,
     random_count: usize,
-    json_path: Option<String>
+    margin: f64,
+    document: Option<document::Document>,
+    json_path: Option<String>,
+    /// What the coordinates of loaded dots are in.
+    coords: fit::Coords,
+    /// Whether exports write the coordinates dots were loaded in, see
+    /// `export::ExportOptions`.
+    data_coords: bool,
+    watch: bool,
+    presentation: bool,
+    keyboard_cursor: bool,
+    export_path: String,
+    timelapse_path: String,
+    relief: f64,
+    export_scale: f64,
+    trail: bool,
+    trail_spacing: f64,
+    trail_lifetime: f64,
+    seed: Option<u64>,
+    osc_port: Option<u16>,
+    serve_port: Option<u16>,
+    http_port: Option<u16>,
+    midi_device: Option<String>,
+    gamepad_device: Option<String>,
+    audio_source: Option<String>,
+    audio_sensitivity: f64,
+    game: bool,
+    game_moves: usize,
+    journal_path: String,
+    recover: bool,
+    autosave: f64,
+    restore_last: bool,
+    record_path: Option<String>,
+    replay_path: Option<String>,
+    replay_speed: f64,
+    morph_path: Option<String>,
+    morph_matching: morph::Matching,
+    morph_duration: f64,
+    relaxation: diagram::Relaxation,
+    repulsion_range: f64,
+    attract: bool,
+    brush_radius: f64,
+    brush_density: f64,
+    brush_jitter: f64,
+    eraser_radius: f64,
+    pen_radius: f64,
+    kaleidoscope: Option<usize>,
+    stamp_path: Option<String>,
+    stamp_size: f64,
+    density: String,
+    field_path: Option<String>,
+    clusters: usize,
+    cluster_size: usize,
+    cluster_spread: f64,
+    dbscan_eps: f64,
+    dbscan_min_points: usize,
+    kmeans_k: usize,
+    kmeans_rate: f64,
+    merge_tolerance: f32,
+    treemap_path: Option<String>,
+    treemap_rate: f64,
+    collapse_merge: f64,
+    collapse_keep_outside: bool,
+    duplicates: diagram::Duplicates,
+    lod_threshold: usize,
+    smoothing: usize,
+    hyperbolic: bool,
+    globe: bool,
+    globe_path: Option<String>,
+    image_path: Option<String>,
+    lowpoly: bool,
+    lowpoly_points: usize,
+    growth_speed: f64,
+    noise_speed: f64,
+    insertion_delay: f64,
+    boundary_path: Option<String>,
+    min_angle: f64,
+    cut_path: String,
+    kerf: f64,
+    tile_path: String,
+    tile_size: u32,
+    bump_path: String,
+    bump_height: bool,
+    bump_field: export::BumpField,
+    bevel: f64,
+    heightmap_path: String,
+    centroids_path: String,
+    edges_path: String,
+    tour_path: String,
+    script_path: Option<String>,
+    beta: f64
 }
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     let mut opts = getopts::Options::new();
     opts.optflag("l", "lines_only", "Don't color polygons, just outline them");
+    opts.optopt("", "dot_shape", "Draw the dots, on screen and in exports, as circle, square or cross, or none to hide them (default circle)", "SHAPE");
+    opts.optopt("", "dot_radius", "Half the width of the dots in pixels (default 4)", "PIXELS");
+    opts.optopt("", "dot_color", "Color of the dots, #rrggbb or #rrggbbaa (default #000000)", "COLOR");
+    opts.optopt("", "dot_outline", "Outline circle and square dots in this color, #rrggbb or #rrggbbaa (default none)", "COLOR");
+    opts.optmulti("", "theme", "Start with this theme, blueprint, pastel paper, neon dark or a JSON theme file, given again to add more files to the ones `Shift+A` cycles through", "THEME");
     opts.optopt("r", "random_count", "On keypress \"R\", put this many random points on-screen", "RANDOMCOUNT");
-    opts.optopt("j", "json_dots", "load dots from json file", "JSON");
+    opts.optopt("", "document", "Compose svg, png, eps and pdf exports on a page of this size, WIDTHxHEIGHT or a4, a4_landscape, letter or square, shown in the middle of the window with the rest shaded and cut off (default the window)", "SIZE");
+    opts.optopt("", "margin", "Pixels the dots of \"R\" and \"U\" keep away from the window's border (default 0)", "PX");
+    opts.optopt("j", "json_dots", "load dots from json file (or csv with one x,y pair per line), with their label, category and value if given, - reads standard input", "JSON");
+    opts.optflag("", "stdin", "load dots piped into standard input, same as -j -");
+    opts.optflag("w", "watch", "Reload the -j file whenever it changes on disk");
+    opts.optopt("", "coords", "What the coordinates of loaded dots are in: window pixels, normalized shares of the window's width and height from 0 to 1, or data in any units, scaled and moved into view (default window)", "SPACE");
+    opts.optflag("", "fit", "Scale and move loaded dots into view, same as --coords data");
+    opts.optopt("", "export_coords", "Coordinates the geojson, shp, json and dot exports, the centroids and \"S\" write: window or data, the ones the dots were loaded in (default window)", "SPACE");
+    opts.optflag("p", "presentation", "Start in presentation mode, showing only the diagram");
+    opts.optflag("", "keyboard_cursor", "Start with the keyboard cursor on, see key \"Z\"");
+    opts.optopt("o", "export", "On keypress \"E\", export the diagram to this file, format by extension: svg, png, pdf, eps, obj, ply, dxf, shp, geojson, or json and dot for the cell adjacency graph (default voronoi.svg)", "FILE");
+    opts.optopt("", "timelapse", "On keypress \"G\", export how the diagram grew dot by dot, as an animated GIF if FILE ends in .gif, otherwise as PNG frames in the directory FILE (default voronoi.gif)", "FILE");
+    opts.optflag("t", "trail", "Start in trail mode, where the cursor leaves fading temporary dots");
+    opts.optopt("", "trail_spacing", "Distance between the dots of the cursor trail (default 20)", "PIXELS");
+    opts.optopt("", "trail_lifetime", "Seconds before a dot of the cursor trail disappears (default 2)", "SECONDS");
+    opts.optopt("", "export-scale", "Make SVG and PNG exports N times the size of the window, e.g. 4 for posters (default 1)", "N");
+    opts.optopt("", "relief", "Extrude exported meshes by cell brightness, up to this height (default 0)", "HEIGHT");
+    opts.optopt("", "seed", "Seed the random generator, for reproducible dots and colors", "SEED");
+    opts.optopt("", "osc-port", "Listen for OSC messages (/point/add x y, /points/clear, /relax/step) on this UDP port", "PORT");
+    opts.optopt("", "serve", "Serve a WebSocket/JSON protocol on this port for adding, moving and removing dots remotely", "PORT");
+    opts.optopt("", "http", "Serve the live diagram over HTTP on this port as /diagram.svg, /points.json and /cells.geojson", "PORT");
+    opts.optopt("", "gamepad", "Read a gamepad from this Linux joystick device (e.g. /dev/input/js0): the left stick moves the cursor, A clicks, B removes the nearest dot, the triggers resize the brush, the shoulder buttons switch tool and relaxation", "DEVICE");
+    opts.optopt("", "midi", "Read MIDI from this raw device (e.g. /dev/snd/midiC1D0): notes spawn dots, the modulation wheel sets the relaxation rate", "DEVICE");
+    opts.optopt("", "audio", "React to raw 16 bit 44.1kHz mono PCM from this file or pipe (- for standard input): loudness jitters the dots, beats spawn new ones", "FILE");
+    opts.optopt("", "audio_sensitivity", "How much louder than average a beat has to be (default 1.5)", "RATIO");
+    opts.optflag("", "game", "Two players take turns placing dots, whoever's cells claim the most area wins");
+    opts.optopt("", "game_moves", "Dots each player may place in game mode (default 10)", "MOVES");
+    opts.optopt("", "journal", "Log every change to the dots to this file (default voronoi.journal)", "FILE");
+    opts.optflag("", "recover", "Restore the last session from the journal instead of starting empty");
+    opts.optopt("", "autosave", "Save the session to the temp directory every this many seconds, keeping the last 3 saves, 0 for never (default 60)", "SECONDS");
+    opts.optflag("", "restore-last", "Start with the session of the newest autosave");
+    opts.optopt("", "record", "Record the session's input with timestamps to FILE, for --replay", "FILE");
+    opts.optopt("", "replay", "Play back a session recorded with --record (or any scenario) in the window", "FILE");
+    opts.optopt("", "replay_speed", "Speed up the replay by this factor (default 1)", "FACTOR");
+    opts.optopt("", "morph", "Animate the -j dots into the points of FILE, json or csv", "FILE");
+    opts.optopt("", "morph_matching", "How --morph pairs up the points: optimal (least travel) or index (default optimal)", "MATCHING");
+    opts.optopt("", "morph_duration", "Seconds the --morph animation takes (default 3)", "SECONDS");
+    opts.optopt("", "relaxation", "How relaxation spreads the dots: lloyd (move to cell centroids), repulsion (push close dots apart) or equal_area (make all cells the same size) (default lloyd)", "METHOD");
+    opts.optopt("", "repulsion_range", "Distance within which dots push each other away in repulsion relaxation (default 60)", "PIXELS");
+    opts.optflag("", "attract", "Make repulsion relaxation also pull the dots towards the cursor");
+    opts.optopt("", "brush_radius", "Radius of the spray brush (default 30)", "PIXELS");
+    opts.optopt("", "brush_density", "Dots the spray brush adds per second (default 40)", "RATE");
+    opts.optopt("", "brush_jitter", "From 0, spraying an even pattern, to 1, spraying at random (default 0.5)", "AMOUNT");
+    opts.optopt("", "pen_radius", "A touch or pen pressed down fully gives its dot the weight of a circle this big in the power diagram (default 40)", "PIXELS");
+    opts.optopt("", "eraser_radius", "Dragging with Shift held removes the dots this close to the cursor (default 30)", "PIXELS");
+    opts.optopt("", "kaleidoscope", "Start with every dot added by hand copied N times around the center, and use N for the K key (default 6)", "N");
+    opts.optopt("", "stamp", "Load a custom shape for the stamp tool from FILE, json or csv", "FILE");
+    opts.optopt("", "stamp_size", "Radius of the stamp tool's shapes before scrolling (default 50)", "PIXELS");
+    opts.optopt("", "density", "Where the random dots of \"R\" land: uniform, radial, a grayscale PNG image (darker is denser) or an expression in x, y and r like 1-r (default uniform)", "DENSITY");
+    opts.optopt("", "field", "A grid of numbers, a CSV file or a grayscale image (brighter is higher), stretched over the window. The first \"C\" paints every cell by the average of the field under it, and hovering shows it", "FILE");
+    opts.optopt("", "clusters", "On keypress \"U\", put this many clusters of random points on-screen (default 5)", "COUNT");
+    opts.optopt("", "cluster_size", "Points per cluster for \"U\" (default 30)", "POINTS");
+    opts.optopt("", "cluster_spread", "Standard deviation of the clusters for \"U\" (default 40)", "PIXELS");
+    opts.optopt("", "dbscan_eps", "Neighborhood radius for finding clusters with \"D\" (default 30)", "PIXELS");
+    opts.optopt("", "dbscan_min_points", "Dots within the radius, the dot itself included, that make a cluster's core for \"D\" (default 4)", "COUNT");
+    opts.optopt("", "kmeans_k", "Clusters k-means looks for with \"J\" (default 5)", "K");
+    opts.optopt("", "kmeans_rate", "k-means steps per second with \"J\" (default 2)", "RATE");
+    opts.optopt("", "merge_tolerance", "How far apart, 0 to 1 per color channel, the colors of cells merged into one region with \"O\" may be (default 0, the same color)", "AMOUNT");
+    opts.optopt("", "treemap", "Lay out the sites in FILE, a json array of objects with a \"weight\" and optionally a \"label\", \"x\" and \"y\", as a Voronoi treemap", "FILE");
+    opts.optopt("", "treemap_rate", "Steps per second --treemap takes to fit the cell areas to the weights (default 10)", "RATE");
+    opts.optopt("", "collapse_merge", "Cell corners this close to each other become one dot when \"W\" collapses the dots to the corners (default 0, only shared corners)", "PIXELS");
+    opts.optflag("", "collapse_keep_outside", "Keep the cell corners outside the window as dots when collapsing with \"W\"");
+    opts.optopt("", "duplicate_distance", "Dots this close to an earlier one, from any source, are dropped (default 0.001)", "PIXELS");
+    opts.optflag("", "accumulate_weights", "A dot dropped for being on top of another one adds its power diagram weight to it");
+    opts.optflag("", "hyperbolic", "Start in the hyperbolic plane, see \"Shift+Q\"");
+    opts.optflag("", "globe", "Put the sites on a globe that turns when dragged instead of the window");
+    opts.optopt("", "globe_sites", "Start --globe with the sites in FILE, a csv or json file of latitude and longitude pairs in degrees", "FILE");
+    opts.optopt("", "image", "A photo, png or jpeg, for --lowpoly or --stipple", "FILE");
+    opts.optflag("", "lowpoly", "Turn the --image photo into triangles, each in the average color of the photo under it, with more of them along its edges. \"Up\" and \"Down\" change how many");
+    opts.optopt("", "points", "Points --lowpoly scatters over the photo, or dots --stipple stipples it with (default 2000)", "COUNT");
+    opts.optopt("", "stipple", "Stipple the --image photo: scatter dots over it, more where it's darker, relax them towards the darkness under their cells, then write them to FILE with .json for its extension and their cells, each in the average color of the photo under it, to FILE, png or any other format of -o, and exit", "FILE");
+    opts.optopt("", "svg-out", "Export the starting diagram, like the --lowpoly one, to FILE, svg or any other format of -o, and exit", "FILE");
+    opts.optopt("", "noise_speed", "How fast the colors of \"Ctrl+N\" drift, in noise cycles per second (default 0.1)", "SPEED");
+    opts.optopt("", "growth_speed", "Pixels per second the regions grow on average with \"Shift+G\" (default 100)", "SPEED");
+    opts.optopt("", "insertion_delay", "Seconds between the steps of adding a dot with \"Shift+B\" (default 0.5)", "SECONDS");
+    opts.optopt("", "boundary", "The polygon \"Shift+R\" refines the mesh inside, a json or csv file of its corners in order (default the window), which mesh exports also keep to", "FILE");
+    opts.optopt("", "min_angle", "Smallest angle in degrees \"Shift+R\" refines the triangles to, up to about 20.7 always works (default 20)", "DEGREES");
+    opts.optopt("", "cut", "On keypress \"Shift+E\", export the cells as numbered cutting paths for a laser cutter, all in FILE if it ends in .svg, otherwise one SVG per cell in the directory FILE (default voronoi-cut.svg)", "FILE");
+    opts.optopt("", "kerf", "Width the --cut paths burn away, in pixels, half of which they're moved out by (default 0)", "WIDTH");
+    opts.optopt("", "tile", "On keypress \"Shift+T\", export the window as a PNG tile that repeats seamlessly, with the diagram wrapping around at its edges (default voronoi-tile.png)", "FILE");
+    opts.optopt("", "tile_size", "Width in pixels of the --tile, its height keeps the window's aspect ratio (default 1024)", "PIXELS");
+    opts.optopt("", "bump", "On keypress \"Shift+N\", export a PNG normal map of the cells for cracked or cobbled materials, from how far each pixel is from the edges of its cell (default voronoi-normal.png)", "FILE");
+    opts.optflag("", "bump_height", "Make the --bump a grayscale height map instead");
+    opts.optflag("", "bump_sites", "Make the --bump cells cones peaking at their dots instead of beveled at their edges");
+    opts.optopt("", "bevel", "Pixels the --bump edges take to rise to full height, which is as many pixels (default 8)", "PIXELS");
+    opts.optopt("", "heightmap", "On keypress \"Shift+M\", export the heights of the \"Shift+H\" terrain as a grayscale PNG (default voronoi-heightmap.png)", "FILE");
+    opts.optopt("", "centroids", "On keypress \"Shift+J\", export the centroids of the cells, as x,y,area lines if FILE ends in .csv and as a json array of [x, y] pairs otherwise, both readable with -j (default voronoi-centroids.json)", "FILE");
+    opts.optopt("", "edges", "On keypress \"Shift+Y\", export every edge of the cells once, joined into polylines for pen plotters, as a json array of polylines if FILE ends in .json, polyline,x,y lines if .csv, and SVG paths otherwise (default voronoi-edges.svg)", "FILE");
+    opts.optopt("", "script", "On keypress \"Shift+K\", run FILE, a program that gets the dots as json on standard input and writes add, move, remove, color or clear lines, or a .rhai script with the rhai feature, see `script`", "FILE");
+    opts.optopt("", "tour", "On keypress \"Shift+U\", export the \"Shift+L\" tour through the dots as an SVG of one closed path for pen plotters (default voronoi-tour.svg)", "FILE");
+    opts.optopt("", "beta", "The beta \"Shift+S\" starts the beta-skeleton at, 1 for the Gabriel graph, 2 for the relative neighborhood graph, larger for sparser ones (default 1)", "BETA");
+    opts.optopt("", "smooth", "Round off the corners of the cells, on screen and in exports, by cutting them off N times (default 0)", "N");
+    opts.optopt("", "lod", "Layers with more than COUNT dots are drawn thinned to about one dot per 4 by 4 pixels, exports still have all of them (default 50000, 0 never thins)", "COUNT");
+    opts.optflag("", "diff", "Compare the two sessions, journals or autosaves, given after the options: which dots the second added, removed, moved or recolored, then exit");
+    opts.optopt("", "merge", "Merge the changes the second and third sessions given after the options made to the first into a new session in FILE, leaving dots both changed differently as they were, then exit", "FILE");
+    opts.optflag("", "stats", "Print statistics and histograms of the Delaunay edge lengths and cell areas of the -j dots, then exit");
+    opts.optopt("", "run-scenario", "Replay the input script in FILE without a window and print the final scene and image hash as JSON to stderr", "FILE");
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
         Err(err) => { 
-            println!("{}\n{}", help_message(&opts), err.to_string());
+            println!("{}\n{}", help_message(&opts), err);
             return; 
         }
     };
 
+    let dot_style = match matches.opt_str("dot_shape").as_deref() {
+        Some("none") => { None },
+        shape => {
+            let default = draw::DotStyle::default();
+            Some(draw::DotStyle {
+                shape: shape.map_or(default.shape, |s| s.parse().expect("Dot shape of bad format")),
+                radius: matches.opt_str("dot_radius").map_or(default.radius, |s| s.parse().expect("Dot radius of bad format")),
+                fill: matches.opt_str("dot_color").map_or(default.fill, |s| color::parse_color(&s).expect("Dot color of bad format")),
+                outline: matches.opt_str("dot_outline").map(|s| color::parse_color(&s).expect("Dot outline of bad format")),
+            })
+        }
+    };
+    let (themes, theme) = load_themes(dot_style, &matches.opt_strs("theme"));
+
     let settings = Settings{
         lines_only: matches.opt_present("l"),
+        themes,
+        theme,
         random_count: match matches.opt_str("r") {
             None => { 50 },
             Some(s) => { s.parse().expect("Random count of bad format") }
         },
-        json_path: matches.opt_str("j")
+        document: matches.opt_str("document").map(|s| s.parse().expect("Document size of bad format")),
+        margin: match matches.opt_str("margin") {
+            None => { 0.0 },
+            Some(s) => { s.parse().expect("Margin of bad format") }
+        },
+        json_path: if matches.opt_present("stdin") { Some("-".to_string()) } else { matches.opt_str("j") },
+        coords: match matches.opt_str("coords") {
+            None if matches.opt_present("fit") => { fit::Coords::Data },
+            None => { fit::Coords::Window },
+            Some(s) => { s.parse().expect("Coordinates of bad format") }
+        },
+        data_coords: match matches.opt_str("export_coords").as_deref() {
+            None | Some("window") => { false },
+            Some("data") => { true },
+            Some(s) => { panic!("Unknown export coordinates \"{}\", use window or data", s) }
+        },
+        watch: matches.opt_present("w"),
+        presentation: matches.opt_present("p"),
+        keyboard_cursor: matches.opt_present("keyboard_cursor"),
+        export_path: matches.opt_str("o").unwrap_or_else(|| "voronoi.svg".to_string()),
+        timelapse_path: matches.opt_str("timelapse").unwrap_or_else(|| "voronoi.gif".to_string()),
+        relief: match matches.opt_str("relief") {
+            None => { 0.0 },
+            Some(s) => { s.parse().expect("Relief height of bad format") }
+        },
+        export_scale: match matches.opt_str("export-scale") {
+            None => { 1.0 },
+            Some(s) => { s.parse().ok().filter(|scale: &f64| *scale > 0.0).expect("Export scale of bad format") }
+        },
+        trail: matches.opt_present("t"),
+        trail_spacing: match matches.opt_str("trail_spacing") {
+            None => { 20.0 },
+            Some(s) => { s.parse().expect("Trail spacing of bad format") }
+        },
+        trail_lifetime: match matches.opt_str("trail_lifetime") {
+            None => { 2.0 },
+            Some(s) => { s.parse().expect("Trail lifetime of bad format") }
+        },
+        seed: matches.opt_str("seed").map(|s| s.parse().expect("Seed of bad format")),
+        osc_port: matches.opt_str("osc-port").map(|s| s.parse().expect("OSC port of bad format")),
+        serve_port: matches.opt_str("serve").map(|s| s.parse().expect("Serve port of bad format")),
+        http_port: matches.opt_str("http").map(|s| s.parse().expect("HTTP port of bad format")),
+        midi_device: matches.opt_str("midi"),
+        gamepad_device: matches.opt_str("gamepad"),
+        audio_source: matches.opt_str("audio"),
+        audio_sensitivity: match matches.opt_str("audio_sensitivity") {
+            None => { 1.5 },
+            Some(s) => { s.parse().expect("Audio sensitivity of bad format") }
+        },
+        game: matches.opt_present("game"),
+        game_moves: match matches.opt_str("game_moves") {
+            None => { 10 },
+            Some(s) => { s.parse().expect("Game moves of bad format") }
+        },
+        journal_path: matches.opt_str("journal").unwrap_or_else(|| "voronoi.journal".to_string()),
+        recover: matches.opt_present("recover"),
+        autosave: match matches.opt_str("autosave") {
+            None => { 60.0 },
+            Some(s) => { s.parse().expect("Autosave interval of bad format") }
+        },
+        restore_last: matches.opt_present("restore-last"),
+        record_path: matches.opt_str("record"),
+        replay_path: matches.opt_str("replay"),
+        replay_speed: match matches.opt_str("replay_speed") {
+            None => { 1.0 },
+            Some(s) => { s.parse().expect("Replay speed of bad format") }
+        },
+        morph_path: matches.opt_str("morph"),
+        morph_matching: match matches.opt_str("morph_matching") {
+            None => { morph::Matching::Optimal },
+            Some(s) => { s.parse().expect("Morph matching of bad format") }
+        },
+        morph_duration: match matches.opt_str("morph_duration") {
+            None => { 3.0 },
+            Some(s) => { s.parse().expect("Morph duration of bad format") }
+        },
+        relaxation: match matches.opt_str("relaxation") {
+            None => { diagram::Relaxation::Lloyd },
+            Some(s) => { s.parse().expect("Relaxation of bad format") }
+        },
+        repulsion_range: match matches.opt_str("repulsion_range") {
+            None => { 60.0 },
+            Some(s) => { s.parse().expect("Repulsion range of bad format") }
+        },
+        attract: matches.opt_present("attract"),
+        brush_radius: match matches.opt_str("brush_radius") {
+            None => { 30.0 },
+            Some(s) => { s.parse().expect("Brush radius of bad format") }
+        },
+        brush_density: match matches.opt_str("brush_density") {
+            None => { 40.0 },
+            Some(s) => { s.parse().expect("Brush density of bad format") }
+        },
+        brush_jitter: match matches.opt_str("brush_jitter") {
+            None => { 0.5 },
+            Some(s) => { s.parse().expect("Brush jitter of bad format") }
+        },
+        eraser_radius: match matches.opt_str("eraser_radius") {
+            None => { 30.0 },
+            Some(s) => { s.parse().expect("Eraser radius of bad format") }
+        },
+        pen_radius: match matches.opt_str("pen_radius") {
+            None => { 40.0 },
+            Some(s) => { s.parse().expect("Pen radius of bad format") }
+        },
+        kaleidoscope: matches.opt_str("kaleidoscope").map(|s| s.parse().expect("Kaleidoscope copies of bad format")),
+        stamp_path: matches.opt_str("stamp"),
+        stamp_size: match matches.opt_str("stamp_size") {
+            None => { 50.0 },
+            Some(s) => { s.parse().expect("Stamp size of bad format") }
+        },
+        density: matches.opt_str("density").unwrap_or_else(|| "uniform".to_string()),
+        field_path: matches.opt_str("field"),
+        clusters: match matches.opt_str("clusters") {
+            None => { 5 },
+            Some(s) => { s.parse().expect("Cluster count of bad format") }
+        },
+        cluster_size: match matches.opt_str("cluster_size") {
+            None => { 30 },
+            Some(s) => { s.parse().expect("Cluster size of bad format") }
+        },
+        cluster_spread: match matches.opt_str("cluster_spread") {
+            None => { 40.0 },
+            Some(s) => { s.parse().expect("Cluster spread of bad format") }
+        },
+        dbscan_eps: match matches.opt_str("dbscan_eps") {
+            None => { 30.0 },
+            Some(s) => { s.parse().expect("DBSCAN radius of bad format") }
+        },
+        dbscan_min_points: match matches.opt_str("dbscan_min_points") {
+            None => { 4 },
+            Some(s) => { s.parse().expect("DBSCAN minimum of bad format") }
+        },
+        kmeans_k: match matches.opt_str("kmeans_k") {
+            None => { 5 },
+            Some(s) => { s.parse().expect("k-means cluster count of bad format") }
+        },
+        kmeans_rate: match matches.opt_str("kmeans_rate") {
+            None => { 2.0 },
+            Some(s) => { s.parse().expect("k-means rate of bad format") }
+        },
+        merge_tolerance: match matches.opt_str("merge_tolerance") {
+            None => { 0.0 },
+            Some(s) => { s.parse().expect("Merge tolerance of bad format") }
+        },
+        treemap_path: matches.opt_str("treemap"),
+        treemap_rate: match matches.opt_str("treemap_rate") {
+            None => { 10.0 },
+            Some(s) => { s.parse().expect("Treemap rate of bad format") }
+        },
+        collapse_merge: match matches.opt_str("collapse_merge") {
+            None => { 0.0 },
+            Some(s) => { s.parse().expect("Collapse merge distance of bad format") }
+        },
+        collapse_keep_outside: matches.opt_present("collapse_keep_outside"),
+        duplicates: diagram::Duplicates {
+            distance: match matches.opt_str("duplicate_distance") {
+                None => { diagram::DEFAULT_DUPLICATE_DISTANCE },
+                Some(s) => { s.parse().ok().filter(|distance: &f64| *distance >= 0.0).expect("Duplicate distance of bad format") }
+            },
+            accumulate: matches.opt_present("accumulate_weights")
+        },
+        lod_threshold: match matches.opt_str("lod") {
+            None => { 50_000 },
+            Some(s) => { s.parse().expect("Level of detail threshold of bad format") }
+        },
+        smoothing: match matches.opt_str("smooth") {
+            None => { 0 },
+            Some(s) => { s.parse().expect("Smoothing iterations of bad format") }
+        },
+        hyperbolic: matches.opt_present("hyperbolic"),
+        globe: matches.opt_present("globe") || matches.opt_present("globe_sites"),
+        globe_path: matches.opt_str("globe_sites"),
+        image_path: matches.opt_str("image"),
+        lowpoly: matches.opt_present("lowpoly"),
+        lowpoly_points: match matches.opt_str("points") {
+            None => { 2000 },
+            Some(s) => { s.parse().expect("Point count of bad format") }
+        },
+        growth_speed: match matches.opt_str("growth_speed") {
+            None => { 100.0 },
+            Some(s) => { s.parse().expect("Growth speed of bad format") }
+        },
+        noise_speed: match matches.opt_str("noise_speed") {
+            None => { 0.1 },
+            Some(s) => { s.parse().expect("Noise speed of bad format") }
+        },
+        insertion_delay: match matches.opt_str("insertion_delay") {
+            None => { 0.5 },
+            Some(s) => { s.parse().expect("Insertion delay of bad format") }
+        },
+        boundary_path: matches.opt_str("boundary"),
+        min_angle: match matches.opt_str("min_angle") {
+            None => { 20.0 },
+            Some(s) => { s.parse().expect("Minimum angle of bad format") }
+        },
+        cut_path: matches.opt_str("cut").unwrap_or_else(|| "voronoi-cut.svg".to_string()),
+        kerf: match matches.opt_str("kerf") {
+            None => { 0.0 },
+            Some(s) => { s.parse().expect("Kerf of bad format") }
+        },
+        tile_path: matches.opt_str("tile").unwrap_or_else(|| "voronoi-tile.png".to_string()),
+        tile_size: match matches.opt_str("tile_size") {
+            None => { 1024 },
+            Some(s) => { s.parse().expect("Tile size of bad format") }
+        },
+        bump_path: matches.opt_str("bump").unwrap_or_else(|| "voronoi-normal.png".to_string()),
+        bump_height: matches.opt_present("bump_height"),
+        bump_field: if matches.opt_present("bump_sites") { export::BumpField::Sites } else { export::BumpField::Edges },
+        bevel: match matches.opt_str("bevel") {
+            None => { 8.0 },
+            Some(s) => { s.parse().expect("Bevel of bad format") }
+        },
+        heightmap_path: matches.opt_str("heightmap").unwrap_or_else(|| "voronoi-heightmap.png".to_string()),
+        centroids_path: matches.opt_str("centroids").unwrap_or_else(|| "voronoi-centroids.json".to_string()),
+        edges_path: matches.opt_str("edges").unwrap_or_else(|| "voronoi-edges.svg".to_string()),
+        tour_path: matches.opt_str("tour").unwrap_or_else(|| "voronoi-tour.svg".to_string()),
+        script_path: matches.opt_str("script"),
+        beta: match matches.opt_str("beta") {
+            None => { proximity::GABRIEL_BETA },
+            Some(s) => { s.parse().expect("Beta of bad format") }
+        }
     };
 
-    event_loop(&settings);
+    if settings.morph_path.is_some() && settings.json_path.is_none() {
+        println!("{}\n--morph needs the points to start from, given with -j", help_message(&opts));
+        return;
+    }
 
-}
+    if matches.opt_present("stats") {
+        let Some(path) = settings.json_path.as_ref() else {
+            println!("{}\n--stats needs the dots, given with -j", help_message(&opts));
+            return;
+        };
+        let mut diagram = diagram::Diagram::new();
+        diagram.duplicates = settings.duplicates;
+        diagram.set_dots(points::load_dots(path));
+        print!("{}", stats::report(&diagram));
+        return;
+    }
 
-fn help_message(opts: &getopts::Options) -> String {
-    let mut msg = opts.usage("Usage: interactive-voronoi [OPTIONS]");
-    let interactive_help = "\n\
-Interactive keys:\n\
-\tPress `N` to clear the screen.\n\
-\tPress `R` to get [RANDOMCOUNT] random dots (default 50).\n\
-\tPress `L` to toggle between wireframe and polygon view.\n\
-\tPress `C` to randomly change polygon colors.\n\
-\tPress `S` to dump current points to console.\n\
-";
+    if matches.opt_present("diff") {
+        let [old, new] = &matches.free[..] else {
+            println!("{}\n--diff needs two sessions", help_message(&opts));
+            return;
+        };
+        match diff::diff(old, new, settings.duplicates) {
+            Ok(report) => print!("{}", report),
+            Err(err) => {
+                println!("Could not compare sessions: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    msg.push_str(interactive_help);
-    msg
-}
+    if let Some(out) = matches.opt_str("merge") {
+        let [base, ours, theirs] = &matches.free[..] else {
+            println!("{}\n--merge needs three sessions, the one both started from first", help_message(&opts));
+            return;
+        };
+        match diff::merge(base, ours, theirs, &out, settings.duplicates) {
+            Ok(report) => print!("{}", report),
+            Err(err) => {
+                println!("Could not merge sessions: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if settings.morph_path.is_some() && settings.treemap_path.is_some() {
+        println!("{}\nCan't --morph and --treemap at the same time", help_message(&opts));
+        return;
+    }
+
+    let stipple = matches.opt_str("stipple");
+    if settings.lowpoly && stipple.is_some() {
+        println!("{}\nCan't --lowpoly and --stipple at the same time", help_message(&opts));
+        return;
+    }
+
+    if (settings.lowpoly || stipple.is_some()) != settings.image_path.is_some() {
+        println!("{}\n--image goes together with --lowpoly or --stipple", help_message(&opts));
+        return;
+    }
 
-fn no_dot_there_yet(dot: &[f64;2], dots: &Vec<[f64;2]>) -> bool {
-    let epsilon = 0.001;
-    for &d in dots {
-        if (dot[0] - d[0]).abs() < epsilon && (dot[1] - d[1]).abs() < epsilon {
-            // println!("Point already there, did not add ({}, {})", dot[0], dot[1]);
-            return false
+    if settings.record_path.is_some() && settings.replay_path.is_some() {
+        println!("{}\nCan't --record and --replay at the same time", help_message(&opts));
+        return;
+    }
+
+    if let Some(scenario) = matches.opt_str("run-scenario") {
+        if let Err(err) = scenario::run(&scenario, &settings) {
+            println!("Could not run scenario {}: {}", scenario, err);
+            std::process::exit(1);
         }
+        return;
     }
-    true
-}
 
-fn random_point() -> [f64; 2] {
-    [rand::random::<f64>() * DEFAULT_WINDOW_WIDTH as f64, rand::random::<f64>() * DEFAULT_WINDOW_HEIGHT as f64]
-}
+    if let Some(seed) = settings.seed {
+        diagram::seed_random(seed);
+    }
 
-fn random_color() -> [f32; 4] {
-    [rand::random::<f32>(), rand::random::<f32>(), rand::random::<f32>(), 1.0]
-}
+    if let Some(path) = stipple {
+        if let Err(err) = stipple_and_exit(&path, &settings) {
+            println!("Could not stipple to {}: {}", path, err);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-fn recolor(dots: & Vec<[f64;2]>, colors: &mut Vec<[f32;4]>) {
-    colors.clear();
+    if let Some(path) = matches.opt_str("svg-out") {
+        if let Err(err) = export_and_exit(&path, &settings) {
+            println!("Could not export to {}: {}", path, err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    event_loop(&settings);
+
+}
 
-    for _ in dots {
-        colors.push(random_color());
+/// The classic look with `dots`, the built-in themes and the theme files
+/// among `names`, and the first of `names` to start with.
+fn load_themes(dots: Option<draw::DotStyle>, names: &[String]) -> (Vec<theme::Theme>, usize) {
+    let mut themes = vec![theme::Theme::classic(dots)];
+    themes.extend(theme::built_in());
+    let mut start = None;
+    for name in names {
+        let index = match themes.iter().position(|t| &t.name == name) {
+            Some(index) => index,
+            None => {
+                themes.push(theme::Theme::load(Path::new(name)).expect("Theme of bad format"));
+                themes.len() - 1
+            }
+        };
+        start = start.or(Some(index));
     }
+    (themes, start.unwrap_or(0))
 }
 
-fn random_voronoi(dots: &mut Vec<[f64;2]>, colors: &mut Vec<[f32;4]>, num: usize) {
-    dots.clear();
-    colors.clear();
+/// Writes the diagram the app would start with to `path`, without opening
+/// a window.
+fn export_and_exit(path: &str, settings: &Settings) -> Result<(), String> {
+    let theme = &settings.themes[settings.theme];
+    let diagram = match settings.image_path.as_ref() {
+        Some(image) => {
+            let photo = lowpoly::Photo::open(Path::new(image))?;
+            lowpoly::LowPoly::new(photo, settings.lowpoly_points).diagram()
+        }
+        None => {
+            let mut diagram = diagram::Diagram::new();
+            diagram.duplicates = settings.duplicates;
+            if !theme.palette.is_empty() {
+                diagram.palette = color::Palette::List(theme.palette.clone());
+            }
+            if let Some(json_path) = settings.json_path.as_ref() {
+                let mut loaded = points::load_points(json_path);
+                let coords = points::fit_points(&mut loaded, settings.coords);
+                diagram.set_sites(loaded.dots, loaded.metadata);
+                diagram.coords = coords;
+            }
+            diagram
+        }
+    };
+    let options = start_options(settings, !settings.lowpoly);
+    export::export(path, &diagram, &options).map_err(|err| err.to_string())
+}
 
-    for _ in 0..num {
-        dots.push(random_point());
-        colors.push(random_color());
-    }
+/// Stipples the --image photo with --points dots, writing them to `path`
+/// with a .json extension and the mosaic of their cells to `path`.
+fn stipple_and_exit(path: &str, settings: &Settings) -> Result<(), String> {
+    let image = settings.image_path.as_ref().ok_or("No --image to stipple")?;
+    let photo = lowpoly::Photo::open(Path::new(image))?;
+    let diagram = stipple::stipple(&photo, settings.lowpoly_points);
+    let dots_path = Path::new(path).with_extension("json");
+    let dots = metadata::dots_json(&diagram.dots, &diagram.metadata).to_string();
+    std::fs::write(&dots_path, dots).map_err(|err| format!("Can't write {}: {}", dots_path.display(), err))?;
+    println!("Stippled with {} dots, saved to {}", diagram.dots.len(), dots_path.display());
+    export::export(path, &diagram, &start_options(settings, false)).map_err(|err| err.to_string())
 }
 
-fn save_current_dots(dots: & Vec<[f64;2]>) {
-    let js = serde_json::to_string(dots).expect("Could not serialize dots");
-    println!("{}", js);
+/// How to export without a window, in the starting theme, with or without
+/// the dots it may draw.
+fn start_options(settings: &Settings, dots: bool) -> export::ExportOptions {
+    let theme = &settings.themes[settings.theme];
+    export::ExportOptions {
+        relief: settings.relief,
+        lines_only: settings.lines_only,
+        merge_tolerance: None,
+        scale: settings.export_scale,
+        smoothing: settings.smoothing,
+        dots: dots && theme.dots.is_some(),
+        dot_style: theme.dots.unwrap_or_default(),
+        background: theme.background,
+        stroke: theme.stroke,
+        boundary: None,
+        data_coords: settings.data_coords,
+        document: settings.document,
+    }
 }
 
-fn load_dots(json_file: &str) -> Vec<[f64;2]> {
-    let js = std::fs::read_to_string(json_file).expect("Can't read provided json file");
-    let dots: Vec<[f64;2]> = serde_json::from_str(&js).expect("Can't convert json to dots");
-    dots
+fn help_message(opts: &getopts::Options) -> String {
+    let mut msg = opts.usage("Usage: interactive-voronoi [OPTIONS]");
+    let interactive_help = "\n\
+Interactive keys:\n\
+\tPress `N` to clear the screen.\n\
+\tPress `R` to get [RANDOMCOUNT] random dots (default 50).\n\
+\tPress `U` to get clusters of random dots, see --clusters.\n\
+\tPress `D` to find clusters among the dots with DBSCAN and color the cells by cluster.\n\
+\tPress `J` to start or stop k-means, watching its centroids settle step by step.\n\
+\tPress `Q` to switch between the Voronoi diagram and the power diagram, where dots put down by pen or touch claim more space the harder they were pressed. Scroll over a cell there to change its dot's weight.\n\
+\tPress `Shift+Q` to switch to the hyperbolic plane in the Poincaré disk and back. Dots go inside the disk, and the cell edges are arcs.\n\
+\tPress `O` to merge neighboring cells of the same color into regions, on screen and in exports.\n\
+\tPress `I` to show or hide histograms of the Delaunay edge lengths and cell areas, see also --stats.\n\
+\tPress `Shift+S` to show the beta-skeleton of the dots, with `Up`/`Down` changing its beta, see `--beta`, then their Urquhart graph, then neither.\n\
+\tPress `Shift+C` to show or hide the smallest circle around all dots, and `Shift+X` the smallest box around them at any angle.\n\
+\tPress `Shift+V` to mark the circumcenters, centroids and incenters of the Delaunay triangles.\n\
+\tPress `Shift+D` to show the grid that finds dots by position, and which of its cells a search at the cursor looks through.\n\
+\tPress `Shift+O` to show the convex layers of the dots, again to color the cells by layer too, and a third time to hide them.\n\
+\tPress `Shift+R` to add points until no triangle inside the --boundary has an angle below --min_angle, then `E` with `-o mesh.obj` exports the mesh.\n\
+\tPress `Shift+I` to color the Delaunay triangles by their minimum angle, again by their aspect ratio, and once more to go back.\n\
+\tPress `W` to replace the dots with the corners of their cells, again and again for fractal-like patterns.\n\
+\tPress `Ctrl+Z` to undo the last `W`.\n\
+\tPress `Shift+Z` to scale and move the dots of the active layer into view, see `--fit`.\n\
+\tPress `Z` to move a crosshair cursor with the arrow keys instead of the mouse. `Enter` works like the mouse button, `Delete` removes the nearest dot.\n\
+\tPress `L` to toggle between wireframe and polygon view.\n\
+\tPress `Shift+W` to compare filled cells and wireframe side by side, again for Voronoi and power diagram, again for the dots now and relaxed, and once more to go back.\n\
+\tPress `C` to randomly change polygon colors, again to color them by the category of their dots, and again so no two neighbors share a color.\n\
+\tHover over a cell to see the label, category and value of its dot, if it was loaded with them.\n\
+\tPress `Shift+A` to switch to the next theme, a look bundling background, cell lines, colors and dots, see --theme.\n\
+\tPress `Ctrl+C` to copy the diagram to the clipboard as SVG.\n\
+\tPress `Ctrl+V` to add the dots on the clipboard.\n\
+\tPress `F1` to show the keys and which modes are on in the window, again for the next page, `Escape` hides them.\n\
+\tPress `Ctrl+D` to compose exports on an A4, letter or square page, or the --document, in the middle of the window, again for the next, and back to the whole window.\n\
+\tPress `Ctrl+N` to let the colors of the cells drift with noise sampled at their dots, again to put the colors back.\n\
+\tPress `Ctrl+P` to find any action by name and run it, `Up`/`Down` pick, `Enter` runs, `Escape` closes.\n\
+\tPress `S` to dump current points to console.\n\
+\tPress `P` to toggle presentation mode (diagram only, no cursor or overlays).\n\
+\tPress `E` to export the diagram to the file given with `-o`.\n\
+\tPress `Shift+E` to export the cells as numbered cutting paths for a laser cutter, see `--cut` and `--kerf`.\n\
+\tPress `Shift+T` to export a seamlessly repeating texture of the diagram wrapped around the window's edges, see `--tile`.\n\
+\tPress `Shift+N` to export a normal map for cracked or cobbled materials, see `--bump`.\n\
+\tPress `Shift+H` to turn the cells into terrain seen from the front, with heights from noise, then from the distance to the border, then off.\n\
+\tPress `Shift+M` to export the terrain's heights as a grayscale heightmap, see `--heightmap`.\n\
+\tPress `Shift+J` to export the centroids of the cells, see `--centroids`.\n\
+\tPress `Shift+Y` to export every edge of the cells once, as polylines for pen plotters, see `--edges`.\n\
+\tPress `Shift+K` to run the --script, again after editing it to run it anew.\n\
+\tPress `Shift+L` to show a short round trip through all dots, and `Shift+U` to export it for pen plotters, see `--tour`.\n\
+\tPress `G` to export a time-lapse of the diagram growing dot by dot, see `--timelapse`.\n\
+\tPress `Shift+G` to watch the cells grow like crystals from their dots, each at its own speed, until they run into each other.\n\
+\tPress `T` to toggle trail mode, where the cursor leaves fading temporary dots.\n\
+\tPress `F` to start or stop relaxing the dots continuously.\n\
+\tPress `M` to switch the relaxation between Lloyd's algorithm, repulsion and equal cell areas.\n\
+\tPress `A` to toggle whether repulsion also pulls the dots towards the cursor.\n\
+\tPress `B` to switch the mouse between adding single dots, spraying them while held, and stamping shapes.\n\
+\tPress `Shift+F` to step through Fortune's sweepline algorithm on the dots, `Space` plays or pauses, `Left`/`Right` go to the previous or next event.\n\
+\tPress `Shift+P` to pick a start and a goal dot with clicks and watch the shortest path between them being searched, again for the next search method, four times, then off.\n\
+\tPress `Shift+B` to watch each click add its dot step by step like Bowyer-Watson does, `Up`/`Down` slow it down or speed it up.\n\
+\tPress `H` to switch the stamp between circle, star, spiral and the --stamp shape. Scroll to resize it.\n\
+\tHold Shift and drag to erase the dots around the cursor.\n\
+\tHold Alt and click a cell to print its geometry as JSON, with Ctrl as well to copy it.\n\
+\tPress `X` or `Y` to mirror new dots across the vertical or horizontal center line.\n\
+\tPress `K` to copy new dots around the center, like a kaleidoscope.\n\
+\tDrag a dot to move it, along with its mirror images and copies.\n\
+\tPress `Tab` to switch to the next layer, going past the top one adds a new layer.\n\
+\tPress `V` to show or hide the active layer.\n\
+\tPress `1` to `9` to place dots for that team and show the territory scoreboard, `0` for ordinary dots.\n\
+\tIn game mode, players click in turns and `N` starts a new game.\n\
+\tWith --morph, press `Space` to play or pause the animation and `Left`/`Right` to scrub.\n\
+\tWith --lowpoly, press `Up` or `Down` to use more or fewer points.\n\
+\tWith --globe, drag to turn the globe and click to put a site on it.\n\
+\tWith --treemap, the cells grow and shrink until their areas match the weights, labeled with achieved and target share.\n\
+\tDrop a json or csv points file on the window to load it, hold Shift to add to the current dots.\n\
+";
+
+    msg.push_str(interactive_help);
+    msg
 }
 
 fn event_loop(settings: &Settings) {
     let opengl = OpenGL::V3_2;
     let mut window : PistonWindow = WindowSettings::new("Interactive Voronoi", [DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT])
-        .exit_on_esc(true)
+        .exit_on_esc(false)
         .samples(16)
         .graphics_api(opengl)
         .build()
         .unwrap_or_else(|e| { panic!("Failed to build PistonWindow: {}", e) });
 
     let mut touch_visualizer = TouchVisualizer::new();
-    let mut dots = Vec::new();
-    let mut colors = Vec::new();
-    let mut poly_list: Vec<Vec<Point>> = Vec::new();
-
-    let mut mp = [0.0,0.0];
-
-    let mut lines_only = settings.lines_only;
+    // Both seed the random generator, so they have to come before the app
+    let mut player = settings.replay_path.as_ref().map(|path| {
+        Player::open(path, settings.replay_speed, Instant::now())
+            .unwrap_or_else(|e| { panic!("Failed to read replay {}: {}", path, e) })
+    });
+    let mut recorder = settings.record_path.as_ref().map(|path| {
+        let seed = settings.seed.unwrap_or_else(rand::random);
+        diagram::seed_random(seed);
+        Recorder::create(path, seed, Instant::now())
+            .unwrap_or_else(|e| { panic!("Failed to record to {}: {}", path, e) })
+    });
+    let mut app = App::new(settings);
+    set_presentation(&window, app.presentation);
+    if settings.restore_last {
+        app.restore_last().unwrap_or_else(|e| { panic!("Failed to restore the last autosave: {}", e) });
+    }
+    app.start_journal(&settings.journal_path, settings.recover)
+        .unwrap_or_else(|e| { panic!("Failed to open journal {}: {}", settings.journal_path, e) });
+    if settings.autosave > 0.0 {
+        app.start_autosave(settings.autosave);
+    }
 
-    if let Some(jsf) = settings.json_path.as_ref() {
-        dots = load_dots(jsf);
-        recolor(&dots, &mut colors);
-        poly_list = update_polygons(&dots);
+    if let Some(port) = settings.osc_port {
+        osc::listen(port, app.command_sender())
+            .unwrap_or_else(|e| { panic!("Failed to listen for OSC on port {}: {}", port, e) });
+    }
+    let gamepad = settings.gamepad_device.as_ref().map(|device| {
+        let (sender, events) = std::sync::mpsc::channel();
+        gamepad::listen(device, sender)
+            .unwrap_or_else(|e| { panic!("Failed to open gamepad {}: {}", device, e) });
+        events
+    });
+    if let Some(device) = settings.midi_device.as_ref() {
+        midi::listen(device, app.command_sender())
+            .unwrap_or_else(|e| { panic!("Failed to open MIDI device {}: {}", device, e) });
+    }
+    if let Some(source) = settings.audio_source.as_ref() {
+        audio::listen(source, settings.audio_sensitivity, app.command_sender())
+            .unwrap_or_else(|e| { panic!("Failed to open audio input {}: {}", source, e) });
+    }
+    if let Some(port) = settings.serve_port {
+        let (commands, states) = (app.command_sender(), app.state_updates());
+        websocket::serve(port, commands, states)
+            .unwrap_or_else(|e| { panic!("Failed to serve WebSocket on port {}: {}", port, e) });
+    }
+    if let Some(port) = settings.http_port {
+        http::serve(port, app.document_updates())
+            .unwrap_or_else(|e| { panic!("Failed to serve HTTP on port {}: {}", port, e) });
     }
 
+    let mut view = View::fit(window.size());
+    app.set_scale(view.scale());
     window.set_lazy(true);
     while let Some(e) = window.next() {
-        touch_visualizer.event(window.size(), &e);
-        e.mouse_cursor(|p|{ mp = p });
-        if let Some(button) = e.release_args() {
-            match button {
-                Button::Keyboard(key) => {
-                    match key {
-                        Key::N => { dots.clear(); colors.clear(); poly_list.clear(); },
-                        Key::R => { random_voronoi(&mut dots, &mut colors, settings.random_count); poly_list = update_polygons(&dots); },
-                        Key::L => { lines_only = ! lines_only; },
-                        Key::C => { recolor(&dots, &mut colors); },
-                        Key::S => { save_current_dots(&dots); },
-                        _ => ()
-                    }
-                }
-                Button::Mouse(_) => {
-                    // Two points at the same place lead to a problem in rust_voronoi
-                    if no_dot_there_yet(&mp, &dots) {
-                        dots.push(mp);
-                        colors.push(random_color());
-
-                        poly_list = update_polygons(&dots);
-                    }
-                },
-                _ => ()
+        if let Some(args) = e.resize_args() {
+            view = View::fit(args.window_size.into());
+            app.set_scale(view.scale());
+        }
+        // From here on, positions are in diagram units rather than window pixels
+        let e = view.map_event(&e);
+        // The visualizer scales the positions by the size, which they already are in
+        touch_visualizer.event([1, 1], &e);
+
+        let now = Instant::now();
+        if let Some(Err(err)) = recorder.as_mut().map(|r| r.record(&e, now)) {
+            println!("Could not record, stopped recording: {}", err);
+            recorder = None;
+        }
+
+        let presentation = app.presentation;
+        // The replay has the say over the input while it runs
+        let user_input = e.press_args().is_some() || e.release_args().is_some() || e.mouse_cursor_args().is_some();
+        // Escape closes the command palette or help first, and the window only after
+        if e.press_args() == Some(Button::Keyboard(Key::Escape)) && !app.command_palette_open() && !app.help_open() {
+            window.set_should_close(true);
+        }
+        if player.is_none() || !user_input {
+            app.event(&e, now);
+        }
+        for pressed in gamepad.iter().flat_map(|events| events.try_iter()) {
+            if let Some(Err(err)) = recorder.as_mut().map(|r| r.record(&pressed, now)) {
+                println!("Could not record, stopped recording: {}", err);
+                recorder = None;
             }
-        };
-        window.draw_2d(&e, |c, g, _| {
-            clear(color::WHITE, g);
-
-            for (i, poly) in poly_list.iter().enumerate() {
-                if lines_only {
-                    draw_lines_in_polygon(&poly, &c, g);
-                } else {
-                    draw_polygon(&poly, &c, g, colors[i]);
-                }
+            app.event(&pressed, now);
+        }
+        if let Some(replay) = player.as_mut() {
+            for replayed in replay.due(now) {
+                app.event(&replayed, now);
             }
-            for d in &dots {
-                draw_ellipse(&d, &c, g);
+            if replay.is_done() {
+                println!("Replay finished");
+                player = None;
             }
+        }
+        if app.presentation != presentation {
+            set_presentation(&window, app.presentation);
+        }
+        // Animations, file reloads and gamepad input happen even when no window input arrives, so keep the frames coming
+        window.set_lazy(!app.wants_updates() && player.is_none() && gamepad.is_none());
+
+        window.draw_2d(&e, |c, g, _| {
+            app.draw(&view.context(&c), g, Instant::now());
+            if !app.presentation {
+                touch_visualizer.draw(&view.context(&c), g);
+            }
+            view.draw_margins(&c, g);
         });
     } 
+    app.close();
 
 }
 
-fn update_polygons(dots: & Vec<[f64;2]>) -> Vec<Vec<Point>> {
-    let mut dt = Delaunay2D::new(
-        (DEFAULT_WINDOW_WIDTH as f64 / 2.0, DEFAULT_WINDOW_HEIGHT as f64 / 2.0), 
-        std::f64::consts::SQRT_2 * std::cmp::max(DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT) as f64);
-    for [x,y] in dots {
-        dt.add_point((x.to_owned(), y.to_owned()));
-    }
-    let (points, regions) = dt.export_voronoi_regions();
-
-    regions.iter().map(|region| { 
-        region.iter().map(|index| { 
-            points[*index] 
-        }).collect::<Vec<Point>>() 
-    }).collect::<Vec<Vec<Point>>>()
-}
-
-fn draw_lines_in_polygon<G: Graphics>(
-    poly: &Vec<Point>,
-    c: &Context,
-    g: &mut G,
-)
-{
-    let color = [0.0, 0.0, 1.0, 1.0];
-
-    for i in 0..poly.len()-1 {
-        graphics::line(
-            color,
-            2.0,
-            [poly[i].0.into(), poly[i].1.into(), poly[i+1].0.into(), poly[i+1].1.into()],
-            c.transform,
-            g
-        )
-    }
-}
-
-fn draw_polygon<G: Graphics>(
-    poly: &Vec<Point>,
-    c: &Context,
-    g: &mut G,
-    color: [f32; 4]
-) {
-    let mut polygon_points: Vec<[f64; 2]> = Vec::new();
-
-    for p in poly {
-        polygon_points.push([p.0.into(), p.1.into()]);
-    }
-
-    graphics::polygon(
-        color,
-        polygon_points.as_slice(),
-        c.transform,
-        g
-    )
-}
-
-fn draw_ellipse<G: Graphics>(
-    cursor: &[f64; 2],
-    c: &Context,
-    g: &mut G,
-) {
-    let color = [0.0, 0.0, 0.0, 1.0];
-    graphics::ellipse(
-        color,
-        graphics::ellipse::circle(cursor[0], cursor[1], 4.0),
-        c.transform,
-        g
-    );
+fn set_presentation(window: &PistonWindow, presentation: bool) {
+    window.window.ctx.window().set_cursor_visible(!presentation);
 }
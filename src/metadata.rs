@@ -0,0 +1,168 @@
+//! What is known about a dot besides where it is, loaded along with it: a
+//! label, a category and a value, each optional. Hovering over a cell shows
+//! its dot's, `C` can color the cells by category, and the exports with room
+//! for it write it along with the dots, so it survives a round trip through
+//! them.
+//!
+//! Points files carry it as objects in place of `[x, y]` pairs,
+//!
+//! ```text
+//! [{"x": 10, "y": 20, "label": "Oslo", "category": "capital", "value": 709000}, [30, 40]]
+//! ```
+//!
+//! or as `label`, `category` and `value` columns of a CSV file with a header.
+
+use serde_json::{json, Map, Value};
+
+/// The columns of a CSV file, and the keys of a JSON object, that hold it.
+pub const FIELDS: [&str; 3] = ["label", "category", "value"];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub label: Option<String>,
+    pub category: Option<String>,
+    pub value: Option<f64>,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        self.label.is_none() && self.category.is_none() && self.value.is_none()
+    }
+
+    /// Reads the fields from the members of a JSON object, skipping all
+    /// others. Labels and categories may be numbers too, values numbers in
+    /// strings.
+    pub fn from_json(object: &Map<String, Value>) -> Metadata {
+        let text = |key: &str| match object.get(key)? {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None
+        };
+        let value = match object.get("value") {
+            Some(Value::Number(n)) => n.as_f64(),
+            Some(Value::String(s)) => s.trim().parse().ok(),
+            _ => None
+        };
+        Metadata { label: text("label"), category: text("category"), value }
+    }
+
+    /// The fields that are set, as members of a JSON object.
+    pub fn to_json(&self) -> Map<String, Value> {
+        let mut object = Map::new();
+        if let Some(label) = &self.label {
+            object.insert("label".to_string(), json!(label));
+        }
+        if let Some(category) = &self.category {
+            object.insert("category".to_string(), json!(category));
+        }
+        if let Some(value) = self.value {
+            object.insert("value".to_string(), json!(value));
+        }
+        object
+    }
+
+    /// The fields that are set, by name, as text.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let value = self.value.map(|v| v.to_string());
+        FIELDS.into_iter().zip([self.label.clone(), self.category.clone(), value])
+            .filter_map(|(name, text)| Some((name, text?)))
+            .collect()
+    }
+
+    /// Sets the field named like a CSV column from its text, an empty one
+    /// leaving it unset. Returns whether there is such a field.
+    pub fn set(&mut self, field: &str, text: &str) -> bool {
+        let text = text.trim();
+        let given = (!text.is_empty()).then(|| text.to_string());
+        match field {
+            "label" => self.label = given,
+            "category" => self.category = given,
+            "value" => self.value = given.and_then(|t| t.parse().ok()),
+            _ => return false
+        }
+        true
+    }
+
+    /// The fields in the order of `FIELDS` as CSV columns, quoted where needed.
+    pub fn csv_columns(&self) -> String {
+        let value = self.value.map(|v| v.to_string()).unwrap_or_default();
+        [self.label.as_deref().unwrap_or_default(), self.category.as_deref().unwrap_or_default(), &value]
+            .map(csv_quote)
+            .join(",")
+    }
+
+    /// A line for each field that is set, for tooltips.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(label) = &self.label {
+            lines.push(label.clone());
+        }
+        if let Some(category) = &self.category {
+            lines.push(format!("Category: {}", category));
+        }
+        if let Some(value) = self.value {
+            lines.push(format!("Value: {}", value));
+        }
+        lines
+    }
+}
+
+/// Whether any of the dots has metadata, and so whether exports need room for it.
+pub fn any(metadata: &[Metadata]) -> bool {
+    metadata.iter().any(|m| !m.is_empty())
+}
+
+/// The dots as a JSON array for points files: `[x, y]` pairs, or objects
+/// with `x`, `y` and the metadata if any dot has some.
+pub fn dots_json(dots: &[[f64; 2]], metadata: &[Metadata]) -> Value {
+    if !any(metadata) {
+        return json!(dots);
+    }
+    let empty = Metadata::default();
+    Value::Array(dots.iter().enumerate().map(|(i, d)| {
+        let mut object = Map::new();
+        object.insert("x".to_string(), json!(d[0]));
+        object.insert("y".to_string(), json!(d[1]));
+        object.extend(metadata.get(i).unwrap_or(&empty).to_json());
+        Value::Object(object)
+    }).collect())
+}
+
+/// The index of each dot's category among all categories in the order they
+/// first appear, and how many there are.
+pub fn categories(metadata: &[Metadata]) -> (Vec<Option<usize>>, usize) {
+    let mut names: Vec<&str> = Vec::new();
+    let indices = metadata.iter().map(|m| {
+        let category = m.category.as_deref()?;
+        Some(names.iter().position(|&n| n == category).unwrap_or_else(|| {
+            names.push(category);
+            names.len() - 1
+        }))
+    }).collect();
+    (indices, names.len())
+}
+
+/// `text` as a CSV field, in quotes if it has commas, quotes or line breaks.
+pub fn csv_quote(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// The fields of a CSV line, unquoting quoted ones.
+pub fn csv_split(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => { chars.next(); fields.last_mut().unwrap().push('"'); },
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
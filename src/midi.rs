@@ -0,0 +1,73 @@
+//! MIDI input read from a raw MIDI device, such as ALSA's
+//! `/dev/snd/midiC1D0` on Linux, or any file or pipe carrying MIDI bytes.
+//!
+//! * Note-on spawns a dot: the pitch picks the horizontal position and the
+//!   hue, the velocity the brightness. The height is random.
+//! * Control change 1 (the modulation wheel) sets the relaxation rate, from
+//!   none up to `MAX_RELAX_RATE` Lloyd steps per second.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use crate::color::hsv;
+use crate::control::Command;
+use crate::diagram::random;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+const MAX_RELAX_RATE: f64 = 20.0;
+const RELAX_RATE_CONTROLLER: u8 = 1;
+
+/// Starts reading MIDI messages from the device in the background.
+pub fn listen(device: &str, commands: Sender<Command>) -> io::Result<()> {
+    let input = BufReader::new(File::open(device)?);
+    println!("Listening for MIDI on {}", device);
+    thread::spawn(move || {
+        let mut status = 0u8;
+        let mut data = Vec::new();
+        for byte in input.bytes() {
+            let Ok(byte) = byte else { return };
+            match byte {
+                // Real-time messages may appear anywhere and don't affect running status
+                0xF8..=0xFF => continue,
+                0x80..=0xF7 => { status = byte; data.clear(); continue; },
+                _ => data.push(byte)
+            }
+            if data.len() < data_length(status) {
+                continue;
+            }
+            let command = to_command(status, &data);
+            // Keep the status byte, MIDI allows "running status" to omit it
+            data.clear();
+            if let Some(command) = command {
+                if commands.send(command).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+fn data_length(status: u8) -> usize {
+    match status & 0xF0 {
+        0xC0 | 0xD0 => 1,
+        0x80..=0xE0 => 2,
+        // System messages we don't care about, swallow their data
+        _ => usize::MAX
+    }
+}
+
+fn to_command(status: u8, data: &[u8]) -> Option<Command> {
+    match (status & 0xF0, data) {
+        (0x90, &[pitch, velocity]) if velocity > 0 => {
+            let x = pitch as f64 / 127.0 * DEFAULT_WINDOW_WIDTH as f64;
+            let y = random::<f64>() * DEFAULT_WINDOW_HEIGHT as f64;
+            let color = hsv((pitch % 12) as f32 / 12.0, 0.8, velocity as f32 / 127.0);
+            Some(Command::AddColoredPoint([x, y], color))
+        }
+        (0xB0, &[RELAX_RATE_CONTROLLER, value]) => Some(Command::SetRelaxRate(value as f64 / 127.0 * MAX_RELAX_RATE)),
+        _ => None
+    }
+}
@@ -0,0 +1,102 @@
+//! The scene is a stack of layers, each with its own dots and style, drawn
+//! bottom to top.
+
+use crate::color::Palette;
+use crate::diagram::{Diagram, Duplicates};
+use crate::draw::CellMesh;
+
+/// The golden ratio spreads the hues of consecutive layers far apart.
+const HUE_STEP: f32 = 0.618_034;
+/// How far apart the dots of a thinned layer are at least, in logical
+/// pixels.
+pub const LOD_SPACING: f64 = 4.0;
+
+pub struct Layer {
+    pub diagram: Diagram,
+    pub visible: bool,
+    /// Multiplies the alpha of the cell colors, so lower layers show through.
+    pub opacity: f32,
+    /// Drawn instead of `diagram` while it has too many dots to draw
+    /// quickly, see `thin`.
+    thinned: Option<Thinned>,
+    /// The filled cells of the diagram as drawn, see `mesh`.
+    mesh: Option<Mesh>,
+}
+
+struct Mesh {
+    /// The revision of the drawn diagram, and the smoothing and opacity
+    /// it was triangulated with.
+    revision: u64,
+    smoothing: usize,
+    opacity: f32,
+    mesh: CellMesh,
+}
+
+struct Thinned {
+    /// The revision of the diagram and the spacing it was thinned with.
+    revision: u64,
+    spacing: f64,
+    diagram: Diagram,
+}
+
+impl Layer {
+    /// The bottom layer looks like the classic single diagram; the ones above
+    /// get a palette of their own and let the lower layers shine through.
+    pub fn new(index: usize, duplicates: Duplicates) -> Layer {
+        let opacity = if index == 0 { 1.0 } else { 0.6 };
+        let mut diagram = Diagram::new();
+        diagram.palette = Layer::palette(index);
+        diagram.duplicates = duplicates;
+        Layer { diagram, visible: true, opacity, thinned: None, mesh: None }
+    }
+
+    /// The palette of the layer at `index` as long as no theme brings one.
+    pub fn palette(index: usize) -> Palette {
+        if index == 0 {
+            Palette::Random
+        } else {
+            Palette::Hue((index as f32 * HUE_STEP).fract())
+        }
+    }
+
+    /// Thins the diagram for drawing while it has more than `threshold`
+    /// dots, or never if that is 0, see `Diagram::thinned`. Only thins
+    /// again after the dots or `spacing` changed.
+    pub fn thin(&mut self, threshold: usize, spacing: f64) {
+        if threshold == 0 || self.diagram.dots.len() <= threshold {
+            self.thinned = None;
+            return;
+        }
+        let revision = self.diagram.revision();
+        if self.thinned.as_ref().is_some_and(|t| t.revision == revision && t.spacing == spacing) {
+            return;
+        }
+        self.thinned = Some(Thinned { revision, spacing, diagram: self.diagram.thinned(spacing) });
+    }
+
+    /// The diagram as it is drawn, thinned or not.
+    pub fn shown(&self) -> &Diagram {
+        self.thinned.as_ref().map_or(&self.diagram, |t| &t.diagram)
+    }
+
+    /// Triangulates the filled cells of `shown` with `smoothing`, unless
+    /// they haven't changed since the last time. Call after `thin`.
+    pub fn triangulate(&mut self, smoothing: usize) {
+        let revision = self.shown().revision();
+        if self.mesh.as_ref().is_some_and(|m| m.revision == revision && m.smoothing == smoothing && m.opacity == self.opacity) {
+            return;
+        }
+        let shown = self.shown();
+        let (_, visible) = shown.smoothed(smoothing);
+        let mesh = CellMesh::new(&visible, &shown.colors, self.opacity);
+        self.mesh = Some(Mesh { revision, smoothing, opacity: self.opacity, mesh });
+    }
+
+    /// The filled cells of `shown`, if they were triangulated with
+    /// `smoothing` since they last changed.
+    pub fn mesh(&self, smoothing: usize) -> Option<&CellMesh> {
+        self.mesh.as_ref()
+            .filter(|m| m.revision == self.shown().revision() && m.smoothing == smoothing && m.opacity == self.opacity)
+            .map(|m| &m.mesh)
+    }
+}
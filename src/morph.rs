@@ -0,0 +1,201 @@
+//! Animating the sites from one set of points to another, moving each in a
+//! straight line to its partner.
+
+use std::str::FromStr;
+
+use crate::color::Palette;
+
+/// Beyond this many points the optimal assignment takes too long, and
+/// partners are picked greedily instead.
+const OPTIMAL_LIMIT: usize = 1500;
+/// How far the arrow keys scrub, as a fraction of the whole morph.
+pub const SCRUB_STEP: f64 = 0.05;
+
+/// How the points of the two sets are paired up.
+#[derive(Clone, Copy)]
+pub enum Matching {
+    /// The first point goes to the first point, and so on.
+    Index,
+    /// The pairing with the least total squared travel distance.
+    Optimal,
+}
+
+impl FromStr for Matching {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Matching, String> {
+        match s {
+            "index" => Ok(Matching::Index),
+            "optimal" => Ok(Matching::Optimal),
+            _ => Err(format!("Unknown matching \"{}\", use index or optimal", s))
+        }
+    }
+}
+
+pub struct Morph {
+    from: Vec<[f64; 2]>,
+    to: Vec<[f64; 2]>,
+    /// One per site, for the whole animation.
+    pub colors: Vec<[f32; 4]>,
+    /// Progress from `0.0` at the start set to `1.0` at the end set.
+    pub t: f64,
+    /// The `t` the sites were last put down at, so they only are again
+    /// once it changes.
+    pub shown: Option<f64>,
+    pub playing: bool,
+    /// Seconds from start to end.
+    duration: f64,
+}
+
+impl Morph {
+    /// If the sets differ in size, the points left over in the larger one
+    /// pair up with the nearest point of the smaller one, so sites split
+    /// off or merge in.
+    pub fn new(from: &[[f64; 2]], to: &[[f64; 2]], matching: Matching, duration: f64, palette: &Palette) -> Morph {
+        let (pairs_from, pairs_to) = if from.is_empty() || to.is_empty() {
+            // Nothing to move from or to, the sites just stay put
+            let only = if from.is_empty() { to } else { from };
+            (only.to_vec(), only.to_vec())
+        } else if from.len() <= to.len() {
+            let partners = partners(from, to, matching);
+            (partners.iter().map(|&i| from[i]).collect(), to.to_vec())
+        } else {
+            let partners = partners(to, from, matching);
+            (from.to_vec(), partners.iter().map(|&i| to[i]).collect::<Vec<_>>())
+        };
+        let colors = pairs_from.iter().map(|_| palette.color()).collect();
+        Morph { from: pairs_from, to: pairs_to, colors, t: 0.0, shown: None, playing: true, duration: duration.max(0.001) }
+    }
+
+    /// Where the sites are at the current point of the animation.
+    pub fn dots(&self) -> Vec<[f64; 2]> {
+        let t = self.t;
+        self.from.iter().zip(&self.to)
+            .map(|(a, b)| [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t])
+            .collect()
+    }
+
+    /// Moves the animation on by `seconds` if it's playing. It stops at the end.
+    pub fn advance(&mut self, seconds: f64) {
+        if !self.playing {
+            return;
+        }
+        self.t = (self.t + seconds / self.duration).min(1.0);
+        if self.t >= 1.0 {
+            self.playing = false;
+        }
+    }
+
+    /// Pauses, or plays on. At the end, plays from the start again.
+    pub fn toggle(&mut self) {
+        if !self.playing && self.t >= 1.0 {
+            self.t = 0.0;
+        }
+        self.playing = !self.playing;
+    }
+
+    /// Jumps by `step`, pausing the animation.
+    pub fn scrub(&mut self, step: f64) {
+        self.playing = false;
+        self.t = (self.t + step).clamp(0.0, 1.0);
+    }
+}
+
+/// For every point in `large`, the index of its partner in `small`, which
+/// has at least one point and no more than `large`. Every point of `small`
+/// gets a partner of its own, the rest go to the nearest point.
+fn partners(small: &[[f64; 2]], large: &[[f64; 2]], matching: Matching) -> Vec<usize> {
+    let assignment = match matching {
+        Matching::Index => (0..small.len()).collect::<Vec<_>>(),
+        Matching::Optimal if large.len() <= OPTIMAL_LIMIT => optimal_assignment(small, large),
+        Matching::Optimal => greedy_assignment(small, large),
+    };
+    let mut partners = vec![None; large.len()];
+    for (i, &j) in assignment.iter().enumerate() {
+        partners[j] = Some(i);
+    }
+    partners.iter().zip(large).map(|(partner, point)| partner.unwrap_or_else(|| {
+        (0..small.len())
+            .min_by(|&a, &b| distance(&small[a], point).total_cmp(&distance(&small[b], point)))
+            .unwrap_or(0)
+    })).collect()
+}
+
+fn distance(a: &[f64; 2], b: &[f64; 2]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)
+}
+
+/// The Hungarian algorithm: assigns every point of `small` a distinct point
+/// of `large`, minimizing the sum of squared distances. O(n²m).
+fn optimal_assignment(small: &[[f64; 2]], large: &[[f64; 2]]) -> Vec<usize> {
+    let (n, m) = (small.len(), large.len());
+    // Potentials and matching are 1-based, 0 being a virtual start column
+    let (mut u, mut v) = (vec![0.0; n + 1], vec![0.0; m + 1]);
+    let mut row_of = vec![0usize; m + 1];
+    let mut way = vec![0usize; m + 1];
+    for row in 1..=n {
+        row_of[0] = row;
+        let mut col = 0;
+        let mut min_to = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[col] = true;
+            let i = row_of[col];
+            let (mut delta, mut next) = (f64::INFINITY, 0);
+            for j in 1..=m {
+                if used[j] {
+                    continue;
+                }
+                let reduced = distance(&small[i - 1], &large[j - 1]) - u[i] - v[j];
+                if reduced < min_to[j] {
+                    min_to[j] = reduced;
+                    way[j] = col;
+                }
+                if min_to[j] < delta {
+                    delta = min_to[j];
+                    next = j;
+                }
+            }
+            for j in 0..=m {
+                if used[j] {
+                    u[row_of[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_to[j] -= delta;
+                }
+            }
+            col = next;
+            if row_of[col] == 0 {
+                break;
+            }
+        }
+        // Flip the augmenting path
+        while col != 0 {
+            let previous = way[col];
+            row_of[col] = row_of[previous];
+            col = previous;
+        }
+    }
+
+    let mut assignment = vec![0; n];
+    for j in 1..=m {
+        if row_of[j] != 0 {
+            assignment[row_of[j] - 1] = j - 1;
+        }
+    }
+    assignment
+}
+
+/// Gives each point of `small` in turn the nearest point of `large` that's
+/// still free. Far from optimal, but quick enough for large sets.
+fn greedy_assignment(small: &[[f64; 2]], large: &[[f64; 2]]) -> Vec<usize> {
+    let mut taken = vec![false; large.len()];
+    small.iter().map(|a| {
+        let nearest = (0..large.len())
+            .filter(|&j| !taken[j])
+            .min_by(|&i, &j| distance(a, &large[i]).total_cmp(&distance(a, &large[j])))
+            .expect("large has at least as many points as small");
+        taken[nearest] = true;
+        nearest
+    }).collect()
+}
@@ -0,0 +1,113 @@
+//! Colors that drift on their own: the hue and brightness of every cell
+//! follow Perlin noise sampled at its dot, with time as the third
+//! dimension, so they shift gently and neighbors change together without a
+//! single dot moving.
+
+use std::collections::HashMap;
+
+use crate::color::hsv;
+use crate::diagram::Diagram;
+
+/// Noise cycles per pixel, so about four swells span the window.
+const SCALE: f64 = 1.0 / 300.0;
+/// Seconds between new colors, the noise changes too slowly to need more.
+const INTERVAL: f64 = 1.0 / 30.0;
+/// Where brightness is sampled, far enough from the hue's samples to be
+/// unrelated.
+const BRIGHTNESS_OFFSET: f64 = 101.7;
+
+pub struct NoiseColors {
+    /// The layer being colored.
+    pub layer: usize,
+    /// How far along the time axis the noise is.
+    time: f64,
+    /// Noise cycles per second.
+    speed: f64,
+    since_update: f64,
+    /// The colors the dots had before, by id, to put back afterwards.
+    saved: HashMap<u64, [f32; 4]>,
+}
+
+impl NoiseColors {
+    pub fn new(layer: usize, diagram: &Diagram, speed: f64) -> NoiseColors {
+        let saved = diagram.ids.iter().copied().zip(diagram.colors.iter().copied()).collect();
+        NoiseColors { layer, time: 0.0, speed, since_update: INTERVAL, saved }
+    }
+
+    /// Moves on in time. Returns whether the colors are due to change.
+    pub fn advance(&mut self, elapsed: f64) -> bool {
+        self.time += elapsed * self.speed;
+        self.since_update += elapsed;
+        if self.since_update < INTERVAL {
+            return false;
+        }
+        self.since_update = 0.0;
+        true
+    }
+
+    /// The color of every dot right now.
+    pub fn colors(&self, dots: &[[f64; 2]]) -> Vec<[f32; 4]> {
+        dots.iter().map(|d| {
+            let (x, y) = (d[0] * SCALE, d[1] * SCALE);
+            let hue = perlin([x, y, self.time]);
+            let brightness = perlin([x + BRIGHTNESS_OFFSET, y, self.time]);
+            hsv(0.55 + hue as f32 * 0.6, 0.55, 0.8 + brightness as f32 * 0.3)
+        }).collect()
+    }
+
+    /// The colors the dots had before, for those that were there then, and
+    /// the current ones for the others.
+    pub fn restored(&self, diagram: &Diagram) -> Vec<[f32; 4]> {
+        diagram.ids.iter().zip(&diagram.colors).map(|(id, c)| self.saved.get(id).copied().unwrap_or(*c)).collect()
+    }
+}
+
+/// Perlin's improved gradient noise, roughly between -1 and 1, smooth
+/// everywhere and zero at the corners of the unit cubes.
+fn perlin(p: [f64; 3]) -> f64 {
+    let cell = p.map(|v| v.floor());
+    let [fx, fy, fz] = [0, 1, 2].map(|k| p[k] - cell[k]);
+    let [u, v, w] = [fx, fy, fz].map(fade);
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        let h = hash([cell[0] as i64 + dx, cell[1] as i64 + dy, cell[2] as i64 + dz]);
+        gradient(h, fx - dx as f64, fy - dy as f64, fz - dz as f64)
+    };
+    let lerp = |t: f64, a: f64, b: f64| a + t * (b - a);
+    lerp(w,
+        lerp(v, lerp(u, corner(0, 0, 0), corner(1, 0, 0)), lerp(u, corner(0, 1, 0), corner(1, 1, 0))),
+        lerp(v, lerp(u, corner(0, 0, 1), corner(1, 0, 1)), lerp(u, corner(0, 1, 1), corner(1, 1, 1))))
+}
+
+/// Eases from 0 to 1 with no jump in slope or curvature at either end.
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// Mixes the corner of a cube into a number that looks random.
+fn hash(corner: [i64; 3]) -> u64 {
+    let mut h = 0x9e37_79b9_7f4a_7c15u64;
+    for c in corner {
+        h = (h ^ c as u64).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h ^= h >> 31;
+    }
+    h
+}
+
+/// The dot product of the offset from a corner with one of the twelve
+/// gradients toward the edges of a cube, picked by `hash`.
+fn gradient(hash: u64, x: f64, y: f64, z: f64) -> f64 {
+    match hash % 12 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
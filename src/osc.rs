@@ -0,0 +1,110 @@
+//! A minimal Open Sound Control listener, so the diagram can be driven from
+//! SuperCollider, TouchDesigner and friends. Understood messages:
+//!
+//! * `/point/add x y` adds a point
+//! * `/points/clear` removes all points
+//! * `/relax/step [n]` runs one (or `n`, at most 100) relaxation steps
+//!
+//! Numeric arguments may be sent as `i`, `f` or `d`. Bundles are unpacked and
+//! their messages applied right away, ignoring the time tag.
+
+use std::io;
+use std::net::UdpSocket;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use crate::control::Command;
+
+/// The most relaxation steps one message runs. They run between two frames,
+/// so many more would freeze the window.
+const MAX_RELAX_STEPS: f64 = 100.0;
+
+enum Argument {
+    Number(f64),
+    Other,
+}
+
+/// Starts listening for OSC packets on the UDP port in the background.
+pub fn listen(port: u16, commands: Sender<Command>) -> io::Result<()> {
+    let socket = UdpSocket::bind(("0.0.0.0", port))?;
+    println!("Listening for OSC on UDP port {}", port);
+    thread::spawn(move || {
+        let mut buffer = [0u8; 65536];
+        while let Ok(size) = socket.recv(&mut buffer) {
+            let mut messages = Vec::new();
+            parse_packet(&buffer[..size], &mut messages);
+            for (address, arguments) in messages {
+                match to_command(&address, &arguments) {
+                    Some(command) => if commands.send(command).is_err() { return },
+                    None => println!("Ignoring OSC message {}", address)
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+fn to_command(address: &str, arguments: &[Argument]) -> Option<Command> {
+    let numbers = arguments.iter().filter_map(|a| match a {
+        Argument::Number(n) => Some(*n),
+        Argument::Other => None
+    }).collect::<Vec<_>>();
+    match (address, numbers.as_slice()) {
+        ("/point/add", [x, y, ..]) => Some(Command::AddPoint([*x, *y])),
+        ("/points/clear", _) => Some(Command::Clear),
+        ("/relax/step", []) => Some(Command::Relax(1)),
+        ("/relax/step", [n, ..]) if n.is_finite() => Some(Command::Relax(n.clamp(0.0, MAX_RELAX_STEPS) as usize)),
+        _ => None
+    }
+}
+
+fn parse_packet(packet: &[u8], messages: &mut Vec<(String, Vec<Argument>)>) {
+    if let Some(mut rest) = packet.strip_prefix(b"#bundle\0") {
+        // Skip the time tag, then read size-prefixed elements
+        rest = rest.get(8..).unwrap_or(&[]);
+        while let Some(size) = read_i32(rest) {
+            let size = size.max(0) as usize;
+            let Some(element) = rest.get(4..4 + size) else { return };
+            parse_packet(element, messages);
+            rest = &rest[4 + size..];
+        }
+    } else if let Some(message) = parse_message(packet) {
+        messages.push(message);
+    }
+}
+
+fn parse_message(packet: &[u8]) -> Option<(String, Vec<Argument>)> {
+    let (address, rest) = read_string(packet)?;
+    let (tags, mut rest) = read_string(rest).unwrap_or((",".to_string(), &[]));
+    let mut arguments = Vec::new();
+    for tag in tags.strip_prefix(',')?.chars() {
+        let (argument, size) = match tag {
+            'i' => (Argument::Number(read_i32(rest)? as f64), 4),
+            'f' => (Argument::Number(f32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as f64), 4),
+            'd' => (Argument::Number(f64::from_be_bytes(rest.get(..8)?.try_into().ok()?)), 8),
+            'h' | 't' => (Argument::Other, 8),
+            's' | 'S' => {
+                let (_, after) = read_string(rest)?;
+                (Argument::Other, rest.len() - after.len())
+            }
+            // Tags without data
+            'T' | 'F' | 'N' | 'I' => (Argument::Other, 0),
+            _ => return None
+        };
+        arguments.push(argument);
+        rest = rest.get(size..)?;
+    }
+    Some((address, arguments))
+}
+
+fn read_i32(data: &[u8]) -> Option<i32> {
+    Some(i32::from_be_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+/// Reads a null-terminated string padded to a multiple of four bytes.
+fn read_string(data: &[u8]) -> Option<(String, &[u8])> {
+    let end = data.iter().position(|&b| b == 0)?;
+    let padded = (end + 4) & !3;
+    let text = String::from_utf8_lossy(&data[..end]).into_owned();
+    Some((text, data.get(padded..).unwrap_or(&[])))
+}
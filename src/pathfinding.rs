@@ -0,0 +1,194 @@
+//! Shortest paths from one dot to another, over either of the two graphs
+//! the diagram is made of. Along the Delaunay edges the path hops from dot
+//! to dot and is about as short as it gets. Along the Voronoi edges it
+//! keeps as far from every dot as it can, the way a robot steers clear of
+//! obstacles, leaving its start and goal through the corners of their cells.
+//!
+//! The search records the order nodes are settled in, so it can be
+//! replayed to show how Dijkstra's algorithm spreads out evenly while A*
+//! heads for the goal.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::adjacency::{point_key, polygon_edges};
+use crate::diagram::{Diagram, Point};
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Graph {
+    Delaunay,
+    Voronoi,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    Dijkstra,
+    /// Dijkstra's algorithm trying the nodes with the shortest way to the
+    /// goal as the crow flies first.
+    AStar,
+}
+
+impl Graph {
+    pub fn name(self) -> &'static str {
+        match self {
+            Graph::Delaunay => "Delaunay edges",
+            Graph::Voronoi => "Voronoi edges",
+        }
+    }
+}
+
+impl Method {
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::Dijkstra => "Dijkstra",
+            Method::AStar => "A*",
+        }
+    }
+}
+
+pub struct Search {
+    /// Where each node of the graph is.
+    pub nodes: Vec<[f64; 2]>,
+    /// The nodes next to each node.
+    pub neighbors: Vec<Vec<usize>>,
+    pub start: usize,
+    pub goal: usize,
+    /// The nodes in the order their shortest way was found, each with the
+    /// node it was reached from.
+    pub settled: Vec<(usize, Option<usize>)>,
+    /// The nodes from start to goal, empty if the goal can't be reached.
+    pub path: Vec<usize>,
+    pub length: f64,
+}
+
+/// A node to look at, cheapest estimate first.
+struct Open {
+    estimate: f64,
+    node: usize,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Open) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Open) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Open) -> Ordering {
+        // BinaryHeap pops the largest
+        other.estimate.total_cmp(&self.estimate).then(other.node.cmp(&self.node))
+    }
+}
+
+/// Searches the way from dot `start` to dot `goal`.
+pub fn find_path(diagram: &Diagram, graph: Graph, method: Method, start: usize, goal: usize) -> Search {
+    let (nodes, neighbors, from, to) = match graph {
+        Graph::Delaunay => delaunay_graph(diagram, start, goal),
+        Graph::Voronoi => voronoi_graph(diagram, start, goal),
+    };
+    let distance = |a: usize, b: usize| (nodes[a][0] - nodes[b][0]).hypot(nodes[a][1] - nodes[b][1]);
+    let guess = |node: usize| match method {
+        Method::Dijkstra => 0.0,
+        Method::AStar => distance(node, to),
+    };
+
+    let mut best = vec![f64::INFINITY; nodes.len()];
+    let mut parent = vec![None; nodes.len()];
+    let mut done = vec![false; nodes.len()];
+    let mut settled = Vec::new();
+    let mut open = BinaryHeap::new();
+    best[from] = 0.0;
+    open.push(Open { estimate: guess(from), node: from });
+    while let Some(Open { node, .. }) = open.pop() {
+        if done[node] {
+            continue;
+        }
+        done[node] = true;
+        settled.push((node, parent[node]));
+        if node == to {
+            break;
+        }
+        for &next in &neighbors[node] {
+            let way = best[node] + distance(node, next);
+            if way < best[next] {
+                best[next] = way;
+                parent[next] = Some(node);
+                open.push(Open { estimate: way + guess(next), node: next });
+            }
+        }
+    }
+
+    let mut path = Vec::new();
+    if done[to] {
+        let mut node = Some(to);
+        while let Some(n) = node {
+            path.push(n);
+            node = parent[n];
+        }
+        path.reverse();
+    }
+    Search { length: best[to], nodes, neighbors, start: from, goal: to, settled, path }
+}
+
+type Built = (Vec<[f64; 2]>, Vec<Vec<usize>>, usize, usize);
+
+/// The dots joined by their Delaunay edges.
+fn delaunay_graph(diagram: &Diagram, start: usize, goal: usize) -> Built {
+    let mut neighbors = vec![Vec::new(); diagram.dots.len()];
+    for t in diagram.triangles() {
+        for (a, b) in [(t.0, t.1), (t.1, t.2), (t.2, t.0)] {
+            if !neighbors[a].contains(&b) {
+                neighbors[a].push(b);
+                neighbors[b].push(a);
+            }
+        }
+    }
+    (diagram.dots.clone(), neighbors, start, goal)
+}
+
+/// The corners of the cells inside the window joined by the edges of the
+/// cells, with the start and goal dots first, each joined to the corners
+/// of its own cell.
+fn voronoi_graph(diagram: &Diagram, start: usize, goal: usize) -> Built {
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    let in_window = |p: &Point| (0.0..=width).contains(&p.0) && (0.0..=height).contains(&p.1);
+
+    let mut nodes = vec![diagram.dots[start], diagram.dots[goal]];
+    let mut neighbors = vec![Vec::new(), Vec::new()];
+    let mut index = HashMap::new();
+    let mut node = |p: &Point, nodes: &mut Vec<[f64; 2]>, neighbors: &mut Vec<Vec<usize>>| {
+        *index.entry(point_key(p)).or_insert_with(|| {
+            nodes.push([p.0, p.1]);
+            neighbors.push(Vec::new());
+            nodes.len() - 1
+        })
+    };
+    for (i, cell) in diagram.polygons.iter().enumerate() {
+        for (a, b) in polygon_edges(cell).filter(|(a, b)| in_window(a) && in_window(b)) {
+            let (a, b) = (node(a, &mut nodes, &mut neighbors), node(b, &mut nodes, &mut neighbors));
+            if a != b && !neighbors[a].contains(&b) {
+                neighbors[a].push(b);
+                neighbors[b].push(a);
+            }
+        }
+        for (end, dot) in [(0, start), (1, goal)] {
+            if i == dot {
+                for corner in cell.iter().filter(|p| in_window(p)) {
+                    let corner = node(corner, &mut nodes, &mut neighbors);
+                    neighbors[end].push(corner);
+                    neighbors[corner].push(end);
+                }
+            }
+        }
+    }
+    (nodes, neighbors, 0, 1)
+}
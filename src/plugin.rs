@@ -0,0 +1,255 @@
+//! Extension points for where new dots go and how the cells are colored.
+//! `R` and `C` step through the generators and colorers of a `Registry`,
+//! one per press, so anything added to it shows up there. Out of the box
+//! there are random dots and colors from the palette, doing what those keys
+//! always did, colors by category, map colors and colors by the `--field`
+//! if there is one; the `extra-strategies` feature adds a few more.
+
+use std::rc::Rc;
+
+use crate::adjacency::shared_edges;
+use crate::color::{group_color, hsv, Palette, UNGROUPED};
+use crate::coloring::dsatur;
+use crate::density::Density;
+use crate::diagram::Diagram;
+use crate::field::Field;
+use crate::metadata::categories;
+#[cfg(feature = "extra-strategies")]
+use crate::{geometry::area, DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// The colors of `MapColors` unless the palette has at least four, muted
+/// like those of a political map. The last two are rarely needed.
+const MAP_COLORS: [[f32; 4]; 6] = [
+    [0.98, 0.85, 0.62, 1.0],
+    [0.70, 0.84, 0.66, 1.0],
+    [0.95, 0.68, 0.66, 1.0],
+    [0.66, 0.78, 0.91, 1.0],
+    [0.83, 0.74, 0.90, 1.0],
+    [0.85, 0.85, 0.80, 1.0],
+];
+
+/// Makes a fresh set of dots.
+pub trait PointGenerator {
+    fn name(&self) -> &str;
+    /// About `count` dots inside the window.
+    fn generate(&mut self, count: usize) -> Vec<[f64; 2]>;
+}
+
+/// Picks the colors of the cells.
+pub trait CellColorer {
+    fn name(&self) -> &str;
+    /// A color for each dot of `diagram`, in order.
+    fn colors(&mut self, diagram: &Diagram) -> Vec<[f32; 4]>;
+}
+
+#[derive(Default)]
+pub struct Registry {
+    pub generators: Vec<Box<dyn PointGenerator>>,
+    pub colorers: Vec<Box<dyn CellColorer>>,
+}
+
+impl Registry {
+    /// The built-in generators and colorers, first random dots from
+    /// `density` and colors from the palette.
+    pub fn built_in(density: Density) -> Registry {
+        let mut registry = Registry::default();
+        registry.add_generator(Sampled(density));
+        registry.add_colorer(FromPalette);
+        registry.add_colorer(ByCategory);
+        registry.add_colorer(MapColors);
+        #[cfg(feature = "extra-strategies")]
+        {
+            registry.add_generator(Halton);
+            registry.add_generator(HexGrid);
+            registry.add_colorer(ByArea);
+            registry.add_colorer(ByNeighbors);
+        }
+        registry
+    }
+
+    pub fn add_generator(&mut self, generator: impl PointGenerator + 'static) {
+        self.generators.push(Box::new(generator));
+    }
+
+    pub fn add_colorer(&mut self, colorer: impl CellColorer + 'static) {
+        self.colorers.push(Box::new(colorer));
+    }
+}
+
+/// Random dots, spread out like `density` says.
+pub struct Sampled(pub Density);
+
+impl PointGenerator for Sampled {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn generate(&mut self, count: usize) -> Vec<[f64; 2]> {
+        (0..count).map(|_| self.0.sample()).collect()
+    }
+}
+
+/// New colors from the diagram's palette, team dots keeping their team's.
+pub struct FromPalette;
+
+impl CellColorer for FromPalette {
+    fn name(&self) -> &str {
+        "palette"
+    }
+
+    fn colors(&mut self, diagram: &Diagram) -> Vec<[f32; 4]> {
+        diagram.palette_colors()
+    }
+}
+
+/// A hue for every category of the dots' metadata, and gray for dots
+/// without one. Without any categories, the same as `FromPalette`.
+pub struct ByCategory;
+
+impl CellColorer for ByCategory {
+    fn name(&self) -> &str {
+        "category"
+    }
+
+    fn colors(&mut self, diagram: &Diagram) -> Vec<[f32; 4]> {
+        let (indices, count) = categories(&diagram.metadata);
+        if count == 0 {
+            return diagram.palette_colors();
+        }
+        indices.iter().map(|k| k.map_or(UNGROUPED, |k| group_color(k, count))).collect()
+    }
+}
+
+/// The average of a `--field` under each cell, from blue for its lowest
+/// values to red for its highest.
+pub struct ByField(pub Rc<Field>);
+
+impl CellColorer for ByField {
+    fn name(&self) -> &str {
+        "field"
+    }
+
+    fn colors(&mut self, diagram: &Diagram) -> Vec<[f32; 4]> {
+        diagram.visible.iter().zip(&diagram.dots).map(|(poly, &dot)| self.0.color(self.0.average(poly, dot))).collect()
+    }
+}
+
+/// As few colors as `coloring::dsatur` manages with, so that no two cells
+/// sharing an edge have the same one, like the countries of a map. Taken
+/// from the palette if it has at least four colors.
+pub struct MapColors;
+
+impl CellColorer for MapColors {
+    fn name(&self) -> &str {
+        "map colors"
+    }
+
+    fn colors(&mut self, diagram: &Diagram) -> Vec<[f32; 4]> {
+        let mut neighbors = vec![Vec::new(); diagram.dots.len()];
+        for edge in shared_edges(&diagram.polygons) {
+            let (a, b) = edge.cells;
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+        let palette = match &diagram.palette {
+            Palette::List(colors) if colors.len() >= 4 => colors.as_slice(),
+            _ => &MAP_COLORS,
+        };
+        // Past the palette, hues a golden angle apart
+        dsatur(&neighbors).into_iter()
+            .map(|k| palette.get(k).copied().unwrap_or_else(|| hsv(k as f32 * 0.618, 0.35, 0.9)))
+            .collect()
+    }
+}
+
+/// The Halton sequence in bases 2 and 3, which covers the window evenly
+/// without the clumps and holes of random dots.
+#[cfg(feature = "extra-strategies")]
+pub struct Halton;
+
+#[cfg(feature = "extra-strategies")]
+impl PointGenerator for Halton {
+    fn name(&self) -> &str {
+        "Halton sequence"
+    }
+
+    fn generate(&mut self, count: usize) -> Vec<[f64; 2]> {
+        let radical_inverse = |mut index: usize, base: usize| {
+            let (mut result, mut fraction) = (0.0, 1.0);
+            while index > 0 {
+                fraction /= base as f64;
+                result += fraction * (index % base) as f64;
+                index /= base;
+            }
+            result
+        };
+        (1..=count)
+            .map(|i| [radical_inverse(i, 2) * DEFAULT_WINDOW_WIDTH as f64, radical_inverse(i, 3) * DEFAULT_WINDOW_HEIGHT as f64])
+            .collect()
+    }
+}
+
+/// Rows of dots, every other one shifted by half a step, for hexagonal cells.
+#[cfg(feature = "extra-strategies")]
+pub struct HexGrid;
+
+#[cfg(feature = "extra-strategies")]
+impl PointGenerator for HexGrid {
+    fn name(&self) -> &str {
+        "hexagonal grid"
+    }
+
+    fn generate(&mut self, count: usize) -> Vec<[f64; 2]> {
+        let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        // Rows are √3/2 steps apart, so that's the area each dot gets in steps squared
+        let step = (width * height / (count.max(1) as f64 * 3f64.sqrt() / 2.0)).sqrt();
+        let row_step = step * 3f64.sqrt() / 2.0;
+        let mut dots = Vec::new();
+        for row in 0..(height / row_step).ceil() as usize {
+            let shift = if row % 2 == 0 { step / 4.0 } else { step * 3.0 / 4.0 };
+            for column in 0..(width / step).ceil() as usize {
+                let x = shift + column as f64 * step;
+                if x < width {
+                    dots.push([x, row_step / 2.0 + row as f64 * row_step]);
+                }
+            }
+        }
+        dots
+    }
+}
+
+/// From blue for the smallest cells to red for the largest.
+#[cfg(feature = "extra-strategies")]
+pub struct ByArea;
+
+#[cfg(feature = "extra-strategies")]
+impl CellColorer for ByArea {
+    fn name(&self) -> &str {
+        "cell area"
+    }
+
+    fn colors(&mut self, diagram: &Diagram) -> Vec<[f32; 4]> {
+        let areas = diagram.visible.iter().map(|cell| area(cell)).collect::<Vec<_>>();
+        let (smallest, largest) = areas.iter().fold((f64::INFINITY, 0.0f64), |(s, l), &a| (s.min(a), l.max(a)));
+        let range = (largest - smallest).max(f64::EPSILON);
+        areas.iter().map(|a| hsv(0.66 * (1.0 - ((a - smallest) / range) as f32), 0.6, 0.95)).collect()
+    }
+}
+
+/// A hue for every number of neighbors, so irregular cells stand out among
+/// the hexagons of a relaxed diagram.
+#[cfg(feature = "extra-strategies")]
+pub struct ByNeighbors;
+
+#[cfg(feature = "extra-strategies")]
+impl CellColorer for ByNeighbors {
+    fn name(&self) -> &str {
+        "neighbor count"
+    }
+
+    fn colors(&mut self, diagram: &Diagram) -> Vec<[f32; 4]> {
+        (0..diagram.dots.len())
+            .map(|i| hsv((diagram.neighbors(i).len() as f32 - 6.0) / 8.0 + 0.33, 0.55, 0.95))
+            .collect()
+    }
+}
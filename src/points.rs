@@ -0,0 +1,188 @@
+//! Reading and writing lists of points, with the metadata of each if they
+//! have some, see `metadata`.
+
+use std::io::{self, Read};
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::fit::{bounding_box, outside, Coords, Fit, MARGIN};
+use crate::metadata::{csv_split, dots_json, Metadata};
+use crate::DEFAULT_WINDOW_HEIGHT;
+
+/// Dots and the metadata of each, `metadata[i]` for `dots[i]`.
+#[derive(Default)]
+pub struct Points {
+    pub dots: Vec<[f64;2]>,
+    pub metadata: Vec<Metadata>,
+}
+
+impl Points {
+    /// Moves the dots where `fit` takes them.
+    pub fn apply(&mut self, fit: &Fit) {
+        for dot in &mut self.dots {
+            *dot = fit.apply(*dot);
+        }
+    }
+}
+
+/// Maps loaded dots from `coords` into the window, see `fit::Coords`, and
+/// returns how. Dots in the window's coordinates stay where they are, but
+/// if they are out of view or squeezed into a corner, this says so.
+pub fn fit_points(points: &mut Points, coords: Coords) -> Option<Fit> {
+    let Some(fit) = coords.fit(&points.dots) else {
+        let count = outside(&points.dots);
+        let tiny = bounding_box(&points.dots).is_some_and(|[low, high]| high[0] - low[0] < MARGIN && high[1] - low[1] < MARGIN);
+        if count > 0 {
+            println!("{} of {} dots are out of view, --fit or Shift+Z scales them into it", count, points.dots.len());
+        } else if tiny && points.dots.len() > 1 {
+            println!("All {} dots are within {} pixels, --fit or Shift+Z scales them into view", points.dots.len(), MARGIN);
+        }
+        return None;
+    };
+    points.apply(&fit);
+    Some(fit)
+}
+
+pub fn save_current_dots(dots: &[[f64;2]], metadata: &[Metadata]) {
+    println!("{}", dots_json(dots, metadata));
+}
+
+/// Loads dots for the `-j` option, where `-` means standard input.
+pub fn load_dots(json_file: &str) -> Vec<[f64;2]> {
+    load_points(json_file).dots
+}
+
+/// Like `load_dots`, with their metadata.
+pub fn load_points(json_file: &str) -> Points {
+    if json_file == "-" {
+        return read_points_from_stdin().expect("Can't load dots from standard input");
+    }
+    read_points(Path::new(json_file)).expect("Can't load dots from provided file")
+}
+
+/// Reads dots piped into standard input, in JSON or CSV form.
+fn read_points_from_stdin() -> io::Result<Points> {
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+    // JSON starts with a bracket or a brace, CSV with a number or a header
+    if text.trim_start().starts_with(['[', '{']) {
+        parse_json(&text)
+    } else {
+        parse_csv(&text)
+    }
+}
+
+/// Reads dots from a `.csv` file with one `x,y` pair per line, or from a
+/// JSON array of `[x, y]` pairs otherwise.
+pub fn read_dots(path: &Path) -> io::Result<Vec<[f64;2]>> {
+    read_points(path).map(|points| points.dots)
+}
+
+/// Like `read_dots`, with their metadata.
+pub fn read_points(path: &Path) -> io::Result<Points> {
+    let text = std::fs::read_to_string(path)?;
+    let is_csv = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        parse_csv(&text)
+    } else {
+        parse_json(&text)
+    }
+}
+
+/// Reads dots from text pasted from anywhere: a JSON array of `[x, y]`
+/// pairs, or numbers separated by whitespace, commas or semicolons, taken
+/// two at a time, as copied from a spreadsheet or a CSV file. A first line
+/// that isn't numeric is taken to be a header and skipped. Only JSON has
+/// room for metadata.
+pub fn parse_points(text: &str) -> io::Result<Points> {
+    if text.trim_start().starts_with(['[', '{']) {
+        return parse_json(text);
+    }
+    let mut numbers = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let fields = line.split(|c: char| c.is_whitespace() || c == ',' || c == ';').filter(|f| !f.is_empty());
+        match fields.map(str::parse::<f64>).collect::<Result<Vec<_>, _>>() {
+            Ok(values) => numbers.extend(values),
+            Err(_) if numbers.is_empty() && number == 0 => continue,
+            Err(_) => return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: expected numbers but found \"{}\"", number + 1, line.trim())))
+        }
+    }
+    if numbers.len() % 2 != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} numbers, which don't make x y pairs", numbers.len())));
+    }
+    let dots = numbers.chunks_exact(2).map(|pair| [pair[0], pair[1]]).collect::<Vec<_>>();
+    Ok(Points { metadata: vec![Metadata::default(); dots.len()], dots })
+}
+
+/// Reads a JSON array of `[x, y]` pairs and objects with `x`, `y` and
+/// metadata, or the cells of a GeoJSON export, whose points are flipped back
+/// upside down.
+fn parse_json(text: &str) -> io::Result<Points> {
+    let invalid = |what: String| io::Error::new(io::ErrorKind::InvalidData, what);
+    let value = serde_json::from_str::<Value>(text).map_err(|err| invalid(err.to_string()))?;
+    let pair = |x: Option<&Value>, y: Option<&Value>| Some([x?.as_f64()?, y?.as_f64()?]);
+    let mut points = Points::default();
+    if let Some(features) = value.get("features").and_then(Value::as_array) {
+        for (number, feature) in features.iter().enumerate() {
+            let properties = feature.get("properties").and_then(Value::as_object)
+                .ok_or_else(|| invalid(format!("feature {}: no properties", number + 1)))?;
+            let site = properties.get("site").and_then(Value::as_array)
+                .and_then(|site| pair(site.first(), site.get(1)))
+                .ok_or_else(|| invalid(format!("feature {}: no site", number + 1)))?;
+            points.dots.push([site[0], DEFAULT_WINDOW_HEIGHT as f64 - site[1]]);
+            points.metadata.push(Metadata::from_json(properties));
+        }
+        return Ok(points);
+    }
+    let items = value.as_array().ok_or_else(|| invalid("expected an array of dots".to_string()))?;
+    for (number, item) in items.iter().enumerate() {
+        let (dot, metadata) = match item {
+            Value::Object(object) => (pair(object.get("x"), object.get("y")), Metadata::from_json(object)),
+            Value::Array(xy) if xy.len() == 2 => (pair(xy.first(), xy.get(1)), Metadata::default()),
+            _ => (None, Metadata::default())
+        };
+        let dot = dot.ok_or_else(|| invalid(format!("dot {}: expected [x, y] or {{\"x\": x, \"y\": y, ...}} but found {}", number + 1, item)))?;
+        points.dots.push(dot);
+        points.metadata.push(metadata);
+    }
+    Ok(points)
+}
+
+/// Parses `x,y` lines. Blank lines and `#` comments are skipped. A first
+/// line that isn't numeric is taken to be a header: the dots are in the
+/// columns it names `x` and `y` if there are any, and in the first two
+/// otherwise, and the columns named like the fields of `Metadata` hold
+/// theirs.
+fn parse_csv(text: &str) -> io::Result<Points> {
+    let mut points = Points::default();
+    let mut header: Option<Vec<String>> = None;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = csv_split(line);
+        let column = |name: &str| header.as_ref().and_then(|h| h.iter().position(|c| c == name));
+        let number_in = |column: usize| fields.get(column).and_then(|f| f.trim().parse::<f64>().ok());
+        match (number_in(column("x").unwrap_or(0)), number_in(column("y").unwrap_or(1))) {
+            (Some(x), Some(y)) => {
+                let mut metadata = Metadata::default();
+                for (name, field) in header.iter().flatten().zip(&fields) {
+                    metadata.set(name, field);
+                }
+                points.dots.push([x, y]);
+                points.metadata.push(metadata);
+            }
+            _ if points.dots.is_empty() && number == 0 => {
+                header = Some(fields.iter().map(|f| f.trim().to_lowercase()).collect());
+            }
+            _ => return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: expected \"x,y\" but found \"{}\"", number + 1, line)))
+        }
+    }
+    Ok(points)
+}
@@ -0,0 +1,86 @@
+//! Power diagrams: Voronoi diagrams where every site has a weight, and a
+//! bigger weight pushes the edges away from its site. Tuning the weights
+//! gives cells of any areas, which the treemap and equal-area relaxation
+//! make use of.
+
+use crate::diagram::Point;
+use crate::geometry::{area, centroid, clip_to_half_plane};
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// Areas within this share of the window of their targets, summed over all
+/// cells and halved, are close enough.
+pub const MAX_ERROR: f64 = 0.01;
+/// How much a weight changes per square pixel a cell is off its target.
+const WEIGHT_RATE: f64 = 0.5;
+/// The nearest sites tried first for each cell, which are almost always all
+/// it takes. Only if they aren't are the others sorted as well.
+const NEAREST: usize = 32;
+
+/// The power cell of each site inside the window: the points `p` where
+/// `|p - site|² - weight` is smallest for that site. Empty for sites
+/// whose cell is squeezed out entirely.
+pub fn power_cells(sites: &[[f64; 2]], weights: &[f64]) -> Vec<Vec<Point>> {
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    let window = vec![(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)];
+    let heaviest = weights.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    sites.iter().zip(weights).enumerate().map(|(i, (a, wa))| {
+        let distance = |j: usize| (sites[j][0] - a[0]).hypot(sites[j][1] - a[1]);
+        let mut others = (0..sites.len()).filter(|&j| j != i).map(|j| (distance(j), j)).collect::<Vec<_>>();
+        let by_distance = |x: &(f64, usize), y: &(f64, usize)| x.0.total_cmp(&y.0);
+        let nearest = NEAREST.min(others.len());
+        if nearest < others.len() {
+            others.select_nth_unstable_by(nearest, by_distance);
+        }
+        others[..nearest].sort_unstable_by(by_distance);
+
+        let mut cell = window.clone();
+        for k in 0..others.len() {
+            if k == nearest {
+                others[nearest..].sort_unstable_by(by_distance);
+            }
+            let (d, j) = others[k];
+            // The edge with a site `d` away is at least this far from `a`,
+            // and further for sites further away, so once it's beyond the
+            // cell's furthest corner no other site can cut the cell
+            let reach = cell.iter().map(|p| (p.0 - a[0]).hypot(p.1 - a[1])).fold(0.0, f64::max);
+            if cell.is_empty() || d > 0.0 && (d * d + wa - heaviest) / (2.0 * d) > reach {
+                break;
+            }
+            let (b, wb) = (sites[j], weights[j]);
+            // |p - a|² - wa <= |p - b|² - wb, which is linear in p
+            let normal = (2.0 * (b[0] - a[0]), 2.0 * (b[1] - a[1]));
+            let offset = (b[0] * b[0] + b[1] * b[1]) - (a[0] * a[0] + a[1] * a[1]) - wb + wa;
+            cell = clip_to_half_plane(&cell, normal, offset);
+        }
+        cell
+    }).collect()
+}
+
+/// Moves the sites to the centers of their `cells`, then grows the weights
+/// of cells covering less of the window than their share in `targets` and
+/// shrinks those of cells covering more. Returns the new cells.
+pub fn fit_step(sites: &mut [[f64; 2]], weights: &mut [f64], cells: &[Vec<Point>], targets: &[f64]) -> Vec<Vec<Point>> {
+    for (site, cell) in sites.iter_mut().zip(cells) {
+        if let Some((x, y)) = centroid(cell) {
+            *site = [x, y];
+        }
+    }
+    let cells = power_cells(sites, weights);
+
+    let window = DEFAULT_WINDOW_WIDTH as f64 * DEFAULT_WINDOW_HEIGHT as f64;
+    for ((weight, cell), target) in weights.iter_mut().zip(&cells).zip(targets) {
+        *weight += WEIGHT_RATE * (target * window - area(cell));
+    }
+    power_cells(sites, weights)
+}
+
+/// The share of the window each cell covers.
+pub fn shares(cells: &[Vec<Point>]) -> Vec<f64> {
+    let window = DEFAULT_WINDOW_WIDTH as f64 * DEFAULT_WINDOW_HEIGHT as f64;
+    cells.iter().map(|cell| area(cell) / window).collect()
+}
+
+/// How much of the window is in the wrong cell, from 0 to 1.
+pub fn area_error(cells: &[Vec<Point>], targets: &[f64]) -> f64 {
+    shares(cells).iter().zip(targets).map(|(share, target)| (share - target).abs()).sum::<f64>() / 2.0
+}
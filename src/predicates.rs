@@ -0,0 +1,157 @@
+//! Exact orientation and in-circle tests. Both are first computed in plain
+//! floating point, which settles nearly every case; only when the result is
+//! too close to zero to trust its sign is the determinant evaluated again
+//! exactly, as a sum of non-overlapping floats (Shewchuk's adaptive
+//! predicates, without the intermediate stages). Collinear and co-circular
+//! points then get the same answer every time, instead of one that depends
+//! on rounding.
+
+use std::cmp::Ordering;
+
+/// Half the distance from 1 to the next float, the relative rounding error
+/// of one operation.
+const EPSILON: f64 = f64::EPSILON / 2.0;
+/// Relative error bounds of the floating point determinants, see Shewchuk,
+/// "Adaptive Precision Floating-Point Arithmetic and Fast Robust Geometric
+/// Predicates".
+const ORIENT_BOUND: f64 = (3.0 + 16.0 * EPSILON) * EPSILON;
+const IN_CIRCLE_BOUND: f64 = (10.0 + 96.0 * EPSILON) * EPSILON;
+const ORIENT_3D_BOUND: f64 = (7.0 + 56.0 * EPSILON) * EPSILON;
+
+/// Whether `c` lies to the left of the line from `a` to `b` (`Greater`, so
+/// `a`, `b`, `c` are counter-clockwise with y pointing up), to the right
+/// (`Less`) or on it (`Equal`).
+pub fn orient(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> Ordering {
+    let left = (a[0] - c[0]) * (b[1] - c[1]);
+    let right = (a[1] - c[1]) * (b[0] - c[0]);
+    let det = left - right;
+    if det.abs() >= ORIENT_BOUND * (left.abs() + right.abs()) {
+        return sign_of(det);
+    }
+
+    let [acx, acy, bcx, bcy] = [diff(a[0], c[0]), diff(a[1], c[1]), diff(b[0], c[0]), diff(b[1], c[1])];
+    sign(&sum(&product(&acx, &bcy), &negate(&product(&acy, &bcx))))
+}
+
+/// Whether `d` lies inside the circle through `a`, `b` and `c` (`Greater`),
+/// outside it (`Less`) or on it (`Equal`). `a`, `b` and `c` have to be
+/// counter-clockwise, see `orient`.
+pub fn in_circle(a: [f64; 2], b: [f64; 2], c: [f64; 2], d: [f64; 2]) -> Ordering {
+    let (adx, ady) = (a[0] - d[0], a[1] - d[1]);
+    let (bdx, bdy) = (b[0] - d[0], b[1] - d[1]);
+    let (cdx, cdy) = (c[0] - d[0], c[1] - d[1]);
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+    let (alift, blift, clift) = (adx * adx + ady * ady, bdx * bdx + bdy * bdy, cdx * cdx + cdy * cdy);
+    let det = alift * (bdxcdy - cdxbdy) + blift * (cdxady - adxcdy) + clift * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * alift
+        + (cdxady.abs() + adxcdy.abs()) * blift
+        + (adxbdy.abs() + bdxady.abs()) * clift;
+    if det.abs() >= IN_CIRCLE_BOUND * permanent {
+        return sign_of(det);
+    }
+
+    let [adx, ady, bdx, bdy, cdx, cdy] = [
+        diff(a[0], d[0]), diff(a[1], d[1]), diff(b[0], d[0]), diff(b[1], d[1]), diff(c[0], d[0]), diff(c[1], d[1]),
+    ];
+    let cross = |px: &[f64], py: &[f64], qx: &[f64], qy: &[f64]| sum(&product(px, qy), &negate(&product(qx, py)));
+    let lift = |x: &[f64], y: &[f64]| sum(&product(x, x), &product(y, y));
+    let det = sum(
+        &sum(&product(&lift(&adx, &ady), &cross(&bdx, &bdy, &cdx, &cdy)), &product(&lift(&bdx, &bdy), &cross(&cdx, &cdy, &adx, &ady))),
+        &product(&lift(&cdx, &cdy), &cross(&adx, &ady, &bdx, &bdy)));
+    sign(&det)
+}
+
+/// Whether `d` lies on the side of the plane through `a`, `b` and `c` that
+/// they are counter-clockwise seen from (`Greater`), on the other side
+/// (`Less`) or on the plane (`Equal`).
+pub fn orient3d(a: [f64; 3], b: [f64; 3], c: [f64; 3], d: [f64; 3]) -> Ordering {
+    let [adx, ady, adz] = [a[0] - d[0], a[1] - d[1], a[2] - d[2]];
+    let [bdx, bdy, bdz] = [b[0] - d[0], b[1] - d[1], b[2] - d[2]];
+    let [cdx, cdy, cdz] = [c[0] - d[0], c[1] - d[1], c[2] - d[2]];
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+    let det = adz * (bdxcdy - cdxbdy) + bdz * (cdxady - adxcdy) + cdz * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * adz.abs()
+        + (cdxady.abs() + adxcdy.abs()) * bdz.abs()
+        + (adxbdy.abs() + bdxady.abs()) * cdz.abs();
+    // The determinant is positive with d below the plane
+    if det.abs() >= ORIENT_3D_BOUND * permanent {
+        return sign_of(-det);
+    }
+
+    let [adx, ady, adz] = [0, 1, 2].map(|i| diff(a[i], d[i]));
+    let [bdx, bdy, bdz] = [0, 1, 2].map(|i| diff(b[i], d[i]));
+    let [cdx, cdy, cdz] = [0, 1, 2].map(|i| diff(c[i], d[i]));
+    let cross = |px: &[f64], py: &[f64], qx: &[f64], qy: &[f64]| sum(&product(px, qy), &negate(&product(qx, py)));
+    let det = sum(
+        &sum(&product(&adz, &cross(&bdx, &bdy, &cdx, &cdy)), &product(&bdz, &cross(&cdx, &cdy, &adx, &ady))),
+        &product(&cdz, &cross(&adx, &ady, &bdx, &bdy)));
+    sign(&negate(&det))
+}
+
+// Exact values are expansions: floats that don't overlap in their bits,
+// smallest first, adding up to the value. Zeros are left out.
+
+/// `a + b` as the rounded sum and the error that rounding made.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let b_virtual = x - a;
+    let a_virtual = x - b_virtual;
+    (x, (a - a_virtual) + (b - b_virtual))
+}
+
+/// `a - b` exactly.
+fn diff(a: f64, b: f64) -> Vec<f64> {
+    let (x, error) = two_sum(a, -b);
+    [error, x].into_iter().filter(|&v| v != 0.0).collect()
+}
+
+/// The expansion `e` plus `b`.
+fn grow(e: &[f64], b: f64) -> Vec<f64> {
+    let mut grown = Vec::with_capacity(e.len() + 1);
+    let mut q = b;
+    for &component in e {
+        let (x, error) = two_sum(q, component);
+        if error != 0.0 {
+            grown.push(error);
+        }
+        q = x;
+    }
+    if q != 0.0 {
+        grown.push(q);
+    }
+    grown
+}
+
+fn sum(e: &[f64], f: &[f64]) -> Vec<f64> {
+    f.iter().fold(e.to_vec(), |sum, &component| grow(&sum, component))
+}
+
+fn negate(e: &[f64]) -> Vec<f64> {
+    e.iter().map(|v| -v).collect()
+}
+
+fn product(e: &[f64], f: &[f64]) -> Vec<f64> {
+    let mut result = Vec::new();
+    for &x in e {
+        for &y in f {
+            // The product rounded and, thanks to the fused multiply-add, the
+            // exact rounding error
+            let rounded = x * y;
+            result = grow(&grow(&result, x.mul_add(y, -rounded)), rounded);
+        }
+    }
+    result
+}
+
+/// The largest component decides the sign.
+fn sign(e: &[f64]) -> Ordering {
+    e.last().map_or(Ordering::Equal, |&v| sign_of(v))
+}
+
+fn sign_of(v: f64) -> Ordering {
+    if v > 0.0 { Ordering::Greater } else if v < 0.0 { Ordering::Less } else { Ordering::Equal }
+}
@@ -0,0 +1,87 @@
+//! Proximity graphs: which dots count as neighbors when only those with
+//! nothing in between are joined. They are all made of Delaunay edges,
+//! each graph dropping more of them than the one before.
+
+use crate::diagram::Diagram;
+
+/// Where the beta-skeleton is the Gabriel graph, whose edges have no dot
+/// in the circle they are the diameter of.
+pub const GABRIEL_BETA: f64 = 1.0;
+/// Where it is the relative neighborhood graph, whose edges have no dot
+/// closer to both their ends than they are to each other.
+pub const RELATIVE_NEIGHBORHOOD_BETA: f64 = 2.0;
+
+/// The graphs there are, one drawn at a time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProximityGraph {
+    BetaSkeleton,
+    Urquhart,
+}
+
+impl ProximityGraph {
+    pub fn name(self) -> &'static str {
+        match self {
+            ProximityGraph::BetaSkeleton => "beta-skeleton",
+            ProximityGraph::Urquhart => "Urquhart graph",
+        }
+    }
+
+    /// The edges of the graph, `beta` only mattering for the beta-skeleton.
+    pub fn edges(self, diagram: &Diagram, beta: f64) -> Vec<(usize, usize)> {
+        match self {
+            ProximityGraph::BetaSkeleton => beta_skeleton(diagram, beta),
+            ProximityGraph::Urquhart => urquhart_graph(diagram),
+        }
+    }
+}
+
+/// The Delaunay edges, each once and with the lower dot first.
+fn delaunay_edges(diagram: &Diagram) -> Vec<(usize, usize)> {
+    let mut edges = diagram.triangles().into_iter()
+        .flat_map(|t| [(t.0, t.1), (t.1, t.2), (t.2, t.0)])
+        .map(|(a, b)| (a.min(b), a.max(b)))
+        .collect::<Vec<_>>();
+    edges.sort_unstable();
+    edges.dedup();
+    edges
+}
+
+/// The lune-based beta-skeleton for `beta` of at least 1: the edges whose
+/// lune holds no other dot, the lune being where two circles of diameter
+/// `beta` times the edge's length overlap. Their centers lie on the edge's
+/// line, and each passes through one of its ends. The larger `beta`, the
+/// larger the lunes and the fewer edges.
+pub fn beta_skeleton(diagram: &Diagram, beta: f64) -> Vec<(usize, usize)> {
+    let beta = beta.max(GABRIEL_BETA);
+    let dots = &diagram.dots;
+    delaunay_edges(diagram).into_iter().filter(|&(a, b)| {
+        let (p, q) = (dots[a], dots[b]);
+        let length = (q[0] - p[0]).hypot(q[1] - p[1]);
+        let radius = beta * length / 2.0;
+        let along = |t: f64| [p[0] + (q[0] - p[0]) * t, p[1] + (q[1] - p[1]) * t];
+        let centers = [along(beta / 2.0), along(1.0 - beta / 2.0)];
+        let inside = |r: [f64; 2]| centers.iter().all(|c| (r[0] - c[0]).hypot(r[1] - c[1]) < radius);
+        // The lune reaches this far from the middle of the edge, across it
+        let reach = length / 2.0 * (2.0 * beta - 1.0).sqrt();
+        !diagram.dots_within(along(0.5), reach).into_iter().any(|r| r != a && r != b && inside(dots[r]))
+    }).collect()
+}
+
+/// The Delaunay edges that aren't the longest side of any triangle. It
+/// holds the relative neighborhood graph, and with it the minimum spanning
+/// tree, and comes close to both while only looking at one triangle at a
+/// time.
+pub fn urquhart_graph(diagram: &Diagram) -> Vec<(usize, usize)> {
+    let dots = &diagram.dots;
+    let length = |(a, b): (usize, usize)| (dots[a][0] - dots[b][0]).hypot(dots[a][1] - dots[b][1]);
+    let mut longest = diagram.triangles().into_iter()
+        .filter_map(|t| {
+            [(t.0, t.1), (t.1, t.2), (t.2, t.0)].into_iter()
+                .map(|(a, b)| (a.min(b), a.max(b)))
+                .max_by(|&e, &f| length(e).total_cmp(&length(f)))
+        })
+        .collect::<Vec<_>>();
+    longest.sort_unstable();
+    longest.dedup();
+    delaunay_edges(diagram).into_iter().filter(|e| longest.binary_search(e).is_err()).collect()
+}
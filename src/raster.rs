@@ -0,0 +1,182 @@
+//! A small software renderer, so the diagram can be drawn without a window
+//! or a GPU. It implements piston's `Graphics` trait, which means the exact
+//! same drawing code runs on screen and off screen.
+
+use graphics::draw_state::DrawState;
+use graphics::{Context, Graphics, ImageSize};
+
+/// An RGBA image that solid-colored triangles can be drawn into.
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major RGBA bytes, top row first.
+    pub pixels: Vec<u8>,
+}
+
+/// Textures aren't supported by the software renderer; images draw as
+/// flat-colored quads.
+pub struct NoTexture;
+
+impl ImageSize for NoTexture {
+    fn get_size(&self) -> (u32, u32) {
+        (0, 0)
+    }
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Canvas {
+        Canvas { width, height, pixels: vec![0; width as usize * height as usize * 4] }
+    }
+
+    /// A drawing context mapping canvas pixels to the renderer's coordinates.
+    pub fn context(&self) -> Context {
+        Context::new_abs(self.width as f64, self.height as f64)
+    }
+
+    /// A canvas `factor` times smaller along each axis, every pixel the
+    /// average of a `factor` by `factor` block of this one's.
+    pub fn downsample(&self, factor: u32) -> Canvas {
+        let mut small = Canvas::new(self.width / factor, self.height / factor);
+        let samples = factor * factor;
+        for y in 0..small.height {
+            for x in 0..small.width {
+                let mut sum = [0u32; 4];
+                for sy in y * factor..(y + 1) * factor {
+                    let row = sy as usize * self.width as usize;
+                    for sx in x * factor..(x + 1) * factor {
+                        let i = (row + sx as usize) * 4;
+                        for (channel, total) in sum.iter_mut().enumerate() {
+                            *total += self.pixels[i + channel] as u32;
+                        }
+                    }
+                }
+                let i = (y as usize * small.width as usize + x as usize) * 4;
+                for (channel, total) in sum.iter().enumerate() {
+                    small.pixels[i + channel] = ((total + samples / 2) / samples) as u8;
+                }
+            }
+        }
+        small
+    }
+
+    /// A stable 64 bit FNV-1a hash of the pixels, for comparing renders.
+    pub fn hash(&self) -> u64 {
+        self.pixels.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    }
+
+    fn blend(&mut self, x: u32, y: u32, color: &[f32; 4]) {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let alpha = color[3].clamp(0.0, 1.0);
+        for (channel, &value) in color[..3].iter().enumerate() {
+            let dst = self.pixels[i + channel] as f32 / 255.0;
+            let out = value.clamp(0.0, 1.0) * alpha + dst * (1.0 - alpha);
+            self.pixels[i + channel] = (out * 255.0).round() as u8;
+        }
+        let dst_alpha = self.pixels[i + 3] as f32 / 255.0;
+        self.pixels[i + 3] = ((alpha + dst_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+    }
+
+    /// Converts normalized device coordinates back to canvas pixels.
+    fn to_pixels(&self, v: &[f32; 2]) -> [f32; 2] {
+        [(v[0] + 1.0) * 0.5 * self.width as f32, (1.0 - v[1]) * 0.5 * self.height as f32]
+    }
+
+    /// Fills a triangle, sampling at pixel centers. Edges shared by two
+    /// triangles are owned by exactly one of them, so translucent meshes
+    /// don't get darker seams.
+    fn fill_triangle(&mut self, vertices: &[[f32; 2]], colors: &[[f32; 4]]) {
+        let (a, b, c) = (self.to_pixels(&vertices[0]), self.to_pixels(&vertices[1]), self.to_pixels(&vertices[2]));
+        let area = edge(&a, &b, &c);
+        if area == 0.0 {
+            return;
+        }
+
+        let min_x = a[0].min(b[0]).min(c[0]).floor().max(0.0) as u32;
+        let min_y = a[1].min(b[1]).min(c[1]).floor().max(0.0) as u32;
+        let max_x = (a[0].max(b[0]).max(c[0]).ceil().max(0.0) as u32).min(self.width);
+        let max_y = (a[1].max(b[1]).max(c[1]).ceil().max(0.0) as u32).min(self.height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = [x as f32 + 0.5, y as f32 + 0.5];
+                // Normalize by the area so the weights are positive inside,
+                // whichever way the triangle is wound
+                let w = [edge(&b, &c, &p) / area, edge(&c, &a, &p) / area, edge(&a, &b, &p) / area];
+                let owned = [owns(&b, &c, w[0], area), owns(&c, &a, w[1], area), owns(&a, &b, w[2], area)];
+                if owned.iter().all(|&o| o) {
+                    let mut color = [0.0; 4];
+                    for (k, channel) in color.iter_mut().enumerate() {
+                        *channel = w[0] * colors[0][k] + w[1] * colors[1][k] + w[2] * colors[2][k];
+                    }
+                    self.blend(x, y, &color);
+                }
+            }
+        }
+    }
+}
+
+fn edge(a: &[f32; 2], b: &[f32; 2], p: &[f32; 2]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// Whether a sample with barycentric weight `w` opposite the edge `a -> b`
+/// belongs to the triangle. Samples exactly on the edge go to the triangle
+/// that traverses the edge in one particular direction.
+fn owns(a: &[f32; 2], b: &[f32; 2], w: f32, area: f32) -> bool {
+    if w != 0.0 {
+        return w > 0.0;
+    }
+    let (dx, dy) = ((b[0] - a[0]) * area.signum(), (b[1] - a[1]) * area.signum());
+    dy > 0.0 || (dy == 0.0 && dx < 0.0)
+}
+
+impl Graphics for Canvas {
+    type Texture = NoTexture;
+
+    fn clear_color(&mut self, color: [f32; 4]) {
+        let bytes = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&bytes);
+        }
+    }
+
+    fn clear_stencil(&mut self, _value: u8) {}
+
+    fn tri_list<F>(&mut self, _draw_state: &DrawState, color: &[f32; 4], mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        f(&mut |vertices| {
+            for triangle in vertices.chunks_exact(3) {
+                self.fill_triangle(triangle, &[*color; 3]);
+            }
+        });
+    }
+
+    fn tri_list_c<F>(&mut self, _draw_state: &DrawState, mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        f(&mut |vertices, colors| {
+            for (triangle, colors) in vertices.chunks_exact(3).zip(colors.chunks_exact(3)) {
+                self.fill_triangle(triangle, colors);
+            }
+        });
+    }
+
+    fn tri_list_uv<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], _texture: &NoTexture, mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        self.tri_list(draw_state, color, |sink| f(&mut |vertices, _uvs| sink(vertices)));
+    }
+
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, _texture: &NoTexture, mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        self.tri_list_c(draw_state, |sink| f(&mut |vertices, _uvs, colors| sink(vertices, colors)));
+    }
+}
@@ -0,0 +1,103 @@
+//! Recording a session as a scenario file, and playing one back in the
+//! window at its original pace or faster.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use piston_window::*;
+
+use crate::diagram::seed_random;
+use crate::scenario::{self, button_name, touch_name, Command};
+
+/// Writes the input events of a session as scenario commands, with waits
+/// for the time between them. See `scenario` for the format.
+pub struct Recorder {
+    file: File,
+    last: Instant,
+}
+
+impl Recorder {
+    /// Starts recording. The seed is written first, so the random dots and
+    /// colors come out the same on replay.
+    pub fn create(path: &str, seed: u64, now: Instant) -> io::Result<Recorder> {
+        let mut file = File::create(path)?;
+        writeln!(file, "# Recorded session, replay with --replay or --run-scenario")?;
+        writeln!(file, "seed {}", seed)?;
+        Ok(Recorder { file, last: now })
+    }
+
+    pub fn record(&mut self, e: &Event, now: Instant) -> io::Result<()> {
+        let line = match e {
+            Event::Input(Input::Move(Motion::MouseCursor([x, y])), _) => format!("move {} {}", x, y),
+            Event::Input(Input::Move(Motion::MouseScroll([x, y])), _) => format!("scroll {} {}", x, y),
+            Event::Input(Input::Move(Motion::ControllerAxis(args)), _) => format!("axis {} {}", args.axis, args.position),
+            Event::Input(Input::Move(Motion::Touch(args)), _) => {
+                let [x, y] = args.position();
+                format!("touch {} {} {} {} {}", touch_name(args.touch), args.id, x, y, args.pressure())
+            }
+            Event::Input(Input::Button(args), _) => {
+                let Some(name) = button_name(args.button) else { return Ok(()) };
+                match args.state {
+                    ButtonState::Press => format!("press {}", name),
+                    ButtonState::Release => format!("release {}", name),
+                }
+            }
+            Event::Input(Input::FileDrag(FileDrag::Drop(path)), _) => format!("drop {}", path.display()),
+            // Scenario lines lose their spaces, which the command palette ignores anyway
+            Event::Input(Input::Text(text), _) if !text.trim().is_empty() => format!("type {}", text.trim()),
+            _ => return Ok(())
+        };
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        if elapsed >= 0.001 {
+            writeln!(self.file, "wait {:.3}", elapsed)?;
+            self.last = now;
+        }
+        writeln!(self.file, "{}", line)
+    }
+}
+
+/// Feeds the commands of a scenario to the app as they fall due.
+pub struct Player {
+    commands: VecDeque<Command>,
+    /// 2 plays twice as fast as recorded.
+    speed: f64,
+    /// When the waits played so far are over.
+    clock: Instant,
+}
+
+impl Player {
+    /// Reads the scenario and seeds the random generator for it, so create
+    /// the player before the app.
+    pub fn open(path: &str, speed: f64, now: Instant) -> io::Result<Player> {
+        let mut commands = scenario::read(path)?;
+        scenario::start(&mut commands);
+        Ok(Player { commands, speed, clock: now })
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// The events that are due by `now`.
+    pub fn due(&mut self, now: Instant) -> Vec<Event> {
+        let mut events = Vec::new();
+        while let Some(command) = self.commands.front() {
+            match command {
+                Command::Wait(seconds) => {
+                    let until = self.clock + Duration::from_secs_f64(seconds / self.speed);
+                    if until > now {
+                        break;
+                    }
+                    self.clock = until;
+                }
+                Command::Seed(seed) => seed_random(*seed),
+                _ => ()
+            }
+            events.extend(scenario::events(command));
+            self.commands.pop_front();
+        }
+        events
+    }
+}
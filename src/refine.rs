@@ -0,0 +1,176 @@
+//! Ruppert's Delaunay refinement: Steiner points are added until every
+//! triangle inside a boundary polygon has no angle below a minimum, which
+//! makes a mesh good enough for simulations out of any set of dots.
+//!
+//! The sides of the boundary are split in half while a point lies inside
+//! the circle they are the diameter of. Once none does, each side is an edge
+//! of the Delaunay triangulation, so the mesh follows the boundary without a
+//! constrained triangulation. The worst triangles then get a point at their
+//! circumcenters, unless that point would lie too close to a side, which is
+//! split instead. Minimum angles up to about 20.7 degrees are always
+//! reached; above that the refinement may stop at `max_dots`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::delaunay::Delaunay;
+use crate::stats::min_angle;
+
+pub struct Refinement {
+    /// The corners of the boundary, the dots inside it and the Steiner
+    /// points, in that order.
+    pub dots: Vec<[f64; 2]>,
+    /// How many Steiner points were added.
+    pub added: usize,
+    /// The smallest angle of the triangles inside the boundary.
+    pub smallest: f64,
+    /// Whether all triangles inside the boundary meet the minimum angle,
+    /// rather than running into `max_dots`.
+    pub complete: bool,
+}
+
+/// Refines the Delaunay triangulation of the dots inside `boundary` until
+/// its triangles inside the boundary have no angle below `angle` degrees
+/// or there are `max_dots` dots.
+pub fn refine(dots: &[[f64; 2]], boundary: &[[f64; 2]], angle: f64, max_dots: usize) -> Refinement {
+    let mut points = boundary.to_vec();
+    points.extend(dots.iter().copied().filter(|&d| inside(boundary, d)));
+    let mut segments = (0..boundary.len()).map(|i| (i, (i + 1) % boundary.len())).collect::<Vec<_>>();
+
+    // A square around the boundary with room to spare
+    let xs = boundary.iter().map(|p| p[0]);
+    let ys = boundary.iter().map(|p| p[1]);
+    let (left, right) = (xs.clone().fold(f64::INFINITY, f64::min), xs.fold(f64::NEG_INFINITY, f64::max));
+    let (top, bottom) = (ys.clone().fold(f64::INFINITY, f64::min), ys.fold(f64::NEG_INFINITY, f64::max));
+    let center = [(left + right) / 2.0, (top + bottom) / 2.0];
+    let radius = 2.0 * (right - left).max(bottom - top).max(1.0);
+    let mut triangulation = Delaunay::new(&points, center, radius);
+
+    let original = points.len();
+    // Triangles whose circumcenter falls outside the boundary without
+    // coming close to a side, which only happens where the boundary
+    // pinches, so no point can fix them
+    let mut hopeless = HashSet::new();
+    let mut complete = false;
+    while points.len() < max_dots {
+        let triangles = triangulation.triangles().into_iter().map(|t| [t.0, t.1, t.2]).collect::<Vec<_>>();
+
+        let mut opposite = HashMap::<(usize, usize), Vec<usize>>::new();
+        for &[a, b, c] in &triangles {
+            for (p, q, r) in [(a, b, c), (b, c, a), (c, a, b)] {
+                opposite.entry((p.min(q), p.max(q))).or_default().push(r);
+            }
+        }
+        // A side that isn't an edge has points in every circle through its
+        // ends, its diametral one included
+        let encroached = segments.iter().copied().filter(|&(a, b)| {
+            opposite.get(&(a.min(b), a.max(b))).is_none_or(|across| across.iter().any(|&p| encroaches(points[p], points[a], points[b])))
+        }).collect::<Vec<_>>();
+        if !encroached.is_empty() {
+            for segment in encroached {
+                split(segment, &mut segments, &mut points, &mut triangulation);
+            }
+            continue;
+        }
+
+        let mut bad = triangles.iter()
+            .filter(|t| !hopeless.contains(*t))
+            .map(|t| (*t, t.map(|i| points[i])))
+            .filter(|&(_, corners)| inside(boundary, middle(corners)))
+            .map(|(t, corners)| (t, corners, min_angle(corners)))
+            .filter(|&(_, _, a)| a < angle)
+            .collect::<Vec<_>>();
+        if bad.is_empty() {
+            complete = true;
+            break;
+        }
+        // Worst first. A point only changes the triangles whose circumcircles
+        // it lies in, so the others are still there to refine in this round
+        bad.sort_by(|a, b| a.2.total_cmp(&b.2));
+        let mut added = Vec::<[f64; 2]>::new();
+        for (triangle, corners, _) in bad {
+            if points.len() >= max_dots {
+                break;
+            }
+            let Some(center) = circumcenter(corners) else {
+                hopeless.insert(triangle);
+                continue;
+            };
+            let radius = distance(center, corners[0]);
+            if added.iter().any(|&p| distance(center, p) < radius) {
+                continue;
+            }
+            let encroached = segments.iter().copied()
+                .filter(|&(a, b)| encroaches(center, points[a], points[b]))
+                .collect::<Vec<_>>();
+            if !encroached.is_empty() {
+                for segment in encroached {
+                    split(segment, &mut segments, &mut points, &mut triangulation);
+                }
+                break;
+            } else if inside(boundary, center) {
+                points.push(center);
+                triangulation.add(center);
+                added.push(center);
+            } else {
+                hopeless.insert(triangle);
+            }
+        }
+    }
+
+    let smallest = triangulation.triangles().into_iter()
+        .map(|t| [points[t.0], points[t.1], points[t.2]])
+        .filter(|&t| inside(boundary, middle(t)))
+        .map(min_angle)
+        .fold(f64::INFINITY, f64::min);
+    Refinement { added: points.len() - original, dots: points, smallest, complete }
+}
+
+/// Whether `p` is inside the polygon, by the even-odd rule.
+pub fn inside(polygon: &[[f64; 2]], p: [f64; 2]) -> bool {
+    let mut inside = false;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        if (a[1] > p[1]) != (b[1] > p[1]) && p[0] < a[0] + (p[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0]) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Whether `p` is strictly inside the circle with `a` and `b` on opposite
+/// sides, which is when the angle at `p` is obtuse.
+fn encroaches(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> bool {
+    p != a && p != b && (a[0] - p[0]) * (b[0] - p[0]) + (a[1] - p[1]) * (b[1] - p[1]) < 0.0
+}
+
+/// Splits a side of the boundary in half at a new point.
+fn split(segment: (usize, usize), segments: &mut Vec<(usize, usize)>, points: &mut Vec<[f64; 2]>, triangulation: &mut Delaunay) {
+    let Some(index) = segments.iter().position(|&s| s == segment) else { return };
+    let (a, b) = segment;
+    let half = [(points[a][0] + points[b][0]) / 2.0, (points[a][1] + points[b][1]) / 2.0];
+    let m = points.len();
+    points.push(half);
+    triangulation.add(half);
+    segments[index] = (a, m);
+    segments.push((m, b));
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+fn middle([a, b, c]: [[f64; 2]; 3]) -> [f64; 2] {
+    [(a[0] + b[0] + c[0]) / 3.0, (a[1] + b[1] + c[1]) / 3.0]
+}
+
+fn circumcenter([a, b, c]: [[f64; 2]; 3]) -> Option<[f64; 2]> {
+    let d = 2.0 * (a[0] * (b[1] - c[1]) + b[0] * (c[1] - a[1]) + c[0] * (a[1] - b[1]));
+    if d == 0.0 {
+        return None;
+    }
+    let (a2, b2, c2) = (a[0] * a[0] + a[1] * a[1], b[0] * b[0] + b[1] * b[1], c[0] * c[0] + c[1] * c[1]);
+    Some([
+        (a2 * (b[1] - c[1]) + b2 * (c[1] - a[1]) + c2 * (a[1] - b[1])) / d,
+        (a2 * (c[0] - b[0]) + b2 * (a[0] - c[0]) + c2 * (b[0] - a[0])) / d,
+    ])
+}
@@ -0,0 +1,120 @@
+//! Merging neighboring cells of the same color into regions, like the
+//! countries of a map.
+
+use std::collections::HashMap;
+
+use crate::adjacency::{point_key as key, polygon_edges as edges, shared_edges};
+use crate::diagram::{Diagram, Point};
+
+pub struct Region {
+    /// The average color of its cells.
+    pub color: [f32; 4],
+    /// Indices of the cells that make up the region.
+    pub cells: Vec<usize>,
+    /// Closed outlines without the edges between the region's own cells,
+    /// holes included. The first point isn't repeated at the end.
+    pub boundary: Vec<Vec<Point>>,
+}
+
+/// Merges cells that share an edge and whose colors differ by at most
+/// `tolerance` in every channel.
+pub fn merge_regions(diagram: &Diagram, tolerance: f32) -> Vec<Region> {
+    let polygons = &diagram.polygons;
+    let similar = |a: usize, b: usize| {
+        (0..3).all(|k| (diagram.colors[a][k] - diagram.colors[b][k]).abs() <= tolerance)
+    };
+
+    let mut parent = (0..polygons.len()).collect::<Vec<_>>();
+    for edge in shared_edges(polygons) {
+        let (a, b) = edge.cells;
+        if similar(a, b) {
+            union(&mut parent, a, b);
+        }
+    }
+
+    let mut regions = Vec::<Region>::new();
+    let mut region_of_root = HashMap::new();
+    for cell in 0..polygons.len() {
+        let root = find(&mut parent, cell);
+        let index = *region_of_root.entry(root).or_insert_with(|| {
+            regions.push(Region { color: [0.0; 4], cells: Vec::new(), boundary: Vec::new() });
+            regions.len() - 1
+        });
+        regions[index].cells.push(cell);
+    }
+    for region in &mut regions {
+        let mut sum = [0.0; 4];
+        for &cell in &region.cells {
+            for (k, channel) in sum.iter_mut().enumerate() {
+                *channel += diagram.colors[cell][k];
+            }
+        }
+        region.color = sum.map(|channel| channel / region.cells.len() as f32);
+        region.boundary = boundary(region.cells.iter().map(|&cell| &polygons[cell][..]));
+    }
+    regions
+}
+
+/// Traces the outlines of the union of `cells`. All cells wind the same
+/// way, so an edge between two of them shows up once in each direction,
+/// and the edges left over chain up into closed loops.
+fn boundary<'a>(cells: impl Iterator<Item = &'a [Point]>) -> Vec<Vec<Point>> {
+    let mut directed = Vec::new();
+    for poly in cells {
+        directed.extend(edges(poly).map(|(a, b)| (*a, *b)));
+    }
+    let mut count = HashMap::<_, usize>::new();
+    for (a, b) in &directed {
+        *count.entry((key(a), key(b))).or_default() += 1;
+    }
+    let outer = directed.into_iter()
+        .filter(|(a, b)| !count.contains_key(&(key(b), key(a))))
+        .collect::<Vec<_>>();
+
+    let mut leaving = HashMap::<_, Vec<usize>>::new();
+    for (i, (a, _)) in outer.iter().enumerate() {
+        leaving.entry(key(a)).or_default().push(i);
+    }
+    let mut used = vec![false; outer.len()];
+    let mut loops = Vec::new();
+    for start in 0..outer.len() {
+        if used[start] {
+            continue;
+        }
+        let mut outline = Vec::new();
+        let mut edge = start;
+        loop {
+            used[edge] = true;
+            let (a, b) = outer[edge];
+            outline.push(a);
+            // Where regions touch at a single corner, any way on will do
+            let next = leaving.get(&key(&b)).and_then(|out| out.iter().copied().find(|&e| !used[e]));
+            match next {
+                Some(next) => edge = next,
+                None => break,
+            }
+        }
+        loops.push(outline);
+    }
+    loops
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    // Point everything on the way straight at the root
+    let mut i = i;
+    while parent[i] != root {
+        let next = parent[i];
+        parent[i] = root;
+        i = next;
+    }
+    root
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    parent[a] = b;
+}
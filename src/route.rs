@@ -0,0 +1,89 @@
+//! A shortest path search played back: the nodes light up in the order
+//! their shortest way was found, with the edges they were reached by, and
+//! the open frontier around them, until the path to the goal is drawn.
+
+use graphics::{Context, Graphics};
+
+use crate::draw::{draw_ellipse, draw_marker};
+use crate::pathfinding::{Graph, Method, Search};
+
+/// How long the nodes take to light up, however many there are.
+const SEARCH_SECONDS: f64 = 3.0;
+/// Nodes lit up per second at the least, so small searches don't crawl.
+const MIN_NODES_PER_SECOND: f64 = 10.0;
+
+const EXPLORED: [f32; 4] = [0.2, 0.4, 0.9, 0.8];
+const FRONTIER: [f32; 4] = [1.0, 0.55, 0.0, 1.0];
+const PATH: [f32; 4] = [0.85, 0.0, 0.1, 1.0];
+pub const START: [f32; 4] = [0.1, 0.7, 0.2, 1.0];
+const GOAL: [f32; 4] = [0.85, 0.0, 0.1, 1.0];
+
+pub struct Route {
+    pub graph: Graph,
+    pub method: Method,
+    search: Search,
+    /// How many of the settled nodes are lit up, fractional in between.
+    shown: f64,
+}
+
+impl Route {
+    pub fn new(graph: Graph, method: Method, search: Search) -> Route {
+        Route { graph, method, search, shown: 0.0 }
+    }
+
+    pub fn done(&self) -> bool {
+        self.shown >= self.search.settled.len() as f64
+    }
+
+    pub fn advance(&mut self, seconds: f64) {
+        let total = self.search.settled.len() as f64;
+        let speed = (total / SEARCH_SECONDS).max(MIN_NODES_PER_SECOND);
+        self.shown = (self.shown + speed * seconds).min(total);
+    }
+
+    pub fn draw<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let nodes = &self.search.nodes;
+        let settled = &self.search.settled[..self.shown as usize];
+        let line = |color, radius, a: usize, b: usize, g: &mut G| {
+            graphics::line(color, radius, [nodes[a][0], nodes[a][1], nodes[b][0], nodes[b][1]], c.transform, g);
+        };
+
+        let mut lit = vec![false; nodes.len()];
+        for &(node, parent) in settled {
+            lit[node] = true;
+            if let Some(parent) = parent {
+                line(EXPLORED, 1.0, parent, node, g);
+            }
+        }
+        if !self.done() {
+            let mut frontier = vec![false; nodes.len()];
+            for &(node, _) in settled {
+                for &next in self.search.neighbors[node].iter().filter(|&&n| !lit[n]) {
+                    frontier[next] = true;
+                }
+            }
+            for (node, _) in frontier.iter().enumerate().filter(|(_, &f)| f) {
+                draw_ellipse(&nodes[node], c, g, FRONTIER);
+            }
+        } else {
+            for pair in self.search.path.windows(2) {
+                line(PATH, 2.5, pair[0], pair[1], g);
+            }
+        }
+        draw_marker(&nodes[self.search.start], c, g, START);
+        draw_marker(&nodes[self.search.goal], c, g, GOAL);
+    }
+
+    /// What's going on, for the status panel.
+    pub fn describe(&self) -> String {
+        let name = format!("{} along {}", self.method.name(), self.graph.name());
+        if !self.done() {
+            format!("{}: {} of {} nodes settled", name, self.shown as usize, self.search.settled.len())
+        } else if self.search.path.is_empty() {
+            format!("{}: no way to the goal after {} nodes", name, self.search.settled.len())
+        } else {
+            format!("{}: path of {} edges, {:.0} pixels long, {} nodes settled",
+                name, self.search.path.len() - 1, self.search.length, self.search.settled.len())
+        }
+    }
+}
@@ -0,0 +1,294 @@
+//! Deterministic replay of scripted input against the software renderer.
+//!
+//! A scenario file holds one command per line; blank lines and lines
+//! starting with `#` are ignored:
+//!
+//! ```text
+//! seed 42          # reseed the random generator (scenarios start with seed 0)
+//! move 100 200     # move the cursor
+//! click 100 200    # move the cursor there and click
+//! key R            # press and release a key, e.g. `key Ctrl+C`
+//! type exp svg     # type text, like into the command palette
+//! press MouseLeft  # press a key or mouse button without releasing it
+//! release MouseLeft
+//! drop points.json # drop a file on the window
+//! scroll 0 1       # turn the mouse wheel, up for positive
+//! touch start 1 100 200  # put finger 1 down, then `move` or `end` it
+//! touch start 2 50 60 0.5  # a pen pressing half as hard as it can
+//! press Gamepad0   # press gamepad button 0 (A), see `gamepad`
+//! axis 0 0.5       # push gamepad axis 0 (the left stick) halfway right
+//! wait 0.5         # let half a second of virtual time pass
+//! ```
+//!
+//! Seeds at the very top also apply to dots loaded at startup. After the
+//! last command, the final scene and a hash of the rendered frame are
+//! printed as JSON to stderr, which keeps them apart from the status lines
+//! the app prints to stdout along the way.
+
+use std::collections::VecDeque;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use piston_window::*;
+
+use crate::app::App;
+use crate::diagram::seed_random;
+use crate::raster::Canvas;
+use crate::{Settings, DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+pub enum Command {
+    Seed(u64),
+    Move([f64; 2]),
+    Click([f64; 2]),
+    Key(Vec<Key>),
+    Press(Button),
+    Release(Button),
+    Drop(PathBuf),
+    Type(String),
+    Scroll([f64; 2]),
+    /// The phase, the id of the finger, where it is and the pressure.
+    Touch(Touch, i64, [f64; 2], f64),
+    /// A gamepad axis and where it is, from -1 to 1.
+    Axis(u8, f64),
+    Wait(f64),
+}
+
+pub fn run(path: &str, settings: &Settings) -> io::Result<()> {
+    let mut commands = read(path)?;
+    start(&mut commands);
+    let mut app = App::new(settings);
+    // Virtual time, so animations come out the same no matter how fast we run
+    let mut now = Instant::now();
+
+    for command in commands {
+        match command {
+            Command::Seed(seed) => seed_random(seed),
+            Command::Wait(seconds) => now += Duration::from_secs_f64(seconds),
+            _ => ()
+        }
+        for e in events(&command) {
+            app.event(&e, now);
+        }
+        app.finish_loading();
+    }
+
+    let mut canvas = Canvas::new(DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT);
+    app.draw(&canvas.context(), &mut canvas, now);
+
+    let result = serde_json::json!({
+        "dots": app.diagram().dots,
+        "colors": app.diagram().colors,
+        "lines_only": app.lines_only,
+        "image_hash": format!("{:016x}", canvas.hash()),
+    });
+    eprintln!("{}", result);
+    Ok(())
+}
+
+pub fn read(path: &str) -> io::Result<VecDeque<Command>> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+/// Seeds the random generator like a scenario starts out, taking the seeds
+/// at the top of `commands` into account. Call it before creating the app.
+pub fn start(commands: &mut VecDeque<Command>) {
+    seed_random(0);
+    while let Some(Command::Seed(seed)) = commands.front() {
+        seed_random(*seed);
+        commands.pop_front();
+    }
+}
+
+/// The input events a command stands for. Seeding and the passing of time
+/// are up to the caller.
+pub fn events(command: &Command) -> Vec<Event> {
+    match command {
+        Command::Seed(_) => vec![],
+        Command::Move(p) => vec![cursor_event(*p)],
+        Command::Click(p) => {
+            let button = Button::Mouse(MouseButton::Left);
+            vec![cursor_event(*p), button_event(button, ButtonState::Press), button_event(button, ButtonState::Release)]
+        }
+        Command::Key(keys) => {
+            let presses = keys.iter().map(|&key| button_event(Button::Keyboard(key), ButtonState::Press));
+            let releases = keys.iter().rev().map(|&key| button_event(Button::Keyboard(key), ButtonState::Release));
+            presses.chain(releases).collect()
+        }
+        Command::Press(button) => vec![button_event(*button, ButtonState::Press)],
+        Command::Release(button) => vec![button_event(*button, ButtonState::Release)],
+        Command::Drop(path) => vec![Event::Input(Input::FileDrag(FileDrag::Drop(path.clone())), None)],
+        Command::Type(text) => vec![Event::Input(Input::Text(text.clone()), None)],
+        Command::Scroll(amount) => vec![Event::Input(Input::Move(Motion::MouseScroll(*amount)), None)],
+        Command::Touch(touch, id, p, pressure) => vec![Event::Input(Input::Move(Motion::Touch(TouchArgs::new(0, *id, *p, *pressure, *touch))), None)],
+        Command::Axis(axis, position) => vec![Event::Input(Input::Move(Motion::ControllerAxis(ControllerAxisArgs::new(0, *axis, *position))), None)],
+        Command::Wait(seconds) => vec![Event::Loop(Loop::Update(UpdateArgs { dt: *seconds }))],
+    }
+}
+
+fn cursor_event(p: [f64; 2]) -> Event {
+    Event::Input(Input::Move(Motion::MouseCursor(p)), None)
+}
+
+fn button_event(button: Button, state: ButtonState) -> Event {
+    Event::Input(Input::Button(ButtonArgs { state, button, scancode: None }), None)
+}
+
+fn parse(script: &str) -> io::Result<VecDeque<Command>> {
+    let mut commands = VecDeque::new();
+    for (number, line) in script.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let bad_line = |what: &str| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Scenario line {}: {} in \"{}\"", number + 1, what, line));
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let number_arg = |i: usize| -> io::Result<f64> {
+            words.get(i).and_then(|w| w.parse().ok()).ok_or_else(|| bad_line("missing or bad number"))
+        };
+        commands.push_back(match words[0].to_ascii_lowercase().as_str() {
+            "seed" => Command::Seed(number_arg(1)? as u64),
+            "move" => Command::Move([number_arg(1)?, number_arg(2)?]),
+            "click" => Command::Click([number_arg(1)?, number_arg(2)?]),
+            "wait" => {
+                let seconds = number_arg(1)?;
+                if !seconds.is_finite() || seconds < 0.0 {
+                    return Err(bad_line("negative or endless wait"));
+                }
+                Command::Wait(seconds)
+            }
+            "scroll" => Command::Scroll([number_arg(1)?, number_arg(2)?]),
+            "key" => {
+                let chord = words.get(1).ok_or_else(|| bad_line("missing key"))?;
+                let keys = chord.split('+').map(parse_key).collect::<Option<Vec<_>>>();
+                Command::Key(keys.ok_or_else(|| bad_line("unknown key"))?)
+            }
+            "press" | "release" => {
+                let name = words.get(1).ok_or_else(|| bad_line("missing key or button"))?;
+                let button = parse_button(name).ok_or_else(|| bad_line("unknown key or button"))?;
+                if words[0].eq_ignore_ascii_case("press") { Command::Press(button) } else { Command::Release(button) }
+            }
+            "touch" => {
+                let touch = words.get(1).and_then(|w| parse_touch(w)).ok_or_else(|| bad_line("missing or unknown touch phase"))?;
+                let pressure = if words.len() > 5 { number_arg(5)? } else { 1.0 };
+                Command::Touch(touch, number_arg(2)? as i64, [number_arg(3)?, number_arg(4)?], pressure)
+            }
+            "axis" => Command::Axis(number_arg(1)? as u8, number_arg(2)?),
+            "drop" => {
+                let path = line[words[0].len()..].trim();
+                if path.is_empty() {
+                    return Err(bad_line("missing file"));
+                }
+                Command::Drop(PathBuf::from(path))
+            }
+            "type" => {
+                let text = line[words[0].len()..].trim();
+                if text.is_empty() {
+                    return Err(bad_line("missing text"));
+                }
+                Command::Type(text.to_string())
+            }
+            _ => return Err(bad_line("unknown command"))
+        });
+    }
+    Ok(commands)
+}
+
+/// `start`, `move`, `end` or `cancel`.
+fn parse_touch(name: &str) -> Option<Touch> {
+    match name.to_ascii_lowercase().as_str() {
+        "start" => Some(Touch::Start),
+        "move" => Some(Touch::Move),
+        "end" => Some(Touch::End),
+        "cancel" => Some(Touch::Cancel),
+        _ => None
+    }
+}
+
+/// The name of a touch phase as understood by `parse_touch`.
+pub fn touch_name(touch: Touch) -> &'static str {
+    match touch {
+        Touch::Start => "start",
+        Touch::Move => "move",
+        Touch::End => "end",
+        Touch::Cancel => "cancel",
+    }
+}
+
+/// Names as understood by `parse_key`, `MouseLeft`, `MouseRight` or
+/// `MouseMiddle`, and `Gamepad` followed by the number of a gamepad button.
+fn parse_button(name: &str) -> Option<Button> {
+    match name.to_ascii_lowercase().as_str() {
+        "mouseleft" => Some(Button::Mouse(MouseButton::Left)),
+        "mouseright" => Some(Button::Mouse(MouseButton::Right)),
+        "mousemiddle" => Some(Button::Mouse(MouseButton::Middle)),
+        lower if lower.starts_with("gamepad") => lower["gamepad".len()..].parse().ok()
+            .map(|number| Button::Controller(ControllerButton::new(0, number))),
+        _ => parse_key(name).map(Button::Keyboard)
+    }
+}
+
+/// The name `parse_button` reads back as `button`, if it has one.
+pub fn button_name(button: Button) -> Option<String> {
+    let name = match button {
+        Button::Mouse(MouseButton::Left) => "MouseLeft",
+        Button::Mouse(MouseButton::Right) => "MouseRight",
+        Button::Mouse(MouseButton::Middle) => "MouseMiddle",
+        Button::Controller(b) => return Some(format!("Gamepad{}", b.button)),
+        Button::Keyboard(key) => {
+            // The app doesn't tell the left and right modifiers apart
+            let key = match key {
+                Key::RCtrl => Key::LCtrl,
+                Key::RShift => Key::LShift,
+                Key::RAlt => Key::LAlt,
+                key => key
+            };
+            return KEY_NAMES.iter()
+            .find(|(_, named)| *named == key)
+            .map(|(name, _)| name.to_string())
+            .or_else(|| {
+                let c = char::from_u32(key.code() as u32).filter(|c| c.is_ascii_lowercase() || c.is_ascii_digit())?;
+                Some(c.to_ascii_uppercase().to_string())
+            });
+        }
+        _ => return None
+    };
+    Some(name.to_string())
+}
+
+const KEY_NAMES: [(&str, Key); 14] = [
+    ("Ctrl", Key::LCtrl),
+    ("Shift", Key::LShift),
+    ("Alt", Key::LAlt),
+    ("Space", Key::Space),
+    ("Return", Key::Return),
+    ("Escape", Key::Escape),
+    ("Backspace", Key::Backspace),
+    ("Delete", Key::Delete),
+    ("Tab", Key::Tab),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("F1", Key::F1),
+];
+
+fn parse_key(name: &str) -> Option<Key> {
+    let name = match name.to_ascii_lowercase().as_str() {
+        "enter" => "return".to_string(),
+        "esc" => "escape".to_string(),
+        name => name.to_string()
+    };
+    if let Some((_, key)) = KEY_NAMES.iter().find(|(named, _)| named.eq_ignore_ascii_case(&name)) {
+        return Some(*key);
+    }
+    // Letters and digits use their lowercase ASCII code as key code
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(Key::from(c as u32)),
+        _ => None
+    }
+}
@@ -0,0 +1,270 @@
+//! Dots made by a script, for spirals, low-discrepancy sequences or layouts
+//! from data without recompiling. A script is any program: it's run with
+//! the interpreter its extension calls for, see `interpreter`, or as is.
+//! It gets the dots of the active layer on standard input as JSON,
+//! `[[x, y], ...]`, the size of the window in `VORONOI_WIDTH` and
+//! `VORONOI_HEIGHT`, and writes one edit per line to standard output:
+//!
+//! ```text
+//! add X Y [COLOR]  # add a dot, in the given color or one from the palette
+//! move INDEX X Y   # move the dot at INDEX
+//! remove INDEX     # remove it, the dots after it move up one index
+//! color INDEX COLOR
+//! clear            # remove all dots
+//! ```
+//!
+//! Colors are `#rrggbb` or `#rrggbbaa`. Blank lines and lines starting with
+//! `#` are skipped.
+//!
+//! With the `rhai` feature, scripts ending in `.rhai` run inside the app
+//! instead, with functions for the same edits and nothing to install:
+//!
+//! ```text
+//! clear();
+//! for i in 0..100 {
+//!     add(WIDTH / 2.0 + i * 3.0, HEIGHT / 2.0);  // or add(x, y, "#ff8000")
+//! }
+//! let n = dots().len();  // the dots as they were, as [x, y] arrays
+//! move(0, 10, 20); color(1, "#0000ff"); remove(2);
+//! ```
+//!
+//! Scripts run in the background, and their edits are applied together
+//! once they're done, undoable with `Ctrl+Z` in one go.
+
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use crate::color::parse_color;
+use crate::diagram::Diagram;
+use crate::metadata::Metadata;
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+pub enum Edit {
+    Add([f64; 2], Option<[f32; 4]>),
+    Move(usize, [f64; 2]),
+    Remove(usize),
+    Color(usize, [f32; 4]),
+    Clear,
+}
+
+pub struct Script {
+    pub path: PathBuf,
+    /// Where the edits come from while the script runs.
+    running: Option<Receiver<Result<Vec<Edit>, String>>>,
+}
+
+impl Script {
+    pub fn new(path: PathBuf) -> Script {
+        Script { path, running: None }
+    }
+
+    pub fn running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Runs the script in the background on `dots`, reading it from disk
+    /// anew, so it can be edited between runs.
+    pub fn start(&mut self, dots: &[[f64; 2]]) {
+        let (sender, receiver) = channel();
+        let (path, dots) = (self.path.clone(), dots.to_vec());
+        thread::spawn(move || {
+            let result = if path.extension().is_some_and(|e| e == "rhai") {
+                run_rhai(&path, dots)
+            } else {
+                run(&path, &serde_json::to_string(&dots).unwrap_or_default())
+            };
+            let _ = sender.send(result);
+        });
+        self.running = Some(receiver);
+    }
+
+    /// The edits of the script, or why there are none, once it's done.
+    pub fn finished(&mut self) -> Option<Result<Vec<Edit>, String>> {
+        let result = self.running.as_ref()?.try_recv().ok()?;
+        self.running = None;
+        Some(result)
+    }
+}
+
+/// The program that runs scripts with this extension.
+fn interpreter(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()? {
+        "py" => Some("python3"),
+        "lua" => Some("lua"),
+        "js" => Some("node"),
+        "sh" => Some("sh"),
+        _ => None
+    }
+}
+
+fn run(path: &Path, input: &str) -> Result<Vec<Edit>, String> {
+    let mut command = match interpreter(path) {
+        Some(program) => { let mut c = Command::new(program); c.arg(path); c },
+        None => Command::new(path),
+    };
+    let mut child = command
+        .env("VORONOI_WIDTH", DEFAULT_WINDOW_WIDTH.to_string())
+        .env("VORONOI_HEIGHT", DEFAULT_WINDOW_HEIGHT.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    // The input goes in from a thread of its own, so a script that starts
+    // writing before it has read everything can't leave both of us waiting
+    // on a full pipe. One that doesn't read it at all closes the pipe
+    // early, which is fine.
+    let writer = child.stdin.take().map(|mut stdin| {
+        let input = input.to_string();
+        thread::spawn(move || { let _ = stdin.write_all(input.as_bytes()); })
+    });
+    let mut output = String::new();
+    let read = child.stdout.take().map(|mut stdout| stdout.read_to_string(&mut output)).transpose();
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    read.map_err(|err| err.to_string())?;
+    let status = child.wait().map_err(|err| err.to_string())?;
+    if !status.success() {
+        return Err(format!("it exited with {}", status));
+    }
+    parse(&output)
+}
+
+/// Runs a Rhai script in this thread, collecting the edits its calls make.
+#[cfg(feature = "rhai")]
+fn run_rhai(path: &Path, dots: Vec<[f64; 2]>) -> Result<Vec<Edit>, String> {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope, INT};
+
+    type Call = Result<(), Box<EvalAltResult>>;
+    let number = |value: Dynamic| -> Result<f64, Box<EvalAltResult>> {
+        value.as_float().or_else(|_| value.as_int().map(|i| i as f64))
+            .map_err(|kind| format!("expected a number, got {}", kind).into())
+    };
+    let color = |value: &str| -> Result<[f32; 4], Box<EvalAltResult>> { parse_color(value).map_err(Into::into) };
+    // Negative indices are never those of a dot, so their edits get skipped
+    let index = |i: INT| usize::try_from(i).unwrap_or(usize::MAX);
+
+    let edits = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = Engine::new();
+    let e = edits.clone();
+    engine.register_fn("add", move |x: Dynamic, y: Dynamic| -> Call {
+        e.borrow_mut().push(Edit::Add([number(x)?, number(y)?], None));
+        Ok(())
+    });
+    let e = edits.clone();
+    engine.register_fn("add", move |x: Dynamic, y: Dynamic, c: &str| -> Call {
+        e.borrow_mut().push(Edit::Add([number(x)?, number(y)?], Some(color(c)?)));
+        Ok(())
+    });
+    let e = edits.clone();
+    engine.register_fn("move", move |i: INT, x: Dynamic, y: Dynamic| -> Call {
+        e.borrow_mut().push(Edit::Move(index(i), [number(x)?, number(y)?]));
+        Ok(())
+    });
+    let e = edits.clone();
+    engine.register_fn("remove", move |i: INT| e.borrow_mut().push(Edit::Remove(index(i))));
+    let e = edits.clone();
+    engine.register_fn("color", move |i: INT, c: &str| -> Call {
+        e.borrow_mut().push(Edit::Color(index(i), color(c)?));
+        Ok(())
+    });
+    let e = edits.clone();
+    engine.register_fn("clear", move || e.borrow_mut().push(Edit::Clear));
+    engine.register_fn("dots", move || -> Array {
+        dots.iter().map(|d| Dynamic::from_array(vec![Dynamic::from_float(d[0]), Dynamic::from_float(d[1])])).collect()
+    });
+
+    let mut scope = Scope::new();
+    scope.push_constant("WIDTH", DEFAULT_WINDOW_WIDTH as f64);
+    scope.push_constant("HEIGHT", DEFAULT_WINDOW_HEIGHT as f64);
+    engine.run_file_with_scope(&mut scope, path.to_path_buf()).map_err(|err| err.to_string())?;
+    Ok(edits.take())
+}
+
+#[cfg(not(feature = "rhai"))]
+fn run_rhai(_path: &Path, _dots: Vec<[f64; 2]>) -> Result<Vec<Edit>, String> {
+    Err("Rhai scripts need a build with the rhai feature".to_string())
+}
+
+fn parse(output: &str) -> Result<Vec<Edit>, String> {
+    let mut edits = Vec::new();
+    for (number, line) in output.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad_line = |what: &str| format!("output line {}: {} in \"{}\"", number + 1, what, line);
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let number_arg = |i: usize| words.get(i).and_then(|w| w.parse::<f64>().ok()).ok_or_else(|| bad_line("missing or bad number"));
+        let index_arg = |i: usize| words.get(i).and_then(|w| w.parse::<usize>().ok()).ok_or_else(|| bad_line("missing or bad index"));
+        let color_arg = |i: usize| words.get(i).ok_or_else(|| bad_line("missing color")).and_then(|w| parse_color(w).map_err(|err| bad_line(&err)));
+        edits.push(match words[0] {
+            "add" => Edit::Add([number_arg(1)?, number_arg(2)?], if words.len() > 3 { Some(color_arg(3)?) } else { None }),
+            "move" => Edit::Move(index_arg(1)?, [number_arg(2)?, number_arg(3)?]),
+            "remove" => Edit::Remove(index_arg(1)?),
+            "color" => Edit::Color(index_arg(1)?, color_arg(2)?),
+            "clear" => Edit::Clear,
+            _ => return Err(bad_line("unknown edit"))
+        });
+    }
+    Ok(edits)
+}
+
+/// Makes the edits to the dots of `diagram` all at once. Edits of dots
+/// that aren't there and dots added on top of others are skipped. Returns
+/// how many edits were.
+pub fn apply(edits: &[Edit], diagram: &mut Diagram) -> usize {
+    let mut dots = diagram.dots.clone();
+    let mut colors = diagram.colors.clone();
+    let mut teams = diagram.teams.clone();
+    let mut metadata = diagram.metadata.clone();
+    let mut weights = diagram.weights.clone();
+    let mut ids = diagram.ids.iter().map(|&id| Some(id)).collect::<Vec<_>>();
+    let key = |d: &[f64; 2]| (d[0].to_bits(), d[1].to_bits());
+    let mut taken = dots.iter().map(key).collect::<HashSet<_>>();
+    let mut skipped = 0;
+    for edit in edits {
+        match *edit {
+            Edit::Add(dot, color) if taken.insert(key(&dot)) => {
+                dots.push(dot);
+                colors.push(color.unwrap_or_else(|| diagram.palette.color()));
+                teams.push(None);
+                metadata.push(Metadata::default());
+                weights.push(0.0);
+                ids.push(None);
+            }
+            Edit::Move(index, dot) if index < dots.len() && taken.insert(key(&dot)) => {
+                taken.remove(&key(&dots[index]));
+                dots[index] = dot;
+            }
+            Edit::Remove(index) if index < dots.len() => {
+                taken.remove(&key(&dots[index]));
+                dots.remove(index);
+                colors.remove(index);
+                teams.remove(index);
+                metadata.remove(index);
+                weights.remove(index);
+                ids.remove(index);
+            }
+            Edit::Color(index, color) if index < dots.len() => { colors[index] = color; },
+            Edit::Clear => {
+                (dots, colors, teams, metadata, weights, ids) = (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+                taken.clear();
+            }
+            _ => { skipped += 1; }
+        }
+    }
+    diagram.metadata = metadata;
+    // Added dots get new ids, which only go after the ones kept
+    diagram.ids = ids.into_iter().map_while(|id| id).collect();
+    diagram.restore(dots, colors, teams);
+    diagram.set_weights(weights);
+    skipped
+}
@@ -0,0 +1,79 @@
+//! A uniform grid over the dots, for finding the ones near a point without
+//! looking at all of them.
+
+use std::collections::HashMap;
+
+/// Side length of a grid cell in pixels.
+pub const CELL_SIZE: f64 = 32.0;
+
+#[derive(Default)]
+pub struct Grid {
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Grid {
+    pub fn new(points: &[[f64; 2]]) -> Grid {
+        let mut cells = HashMap::<_, Vec<usize>>::new();
+        for (i, p) in points.iter().enumerate() {
+            cells.entry(cell(p[0], p[1])).or_default().push(i);
+        }
+        Grid { cells }
+    }
+
+    /// Adds the point at `index`, which has to come after all the others.
+    pub fn insert(&mut self, index: usize, p: [f64; 2]) {
+        self.cells.entry(cell(p[0], p[1])).or_default().push(index);
+    }
+
+    /// Moves the point at `index` from `from` to `to`.
+    pub fn relocate(&mut self, index: usize, from: [f64; 2], to: [f64; 2]) {
+        if let Some(indices) = self.cells.get_mut(&cell(from[0], from[1])) {
+            indices.retain(|&i| i != index);
+        }
+        self.cells.entry(cell(to[0], to[1])).or_default().push(index);
+    }
+
+    /// The indices of the points within `radius` of `center`, in no particular order.
+    /// `points` must be the ones the grid was built from.
+    pub fn within(&self, points: &[[f64; 2]], center: [f64; 2], radius: f64) -> Vec<usize> {
+        let mut found = Vec::new();
+        for key in searched(center, radius) {
+            let Some(indices) = self.cells.get(&key) else { continue };
+            found.extend(indices.iter().copied().filter(|&i| {
+                let (dx, dy) = (points[i][0] - center[0], points[i][1] - center[1]);
+                dx * dx + dy * dy <= radius * radius
+            }));
+        }
+        found
+    }
+
+    /// The grid cells holding points, each as its corner and side length
+    /// with how many points it holds, in no particular order.
+    pub fn occupied(&self) -> Vec<([f64; 4], usize)> {
+        self.cells.iter().filter(|(_, indices)| !indices.is_empty())
+            .map(|(&key, indices)| (rectangle(key), indices.len()))
+            .collect()
+    }
+
+    /// The grid cells `within` looks through for `center` and `radius`,
+    /// and how many points they hold between them.
+    pub fn searched(&self, center: [f64; 2], radius: f64) -> (Vec<[f64; 4]>, usize) {
+        let keys = searched(center, radius).collect::<Vec<_>>();
+        let points = keys.iter().filter_map(|key| self.cells.get(key)).map(Vec::len).sum();
+        (keys.into_iter().map(rectangle).collect(), points)
+    }
+}
+
+fn searched(center: [f64; 2], radius: f64) -> impl Iterator<Item = (i64, i64)> {
+    let (min_x, min_y) = cell(center[0] - radius, center[1] - radius);
+    let (max_x, max_y) = cell(center[0] + radius, center[1] + radius);
+    (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+}
+
+fn rectangle((x, y): (i64, i64)) -> [f64; 4] {
+    [x as f64 * CELL_SIZE, y as f64 * CELL_SIZE, CELL_SIZE, CELL_SIZE]
+}
+
+fn cell(x: f64, y: f64) -> (i64, i64) {
+    ((x / CELL_SIZE).floor() as i64, (y / CELL_SIZE).floor() as i64)
+}
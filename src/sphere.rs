@@ -0,0 +1,207 @@
+//! Voronoi diagrams on the sphere. The sites are unit vectors, and the
+//! Delaunay triangles between them are the faces of their convex hull,
+//! built by adding the sites one at a time with exact orientation tests.
+//! The outward normal of a face is the point of the sphere equally far from
+//! its three corners, so the cell of a site has the normals of the faces
+//! around it for corners, joined by arcs of great circles.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::TAU;
+
+use crate::predicates::orient3d;
+
+pub type Vector = [f64; 3];
+
+/// The point at this latitude and longitude in degrees. Latitude 0,
+/// longitude 0 is `[0, 0, 1]`, the north pole `[0, 1, 0]` and east along
+/// the equator `[1, 0, 0]`.
+pub fn from_lat_lon(lat: f64, lon: f64) -> Vector {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.sin(), lat.sin(), lat.cos() * lon.cos()]
+}
+
+pub fn dot(a: Vector, b: Vector) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: Vector, b: Vector) -> Vector {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+pub fn normalize(v: Vector) -> Vector {
+    let length = dot(v, v).sqrt();
+    v.map(|x| x / length)
+}
+
+fn sub(a: Vector, b: Vector) -> Vector {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+/// The Voronoi cell of every site on the unit sphere, its corners
+/// counter-clockwise seen from outside, each joined to the next by the
+/// shorter great circle arc. The sites have to be apart. A lone site has
+/// the whole sphere, which no corners can describe, so its cell is empty.
+pub fn spherical_cells(sites: &[Vector]) -> Vec<Vec<Vector>> {
+    if sites.len() < 2 {
+        return vec![Vec::new(); sites.len()];
+    }
+    let Some(hull) = Hull::new(sites) else { return lunes(sites) };
+
+    let mut face_at = vec![None; sites.len()];
+    for (face, corners) in hull.faces.iter().enumerate() {
+        for &corner in corners.iter().flatten() {
+            face_at[corner] = Some(face);
+        }
+    }
+    let normal = |face: usize| {
+        let [a, b, c] = hull.faces[face].unwrap().map(|corner| sites[corner]);
+        normalize(cross(sub(b, a), sub(c, a)))
+    };
+    face_at.iter().enumerate().map(|(site, start)| {
+        // Sites left inside the hull by rounding have no faces
+        let Some(start) = *start else { return Vec::new() };
+        let mut cell = Vec::new();
+        let mut face = start;
+        loop {
+            cell.push(normal(face));
+            let corners = hull.faces[face].unwrap();
+            let at = corners.iter().position(|&c| c == site).unwrap();
+            face = hull.edges[&(site, corners[(at + 2) % 3])];
+            if face == start {
+                return cell;
+            }
+        }
+    }).collect()
+}
+
+/// The convex hull of points on the sphere, as triangles counter-clockwise
+/// seen from outside.
+struct Hull {
+    /// `None` for faces that were removed again.
+    faces: Vec<Option<[usize; 3]>>,
+    /// The face each edge belongs to, by the corners it goes from and to.
+    edges: HashMap<(usize, usize), usize>,
+}
+
+impl Hull {
+    /// `None` if the points all lie on one plane and have no hull with
+    /// any volume.
+    fn new(points: &[Vector]) -> Option<Hull> {
+        // Three points of a sphere never lie on one line, so the first
+        // three and any point off their plane make a tetrahedron
+        let off = (3..points.len()).find(|&i| orient3d(points[0], points[1], points[2], points[i]) != Ordering::Equal)?;
+        let mut hull = Hull { faces: Vec::new(), edges: HashMap::new() };
+        let tetrahedron = [0, 1, 2, off];
+        for skipped in 0..4 {
+            let [a, b, c] = [0, 1, 2].map(|i| tetrahedron[(skipped + 1 + i) % 4]);
+            // The corner left out has to be behind the face
+            if orient3d(points[a], points[b], points[c], points[tetrahedron[skipped]]) == Ordering::Greater {
+                hull.add_face([a, c, b]);
+            } else {
+                hull.add_face([a, b, c]);
+            }
+        }
+        for point in (3..points.len()).filter(|&i| i != off) {
+            hull.insert(points, point);
+        }
+        Some(hull)
+    }
+
+    fn add_face(&mut self, corners: [usize; 3]) {
+        for i in 0..3 {
+            self.edges.insert((corners[i], corners[(i + 1) % 3]), self.faces.len());
+        }
+        self.faces.push(Some(corners));
+    }
+
+    fn remove_face(&mut self, face: usize) {
+        let corners = self.faces[face].take().unwrap();
+        for i in 0..3 {
+            self.edges.remove(&(corners[i], corners[(i + 1) % 3]));
+        }
+    }
+
+    /// Replaces the faces the point sees with a fan of faces around it.
+    fn insert(&mut self, points: &[Vector], point: usize) {
+        let p = points[point];
+        let sees = |corners: [usize; 3]| orient3d(points[corners[0]], points[corners[1]], points[corners[2]], p) == Ordering::Greater;
+        let first = self.faces.iter().position(|face| face.is_some_and(sees)).unwrap_or_else(|| {
+            // A point a hair inside the hull after rounding, which goes
+            // through the face closest to it instead
+            let height = |corners: [usize; 3]| {
+                let [a, b, c] = corners.map(|corner| points[corner]);
+                dot(normalize(cross(sub(b, a), sub(c, a))), sub(p, a))
+            };
+            (0..self.faces.len()).filter(|&f| self.faces[f].is_some())
+                .max_by(|&f, &g| height(self.faces[f].unwrap()).total_cmp(&height(self.faces[g].unwrap())))
+                .unwrap()
+        });
+
+        let mut visible = HashSet::from([first]);
+        let mut queue = vec![first];
+        let mut horizon = Vec::new();
+        while let Some(face) = queue.pop() {
+            let corners = self.faces[face].unwrap();
+            for i in 0..3 {
+                let (from, to) = (corners[i], corners[(i + 1) % 3]);
+                let beyond = self.edges[&(to, from)];
+                if visible.contains(&beyond) {
+                    continue;
+                }
+                if sees(self.faces[beyond].unwrap()) {
+                    visible.insert(beyond);
+                    queue.push(beyond);
+                } else {
+                    horizon.push((from, to));
+                }
+            }
+        }
+        for &face in &visible {
+            self.remove_face(face);
+        }
+        for (from, to) in horizon {
+            self.add_face([from, to, point]);
+        }
+    }
+}
+
+/// The cells of sites that all lie on one circle: slices of the sphere
+/// from the circle's axis at one pole to the other, like the segments of an
+/// orange.
+fn lunes(sites: &[Vector]) -> Vec<Vec<Vector>> {
+    let axis = if sites.len() == 2 {
+        cross(sites[0], sites[1])
+    } else {
+        cross(sub(sites[1], sites[0]), sub(sites[2], sites[0]))
+    };
+    // Two opposite sites leave the axis open, any one square to them does
+    let axis = if dot(axis, axis) > 0.0 { normalize(axis) } else { normalize(perpendicular(sites[0])) };
+    let e1 = normalize(perpendicular(axis));
+    let e2 = cross(axis, e1);
+    let angle = |site: Vector| dot(site, e2).atan2(dot(site, e1));
+    let at_angle = |angle: f64| [0, 1, 2].map(|i| angle.cos() * e1[i] + angle.sin() * e2[i]);
+    // The direction halfway from one site to the next, counter-clockwise
+    // around the axis
+    let halfway = |from: f64, to: f64| {
+        let gap = (to - from).rem_euclid(TAU);
+        at_angle(from + if gap == 0.0 { TAU } else { gap } / 2.0)
+    };
+
+    let mut order = (0..sites.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| angle(sites[a]).total_cmp(&angle(sites[b])));
+    let mut cells = vec![Vec::new(); sites.len()];
+    for (k, &site) in order.iter().enumerate() {
+        let previous = order[(k + order.len() - 1) % order.len()];
+        let next = order[(k + 1) % order.len()];
+        let (before, at, after) = (angle(sites[previous]), angle(sites[site]), angle(sites[next]));
+        let south = axis.map(|x| -x);
+        cells[site] = vec![axis, halfway(before, at), south, halfway(at, after)];
+    }
+    cells
+}
+
+/// Some vector square to `v`.
+fn perpendicular(v: Vector) -> Vector {
+    if v[0].abs() < 0.9 { cross(v, [1.0, 0.0, 0.0]) } else { cross(v, [0.0, 1.0, 0.0]) }
+}
@@ -0,0 +1,103 @@
+//! The stamp tool: a click adds a whole constellation of dots at once.
+
+use std::f64::consts::TAU;
+
+/// Dots on the circle.
+const CIRCLE_DOTS: usize = 12;
+/// Points of the star, each with a dot at its tip and one in the notch after it.
+const STAR_POINTS: usize = 5;
+/// How far in the star's notches are, as a fraction of its radius.
+const STAR_NOTCH: f64 = 0.4;
+const SPIRAL_DOTS: usize = 24;
+const SPIRAL_TURNS: f64 = 2.5;
+/// How far one scroll step grows or shrinks the stamp.
+const SCALE_STEP: f64 = 1.1;
+const MIN_SCALE: f64 = 5.0;
+const MAX_SCALE: f64 = 500.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Shape {
+    Circle,
+    Star,
+    Spiral,
+    /// Loaded with `--stamp`.
+    Custom,
+}
+
+impl Shape {
+    pub fn name(self) -> &'static str {
+        match self {
+            Shape::Circle => "circle",
+            Shape::Star => "star",
+            Shape::Spiral => "spiral",
+            Shape::Custom => "custom",
+        }
+    }
+}
+
+pub struct Stamp {
+    pub shape: Shape,
+    /// Radius of the stamp in pixels.
+    pub scale: f64,
+    /// The custom shape, centered and fit into the unit circle.
+    custom: Vec<[f64; 2]>,
+}
+
+impl Stamp {
+    /// `custom` is the shape loaded with `--stamp`, in any size and place.
+    pub fn new(scale: f64, custom: Vec<[f64; 2]>) -> Stamp {
+        Stamp { shape: Shape::Circle, scale: scale.clamp(MIN_SCALE, MAX_SCALE), custom: normalize(custom) }
+    }
+
+    /// Switches to the next shape, skipping the custom one if none was loaded.
+    pub fn next_shape(&mut self) {
+        self.shape = match self.shape {
+            Shape::Circle => Shape::Star,
+            Shape::Star => Shape::Spiral,
+            Shape::Spiral if !self.custom.is_empty() => Shape::Custom,
+            Shape::Spiral | Shape::Custom => Shape::Circle,
+        };
+    }
+
+    /// Grows the stamp for scrolling up, shrinks it for scrolling down.
+    pub fn scroll(&mut self, steps: f64) {
+        self.scale = (self.scale * SCALE_STEP.powf(steps)).clamp(MIN_SCALE, MAX_SCALE);
+    }
+
+    /// The dots the stamp puts down around `center`.
+    pub fn dots(&self, center: [f64; 2]) -> Vec<[f64; 2]> {
+        let unit = match self.shape {
+            Shape::Circle => (0..CIRCLE_DOTS).map(|i| polar(1.0, i as f64 / CIRCLE_DOTS as f64 * TAU)).collect(),
+            Shape::Star => (0..2 * STAR_POINTS).map(|i| {
+                let radius = if i % 2 == 0 { 1.0 } else { STAR_NOTCH };
+                // The first tip points up
+                polar(radius, i as f64 / (2 * STAR_POINTS) as f64 * TAU - TAU / 4.0)
+            }).collect(),
+            Shape::Spiral => (0..SPIRAL_DOTS).map(|i| {
+                let t = (i + 1) as f64 / SPIRAL_DOTS as f64;
+                polar(t, t * SPIRAL_TURNS * TAU)
+            }).collect(),
+            Shape::Custom => self.custom.clone(),
+        };
+        unit.iter().map(|p| [center[0] + p[0] * self.scale, center[1] + p[1] * self.scale]).collect()
+    }
+}
+
+/// Moves the middle of the dots' bounding box to the origin and scales
+/// them so the farthest one is at distance 1.
+fn normalize(dots: Vec<[f64; 2]>) -> Vec<[f64; 2]> {
+    let Some(first) = dots.first() else { return dots };
+    let (min, max) = dots.iter().fold((*first, *first), |(min, max), d| {
+        ([min[0].min(d[0]), min[1].min(d[1])], [max[0].max(d[0]), max[1].max(d[1])])
+    });
+    let middle = [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0];
+    let reach = dots.iter()
+        .map(|d| ((d[0] - middle[0]).powi(2) + (d[1] - middle[1]).powi(2)).sqrt())
+        .fold(0.0, f64::max);
+    let reach = if reach > 0.0 { reach } else { 1.0 };
+    dots.iter().map(|d| [(d[0] - middle[0]) / reach, (d[1] - middle[1]) / reach]).collect()
+}
+
+fn polar(radius: f64, angle: f64) -> [f64; 2] {
+    [radius * angle.cos(), radius * angle.sin()]
+}
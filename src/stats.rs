@@ -0,0 +1,162 @@
+//! How evenly the dots are spread: the lengths of the Delaunay edges and the
+//! areas of the cells, summed up and binned into histograms. Relaxation makes
+//! both distributions narrower.
+
+use crate::diagram::Diagram;
+use crate::geometry::area;
+
+/// Bins of the histograms, on screen and printed.
+pub const BINS: usize = 20;
+/// Width of the longest bar when printing a histogram.
+const PRINTED_BAR: usize = 40;
+
+pub struct Stats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// How many values fall into each of `BINS` equal parts of `min..=max`.
+    pub histogram: Vec<usize>,
+}
+
+impl Stats {
+    /// `None` for no values.
+    pub fn new(values: &[f64]) -> Option<Stats> {
+        if values.is_empty() {
+            return None;
+        }
+        let count = values.len();
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count as f64;
+
+        let mut histogram = vec![0; BINS];
+        for v in values {
+            let bin = if max > min { ((v - min) / (max - min) * BINS as f64) as usize } else { 0 };
+            histogram[bin.min(BINS - 1)] += 1;
+        }
+        Some(Stats { count, min, max, mean, std_dev: variance.sqrt(), histogram })
+    }
+
+    /// One line with the numbers, good for a panel title.
+    pub fn summary(&self) -> String {
+        format!("n {}  mean {}  sd {}  min {}  max {}",
+            self.count, short(self.mean), short(self.std_dev), short(self.min), short(self.max))
+    }
+
+    /// The summary followed by one line per bin with a bar of `#`.
+    fn printed(&self, title: &str) -> String {
+        let mut text = format!("{}: {}\n", title, self.summary());
+        let tallest = self.histogram.iter().copied().max().unwrap_or(0).max(1);
+        let width = (self.max - self.min) / BINS as f64;
+        for (i, &n) in self.histogram.iter().enumerate() {
+            let from = self.min + i as f64 * width;
+            let bar = "#".repeat((n * PRINTED_BAR).div_ceil(tallest));
+            text += &format!("{:>10.1} - {:<10.1}{:>6} {}\n", from, from + width, n, bar);
+        }
+        text
+    }
+}
+
+/// `value` with a decimal only while it's small, so areas don't get too long.
+pub fn short(value: f64) -> String {
+    if value.abs() < 100.0 { format!("{:.1}", value) } else { format!("{:.0}", value) }
+}
+
+/// The length of every Delaunay edge, each counted once.
+pub fn edge_lengths(diagram: &Diagram) -> Vec<f64> {
+    let mut edges = diagram.triangles().into_iter()
+        .flat_map(|t| [(t.0, t.1), (t.1, t.2), (t.2, t.0)])
+        .map(|(a, b)| (a.min(b), a.max(b)))
+        .collect::<Vec<_>>();
+    edges.sort_unstable();
+    edges.dedup();
+    edges.into_iter().map(|(a, b)| {
+        let (p, q) = (diagram.dots[a], diagram.dots[b]);
+        (p[0] - q[0]).hypot(p[1] - q[1])
+    }).collect()
+}
+
+/// How well shaped a triangle is, for the quality overlay.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+    /// The smallest angle, in degrees, 60 at best.
+    MinAngle,
+    /// The circumradius over twice the inradius, 1 at best.
+    AspectRatio,
+}
+
+impl Quality {
+    pub fn name(self) -> &'static str {
+        match self {
+            Quality::MinAngle => "minimum angle",
+            Quality::AspectRatio => "aspect ratio",
+        }
+    }
+
+    pub fn measure(self, corners: [[f64; 2]; 3]) -> f64 {
+        match self {
+            Quality::MinAngle => min_angle(corners),
+            Quality::AspectRatio => aspect_ratio(corners),
+        }
+    }
+
+    /// How good `value` is, from 0 for degenerate to 1 for equilateral.
+    pub fn goodness(self, value: f64) -> f64 {
+        match self {
+            Quality::MinAngle => (value / 60.0).clamp(0.0, 1.0),
+            Quality::AspectRatio => (1.0 / value).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// The Delaunay triangles by their corners.
+pub fn triangle_corners(diagram: &Diagram) -> Vec<[[f64; 2]; 3]> {
+    diagram.triangles().into_iter().map(|t| [diagram.dots[t.0], diagram.dots[t.1], diagram.dots[t.2]]).collect()
+}
+
+/// The smallest angle of a triangle in degrees.
+pub fn min_angle(corners: [[f64; 2]; 3]) -> f64 {
+    let [a, b, c] = sides(corners);
+    // The smallest angle is across from the shortest side
+    let (shortest, others) = if a <= b && a <= c { (a, (b, c)) } else if b <= c { (b, (a, c)) } else { (c, (a, b)) };
+    if others.0 == 0.0 || others.1 == 0.0 {
+        return 0.0;
+    }
+    let cosine = (others.0 * others.0 + others.1 * others.1 - shortest * shortest) / (2.0 * others.0 * others.1);
+    cosine.clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+/// The circumradius of a triangle over twice its inradius, which is 1 for
+/// an equilateral one and grows without bound as it flattens.
+pub fn aspect_ratio(corners: [[f64; 2]; 3]) -> f64 {
+    let [a, b, c] = sides(corners);
+    let s = (a + b + c) / 2.0;
+    let denominator = 8.0 * (s - a) * (s - b) * (s - c);
+    if denominator <= 0.0 { f64::INFINITY } else { a * b * c / denominator }
+}
+
+fn sides([p, q, r]: [[f64; 2]; 3]) -> [f64; 3] {
+    let length = |u: [f64; 2], v: [f64; 2]| (u[0] - v[0]).hypot(u[1] - v[1]);
+    [length(q, r), length(r, p), length(p, q)]
+}
+
+/// The area of each cell inside the window.
+pub fn cell_areas(diagram: &Diagram) -> Vec<f64> {
+    diagram.visible.iter().map(|cell| area(cell)).collect()
+}
+
+/// Both distributions as text, for `--stats`.
+pub fn report(diagram: &Diagram) -> String {
+    let mut text = format!("{} dots\n", diagram.dots.len());
+    for (title, values) in [("Delaunay edge lengths", edge_lengths(diagram)), ("Cell areas", cell_areas(diagram))] {
+        text += "\n";
+        match Stats::new(&values) {
+            Some(stats) => text += &stats.printed(title),
+            None => text += &format!("{}: none\n", title),
+        }
+    }
+    text
+}
@@ -0,0 +1,88 @@
+//! Stippling a photo by weighted Voronoi relaxation: dots scattered over it,
+//! more of them where it's darker, then moved again and again to the
+//! centroid of the darkness under their cells, until they settle. The dots
+//! are the stipple drawing, and their cells, each in the average color of
+//! the photo under it, a mosaic of the photo.
+
+use crate::diagram::{random, Diagram, Point};
+use crate::geometry::centroid;
+use crate::lowpoly::Photo;
+
+/// Relaxation stops once no dot moves further than this, in pixels.
+const SETTLED: f64 = 0.1;
+/// Relaxation stops after this many steps even if the dots haven't settled.
+const MAX_STEPS: usize = 100;
+/// Tries at a dark enough random point before taking any point of the
+/// photo, so an all white photo gets its dots too.
+const MAX_TRIES: usize = 1000;
+
+/// Stipples the photo with `count` dots, returning them as a diagram with
+/// the cells painted in the average color of the photo under them.
+pub fn stipple(photo: &Photo, count: usize) -> Diagram {
+    let mut diagram = Diagram::new();
+    diagram.set_dots((0..count).map(|_| sample(photo)).collect());
+    for _ in 0..MAX_STEPS {
+        if step(&mut diagram, photo) < SETTLED {
+            break;
+        }
+    }
+    let colors = diagram.visible.iter().map(|poly| average(photo, poly)).collect();
+    diagram.set_colors(colors);
+    diagram
+}
+
+/// A random point of the photo, likelier where it's dark.
+fn sample(photo: &Photo) -> [f64; 2] {
+    let [left, top, right, bottom] = photo.bounds();
+    let mut point = [0.0; 2];
+    for _ in 0..MAX_TRIES {
+        point = [left + random::<f64>() * (right - left), top + random::<f64>() * (bottom - top)];
+        if random::<f64>() < photo.darkness(point) {
+            break;
+        }
+    }
+    point
+}
+
+/// Moves every dot to the centroid of its cell weighted by the darkness of
+/// the photo, or the plain centroid if the photo is white there. Returns
+/// the furthest any dot moved.
+fn step(diagram: &mut Diagram, photo: &Photo) -> f64 {
+    let mut furthest: f64 = 0.0;
+    for (dot, poly) in diagram.dots.iter_mut().zip(&diagram.visible) {
+        let (mut total, mut weight) = ([0.0; 2], 0.0);
+        for (p, _) in photo.pixels_inside(poly) {
+            let darkness = photo.darkness(p);
+            total = [total[0] + p[0] * darkness, total[1] + p[1] * darkness];
+            weight += darkness;
+        }
+        let target = if weight > 0.0 {
+            [total[0] / weight, total[1] / weight]
+        } else if let Some(c) = centroid(poly) {
+            [c.0, c.1]
+        } else {
+            continue;
+        };
+        furthest = furthest.max((target[0] - dot[0]).hypot(target[1] - dot[1]));
+        *dot = target;
+    }
+    diagram.update();
+    furthest
+}
+
+/// The average color of the photo under a cell, or white where the cell
+/// doesn't cover any of it.
+fn average(photo: &Photo, poly: &[Point]) -> [f32; 4] {
+    let pixels = photo.pixels_inside(poly);
+    if pixels.is_empty() {
+        return [1.0; 4];
+    }
+    let mut total = [0.0; 3];
+    for (_, color) in &pixels {
+        for i in 0..3 {
+            total[i] += color[i];
+        }
+    }
+    let [r, g, b] = total.map(|t| (t / pixels.len() as f64) as f32);
+    [r, g, b, 1.0]
+}
@@ -0,0 +1,103 @@
+//! Loading big points files without freezing the window. The file is read
+//! and parsed in the background, then its dots join the layer a batch at a
+//! time, each half as big as what's already there, so the diagram fills in
+//! over a few frames and the window keeps redrawing and showing how far
+//! along it is.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use interactive_voronoi::fit::{Coords, Fit};
+
+use crate::points::{fit_points, read_points, Points};
+
+/// Files bigger than this, in bytes, are loaded in the background.
+const STREAM_ABOVE: u64 = 1 << 20;
+/// Dots in the first batch, and at least in every later one.
+const FIRST_BATCH: usize = 10_000;
+
+/// The dots of a parsed file and how they were mapped into the window.
+type Parsed = io::Result<(Points, Option<Fit>)>;
+
+pub struct Stream {
+    pub path: PathBuf,
+    /// The layer the dots go to.
+    pub layer: usize,
+    /// Whether they replace the dots of the layer rather than join them.
+    pub replace: bool,
+    /// Where the parsed dots come from while the file is read.
+    parsing: Option<Receiver<Parsed>>,
+    /// The parsed dots, in the order they go to the layer.
+    points: Points,
+    /// How the dots were mapped into the window, see `points::fit_points`.
+    pub coords: Option<Fit>,
+    /// Dots that went to the layer so far.
+    pub loaded: usize,
+}
+
+impl Stream {
+    /// Whether the file is big enough to load in the background.
+    pub fn wanted(path: &Path) -> bool {
+        fs::metadata(path).is_ok_and(|m| m.len() > STREAM_ABOVE)
+    }
+
+    /// Starts reading the file in the background, mapping its dots from
+    /// `coords` into the window.
+    pub fn start(path: PathBuf, layer: usize, replace: bool, coords: Coords) -> Stream {
+        let (sender, receiver) = channel();
+        let file = path.clone();
+        thread::spawn(move || {
+            let _ = sender.send(read_points(&file).map(|mut points| {
+                let fit = fit_points(&mut points, coords);
+                (points, fit)
+            }));
+        });
+        Stream { path, layer, replace, parsing: Some(receiver), points: Points::default(), coords: None, loaded: 0 }
+    }
+
+    /// The next batch of dots once the file is parsed, waiting for that
+    /// with `wait`, or why there are none. The first batch comes even if
+    /// the file has no dots.
+    pub fn next_batch(&mut self, wait: bool) -> Option<io::Result<Points>> {
+        if let Some(parsing) = self.parsing.as_ref() {
+            let parsed = if wait {
+                parsing.recv().unwrap_or_else(|_| Err(io::Error::other("reading the file stopped")))
+            } else {
+                parsing.try_recv().ok()?
+            };
+            let (points, coords) = match parsed {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    self.parsing = None;
+                    return Some(Err(err));
+                }
+            };
+            self.parsing = None;
+            (self.points, self.coords) = (points, coords);
+        } else if self.done() {
+            return None;
+        }
+        let count = FIRST_BATCH.max(self.loaded / 2).min(self.points.dots.len() - self.loaded);
+        let range = self.loaded..self.loaded + count;
+        self.loaded += count;
+        Some(Ok(Points { dots: self.points.dots[range.clone()].to_vec(), metadata: self.points.metadata[range].to_vec() }))
+    }
+
+    /// Whether all dots went to the layer.
+    pub fn done(&self) -> bool {
+        self.parsing.is_none() && self.loaded == self.points.dots.len()
+    }
+
+    /// How far along loading is.
+    pub fn describe(&self) -> String {
+        let name = self.path.file_name().map_or_else(|| self.path.display().to_string(), |n| n.to_string_lossy().into_owned());
+        if self.parsing.is_some() {
+            return format!("Loading {}: reading the file", name);
+        }
+        let total = self.points.dots.len().max(1);
+        format!("Loading {}: {} of {} dots ({}%)", name, self.loaded, self.points.dots.len(), self.loaded * 100 / total)
+    }
+}
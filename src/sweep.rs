@@ -0,0 +1,107 @@
+//! Stepping through Fortune's algorithm on the dots of the active layer, see
+//! `fortune`: the sweep line moving down, the beach line above it, the edges
+//! traced so far and the circle events waiting to happen.
+
+use graphics::{Context, Graphics};
+
+use crate::diagram::Point;
+use crate::draw::{draw_circle, draw_dots, draw_ellipse};
+use crate::fortune::{event_positions, snapshot, Snapshot};
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// Pixels per second the sweep line moves while playing.
+const SPEED: f64 = 60.0;
+
+pub struct Sweep {
+    /// The revision of the diagram whose dots are swept.
+    pub revision: u64,
+    dots: Vec<[f64; 2]>,
+    /// Where the sweep line is at each event.
+    events: Vec<f64>,
+    /// Where the sweep line is now.
+    position: f64,
+    pub playing: bool,
+    snapshot: Snapshot,
+}
+
+impl Sweep {
+    /// Starts paused above the window, before any event.
+    pub fn new(dots: &[[f64; 2]], revision: u64) -> Sweep {
+        let mut sweep = Sweep { revision, dots: Vec::new(), events: Vec::new(), position: 0.0, playing: false, snapshot: snapshot(&[], 0.0, 0.0) };
+        sweep.lay_out(dots, revision);
+        sweep
+    }
+
+    /// Sweeps changed dots, from where the sweep line is now.
+    pub fn lay_out(&mut self, dots: &[[f64; 2]], revision: u64) {
+        self.revision = revision;
+        self.dots = dots.to_vec();
+        self.events = event_positions(dots);
+        self.position = self.position.min(self.end());
+        self.take_snapshot();
+    }
+
+    /// Below the window, or lower if the last circle event is.
+    fn end(&self) -> f64 {
+        self.events.last().copied().unwrap_or(0.0).max(DEFAULT_WINDOW_HEIGHT as f64)
+    }
+
+    fn take_snapshot(&mut self) {
+        self.snapshot = snapshot(&self.dots, self.position, DEFAULT_WINDOW_WIDTH as f64);
+    }
+
+    /// Moves the sweep line on by `seconds` if it's playing. It stops at the end.
+    pub fn advance(&mut self, seconds: f64) {
+        if !self.playing || seconds <= 0.0 {
+            return;
+        }
+        self.position = (self.position + seconds * SPEED).min(self.end());
+        if self.position >= self.end() {
+            self.playing = false;
+        }
+        self.take_snapshot();
+    }
+
+    pub fn toggle(&mut self) {
+        if !self.playing && self.position >= self.end() {
+            self.position = 0.0;
+        }
+        self.playing = !self.playing;
+    }
+
+    /// Jumps to the next or previous event, pausing the animation.
+    pub fn step(&mut self, forward: bool) {
+        self.playing = false;
+        self.position = if forward {
+            self.events.iter().copied().find(|&e| e > self.position).unwrap_or(self.end())
+        } else {
+            self.events.iter().copied().rev().find(|&e| e < self.position).unwrap_or(0.0)
+        };
+        self.take_snapshot();
+    }
+
+    /// How many of the events have happened, and how many there are.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.events.iter().filter(|&&e| e <= self.position).count(), self.events.len())
+    }
+
+    pub fn draw<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let snapshot = &self.snapshot;
+        let line = |a: Point, b: Point, color: [f32; 4], g: &mut G| graphics::line(color, 1.0, [a.0, a.1, b.0, b.1], c.transform, g);
+        for &[a, b] in &snapshot.edges {
+            line(a, b, [0.0, 0.0, 1.0, 1.0], g);
+        }
+        let vertices = snapshot.vertices.iter().map(|v| [v.0, v.1]).collect::<Vec<_>>();
+        draw_dots(&vertices, c, g, [0.0, 0.0, 1.0, 1.0]);
+        for (center, radius) in &snapshot.circles {
+            draw_circle(&[center.0, center.1], *radius, c, g, [0.0, 0.0, 0.0, 0.3]);
+            draw_ellipse(&[center.0, center.1 + radius], c, g, [0.6, 0.6, 0.6, 1.0]);
+        }
+        for arc in &snapshot.beach {
+            for pair in arc.windows(2) {
+                line(pair[0], pair[1], [0.9, 0.1, 0.1, 1.0], g);
+            }
+        }
+        line((0.0, snapshot.sweep), (DEFAULT_WINDOW_WIDTH as f64, snapshot.sweep), [0.0, 0.0, 0.0, 1.0], g);
+    }
+}
@@ -0,0 +1,48 @@
+//! Symmetric insertion: every dot added by hand gets mirror images and
+//! rotated copies, so symmetric diagrams can be built one dot at a time.
+
+use std::f64::consts::TAU;
+
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+pub struct Symmetry {
+    /// Mirror across the vertical center line, left to right.
+    pub mirror_x: bool,
+    /// Mirror across the horizontal center line, top to bottom.
+    pub mirror_y: bool,
+    /// Copies around the window center, like a kaleidoscope. One for none.
+    pub rotations: usize,
+}
+
+impl Default for Symmetry {
+    fn default() -> Symmetry {
+        Symmetry { mirror_x: false, mirror_y: false, rotations: 1 }
+    }
+}
+
+impl Symmetry {
+    /// The dot and all its images, the dot itself first. The same
+    /// transformation is at the same position for every dot, so the images
+    /// of a dragged dot can follow it.
+    pub fn images(&self, dot: [f64; 2]) -> Vec<[f64; 2]> {
+        let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        let mut images = vec![dot];
+        if self.mirror_x {
+            images.push([width - dot[0], dot[1]]);
+        }
+        if self.mirror_y {
+            let mirrored = images.iter().map(|d| [d[0], height - d[1]]).collect::<Vec<_>>();
+            images.extend(mirrored);
+        }
+        let center = [width / 2.0, height / 2.0];
+        let mirrored = images.len();
+        for k in 1..self.rotations.max(1) {
+            let (sin, cos) = (k as f64 * TAU / self.rotations as f64).sin_cos();
+            for i in 0..mirrored {
+                let (x, y) = (images[i][0] - center[0], images[i][1] - center[1]);
+                images.push([center[0] + x * cos - y * sin, center[1] + x * sin + y * cos]);
+            }
+        }
+        images
+    }
+}
@@ -0,0 +1,197 @@
+//! Terrain out of the cells: every cell gets a height, from smooth noise or
+//! from how many cells it is from the border of the window, which turns
+//! the diagram into a plate of land for sketching game maps. Seen from the
+//! front, each cell is a column that tall.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::adjacency::{point_key, polygon_edges};
+use crate::diagram::{Diagram, Point};
+use crate::geometry::{centroid, signed_area};
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// Pixels across the largest bumps of the noise.
+const NOISE_SIZE: f64 = 400.0;
+/// Layers of noise, each half as large and half as strong as the last.
+const OCTAVES: u32 = 4;
+/// Heights below this are under water, and drawn level with it.
+pub const SEA_LEVEL: f64 = 0.3;
+/// Pixels the highest column rises above the ground.
+const EXTRUSION: f64 = 60.0;
+/// How much darker the walls facing the viewer are than the tops, the
+/// ones facing sideways being darker still.
+const WALL_SHADE: f32 = 0.75;
+const SIDE_SHADE: f32 = 0.55;
+
+/// Colors of the heights from the sea floor up to the peaks.
+const RAMP: [(f64, [f32; 3]); 7] = [
+    (0.0, [0.05, 0.15, 0.4]),
+    (SEA_LEVEL, [0.25, 0.5, 0.8]),
+    (SEA_LEVEL + 0.02, [0.9, 0.85, 0.6]),
+    (0.45, [0.35, 0.65, 0.3]),
+    (0.7, [0.25, 0.45, 0.2]),
+    (0.85, [0.5, 0.42, 0.35]),
+    (1.0, [0.97, 0.97, 0.97]),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Terrain {
+    /// Rolling hills from value noise at the dots.
+    Noise,
+    /// Steps up from the cells on the border of the window, one per cell
+    /// crossed, so the middle ends up highest like an island.
+    Distance,
+}
+
+impl Terrain {
+    pub fn name(self) -> &'static str {
+        match self {
+            Terrain::Noise => "noise",
+            Terrain::Distance => "distance from the border",
+        }
+    }
+
+    /// The height of each dot's cell, from 0 for the lowest to 1 for the
+    /// highest. `seed` picks the noise.
+    pub fn heights(self, diagram: &Diagram, seed: u64) -> Vec<f64> {
+        let heights = match self {
+            Terrain::Noise => diagram.dots.iter().map(|d| noise(*d, seed)).collect(),
+            Terrain::Distance => steps_from_border(diagram),
+        };
+        normalized(heights)
+    }
+}
+
+/// Fractal value noise: random values at the corners of a grid, blended
+/// smoothly in between, with finer grids added on top.
+fn noise(p: [f64; 2], seed: u64) -> f64 {
+    let mut total = 0.0;
+    let (mut size, mut strength) = (NOISE_SIZE, 1.0);
+    for octave in 0..OCTAVES {
+        let (x, y) = (p[0] / size, p[1] / size);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+        let corner = |dx: f64, dy: f64| lattice((x0 + dx) as i64, (y0 + dy) as i64, seed.wrapping_add(octave as u64));
+        let top = corner(0.0, 0.0) + (corner(1.0, 0.0) - corner(0.0, 0.0)) * tx;
+        let bottom = corner(0.0, 1.0) + (corner(1.0, 1.0) - corner(0.0, 1.0)) * tx;
+        total += (top + (bottom - top) * ty) * strength;
+        size /= 2.0;
+        strength /= 2.0;
+    }
+    total
+}
+
+fn smooth(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// A random value between 0 and 1 for a corner of the grid, always the
+/// same for the same corner and seed.
+fn lattice(x: i64, y: i64, seed: u64) -> f64 {
+    let mut h = seed ^ (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    // The finalizer of SplitMix64
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^= h >> 31;
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// How many cells each cell is away from one touching the border of the
+/// window, going from neighbor to neighbor.
+fn steps_from_border(diagram: &Diagram) -> Vec<f64> {
+    const EPSILON: f64 = 1e-6;
+    let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+    let on_border = |p: &Point| p.0 < EPSILON || p.1 < EPSILON || p.0 > width - EPSILON || p.1 > height - EPSILON;
+
+    let mut steps = vec![None; diagram.dots.len()];
+    let mut queue = VecDeque::new();
+    for (i, cell) in diagram.visible.iter().enumerate() {
+        if cell.is_empty() || cell.iter().any(on_border) {
+            steps[i] = Some(0);
+            queue.push_back(i);
+        }
+    }
+    let mut neighbors = vec![Vec::new(); diagram.dots.len()];
+    for t in diagram.triangles() {
+        for (a, b) in [(t.0, t.1), (t.1, t.2), (t.2, t.0)] {
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+    }
+    while let Some(i) = queue.pop_front() {
+        let next = steps[i].map(|s| s + 1);
+        for &j in &neighbors[i] {
+            if steps[j].is_none() {
+                steps[j] = next;
+                queue.push_back(j);
+            }
+        }
+    }
+    steps.into_iter().map(|s| s.unwrap_or(0) as f64).collect()
+}
+
+/// Stretched to go from 0 to 1.
+fn normalized(values: Vec<f64>) -> Vec<f64> {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if max > min {
+        values.into_iter().map(|v| (v - min) / (max - min)).collect()
+    } else {
+        vec![0.5; values.len()]
+    }
+}
+
+/// The color of a height, from deep water through beaches, grass and rock
+/// to snow.
+pub fn elevation_color(height: f64) -> [f32; 4] {
+    let height = height.clamp(0.0, 1.0);
+    let upper = RAMP.iter().position(|&(h, _)| h >= height).unwrap_or(RAMP.len() - 1).max(1);
+    let ((h0, c0), (h1, c1)) = (RAMP[upper - 1], RAMP[upper]);
+    let t = if h1 > h0 { ((height - h0) / (h1 - h0)) as f32 } else { 1.0 };
+    [c0[0] + (c1[0] - c0[0]) * t, c0[1] + (c1[1] - c0[1]) * t, c0[2] + (c1[2] - c0[2]) * t, 1.0]
+}
+
+/// The cells as columns seen from the front and a little above: polygons
+/// to fill in the order given, back to front, with the walls facing the
+/// viewer under the raised top of each cell. Walls only reach down to
+/// the top of the neighbor in front.
+pub fn columns(diagram: &Diagram, heights: &[f64]) -> Vec<(Vec<Point>, [f32; 4])> {
+    let lift = |i: usize| heights[i].max(SEA_LEVEL) * EXTRUSION;
+    let mut cells_on = HashMap::<_, Vec<usize>>::new();
+    for (i, cell) in diagram.visible.iter().enumerate() {
+        for (a, b) in polygon_edges(cell) {
+            let (ka, kb) = (point_key(a), point_key(b));
+            cells_on.entry((ka.min(kb), ka.max(kb))).or_default().push(i);
+        }
+    }
+    let mut cells = diagram.visible.iter().enumerate()
+        .filter(|(_, cell)| cell.len() >= 3)
+        .filter_map(|(i, cell)| centroid(cell).map(|middle| (i, cell, middle.1)))
+        .collect::<Vec<_>>();
+    cells.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    let mut shapes = Vec::new();
+    for (i, cell, _) in cells {
+        let top = elevation_color(heights[i]);
+        let raised = |p: Point, lift: f64| (p.0, p.1 - lift);
+        // Outward normals point down the screen on the near side
+        let turn = if signed_area(cell) > 0.0 { 1.0 } else { -1.0 };
+        for j in 0..cell.len() {
+            let (a, b) = (cell[j], cell[(j + 1) % cell.len()]);
+            let (nx, ny) = ((b.1 - a.1) * turn, (a.0 - b.0) * turn);
+            let facing = ny / nx.hypot(ny);
+            let (ka, kb) = (point_key(&a), point_key(&b));
+            let front = cells_on[&(ka.min(kb), ka.max(kb))].iter().find(|&&j| j != i).map_or(0.0, |&j| lift(j));
+            if facing.is_nan() || facing <= 0.0 || front >= lift(i) {
+                continue;
+            }
+            // Lit from the front left
+            let facing = facing as f32;
+            let shade = SIDE_SHADE + (WALL_SHADE - SIDE_SHADE) * facing;
+            let shade = if nx < 0.0 { shade } else { shade * 0.9 };
+            shapes.push((vec![raised(a, front), raised(b, front), raised(b, lift(i)), raised(a, lift(i))], [top[0] * shade, top[1] * shade, top[2] * shade, 1.0]));
+        }
+        shapes.push((cell.iter().map(|&p| raised(p, lift(i))).collect(), top));
+    }
+    shapes
+}
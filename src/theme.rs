@@ -0,0 +1,111 @@
+//! Themes bundle the look of the diagram under a name: the background, the
+//! lines around the cells, the colors cells are painted with and the dots.
+//! They are JSON files like those in `themes/`, which also come built in:
+//!
+//! ```json
+//! {
+//!   "name": "blueprint",
+//!   "background": "#1b3f73",
+//!   "stroke": {"color": "#dce8f7", "width": 1.0},
+//!   "palette": ["#1b3f73", "#1e4680"],
+//!   "dots": {"shape": "cross", "radius": 3, "color": "#ffffff", "outline": "#000000"}
+//! }
+//! ```
+//!
+//! Everything but the name may be left out and is then as in the classic
+//! look. `"stroke": null` leaves the cells without lines and `"dots": null`
+//! hides the dots.
+
+use std::path::{Path, PathBuf};
+
+use graphics::color::WHITE;
+use serde_json::Value;
+
+use crate::color::parse_color;
+use crate::draw::DotStyle;
+
+const BUILT_IN: [&str; 3] = [
+    include_str!("../themes/blueprint.json"),
+    include_str!("../themes/pastel-paper.json"),
+    include_str!("../themes/neon-dark.json"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub background: [f32; 4],
+    /// Color and width of the lines around filled cells.
+    pub stroke: Option<([f32; 4], f64)>,
+    /// The colors new cells are painted with. Empty keeps the palettes of
+    /// the layers.
+    pub palette: Vec<[f32; 4]>,
+    /// `None` hides the dots.
+    pub dots: Option<DotStyle>,
+    /// The file the theme was loaded from, if it was.
+    pub path: Option<PathBuf>,
+}
+
+impl Theme {
+    /// White, with cells of any color and no lines between them, and dots
+    /// drawn with `dots`.
+    pub fn classic(dots: Option<DotStyle>) -> Theme {
+        Theme { name: "classic".to_string(), background: WHITE, stroke: None, palette: Vec::new(), dots, path: None }
+    }
+
+    /// Reads a theme from JSON, see the module documentation.
+    pub fn parse(text: &str) -> Result<Theme, String> {
+        let value: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        let name = value.get("name").and_then(Value::as_str).ok_or("expected a \"name\"")?;
+        let color = |value: &Value, key: &str| -> Result<Option<[f32; 4]>, String> {
+            match value.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(v) => v.as_str().ok_or_else(|| format!("\"{}\" should be a color", key)).and_then(parse_color).map(Some),
+            }
+        };
+        let mut theme = Theme::classic(Some(DotStyle::default()));
+        theme.name = name.to_string();
+        theme.background = color(&value, "background")?.unwrap_or(WHITE);
+        theme.stroke = match value.get("stroke") {
+            None | Some(Value::Null) => None,
+            Some(stroke) => {
+                let width = stroke.get("width").and_then(Value::as_f64).unwrap_or(1.0);
+                Some((color(stroke, "color")?.ok_or("the stroke needs a \"color\"")?, width))
+            }
+        };
+        if let Some(palette) = value.get("palette") {
+            let entries = palette.as_array().ok_or("\"palette\" should be an array of colors")?;
+            theme.palette = entries.iter()
+                .map(|c| c.as_str().ok_or_else(|| "\"palette\" should be an array of colors".to_string()).and_then(parse_color))
+                .collect::<Result<_, _>>()?;
+        }
+        theme.dots = match value.get("dots") {
+            Some(Value::Null) => None,
+            None => Some(DotStyle::default()),
+            Some(dots) => {
+                let default = DotStyle::default();
+                Some(DotStyle {
+                    shape: match dots.get("shape").and_then(Value::as_str) {
+                        Some(shape) => shape.parse()?,
+                        None => default.shape,
+                    },
+                    radius: dots.get("radius").and_then(Value::as_f64).unwrap_or(default.radius),
+                    fill: color(dots, "color")?.unwrap_or(default.fill),
+                    outline: color(dots, "outline")?,
+                })
+            }
+        };
+        Ok(theme)
+    }
+
+    /// Reads a theme from a JSON file.
+    pub fn load(path: &Path) -> Result<Theme, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let theme = Theme::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Ok(Theme { path: Some(path.to_path_buf()), ..theme })
+    }
+}
+
+/// The themes that come with the app, from `themes/`.
+pub fn built_in() -> Vec<Theme> {
+    BUILT_IN.iter().map(|text| Theme::parse(text).expect("Built-in theme of bad format")).collect()
+}
@@ -0,0 +1,146 @@
+//! A short round trip through all the dots, for TSP art: drawn as one
+//! unbroken line, a pen plotter never has to lift the pen. Finding the
+//! shortest is hopeless for thousands of dots, so the tour starts from the
+//! nearest unvisited dot again and again, and then 2-opt uncrosses it: two
+//! edges are swapped for two shorter ones by reversing the stretch between
+//! them, as long as that helps.
+
+use crate::diagram::Diagram;
+use crate::spatial::CELL_SIZE;
+
+/// Rounds over the whole tour that 2-opt makes at most, which is plenty
+/// since each round fixes nearly everything the last one uncovered.
+const MAX_PASSES: usize = 50;
+
+pub struct Tour {
+    /// The revision of the diagram the tour goes through.
+    pub revision: u64,
+    /// The dots in the order they're visited, back to the first at the end.
+    pub order: Vec<usize>,
+    pub length: f64,
+}
+
+impl Tour {
+    pub fn new(diagram: &Diagram) -> Tour {
+        let dots = &diagram.dots;
+        let mut order = nearest_neighbor(diagram);
+        two_opt(diagram, &mut order);
+        let length = (0..order.len()).map(|i| distance(dots[order[i]], dots[order[(i + 1) % order.len()]])).sum();
+        Tour { revision: diagram.revision(), order, length }
+    }
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+/// From the first dot on, always on to the closest one not visited yet,
+/// looking further and further around the last one until there is one.
+fn nearest_neighbor(diagram: &Diagram) -> Vec<usize> {
+    let dots = &diagram.dots;
+    let mut visited = vec![false; dots.len()];
+    let mut order = Vec::with_capacity(dots.len());
+    let mut current = 0;
+    while order.len() < dots.len() {
+        visited[current] = true;
+        order.push(current);
+        if order.len() == dots.len() {
+            break;
+        }
+        let mut radius = CELL_SIZE;
+        current = loop {
+            let closest = diagram.dots_within(dots[current], radius).into_iter()
+                .filter(|&i| !visited[i])
+                .min_by(|&a, &b| distance(dots[a], dots[current]).total_cmp(&distance(dots[b], dots[current])));
+            // Dots outside the window are out of reach of any radius that
+            // covers it, so after that every dot is looked at
+            match closest {
+                Some(next) => break next,
+                None if radius > 4096.0 => break (0..dots.len()).filter(|&i| !visited[i])
+                    .min_by(|&a, &b| distance(dots[a], dots[current]).total_cmp(&distance(dots[b], dots[current])))
+                    .unwrap(),
+                None => radius *= 2.0,
+            }
+        };
+    }
+    order
+}
+
+/// Swaps two edges for the two joining their first and their second ends
+/// whenever that's shorter. Only the edges leaving the Delaunay neighbors
+/// of a dot are tried against the two edges at that dot, as the swaps
+/// that help nearly always join close dots.
+fn two_opt(diagram: &Diagram, order: &mut [usize]) {
+    let n = order.len();
+    if n < 4 {
+        return;
+    }
+    let dots = &diagram.dots;
+    let mut neighbors = vec![Vec::new(); dots.len()];
+    for t in diagram.triangles() {
+        for (a, b) in [(t.0, t.1), (t.1, t.2), (t.2, t.0)] {
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+    }
+    let mut position = vec![0; dots.len()];
+    for (i, &dot) in order.iter().enumerate() {
+        position[dot] = i;
+    }
+
+    for _ in 0..MAX_PASSES {
+        let mut improved = false;
+        for a in 0..dots.len() {
+            for &c in &neighbors[a] {
+                // The edges leaving `a` and `c`, or the ones arriving there
+                for step in [0, n - 1] {
+                    let (i, j) = ((position[a] + step) % n, (position[c] + step) % n);
+                    if swap_if_shorter(dots, order, &mut position, i, j) {
+                        improved = true;
+                    }
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+}
+
+/// Swaps the edge from index `i` to the next and the edge from index `j`
+/// to the next for the shorter pair joining their ends, if it's shorter.
+fn swap_if_shorter(dots: &[[f64; 2]], order: &mut [usize], position: &mut [usize], i: usize, j: usize) -> bool {
+    let n = order.len();
+    let (a, b, c, d) = (order[i], order[(i + 1) % n], order[j], order[(j + 1) % n]);
+    if a == c || a == d || b == c {
+        return false;
+    }
+    let gain = distance(dots[a], dots[b]) + distance(dots[c], dots[d]) - distance(dots[a], dots[c]) - distance(dots[b], dots[d]);
+    if gain <= 1e-9 {
+        return false;
+    }
+    // Either stretch between the two edges can be reversed, the shorter
+    // one is cheaper
+    let inside = (j + n - i) % n;
+    if inside <= n - inside {
+        reverse(order, position, (i + 1) % n, j);
+    } else {
+        reverse(order, position, (j + 1) % n, i);
+    }
+    true
+}
+
+/// Reverses the tour from index `from` to index `to`, both included,
+/// wrapping around the end.
+fn reverse(order: &mut [usize], position: &mut [usize], from: usize, to: usize) {
+    let n = order.len();
+    let length = (to + n - from) % n + 1;
+    let (mut i, mut j) = (from, to);
+    for _ in 0..length / 2 {
+        order.swap(i, j);
+        position[order[i]] = i;
+        position[order[j]] = j;
+        i = (i + 1) % n;
+        j = (j + n - 1) % n;
+    }
+}
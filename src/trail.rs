@@ -0,0 +1,69 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::diagram::{random_color, Diagram};
+
+struct TrailSite {
+    dot: [f64; 2],
+    color: [f32; 4],
+    born: Instant,
+}
+
+/// Temporary sites left behind by the moving cursor. They fade out and
+/// disappear after `lifetime`, so the diagram keeps retessellating in the
+/// cursor's wake.
+pub struct Trail {
+    /// Minimum distance the cursor has to travel before the next site is dropped.
+    pub spacing: f64,
+    pub lifetime: Duration,
+    sites: VecDeque<TrailSite>,
+}
+
+impl Trail {
+    pub fn new(spacing: f64, lifetime: Duration) -> Trail {
+        Trail { spacing, lifetime, sites: VecDeque::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.sites.clear();
+    }
+
+    /// Drops a site at the cursor if it moved far enough from the last one.
+    /// Returns whether a site was added.
+    pub fn follow(&mut self, cursor: [f64; 2]) -> bool {
+        if let Some(last) = self.sites.back() {
+            let (dx, dy) = (cursor[0] - last.dot[0], cursor[1] - last.dot[1]);
+            if (dx * dx + dy * dy).sqrt() < self.spacing {
+                return false;
+            }
+        }
+        self.sites.push_back(TrailSite { dot: cursor, color: random_color(), born: Instant::now() });
+        true
+    }
+
+    /// Removes the sites that outlived their lifetime. Returns whether any were removed.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let before = self.sites.len();
+        // Sites are added in order, so the oldest ones are always at the front
+        while self.sites.front().is_some_and(|s| now.duration_since(s.born) >= self.lifetime) {
+            self.sites.pop_front();
+        }
+        self.sites.len() != before
+    }
+
+    /// The diagram with the trail sites mixed in, each faded by its age.
+    pub fn overlay(&self, diagram: &Diagram, now: Instant) -> Diagram {
+        let lifetime = self.lifetime.as_secs_f32().max(f32::EPSILON);
+        let dots = self.sites.iter().map(|s| s.dot).collect::<Vec<_>>();
+        let colors = self.sites.iter().map(|s| {
+            let age = now.duration_since(s.born).as_secs_f32();
+            let [r, g, b, _] = s.color;
+            [r, g, b, (1.0 - age / lifetime).clamp(0.0, 1.0)]
+        }).collect::<Vec<_>>();
+        diagram.merged(&dots, &colors)
+    }
+}
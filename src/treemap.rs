@@ -0,0 +1,95 @@
+//! Voronoi treemaps: cells whose areas are proportional to given weights.
+//! The cells are those of a power diagram, whose weights are adjusted step
+//! by step until the areas match, while the sites move to the centers of
+//! their cells to keep them round.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::color::Palette;
+use crate::diagram::{random_point, Point};
+use crate::power::{area_error, fit_step, power_cells, shares, MAX_ERROR};
+
+/// Steps before giving up on getting the error below `MAX_ERROR`.
+const MAX_STEPS: usize = 1000;
+
+/// A site as given in the treemap file.
+pub struct Site {
+    pub weight: f64,
+    pub label: Option<String>,
+    /// Where the site starts out, somewhere random if not given.
+    pub position: Option<[f64; 2]>,
+}
+
+/// Reads a JSON array of objects, each with a positive `weight` and
+/// optionally a `label` and a starting `x` and `y`.
+pub fn read_sites(path: &Path) -> Result<Vec<Site>, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let value: Value = serde_json::from_str(&text).map_err(|err| err.to_string())?;
+    let entries = value.as_array().ok_or("expected an array of sites")?;
+    if entries.is_empty() {
+        return Err("no sites".to_string());
+    }
+    entries.iter().enumerate().map(|(i, entry)| {
+        let number = |key: &str| entry.get(key).and_then(Value::as_f64);
+        let weight = number("weight").filter(|w| *w > 0.0)
+            .ok_or_else(|| format!("site {} needs a positive \"weight\"", i))?;
+        let label = entry.get("label").map(|l| l.as_str().map_or_else(|| l.to_string(), str::to_string));
+        let position = number("x").zip(number("y")).map(|(x, y)| [x, y]);
+        Ok(Site { weight, label, position })
+    }).collect()
+}
+
+pub struct Treemap {
+    pub sites: Vec<[f64; 2]>,
+    pub colors: Vec<[f32; 4]>,
+    pub labels: Vec<String>,
+    /// The share of the window each cell should cover, summing to 1.
+    pub targets: Vec<f64>,
+    /// The power diagram weights, squared distances.
+    weights: Vec<f64>,
+    /// The power cells of the sites, inside the window.
+    pub cells: Vec<Vec<Point>>,
+    pub steps: usize,
+    /// Whether the steps are over, because the areas match or it gave up.
+    pub done: bool,
+}
+
+impl Treemap {
+    pub fn new(sites: &[Site], palette: &Palette) -> Treemap {
+        let total = sites.iter().map(|s| s.weight).sum::<f64>();
+        let positions = sites.iter().map(|s| s.position.unwrap_or_else(random_point)).collect::<Vec<_>>();
+        // Equal weights give the plain Voronoi diagram to start from
+        let weights = vec![0.0; sites.len()];
+        Treemap {
+            cells: power_cells(&positions, &weights),
+            sites: positions,
+            colors: sites.iter().map(|_| palette.color()).collect(),
+            labels: sites.iter().enumerate().map(|(i, s)| s.label.clone().unwrap_or_else(|| i.to_string())).collect(),
+            targets: sites.iter().map(|s| s.weight / total).collect(),
+            weights,
+            steps: 0,
+            done: false,
+        }
+    }
+
+    /// The share of the window each cell covers now.
+    pub fn shares(&self) -> Vec<f64> {
+        shares(&self.cells)
+    }
+
+    /// How much of the window is in the wrong cell, from 0 to 1.
+    pub fn error(&self) -> f64 {
+        area_error(&self.cells, &self.targets)
+    }
+
+    pub fn step(&mut self) {
+        if self.done {
+            return;
+        }
+        self.cells = fit_step(&mut self.sites, &mut self.weights, &self.cells, &self.targets);
+        self.steps += 1;
+        self.done = self.error() < MAX_ERROR || self.steps >= MAX_STEPS;
+    }
+}
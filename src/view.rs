@@ -0,0 +1,79 @@
+//! Where the diagram sits in the window. The diagram is always
+//! `DEFAULT_WINDOW_WIDTH` by `DEFAULT_WINDOW_HEIGHT` units, but the window
+//! may end up another size in logical pixels, for example when a HiDPI
+//! scale factor makes it too big for the screen. The view fits the diagram
+//! into the window and maps the cursor back, so clicks land on the cells
+//! under them whatever the size and scale factor.
+
+use piston_window::{rectangle, Context, Event, Graphics, Input, Motion, Size, TouchArgs, Transformed};
+
+use crate::{DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH};
+
+/// Fills the window around the diagram when their shapes differ.
+const MARGIN_COLOR: [f32; 4] = [0.2, 0.2, 0.2, 1.0];
+
+pub struct View {
+    /// Logical pixels per diagram unit.
+    scale: f64,
+    /// Where the top left corner of the diagram is, in logical pixels.
+    offset: [f64; 2],
+    window: [f64; 2],
+}
+
+impl View {
+    /// The diagram as big as fits into a window of this logical size,
+    /// centered.
+    pub fn fit(window: Size) -> View {
+        let (width, height) = (DEFAULT_WINDOW_WIDTH as f64, DEFAULT_WINDOW_HEIGHT as f64);
+        let scale = (window.width / width).min(window.height / height);
+        let scale = if scale.is_finite() && scale > 0.0 { scale } else { 1.0 };
+        View {
+            scale,
+            offset: [(window.width - width * scale) / 2.0, (window.height - height * scale) / 2.0],
+            window: [window.width, window.height],
+        }
+    }
+
+    /// Logical pixels per diagram unit.
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// A point in the window in diagram units, kept inside the diagram.
+    pub fn to_diagram(&self, p: [f64; 2]) -> [f64; 2] {
+        [
+            ((p[0] - self.offset[0]) / self.scale).clamp(0.0, DEFAULT_WINDOW_WIDTH as f64),
+            ((p[1] - self.offset[1]) / self.scale).clamp(0.0, DEFAULT_WINDOW_HEIGHT as f64),
+        ]
+    }
+
+    /// The event with the cursor or touch position in diagram units.
+    pub fn map_event(&self, e: &Event) -> Event {
+        match e {
+            Event::Input(Input::Move(Motion::MouseCursor(p)), timestamp) =>
+                Event::Input(Input::Move(Motion::MouseCursor(self.to_diagram(*p))), *timestamp),
+            Event::Input(Input::Move(Motion::Touch(args)), timestamp) => {
+                let [x, y] = self.to_diagram(args.position());
+                let args = TouchArgs { position_3d: [x, y, args.position_3d[2]], ..*args };
+                Event::Input(Input::Move(Motion::Touch(args)), *timestamp)
+            }
+            _ => e.clone()
+        }
+    }
+
+    /// A context drawing in diagram units.
+    pub fn context(&self, c: &Context) -> Context {
+        c.trans(self.offset[0], self.offset[1]).scale(self.scale, self.scale)
+    }
+
+    /// Covers the parts of the window beside the diagram, where cells
+    /// reaching past its edges would otherwise show.
+    pub fn draw_margins<G: Graphics>(&self, c: &Context, g: &mut G) {
+        let [x, y] = self.offset;
+        let [width, height] = self.window;
+        let bars = [[0.0, 0.0, x, height], [width - x, 0.0, x, height], [0.0, 0.0, width, y], [0.0, height - y, width, y]];
+        for bar in bars.iter().filter(|bar| bar[2] > 0.0 && bar[3] > 0.0) {
+            rectangle(MARGIN_COLOR, *bar, c.transform, g);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the watched file's modification time is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Notices when a file changes on disk by polling its modification time.
+pub struct FileWatcher {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: Option<Instant>,
+}
+
+impl FileWatcher {
+    pub fn new(path: PathBuf) -> FileWatcher {
+        let modified = modified_time(&path);
+        FileWatcher { path, modified, last_poll: None }
+    }
+
+    /// Whether the file changed since the last call. Checks the disk at most
+    /// every `POLL_INTERVAL`.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if self.last_poll.is_some_and(|last| now.duration_since(last) < POLL_INTERVAL) {
+            return false;
+        }
+        self.last_poll = Some(now);
+
+        let modified = modified_time(&self.path);
+        // A writer may briefly remove or truncate the file, wait until it's back
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,309 @@
+//! A tiny WebSocket server that lets other programs edit the diagram and get
+//! the resulting cells back, e.g. for interactive installations.
+//!
+//! Clients send JSON text messages:
+//!
+//! * `{"op": "add", "x": 10, "y": 20}`
+//! * `{"op": "move", "index": 3, "x": 10, "y": 20}`
+//! * `{"op": "remove", "index": 3}`
+//! * `{"op": "clear"}`
+//!
+//! Whenever the diagram changes, and right after connecting, every client is
+//! sent `{"dots": [[x, y], ...], "colors": [[r, g, b, a], ...], "cells": [[[x, y], ...], ...]}`
+//! with the cells clipped to the window, and `"metadata": [{"label": ..., ...}, ...]`
+//! if the dots have some.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::control::Command;
+use crate::diagram::Diagram;
+use crate::metadata;
+
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// A client that takes longer than this to take a message is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Where a client's frames are written. Only one thread writes to a client
+/// at a time, so frames don't get mixed up.
+type Writer = Arc<Mutex<TcpStream>>;
+
+/// Connected clients and the last state sent to them, for newcomers.
+#[derive(Default)]
+struct Clients {
+    writers: Vec<Writer>,
+    latest: String,
+}
+
+/// The message broadcast to clients for a diagram.
+pub fn state_message(diagram: &Diagram) -> String {
+    let cells = diagram.visible.iter()
+        .map(|cell| cell.iter().map(|p| [p.0, p.1]).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let mut message = serde_json::json!({
+        "dots": diagram.dots,
+        "colors": diagram.colors,
+        "cells": cells,
+    });
+    if metadata::any(&diagram.metadata) {
+        message["metadata"] = diagram.metadata.iter().map(|m| Value::Object(m.to_json())).collect();
+    }
+    message.to_string()
+}
+
+/// Serves WebSocket clients on the TCP port in the background. Their edits
+/// go to `commands`, and every state arriving on `states` is broadcast.
+pub fn serve(port: u16, commands: Sender<Command>, states: Receiver<String>) -> io::Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Serving WebSocket clients on port {}", port);
+    let clients = Arc::new(Mutex::new(Clients::default()));
+
+    let broadcast_to = clients.clone();
+    thread::spawn(move || {
+        for state in states {
+            // Writes outside the lock, so a slow client holds up neither
+            // newcomers nor, for longer than the timeout, the others
+            let writers = {
+                let mut clients = broadcast_to.lock().expect("WebSocket clients poisoned");
+                clients.latest = state.clone();
+                clients.writers.clone()
+            };
+            let failed = writers.into_iter()
+                .filter(|writer| write_frame(&mut writer.lock().expect("WebSocket client poisoned"), 0x1, state.as_bytes()).is_err())
+                .collect::<Vec<_>>();
+            if !failed.is_empty() {
+                let mut clients = broadcast_to.lock().expect("WebSocket clients poisoned");
+                clients.writers.retain(|writer| !failed.iter().any(|f| Arc::ptr_eq(writer, f)));
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let clients = clients.clone();
+            let commands = commands.clone();
+            thread::spawn(move || {
+                if let Err(err) = handle_client(stream, &clients, &commands) {
+                    println!("WebSocket client disconnected: {}", err);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn handle_client(mut stream: TcpStream, clients: &Mutex<Clients>, commands: &Sender<Command>) -> io::Result<()> {
+    handshake(&mut stream)?;
+    let writer = stream.try_clone()?;
+    writer.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let writer = Arc::new(Mutex::new(writer));
+    {
+        // Holding on to the writer before letting go of the clients makes
+        // the latest state arrive before any newer one is broadcast
+        let (mut writing, latest) = {
+            let mut clients = clients.lock().expect("WebSocket clients poisoned");
+            clients.writers.push(writer.clone());
+            (writer.lock().expect("WebSocket client poisoned"), clients.latest.clone())
+        };
+        if !latest.is_empty() {
+            write_frame(&mut writing, 0x1, latest.as_bytes())?;
+        }
+    }
+    let write = |opcode: u8, payload: &[u8]| write_frame(&mut writer.lock().expect("WebSocket client poisoned"), opcode, payload);
+
+    let mut message = Vec::new();
+    loop {
+        let (fin, opcode, payload) = read_frame(&mut stream)?;
+        match opcode {
+            // Text, binary and continuation frames
+            0x0..=0x2 => {
+                message.extend_from_slice(&payload);
+                if fin {
+                    match parse_command(&message) {
+                        Some(command) => if commands.send(command).is_err() { return Ok(()) },
+                        None => println!("Ignoring WebSocket message {}", String::from_utf8_lossy(&message))
+                    }
+                    message.clear();
+                }
+            }
+            0x8 => {
+                write(0x8, &payload)?;
+                return Ok(());
+            }
+            0x9 => write(0xA, &payload)?,
+            _ => ()
+        }
+    }
+}
+
+fn parse_command(message: &[u8]) -> Option<Command> {
+    let value: Value = serde_json::from_slice(message).ok()?;
+    let number = |key: &str| value.get(key).and_then(Value::as_f64);
+    let index = || value.get("index").and_then(Value::as_u64).map(|i| i as usize);
+    match value.get("op")?.as_str()? {
+        "add" => Some(Command::AddPoint([number("x")?, number("y")?])),
+        "move" => Some(Command::MovePoint(index()?, [number("x")?, number("y")?])),
+        "remove" => Some(Command::RemovePoint(index()?)),
+        "clear" => Some(Command::Clear),
+        _ => None
+    }
+}
+
+/// Reads the HTTP upgrade request and answers it.
+fn handshake(stream: &mut TcpStream) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut byte = [0u8; 1];
+    while !request.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte)? == 0 || request.len() > 16 * 1024 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "incomplete handshake"));
+        }
+        request.push(byte[0]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let key = request.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("sec-websocket-key"))
+        .map(|(_, value)| value.trim().to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a WebSocket request"))?;
+
+    let accept = base64(&sha1(format!("{}{}", key, HANDSHAKE_GUID).as_bytes()));
+    write!(stream, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", accept)
+}
+
+/// Reads one frame, returning its FIN flag, opcode and unmasked payload.
+fn read_frame(stream: &mut TcpStream) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header)?;
+    let fin = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0F;
+    let masked = header[1] & 0x80 != 0;
+    let length = match header[1] & 0x7F {
+        126 => { let mut l = [0u8; 2]; stream.read_exact(&mut l)?; u16::from_be_bytes(l) as u64 },
+        127 => { let mut l = [0u8; 8]; stream.read_exact(&mut l)?; u64::from_be_bytes(l) },
+        l => l as u64
+    };
+    if length > 16 * 1024 * 1024 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+    }
+    let mut mask = [0u8; 4];
+    if masked {
+        stream.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0u8; length as usize];
+    stream.read_exact(&mut payload)?;
+    if masked {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+    Ok((fin, opcode, payload))
+}
+
+/// Writes a single unmasked frame, as servers do.
+fn write_frame(stream: &mut TcpStream, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        l if l < 126 => frame.push(l as u8),
+        l if l <= u16::MAX as usize => { frame.push(126); frame.extend_from_slice(&(l as u16).to_be_bytes()); },
+        l => { frame.push(127); frame.extend_from_slice(&(l as u64).to_be_bytes()); }
+    }
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes(chunk[i * 4..i * 4 + 4].try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6)
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (i, word) in h.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handshake_accept_matches_rfc_6455() {
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        let accept = base64(&sha1(format!("{}{}", key, HANDSHAKE_GUID).as_bytes()));
+        assert_eq!(accept, "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn sha1_of_known_messages() {
+        let hex = |digest: [u8; 20]| digest.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(hex(sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        // Long enough that the padding spills into a second block
+        assert_eq!(hex(sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")), "84983e441c3bd26ebaae4aa1f95129e5e54670f1");
+    }
+
+    #[test]
+    fn base64_pads_to_whole_groups() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
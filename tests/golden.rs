@@ -0,0 +1,174 @@
+//! Golden image tests: small diagrams rendered without a window and compared
+//! pixel by pixel against the reference images in `test_data/golden`.
+//!
+//! After an intended rendering change, rerun with `UPDATE_GOLDEN=1` to write
+//! new reference images, and look at them before committing. When a test
+//! fails, the rendered image and a map of the differing pixels are written
+//! to `target/tmp/golden` next to each other.
+
+use std::fs;
+use std::path::Path;
+
+use interactive_voronoi::color::Palette;
+use interactive_voronoi::density::Density;
+use interactive_voronoi::diagram::{seed_random, Diagram};
+use interactive_voronoi::draw::DotStyle;
+use interactive_voronoi::export::{render_bump, render_heightmap, render_image, Bump, BumpField, ExportOptions};
+use interactive_voronoi::hyperbolic;
+use interactive_voronoi::raster::Canvas;
+use interactive_voronoi::terrain::Terrain;
+
+/// The images are a quarter of the window's size, which keeps them small
+/// while leaving every cell a few pixels wide.
+const SCALE: f64 = 0.25;
+/// How far a channel may be off before the pixel counts as different, to
+/// allow for floating point differences between platforms.
+const CHANNEL_TOLERANCE: u8 = 2;
+/// How many pixels may differ, for the same reason.
+const MAX_DIFFERENT_PIXELS: usize = 4;
+
+fn options() -> ExportOptions {
+    ExportOptions { relief: 0.0, lines_only: false, merge_tolerance: None, scale: SCALE, smoothing: 0, dots: true, dot_style: DotStyle::default(), background: [1.0; 4], stroke: None, boundary: None, data_coords: false, document: None }
+}
+
+fn random_diagram(seed: u64, count: usize) -> Diagram {
+    seed_random(seed);
+    let mut diagram = Diagram::new();
+    diagram.randomize(count, &Density::Uniform);
+    diagram
+}
+
+/// Renders the diagram and compares it with `test_data/golden/NAME.png`.
+fn check(name: &str, diagram: &Diagram, options: &ExportOptions) {
+    compare(name, &render_image(diagram, options).unwrap());
+}
+
+/// Compares the image with `test_data/golden/NAME.png`.
+fn compare(name: &str, image: &Canvas) {
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data/golden").join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        save(&golden, image);
+        return;
+    }
+
+    let expected = ::image::open(&golden)
+        .unwrap_or_else(|e| panic!("Can't read {}: {}, run with UPDATE_GOLDEN=1 to create it", golden.display(), e))
+        .to_rgba8();
+    assert_eq!((image.width, image.height), expected.dimensions(), "{} changed size", name);
+
+    let mut diff = Canvas::new(image.width, image.height);
+    let mut different = 0;
+    for ((actual, expected), marked) in image.pixels.chunks(4).zip(expected.as_raw().chunks(4)).zip(diff.pixels.chunks_mut(4)) {
+        let off = actual.iter().zip(expected).any(|(a, e)| a.abs_diff(*e) > CHANNEL_TOLERANCE);
+        different += off as usize;
+        marked.copy_from_slice(if off { &[255, 0, 0, 255] } else { &[255, 255, 255, 255] });
+    }
+    if different > MAX_DIFFERENT_PIXELS {
+        let failures = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        save(&failures.join(format!("{}.png", name)), image);
+        save(&failures.join(format!("{}-diff.png", name)), &diff);
+        panic!("{} pixels of {} differ from {}, see {}", different, name, golden.display(), failures.display());
+    }
+}
+
+fn save(path: &Path, canvas: &Canvas) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    ::image::save_buffer(path, &canvas.pixels, canvas.width, canvas.height, ::image::ColorType::Rgba8).unwrap();
+}
+
+#[test]
+fn filled_cells() {
+    check("filled_cells", &random_diagram(1, 40), &options());
+}
+
+#[test]
+fn wireframe() {
+    check("wireframe", &random_diagram(1, 40), &ExportOptions { lines_only: true, ..options() });
+}
+
+#[test]
+fn smoothed_cells() {
+    check("smoothed_cells", &random_diagram(1, 40), &ExportOptions { smoothing: 3, ..options() });
+}
+
+#[test]
+fn hue_palette() {
+    seed_random(2);
+    let mut diagram = Diagram::new();
+    diagram.palette = Palette::Hue(0.6);
+    diagram.randomize(40, &Density::Uniform);
+    check("hue_palette", &diagram, &options());
+}
+
+#[test]
+fn merged_regions() {
+    seed_random(3);
+    let mut diagram = Diagram::new();
+    diagram.palette = Palette::Hue(0.1);
+    diagram.randomize(60, &Density::Uniform);
+    check("merged_regions", &diagram, &ExportOptions { merge_tolerance: Some(0.1), ..options() });
+}
+
+/// Dots on the edges and corners of the window, whose cells have to be cut
+/// off there.
+#[test]
+fn clipped_at_edges() {
+    seed_random(4);
+    let mut diagram = Diagram::new();
+    diagram.set_dots(vec![[0.0, 0.0], [1280.0, 0.0], [0.0, 720.0], [1280.0, 720.0], [640.0, 0.0], [640.0, 720.0], [0.0, 360.0], [1280.0, 360.0], [640.0, 360.0]]);
+    check("clipped_at_edges", &diagram, &options());
+}
+
+#[test]
+fn power_cells() {
+    let mut diagram = random_diagram(5, 20);
+    for (i, weight) in diagram.weights.iter_mut().enumerate() {
+        *weight = (i % 4) as f64 * 2000.0;
+    }
+    diagram.set_power(true);
+    check("power_cells", &diagram, &options());
+}
+
+/// Dots in the Poincaré disk, with cells that shrink towards its rim and
+/// arcs for edges.
+#[test]
+fn hyperbolic_cells() {
+    let mut diagram = random_diagram(7, 60);
+    let inside = diagram.dots.iter().copied().filter(|&d| hyperbolic::in_disk(d)).collect();
+    diagram.set_dots(inside);
+    diagram.set_hyperbolic(true);
+    check("hyperbolic_cells", &diagram, &options());
+}
+
+/// A grid and a ring of dots, where four or more dots share a circumcircle
+/// all over, which the triangulation has to settle the same way every time.
+#[test]
+fn cocircular_dots() {
+    seed_random(6);
+    let grid = (0..8).flat_map(|i| (0..4).map(move |j| [80.0 + i as f64 * 160.0, 90.0 + j as f64 * 180.0]));
+    let ring = (0..12).map(|i| {
+        let angle = i as f64 * std::f64::consts::TAU / 12.0;
+        [640.0 + 250.0 * angle.cos(), 360.0 + 250.0 * angle.sin()]
+    });
+    let mut diagram = Diagram::new();
+    diagram.set_dots(grid.chain(ring).chain([[640.0, 360.0]]).collect());
+    check("cocircular_dots", &diagram, &options());
+}
+
+#[test]
+fn periodic_tile() {
+    check("periodic_tile", &random_diagram(1, 40).periodic(), &options());
+}
+
+#[test]
+fn normal_map() {
+    let bump = Bump { field: BumpField::Edges, normals: true, bevel: 8.0 };
+    compare("normal_map", &render_bump(&random_diagram(1, 40), &options(), &bump).unwrap());
+}
+
+#[test]
+fn terrain_heightmap() {
+    let diagram = random_diagram(1, 40);
+    let heights = Terrain::Noise.heights(&diagram, 7);
+    compare("terrain_heightmap", &render_heightmap(&diagram, &options(), &heights).unwrap());
+}